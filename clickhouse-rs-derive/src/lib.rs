@@ -0,0 +1,201 @@
+//! Derive macros for [clickhouse-rs](https://docs.rs/clickhouse-rs).
+//!
+//! These are re-exported by `clickhouse-rs`, use them from there.
+
+extern crate proc_macro;
+
+mod query;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Lit, Meta, NestedMeta, Result};
+
+/// Implements `clickhouse_rs::types::InsertRow` for a struct with named fields.
+///
+/// Every field becomes a column of the same name. Field attributes:
+///
+/// - `#[clickhouse(rename = "name")]` — use another column name;
+/// - `#[clickhouse(skip)]` — don't insert the field.
+///
+/// Struct attributes used by `InsertRow::create_table_sql`:
+///
+/// - `#[clickhouse(engine = "ReplacingMergeTree")]` — table engine, `MergeTree` by default;
+/// - `#[clickhouse(order_by = "(id, time)")]` — `ORDER BY` expression;
+/// - `#[clickhouse(partition_by = "toYYYYMM(time)")]` — `PARTITION BY` expression.
+#[proc_macro_derive(Row, attributes(clickhouse))]
+pub fn derive_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_row(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Generates a struct for the rows of a `SELECT` statement, checking its
+/// columns against a schema snapshot at compile time.
+///
+/// The snapshot is the `TabSeparated` output of
+/// `SELECT database, table, name, type FROM system.columns`, read from
+/// `clickhouse-schema.tsv` in the crate root, the file named by the
+/// `CLICKHOUSE_SCHEMA` environment variable or `#[schema = "path"]`.
+///
+/// Only plain columns of a single table (optionally aliased) and `*` can be
+/// selected, `Date` and `DateTime` columns require `chrono` and `chrono-tz`.
+///
+/// ```ignore
+/// clickhouse_rs::query! {
+///     #[schema = "schema.tsv"]
+///     pub struct Event = "SELECT id, name AS title FROM events WHERE id > ?";
+/// }
+/// ```
+///
+/// The struct has a field for every column, the associated `SQL` constant,
+/// `query()` and `from_block(&block)` that reads the rows of the result.
+#[proc_macro]
+pub fn query(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as query::QueryInput);
+    match query::expand_query(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+enum Attr {
+    Rename(String),
+    Skip,
+    Engine(String),
+    OrderBy(String),
+    PartitionBy(String),
+}
+
+fn expand_row(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    "#[derive(Row)] requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "#[derive(Row)] can only be used with structs",
+            ))
+        }
+    };
+
+    let mut names = Vec::new();
+    let mut types = Vec::new();
+    let mut idents = Vec::new();
+
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let mut name = ident.to_string();
+        let mut skip = false;
+
+        for attr in parse_attrs(&field.attrs)? {
+            match attr {
+                Attr::Rename(value) => name = value,
+                Attr::Skip => skip = true,
+                _ => return Err(Error::new_spanned(field, "unexpected field attribute")),
+            }
+        }
+
+        if !skip {
+            names.push(name);
+            types.push(&field.ty);
+            idents.push(ident);
+        }
+    }
+
+    let mut table_fns = Vec::new();
+    for attr in parse_attrs(&input.attrs)? {
+        table_fns.push(match attr {
+            Attr::Engine(value) => quote! {
+                fn engine() -> &'static str {
+                    #value
+                }
+            },
+            Attr::OrderBy(value) => quote! {
+                fn order_by() -> Option<&'static str> {
+                    Some(#value)
+                }
+            },
+            Attr::PartitionBy(value) => quote! {
+                fn partition_by() -> Option<&'static str> {
+                    Some(#value)
+                }
+            },
+            _ => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    "unexpected struct attribute",
+                ))
+            }
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::clickhouse_rs::types::InsertRow for #ident #ty_generics #where_clause {
+            fn columns() -> Vec<(&'static str, ::clickhouse_rs::types::SqlType)> {
+                vec![
+                    #( (#names, <#types as ::clickhouse_rs::types::ColumnValue>::sql_type()) ),*
+                ]
+            }
+
+            fn values(&self) -> Vec<::clickhouse_rs::types::Value> {
+                vec![
+                    #( ::clickhouse_rs::types::ColumnValue::to_value(&self.#idents) ),*
+                ]
+            }
+
+            #( #table_fns )*
+        }
+    })
+}
+
+fn parse_attrs(attrs: &[syn::Attribute]) -> Result<Vec<Attr>> {
+    let mut result = Vec::new();
+
+    for attr in attrs {
+        if !attr.path.is_ident("clickhouse") {
+            continue;
+        }
+
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "expected #[clickhouse(...)]")),
+        };
+
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(ref nv)) => {
+                    let value = match nv.lit {
+                        Lit::Str(ref value) => value.value(),
+                        ref lit => return Err(Error::new_spanned(lit, "expected a string")),
+                    };
+                    result.push(match nv.path.get_ident() {
+                        Some(ident) if ident == "rename" => Attr::Rename(value),
+                        Some(ident) if ident == "engine" => Attr::Engine(value),
+                        Some(ident) if ident == "order_by" => Attr::OrderBy(value),
+                        Some(ident) if ident == "partition_by" => Attr::PartitionBy(value),
+                        _ => return Err(Error::new_spanned(nv, "unknown clickhouse attribute")),
+                    });
+                }
+                NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("skip") => {
+                    result.push(Attr::Skip)
+                }
+                other => return Err(Error::new_spanned(other, "unknown clickhouse attribute")),
+            }
+        }
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,522 @@
+//! `query!` macro: `SELECT` statements checked against a schema snapshot.
+
+use std::{env, fs, path::PathBuf};
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    Attribute, Error, Ident, Lit, LitStr, Meta, Result, Token, Visibility,
+};
+
+/// Snapshot that is used unless `#[schema = "..."]` is given.
+const DEFAULT_SCHEMA: &str = "clickhouse-schema.tsv";
+
+pub(crate) struct QueryInput {
+    attrs: Vec<Attribute>,
+    schema: Option<LitStr>,
+    vis: Visibility,
+    ident: Ident,
+    sql: LitStr,
+}
+
+impl Parse for QueryInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut attrs = Vec::new();
+        let mut schema = None;
+        for attr in input.call(Attribute::parse_outer)? {
+            if !attr.path.is_ident("schema") {
+                attrs.push(attr);
+                continue;
+            }
+            match attr.parse_meta()? {
+                Meta::NameValue(nv) => match nv.lit {
+                    Lit::Str(path) => schema = Some(path),
+                    lit => return Err(Error::new_spanned(lit, "expected a string")),
+                },
+                meta => return Err(Error::new_spanned(meta, "expected #[schema = \"...\"]")),
+            }
+        }
+
+        let vis = input.parse()?;
+        input.parse::<Token![struct]>()?;
+        let ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let sql = input.parse()?;
+        if input.peek(Token![;]) {
+            input.parse::<Token![;]>()?;
+        }
+
+        Ok(Self {
+            attrs,
+            schema,
+            vis,
+            ident,
+            sql,
+        })
+    }
+}
+
+/// Column of the schema snapshot.
+#[derive(Clone, Debug, PartialEq)]
+struct SchemaColumn {
+    database: String,
+    table: String,
+    name: String,
+    type_name: String,
+}
+
+/// Column of the result of a query.
+#[derive(Debug, PartialEq)]
+struct ResultColumn {
+    name: String,
+    type_name: String,
+}
+
+pub(crate) fn expand_query(input: QueryInput) -> Result<TokenStream> {
+    let sql_span = input.sql.span();
+    let sql = input.sql.value();
+
+    let path = schema_path(input.schema.as_ref())?;
+    let snapshot = fs::read_to_string(&path).map_err(|err| {
+        let message = format!("can't read schema snapshot {}: {}", path.display(), err);
+        Error::new(
+            input.schema.as_ref().map_or(sql_span, LitStr::span),
+            message,
+        )
+    })?;
+    let schema = parse_schema(&snapshot).map_err(|err| Error::new(sql_span, err))?;
+    let columns = result_columns(&sql, &schema).map_err(|err| Error::new(sql_span, err))?;
+
+    let mut idents = Vec::new();
+    let mut types = Vec::new();
+    let mut names = Vec::new();
+    for column in columns {
+        let message =
+            |err: String| Error::new(sql_span, format!("column `{}`: {}", column.name, err));
+        idents.push(field_ident(&column.name).map_err(message)?);
+        types.push(rust_type(&column.type_name).map_err(message)?);
+        names.push(column.name);
+    }
+
+    let QueryInput {
+        attrs, vis, ident, ..
+    } = input;
+    let schema_path = path.to_string_lossy().into_owned();
+
+    Ok(quote! {
+        #( #attrs )*
+        #[derive(Clone, Debug, PartialEq)]
+        #vis struct #ident {
+            #( pub #idents: #types ),*
+        }
+
+        impl #ident {
+            /// Statement the struct was generated from.
+            pub const SQL: &'static str = #sql;
+
+            /// Returns the statement as a `Query`.
+            #[allow(dead_code)]
+            pub fn query() -> ::clickhouse_rs::types::Query {
+                ::clickhouse_rs::types::Query::new(Self::SQL)
+            }
+
+            /// Reads the rows of a result of the statement.
+            #[allow(dead_code)]
+            pub fn from_block<K: ::clickhouse_rs::types::ColumnType>(
+                block: &::clickhouse_rs::types::Block<K>,
+            ) -> ::clickhouse_rs::errors::Result<Vec<Self>> {
+                block
+                    .rows()
+                    .map(|row| {
+                        Ok(Self {
+                            #( #idents: row.get(#names)? ),*
+                        })
+                    })
+                    .collect()
+            }
+        }
+
+        // Recompiles the statement when the snapshot changes.
+        const _: &str = include_str!(#schema_path);
+    })
+}
+
+fn schema_path(schema: Option<&LitStr>) -> Result<PathBuf> {
+    let root = env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| Error::new(Span::call_site(), "CARGO_MANIFEST_DIR is not set"))?;
+    let relative = match schema {
+        Some(path) => path.value(),
+        None => env::var("CLICKHOUSE_SCHEMA").unwrap_or_else(|_| DEFAULT_SCHEMA.into()),
+    };
+    Ok(PathBuf::from(root).join(relative))
+}
+
+/// Parses the snapshot, it's the `TabSeparated` output of
+/// `SELECT database, table, name, type FROM system.columns`.
+fn parse_schema(snapshot: &str) -> std::result::Result<Vec<SchemaColumn>, String> {
+    let mut schema = Vec::new();
+    for (index, line) in snapshot.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<String> = line.split('\t').map(unescape).collect();
+        match fields.as_slice() {
+            [database, table, name, type_name] => schema.push(SchemaColumn {
+                database: database.clone(),
+                table: table.clone(),
+                name: name.clone(),
+                type_name: type_name.clone(),
+            }),
+            _ => {
+                return Err(format!(
+                    "line {} of the schema snapshot doesn't have 4 columns",
+                    index + 1
+                ))
+            }
+        }
+    }
+    Ok(schema)
+}
+
+fn unescape(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Returns the columns `sql` selects, only plain columns of a single table
+/// (optionally aliased) and `*` are supported.
+fn result_columns(
+    sql: &str,
+    schema: &[SchemaColumn],
+) -> std::result::Result<Vec<ResultColumn>, String> {
+    let tokens = tokenize(sql)?;
+    let mut tokens = tokens.iter().peekable();
+
+    match tokens.next() {
+        Some(SqlToken::Word(word)) if word.eq_ignore_ascii_case("SELECT") => {}
+        _ => return Err("only SELECT statements are supported".into()),
+    }
+    if let Some(SqlToken::Word(word)) = tokens.peek() {
+        if word.eq_ignore_ascii_case("DISTINCT") {
+            tokens.next();
+        }
+    }
+
+    let mut items = vec![Vec::new()];
+    loop {
+        match tokens.next() {
+            None => return Err("FROM clause is missing".into()),
+            Some(SqlToken::Word(word)) if word.eq_ignore_ascii_case("FROM") => break,
+            Some(SqlToken::Punct(',')) => items.push(Vec::new()),
+            Some(token) => items.last_mut().unwrap().push(token),
+        }
+    }
+
+    let mut table = vec![expect_name(tokens.next())?];
+    if let Some(SqlToken::Punct('.')) = tokens.peek() {
+        tokens.next();
+        table.push(expect_name(tokens.next())?);
+    }
+    let table_columns = find_table(schema, &table)?;
+
+    let mut columns = Vec::new();
+    for item in items {
+        match item.as_slice() {
+            [SqlToken::Punct('*')] => {
+                columns.extend(table_columns.iter().map(|column| ResultColumn {
+                    name: column.name.clone(),
+                    type_name: column.type_name.clone(),
+                }))
+            }
+            [column] => columns.push(find_column(&table_columns, column, None)?),
+            [column, SqlToken::Word(as_), alias] if as_.eq_ignore_ascii_case("AS") => {
+                columns.push(find_column(&table_columns, column, Some(alias))?)
+            }
+            _ => {
+                let item: Vec<String> = item.iter().map(ToString::to_string).collect();
+                return Err(format!(
+                    "`{}` isn't a column of the table, only columns can be selected",
+                    item.join(" ")
+                ));
+            }
+        }
+    }
+    Ok(columns)
+}
+
+fn expect_name(token: Option<&SqlToken>) -> std::result::Result<String, String> {
+    match token {
+        Some(SqlToken::Word(name)) | Some(SqlToken::Quoted(name)) => Ok(name.clone()),
+        _ => Err("expected a table name after FROM".into()),
+    }
+}
+
+fn find_table(
+    schema: &[SchemaColumn],
+    table: &[String],
+) -> std::result::Result<Vec<SchemaColumn>, String> {
+    let columns: Vec<SchemaColumn> = schema
+        .iter()
+        .filter(|column| match table {
+            [name] => column.table == *name,
+            [database, name] => column.database == *database && column.table == *name,
+            _ => false,
+        })
+        .cloned()
+        .collect();
+
+    let table = table.join(".");
+    if columns.is_empty() {
+        return Err(format!("table `{}` isn't in the schema snapshot", table));
+    }
+    if columns
+        .iter()
+        .any(|column| column.database != columns[0].database)
+    {
+        return Err(format!(
+            "table `{}` is in several databases, qualify it with the database",
+            table
+        ));
+    }
+    Ok(columns)
+}
+
+fn find_column(
+    columns: &[SchemaColumn],
+    column: &SqlToken,
+    alias: Option<&SqlToken>,
+) -> std::result::Result<ResultColumn, String> {
+    let name = match column {
+        SqlToken::Word(name) | SqlToken::Quoted(name) => name,
+        token => return Err(format!("`{}` isn't a column of the table", token)),
+    };
+    let column = match columns.iter().find(|column| column.name == *name) {
+        Some(column) => column,
+        None => return Err(format!("column `{}` isn't in the table", name)),
+    };
+    let name = match alias {
+        None => name.clone(),
+        Some(SqlToken::Word(alias)) | Some(SqlToken::Quoted(alias)) => alias.clone(),
+        Some(token) => return Err(format!("`{}` isn't an alias", token)),
+    };
+    Ok(ResultColumn {
+        name,
+        type_name: column.type_name.clone(),
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum SqlToken {
+    Word(String),
+    /// Identifier in backquotes or double quotes.
+    Quoted(String),
+    Literal(String),
+    Punct(char),
+}
+
+impl std::fmt::Display for SqlToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SqlToken::Word(word) => write!(f, "{}", word),
+            SqlToken::Quoted(name) => write!(f, "`{}`", name),
+            SqlToken::Literal(literal) => write!(f, "'{}'", literal),
+            SqlToken::Punct(ch) => write!(f, "{}", ch),
+        }
+    }
+}
+
+fn tokenize(sql: &str) -> std::result::Result<Vec<SqlToken>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            ch if ch.is_whitespace() => {}
+            '`' | '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err("unterminated quote".into()),
+                        Some('\\') => value.extend(chars.next()),
+                        Some(end) if end == ch => break,
+                        Some(other) => value.push(other),
+                    }
+                }
+                tokens.push(match ch {
+                    '\'' => SqlToken::Literal(value),
+                    _ => SqlToken::Quoted(value),
+                });
+            }
+            ch if ch.is_alphanumeric() || ch == '_' => {
+                let mut word = ch.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !next.is_alphanumeric() && next != '_' {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                tokens.push(SqlToken::Word(word));
+            }
+            ch => tokens.push(SqlToken::Punct(ch)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn field_ident(name: &str) -> std::result::Result<Ident, String> {
+    let mut chars = name.chars();
+    let is_ident = match chars.next() {
+        Some(first) => {
+            (first.is_ascii_alphabetic() || first == '_')
+                && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+        }
+        None => false,
+    };
+    if !is_ident || name == "_" {
+        return Err("not a valid field name, give the column an alias".into());
+    }
+
+    match syn::parse_str::<Ident>(name) {
+        Ok(ident) => Ok(ident),
+        Err(_) => Ok(Ident::new_raw(name, Span::call_site())),
+    }
+}
+
+/// Rust type of a field for a ClickHouse type.
+fn rust_type(type_name: &str) -> std::result::Result<TokenStream, String> {
+    let type_name = type_name.trim();
+    if let Some(inner) = unwrap_type(type_name, "Nullable") {
+        let inner = rust_type(inner)?;
+        return Ok(quote!(Option<#inner>));
+    }
+    if let Some(inner) = unwrap_type(type_name, "Array") {
+        if inner.starts_with("Nullable(") || inner.starts_with("Array(") {
+            return Err(format!("type `{}` is not supported", type_name));
+        }
+        let inner = rust_type(inner)?;
+        return Ok(quote!(Vec<#inner>));
+    }
+
+    let base = type_name.split('(').next().unwrap_or(type_name);
+    Ok(match base {
+        "UInt8" => quote!(u8),
+        "UInt16" => quote!(u16),
+        "UInt32" => quote!(u32),
+        "UInt64" => quote!(u64),
+        "Int8" => quote!(i8),
+        "Int16" => quote!(i16),
+        "Int32" => quote!(i32),
+        "Int64" => quote!(i64),
+        "Float32" => quote!(f32),
+        "Float64" => quote!(f64),
+        "String" | "FixedString" => quote!(String),
+        "Date" => quote!(::chrono::Date<::chrono_tz::Tz>),
+        "DateTime" => quote!(::chrono::DateTime<::chrono_tz::Tz>),
+        "Decimal" => quote!(::clickhouse_rs::types::Decimal),
+        _ => return Err(format!("type `{}` is not supported", type_name)),
+    })
+}
+
+/// Returns `T` of `wrapper(T)`.
+fn unwrap_type<'a>(type_name: &'a str, wrapper: &str) -> Option<&'a str> {
+    type_name
+        .strip_prefix(wrapper)?
+        .strip_prefix('(')?
+        .strip_suffix(')')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn schema() -> Vec<SchemaColumn> {
+        parse_schema(
+            "default\tevents\tid\tUInt32\n\
+             default\tevents\tname\tNullable(String)\n\
+             default\tevents\ttime\tDateTime('Europe/Moscow')\n\
+             system\tnumbers\tnumber\tUInt64\n",
+        )
+        .unwrap()
+    }
+
+    fn names(columns: &[ResultColumn]) -> Vec<&str> {
+        columns.iter().map(|column| column.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_result_columns() {
+        let schema = schema();
+
+        let columns = result_columns("SELECT * FROM events WHERE id > 1", &schema).unwrap();
+        assert_eq!(names(&columns), vec!["id", "name", "time"]);
+
+        let columns = result_columns(
+            "select distinct `name` AS title, id FROM default.events ORDER BY id",
+            &schema,
+        )
+        .unwrap();
+        assert_eq!(
+            columns,
+            vec![
+                ResultColumn {
+                    name: "title".into(),
+                    type_name: "Nullable(String)".into(),
+                },
+                ResultColumn {
+                    name: "id".into(),
+                    type_name: "UInt32".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_queries() {
+        let schema = schema();
+        for sql in &[
+            "INSERT INTO events VALUES",
+            "SELECT id FROM missing",
+            "SELECT missing FROM events",
+            "SELECT count() FROM events",
+            "SELECT id FROM system.events",
+            "SELECT id",
+        ] {
+            assert!(result_columns(sql, &schema).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_rust_type() {
+        let to_string = |type_name| rust_type(type_name).unwrap().to_string();
+        assert_eq!(to_string("UInt32"), "u32");
+        assert_eq!(to_string("Nullable(String)"), "Option < String >");
+        assert_eq!(to_string("Array(Float64)"), "Vec < f64 >");
+        assert_eq!(
+            to_string("Decimal(9, 2)"),
+            ":: clickhouse_rs :: types :: Decimal"
+        );
+        assert!(rust_type("Array(Nullable(UInt8))").is_err());
+        assert!(rust_type("Tuple(UInt8, String)").is_err());
+    }
+
+    #[test]
+    fn test_field_ident() {
+        assert_eq!(field_ident("id").unwrap().to_string(), "id");
+        assert_eq!(field_ident("type").unwrap().to_string(), "r#type");
+        assert!(field_ident("count()").is_err());
+    }
+}
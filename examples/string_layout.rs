@@ -0,0 +1,77 @@
+extern crate clickhouse_rs;
+
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+use clickhouse_rs::{
+    errors::Error,
+    types::{Block, Simple},
+};
+
+/// Dimension values, each one fits the 22 bytes a string is stored inline in.
+const SHORT: &[&str] = &["US", "Germany", "mobile", "desktop", "organic", "2019-12-31"];
+
+/// Values that are too long to be inline and are stored in a shared chunk.
+const LONG: &[&str] = &[
+    "https://example.com/landing/spring",
+    "Mozilla/5.0 (X11; Linux x86_64)",
+    "utm_campaign=newsletter_december",
+];
+
+const COLUMNS: usize = 8;
+
+fn block(values: &[&str], rows: usize) -> Block<Simple> {
+    (0..COLUMNS).fold(Block::new(), |block, i| {
+        let column: Vec<&str> = (0..rows).map(|row| values[(row + i) % values.len()]).collect();
+        block.column(&format!("dim{}", i), column)
+    })
+}
+
+fn scan(block: &Block<Simple>) -> Result<usize, Error> {
+    let mut total = 0;
+    for column in block.columns() {
+        for value in column.iter::<&str>()? {
+            total += value.len();
+        }
+    }
+    Ok(total)
+}
+
+fn measure(name: &str, values: &[&str], rows: usize, rounds: u32) -> Result<(), Error> {
+    let (mut build, mut read) = (Duration::default(), Duration::default());
+    for _ in 0..rounds {
+        let start = Instant::now();
+        let block = block(values, rows);
+        build += start.elapsed();
+
+        let start = Instant::now();
+        assert!(scan(&block)? > 0);
+        read += start.elapsed();
+    }
+
+    println!(
+        "{:>6}: build {:>10?}, read {:>10?} per block of {} rows x {} columns",
+        name,
+        build / rounds,
+        read / rounds,
+        rows,
+        COLUMNS
+    );
+    Ok(())
+}
+
+/// Compares blocks of short strings, which are stored inline,
+/// with blocks of longer strings stored in chunks.
+///
+/// `cargo run --release --example string_layout [rows] [rounds]`
+fn main() -> Result<(), Error> {
+    let mut args = env::args().skip(1);
+    let rows = args.next().and_then(|s| s.parse().ok()).unwrap_or(100_000);
+    let rounds = args.next().and_then(|s| s.parse().ok()).unwrap_or(10);
+
+    measure("inline", SHORT, rows, rounds)?;
+    measure("chunk", LONG, rows, rounds)?;
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use std::sync::{Arc, Mutex};
+
+/// Number of buffers a pool keeps.
+const MAX_BUFFERS: usize = 4;
+
+/// Buffers that grew larger than this are freed instead of kept.
+const MAX_BUFFER_CAPACITY: usize = 16 * 1024 * 1024;
+
+/// Byte buffers of a connection, reused by the packets it sends.
+#[derive(Clone, Default)]
+pub(crate) struct BufferPool(Arc<Mutex<Vec<Vec<u8>>>>);
+
+impl BufferPool {
+    /// Returns an empty buffer, allocated by a previous packet if there is one.
+    pub(crate) fn take(&self) -> Vec<u8> {
+        self.0.lock().unwrap().pop().unwrap_or_default()
+    }
+
+    /// Keeps `buffer` for reuse unless the pool is full or the buffer is too large.
+    pub(crate) fn put(&self, mut buffer: Vec<u8>) {
+        if buffer.capacity() == 0 || buffer.capacity() > MAX_BUFFER_CAPACITY {
+            return;
+        }
+
+        buffer.clear();
+        let mut buffers = self.0.lock().unwrap();
+        if buffers.len() < MAX_BUFFERS {
+            buffers.push(buffer);
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::binary::Encoder;
+
+    #[test]
+    fn test_reuse_buffer() {
+        let pool = BufferPool::default();
+        let mut buffer = pool.take();
+        buffer.extend_from_slice(b"hello");
+        let capacity = buffer.capacity();
+        pool.put(buffer);
+
+        let buffer = pool.take();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.capacity(), capacity);
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn test_pool_is_capped() {
+        let pool = BufferPool::default();
+        for _ in 0..MAX_BUFFERS + 2 {
+            pool.put(Vec::with_capacity(8));
+        }
+        assert_eq!(pool.len(), MAX_BUFFERS);
+
+        let pool = BufferPool::default();
+        pool.put(Vec::with_capacity(MAX_BUFFER_CAPACITY + 1));
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn test_encoder_returns_buffer() {
+        let pool = BufferPool::default();
+        {
+            let mut encoder = Encoder::pooled(&pool);
+            let mut scratch = encoder.scratch();
+            scratch.write_bytes(b"scratch");
+            encoder.write_bytes(b"packet");
+        }
+        assert_eq!(pool.len(), 2);
+
+        let mut encoder = Encoder::pooled(&pool);
+        encoder.write_bytes(b"packet");
+        assert_eq!(encoder.get_buffer(), b"packet");
+        assert_eq!(pool.len(), 1);
+    }
+}
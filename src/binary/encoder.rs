@@ -1,18 +1,57 @@
+use std::mem;
+
 use crate::{
-    binary,
-    types::{Marshal, StatBuffer},
+    binary::{self, BufferPool, Segment, Segments},
+    types::{column::ArcColumnData, Marshal, StatBuffer},
 };
 
 const MAX_VARINT_LEN64: usize = 10;
 
+/// Smaller column data is copied, a separate segment isn't worth it.
+const MIN_SHARED_BYTES: usize = 4096;
+
 #[derive(Default)]
 pub struct Encoder {
     buffer: Vec<u8>,
+    pool: Option<BufferPool>,
+    segments: Option<Vec<Segment>>,
 }
 
 impl Encoder {
     pub fn new() -> Self {
-        Encoder { buffer: Vec::new() }
+        Encoder {
+            buffer: Vec::new(),
+            pool: None,
+            segments: None,
+        }
+    }
+
+    /// Creates an encoder that writes to a buffer of `pool`
+    /// and gives it back when dropped.
+    pub(crate) fn pooled(pool: &BufferPool) -> Self {
+        Encoder {
+            buffer: pool.take(),
+            pool: Some(pool.clone()),
+            segments: None,
+        }
+    }
+
+    /// Creates a pooled encoder that refers to large column data
+    /// instead of copying it, see `into_segments`.
+    pub(crate) fn vectored(pool: &BufferPool) -> Self {
+        Encoder {
+            buffer: pool.take(),
+            pool: Some(pool.clone()),
+            segments: Some(Vec::new()),
+        }
+    }
+
+    /// Creates an encoder for intermediate data that shares the pool of this one.
+    pub(crate) fn scratch(&self) -> Self {
+        match self.pool {
+            Some(ref pool) => Encoder::pooled(pool),
+            None => Encoder::new(),
+        }
     }
 
     pub fn uvarint(&mut self, v: u64) {
@@ -44,11 +83,57 @@ impl Encoder {
         self.buffer.extend_from_slice(b);
     }
 
-    pub fn get_buffer(self) -> Vec<u8> {
-        self.buffer
+    /// Writes the values of `data` from `start` to `end` by reference, if the
+    /// encoder is vectored and they're stored the way they're sent.
+    ///
+    /// Returns `false` if the values have to be saved instead.
+    pub(crate) fn write_shared(&mut self, data: &ArcColumnData, start: usize, end: usize) -> bool {
+        let segments = match self.segments {
+            Some(ref mut segments) => segments,
+            None => return false,
+        };
+        match data.wire_bytes(start, end) {
+            Some(bytes) if bytes.len() >= MIN_SHARED_BYTES => {}
+            _ => return false,
+        }
+
+        let next = match self.pool {
+            Some(ref pool) => pool.take(),
+            None => Vec::new(),
+        };
+        segments.push(Segment::Bytes(mem::replace(&mut self.buffer, next)));
+        segments.push(Segment::Column {
+            data: data.clone(),
+            start,
+            end,
+        });
+        true
+    }
+
+    pub fn get_buffer(mut self) -> Vec<u8> {
+        mem::take(&mut self.buffer)
+    }
+
+    /// Returns the written data, split where column data was written by reference.
+    pub(crate) fn into_segments(mut self) -> Segments {
+        let mut segments = self.segments.take().unwrap_or_default();
+        segments.push(Segment::Bytes(mem::take(&mut self.buffer)));
+        Segments::from(segments)
     }
 
     pub fn get_buffer_ref(&self) -> &[u8] {
         self.buffer.as_ref()
     }
+
+    pub(crate) fn get_buffer_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.buffer
+    }
+}
+
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            pool.put(mem::take(&mut self.buffer));
+        }
+    }
 }
@@ -1,7 +1,16 @@
-pub(crate) use self::{encoder::Encoder, parser::Parser, read_ex::ReadEx, uvarint::put_uvarint};
+pub(crate) use self::{
+    buffer_pool::BufferPool,
+    encoder::Encoder,
+    parser::Parser,
+    read_ex::ReadEx,
+    segments::{Segment, Segments},
+    uvarint::put_uvarint,
+};
 
+mod buffer_pool;
 mod encoder;
 mod parser;
 pub mod protocol;
 mod read_ex;
+mod segments;
 mod uvarint;
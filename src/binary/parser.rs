@@ -1,34 +1,34 @@
-use std::io::{self, Read};
+use std::io::{self, BufRead};
 
 use chrono_tz::Tz;
 
 use crate::{
     binary::{protocol, ReadEx},
     errors::{DriverError, Error, ServerError, Result},
-    types::{Block, Packet, ProfileInfo, Progress, ServerInfo},
+    types::{Block, LoadOptions, Packet, ProfileInfo, Progress, ServerInfo},
 };
 
 /// The internal clickhouse response parser.
 pub(crate) struct Parser<T> {
     reader: T,
     tz: Option<Tz>,
-    compress: bool,
+    options: LoadOptions,
 }
 
 /// The parser can be used to parse clickhouse responses into values.  Generally
 /// you normally do not use this directly as it's already done for you by
 /// the client but in some more complex situations it might be useful to be
 /// able to parse the clickhouse responses.
-impl<'a, T: Read> Parser<T> {
+impl<'a, T: BufRead> Parser<T> {
     /// Creates a new parser that parses the data behind the reader.  More
     /// than one value can be behind the reader in which case the parser can
     /// be invoked multiple times.  In other words: the stream does not have
     /// to be terminated.
-    pub(crate) fn new(reader: T, tz: Option<Tz>, compress: bool) -> Parser<T> {
+    pub(crate) fn new(reader: T, tz: Option<Tz>, options: LoadOptions) -> Parser<T> {
         Self {
             reader,
             tz,
-            compress,
+            options,
         }
     }
 
@@ -56,7 +56,7 @@ impl<'a, T: Read> Parser<T> {
             None => Err(Error::Driver(DriverError::UnexpectedPacket)),
             Some(tz) => {
                 self.reader.skip_string()?;
-                let block = Block::load(&mut self.reader, tz, self.compress)?;
+                let block = Block::load_with(&mut self.reader, tz, self.options)?;
                 Ok(Packet::Block(block))
             }
         }
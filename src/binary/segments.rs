@@ -0,0 +1,238 @@
+use std::{collections::VecDeque, io};
+
+use bytes::Buf;
+use iovec::IoVec;
+use tokio::{io::AsyncWrite, prelude::*};
+
+use crate::{binary::BufferPool, types::column::ArcColumnData};
+
+/// Part of an encoded packet.
+pub(crate) enum Segment {
+    Bytes(Vec<u8>),
+    /// Values of a column, written from the storage of the column.
+    Column {
+        data: ArcColumnData,
+        start: usize,
+        end: usize,
+    },
+}
+
+impl Segment {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Segment::Bytes(bytes) => bytes,
+            Segment::Column { data, start, end } => data.wire_bytes(*start, *end).unwrap_or(&[]),
+        }
+    }
+}
+
+/// Encoded packets that are written to the socket without joining their segments.
+#[derive(Default)]
+pub(crate) struct Segments {
+    queue: VecDeque<Segment>,
+    // Written bytes of the first segment
+    pos: usize,
+}
+
+impl From<Vec<u8>> for Segments {
+    fn from(bytes: Vec<u8>) -> Self {
+        Segments::from(vec![Segment::Bytes(bytes)])
+    }
+}
+
+impl From<Vec<Segment>> for Segments {
+    fn from(segments: Vec<Segment>) -> Self {
+        Self {
+            queue: segments
+                .into_iter()
+                .filter(|segment| !segment.as_bytes().is_empty())
+                .collect(),
+            pos: 0,
+        }
+    }
+}
+
+impl Segments {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Writes as much as `writer` takes in one call, with a single `writev`
+    /// for sockets. Buffers of the written segments are given back to `pool`.
+    pub(crate) fn write_to<W: AsyncWrite>(
+        &mut self,
+        writer: &mut W,
+        pool: &BufferPool,
+    ) -> Poll<usize, io::Error> {
+        writer.write_buf(&mut SegmentsBuf {
+            segments: self,
+            pool,
+        })
+    }
+
+    fn advance(&mut self, mut written: usize, pool: &BufferPool) {
+        while let Some(segment) = self.queue.front() {
+            let rest = segment.as_bytes().len() - self.pos;
+            if written < rest {
+                self.pos += written;
+                break;
+            }
+
+            written -= rest;
+            self.pos = 0;
+            if let Some(Segment::Bytes(bytes)) = self.queue.pop_front() {
+                pool.put(bytes);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn to_vec(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for segment in &self.queue {
+            bytes.extend_from_slice(segment.as_bytes());
+        }
+        bytes[self.pos..].to_vec()
+    }
+}
+
+/// Segments seen as a `Buf`, so that the socket writes them without copying.
+struct SegmentsBuf<'a> {
+    segments: &'a mut Segments,
+    pool: &'a BufferPool,
+}
+
+impl Buf for SegmentsBuf<'_> {
+    fn remaining(&self) -> usize {
+        let total: usize = self.segments.queue.iter().map(|s| s.as_bytes().len()).sum();
+        total - self.segments.pos
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self.segments.queue.front() {
+            Some(segment) => &segment.as_bytes()[self.segments.pos..],
+            None => &[],
+        }
+    }
+
+    fn bytes_vec<'b>(&'b self, dst: &mut [&'b IoVec]) -> usize {
+        let mut n = 0;
+        for (slot, segment) in dst.iter_mut().zip(&self.segments.queue) {
+            let bytes = match n {
+                0 => &segment.as_bytes()[self.segments.pos..],
+                _ => segment.as_bytes(),
+            };
+            *slot = bytes.into();
+            n += 1;
+        }
+        n
+    }
+
+    fn advance(&mut self, cnt: usize) {
+        self.segments.advance(cnt, self.pool);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        binary::Encoder,
+        types::{Block, Simple},
+    };
+
+    /// Socket that takes at most `limit` bytes per write.
+    struct Socket {
+        data: Vec<u8>,
+        limit: usize,
+    }
+
+    impl Write for Socket {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.limit);
+            self.data.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl AsyncWrite for Socket {
+        fn shutdown(&mut self) -> Poll<(), io::Error> {
+            Ok(Async::Ready(()))
+        }
+    }
+
+    #[test]
+    fn test_shared_columns() {
+        let block = Block::<Simple>::new()
+            .column("id", vec![7_u64; 1000])
+            .column("name", vec!["a"; 1000])
+            .column("flag", vec![1_u8; 1000]);
+
+        let mut expected = Encoder::new();
+        block.send_data(&mut expected, false, 0, None);
+
+        let pool = BufferPool::default();
+        let mut encoder = Encoder::vectored(&pool);
+        block.send_data(&mut encoder, false, 0, None);
+        let mut segments = encoder.into_segments();
+        assert_eq!(segments.queue.len(), 3);
+        assert_eq!(segments.to_vec(), expected.get_buffer_ref());
+
+        let mut socket = Socket {
+            data: Vec::new(),
+            limit: 1000,
+        };
+        while !segments.is_empty() {
+            match segments.write_to(&mut socket, &pool).unwrap() {
+                Async::Ready(n) => assert!(n > 0),
+                Async::NotReady => panic!("the socket is always ready"),
+            }
+        }
+        assert_eq!(socket.data, expected.get_buffer_ref());
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_bytes_vec() {
+        let pool = BufferPool::default();
+        let mut segments = Segments::from(vec![
+            Segment::Bytes(b"abc".to_vec()),
+            Segment::Bytes(Vec::new()),
+            Segment::Bytes(b"de".to_vec()),
+            Segment::Bytes(b"fgh".to_vec()),
+        ]);
+        segments.advance(1, &pool);
+
+        let buf = SegmentsBuf {
+            segments: &mut segments,
+            pool: &pool,
+        };
+        assert_eq!(buf.remaining(), 7);
+        assert_eq!(buf.bytes(), b"bc");
+
+        let dummy: &IoVec = b"-"[..].into();
+        let mut dst = [dummy; 2];
+        assert_eq!(buf.bytes_vec(&mut dst), 2);
+        assert_eq!(&dst[0][..], b"bc");
+        assert_eq!(&dst[1][..], b"de");
+
+        let mut buf = buf;
+        buf.advance(5);
+        assert_eq!(buf.bytes(), b"gh");
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_small_columns_are_copied() {
+        let block = Block::<Simple>::new().column("id", vec![7_u64; 10]);
+
+        let pool = BufferPool::default();
+        let mut encoder = Encoder::vectored(&pool);
+        block.send_data(&mut encoder, false, 0, None);
+        assert_eq!(encoder.into_segments().queue.len(), 1);
+    }
+}
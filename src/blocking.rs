@@ -0,0 +1,252 @@
+//! Synchronous client for programs that don't run an async runtime.
+//!
+//! The connections are served by a runtime the pool starts for itself,
+//! so the methods shouldn't be called from a task of another runtime.
+//!
+//! ```rust,no_run
+//! # use clickhouse_rs::{blocking::Pool, errors::Error, types::Block};
+//! # fn main() -> Result<(), Error> {
+//! let pool = Pool::new("tcp://localhost:9000")?;
+//! let mut client = pool.get_handle()?;
+//!
+//! client.execute("CREATE TABLE IF NOT EXISTS ids (id UInt32) ENGINE = Memory")?;
+//! client.insert("ids", Block::new().column("id", vec![1_u32, 2, 3]))?;
+//!
+//! for row in client.query("SELECT id FROM ids").rows() {
+//!     let id: u32 = row?.get("id")?;
+//!     println!("{}", id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use futures::{
+    stream,
+    sync::{mpsc, oneshot},
+    Future, Sink, Stream,
+};
+use serde::de::DeserializeOwned;
+use tokio::runtime::{self, Runtime};
+
+use crate::{
+    errors::{Error, Result},
+    types::{Block, Complex, IntoOptions, Query, Row, Simple},
+};
+
+/// Number of threads of the runtime a pool starts.
+const RUNTIME_THREADS: usize = 2;
+
+/// Pool of connections, see `crate::Pool`.
+#[derive(Clone)]
+pub struct Pool {
+    inner: crate::Pool,
+    runtime: Arc<Runtime>,
+}
+
+/// Connection taken from a `Pool`, it goes back to the pool once dropped.
+///
+/// Streamed results (see `QueryResult::blocks`) take the connection along,
+/// the next call takes another one from the pool.
+pub struct ClientHandle {
+    inner: Option<crate::ClientHandle>,
+    pool: Pool,
+}
+
+/// Result of a query, see `ClientHandle::query`.
+pub struct QueryResult<'a> {
+    client: &'a mut ClientHandle,
+    query: Query,
+}
+
+/// Iterator over the items of a stream that is polled by the runtime.
+pub struct Iter<T> {
+    inner: stream::Wait<mpsc::Receiver<Result<T>>>,
+}
+
+impl Pool {
+    /// Constructs a new pool, along with the runtime serving its connections.
+    pub fn new<O>(options: O) -> Result<Self>
+    where
+        O: IntoOptions,
+    {
+        let runtime = runtime::Builder::new()
+            .core_threads(RUNTIME_THREADS)
+            .name_prefix("clickhouse-runtime-")
+            .build()?;
+
+        Ok(Self {
+            inner: crate::Pool::new(options),
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Takes a connection from the pool, waiting for one if all are busy.
+    pub fn get_handle(&self) -> Result<ClientHandle> {
+        let inner = self.run(self.inner.get_handle())?;
+        Ok(ClientHandle {
+            inner: Some(inner),
+            pool: self.clone(),
+        })
+    }
+
+    fn run<F>(&self, future: F) -> Result<F::Item>
+    where
+        F: Future<Error = Error> + Send + 'static,
+        F::Item: Send + 'static,
+    {
+        oneshot::spawn(future, &self.runtime.executor()).wait()
+    }
+
+    /// Polls `source` on the runtime, at most one item is buffered ahead.
+    fn iter<S>(&self, source: S) -> Iter<S::Item>
+    where
+        S: Stream<Error = Error> + Send + 'static,
+        S::Item: Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(0);
+        // The stream is dropped, and the query cancelled, once the iterator is.
+        let forward = source
+            .then(Ok)
+            .forward(tx.sink_map_err(|_| ()))
+            .map(|_| ());
+        self.runtime.executor().spawn(forward);
+
+        Iter { inner: rx.wait() }
+    }
+}
+
+impl ClientHandle {
+    fn take(&mut self) -> Result<crate::ClientHandle> {
+        match self.inner.take() {
+            Some(inner) => Ok(inner),
+            None => self.pool.run(self.pool.inner.get_handle()),
+        }
+    }
+
+    /// Runs `f` with the connection, which is kept if `f` gives it back.
+    fn call<F, R, T>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(crate::ClientHandle) -> R,
+        R: Future<Item = (crate::ClientHandle, T), Error = Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        let inner = self.take()?;
+        let (inner, result) = self.pool.run(f(inner))?;
+        self.inner = Some(inner);
+        Ok(result)
+    }
+
+    /// Checks that the server is alive.
+    pub fn ping(&mut self) -> Result<()> {
+        self.call(|c| c.ping().map(|c| (c, ())))
+    }
+
+    /// Executes a statement that doesn't return rows.
+    pub fn execute<Q>(&mut self, sql: Q) -> Result<()>
+    where
+        Query: From<Q>,
+    {
+        self.call(|c| c.execute(sql).map(|c| (c, ())))
+    }
+
+    /// Inserts the rows of `block` into `table`, see `crate::ClientHandle::insert`.
+    pub fn insert<Q>(&mut self, table: Q, block: Block) -> Result<()>
+    where
+        Query: From<Q>,
+    {
+        self.call(|c| c.insert(table, block).map(|c| (c, ())))
+    }
+
+    /// Prepares a query, its result is fetched by the methods of `QueryResult`.
+    pub fn query<Q>(&mut self, sql: Q) -> QueryResult<'_>
+    where
+        Query: From<Q>,
+    {
+        QueryResult {
+            client: self,
+            query: Query::from(sql),
+        }
+    }
+}
+
+impl<'a> QueryResult<'a> {
+    /// Returns all rows of the result in a single block.
+    pub fn fetch_all(self) -> Result<Block<Complex>> {
+        let query = self.query;
+        self.client.call(|c| c.query(query).fetch_all())
+    }
+
+    /// Returns all rows of the result deserialized into `T`,
+    /// see `crate::types::QueryResult::fetch_all_as`.
+    pub fn fetch_all_as<T>(self) -> Result<Vec<T>>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let query = self.query;
+        self.client.call(|c| c.query(query).fetch_all_as())
+    }
+
+    /// Returns the blocks of the result as they are received.
+    pub fn blocks(self) -> Iter<Block> {
+        match self.client.take() {
+            Ok(c) => self.client.pool.iter(c.query(self.query).stream_blocks()),
+            Err(err) => self.client.pool.iter(stream::once(Err(err))),
+        }
+    }
+
+    /// Returns the rows of the result as they are received.
+    pub fn rows(self) -> Iter<Row<'static, Simple>> {
+        match self.client.take() {
+            Ok(c) => self.client.pool.iter(c.query(self.query).stream_rows()),
+            Err(err) => self.client.pool.iter(stream::once(Err(err))),
+        }
+    }
+}
+
+impl<T> Iterator for Iter<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The receiver never fails.
+        self.inner.next()?.ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use tokio_timer::Delay;
+
+    use super::*;
+    use crate::errors::DriverError;
+
+    #[test]
+    fn test_run() {
+        let pool = Pool::new("tcp://localhost:9000").unwrap();
+
+        // Timers work only within the runtime.
+        let delay = Delay::new(Instant::now() + Duration::from_millis(10));
+        pool.run(delay.map_err(Error::from)).unwrap();
+
+        let failed = futures::future::err::<(), _>(Error::Driver(DriverError::Timeout));
+        assert!(pool.run(failed).is_err());
+    }
+
+    #[test]
+    fn test_iter() {
+        let pool = Pool::new("tcp://localhost:9000").unwrap();
+
+        let source = stream::iter_ok(vec![1, 2, 3])
+            .chain(stream::once(Err(Error::Driver(DriverError::Timeout))));
+        let items: Vec<_> = pool.iter(source).collect();
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[2].as_ref().unwrap(), &3);
+        assert!(items[3].is_err());
+
+        let numbers = pool.iter(stream::iter_ok::<_, Error>(0..));
+        assert_eq!(numbers.take(3).map(Result::unwrap).sum::<u64>(), 3);
+    }
+}
@@ -1,62 +1,210 @@
-use std::{io, net::ToSocketAddrs};
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    net::{SocketAddr, ToSocketAddrs},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
 
-use futures::{future::FutureResult, SelectOk};
+use futures::{future::FutureResult, sync::oneshot};
 use tokio::net::{tcp::ConnectFuture, TcpStream};
 use tokio::prelude::*;
+use tokio::timer::{timeout, Timeout};
 
-enum State {
-    Wait(SelectOk<ConnectFuture>),
-    Fail(FutureResult<TcpStream, io::Error>),
+use crate::types::Address;
+
+/// Number of threads host names are resolved on.
+const RESOLVER_THREADS: usize = 4;
+
+type Resolved = io::Result<Vec<SocketAddr>>;
+
+struct Lookup {
+    addr: Address,
+    ttl: Option<Duration>,
+    tx: oneshot::Sender<Resolved>,
 }
 
-impl State {
-    fn poll(&mut self) -> Poll<TcpStream, io::Error> {
-        match self {
-            State::Wait(ref mut inner) => match inner.poll() {
-                Ok(Async::Ready((tcp, _))) => Ok(Async::Ready(tcp)),
-                Ok(Async::NotReady) => Ok(Async::NotReady),
-                Err(err) => Err(err),
-            },
-            State::Fail(ref mut inner) => match inner.poll() {
-                Err(err) => Err(err),
-                _ => unreachable!(),
-            },
+lazy_static! {
+    static ref DNS_CACHE: Mutex<HashMap<String, (Instant, Vec<SocketAddr>)>> =
+        Mutex::new(HashMap::new());
+    static ref RESOLVER: Mutex<Option<mpsc::Sender<Lookup>>> = Mutex::new(None);
+}
+
+/// Returns the addresses of `addr` if they're known without asking the resolver:
+/// for IP addresses and for host names resolved less than `ttl` ago.
+fn resolve_known(addr: &Address, ttl: Option<Duration>) -> Option<Vec<SocketAddr>> {
+    let host = match addr {
+        Address::SocketAddr(address) => return Some(vec![*address]),
+        Address::Url(host) => host,
+    };
+    if let Ok(address) = host.parse() {
+        return Some(vec![address]);
+    }
+
+    match (ttl, DNS_CACHE.lock().unwrap().get(host)) {
+        (Some(_), Some((expires, addresses))) if *expires > Instant::now() => {
+            Some(addresses.clone())
         }
+        _ => None,
+    }
+}
+
+/// Resolves `addr`, reusing a previous resolution of the same host name
+/// for `ttl` if it is given.
+fn resolve(addr: &Address, ttl: Option<Duration>) -> io::Result<Vec<SocketAddr>> {
+    if let Some(addresses) = resolve_known(addr, ttl) {
+        return Ok(addresses);
+    }
+
+    let (host, ttl) = match (addr, ttl) {
+        (Address::Url(host), Some(ttl)) => (host, ttl),
+        _ => return Ok(addr.to_socket_addrs()?.collect()),
+    };
+
+    let now = Instant::now();
+    let addresses: Vec<_> = addr.to_socket_addrs()?.collect();
+    DNS_CACHE
+        .lock()
+        .unwrap()
+        .insert(host.clone(), (now + ttl, addresses.clone()));
+    Ok(addresses)
+}
+
+fn forget(host: &str) {
+    DNS_CACHE.lock().unwrap().remove(host);
+}
+
+/// Starts the threads lookups are queued to, at most `RESOLVER_THREADS`
+/// lookups run at once.
+fn start_resolver() -> io::Result<mpsc::Sender<Lookup>> {
+    let (tx, rx) = mpsc::channel::<Lookup>();
+    let rx = Arc::new(Mutex::new(rx));
+
+    for i in 0..RESOLVER_THREADS {
+        let rx = rx.clone();
+        thread::Builder::new()
+            .name(format!("clickhouse-dns-{}", i))
+            .spawn(move || loop {
+                let lookup = match rx.lock().unwrap().recv() {
+                    Ok(lookup) => lookup,
+                    Err(_) => return,
+                };
+                // The connection may be given up already.
+                let _ = lookup.tx.send(resolve(&lookup.addr, lookup.ttl));
+            })?;
     }
+    Ok(tx)
 }
 
+/// Resolves `addr` on a thread of the resolver pool, so a slow resolver
+/// doesn't hold up the reactor.
+fn resolve_async(
+    addr: &Address,
+    ttl: Option<Duration>,
+) -> io::Result<oneshot::Receiver<Resolved>> {
+    let (tx, rx) = oneshot::channel();
+    let lookup = Lookup {
+        addr: addr.clone(),
+        ttl,
+        tx,
+    };
+
+    let mut resolver = RESOLVER.lock().unwrap();
+    if resolver.is_none() {
+        *resolver = Some(start_resolver()?);
+    }
+    match resolver.as_ref().unwrap().send(lookup) {
+        Ok(()) => Ok(rx),
+        Err(_) => Err(io::Error::other("host name resolver is stopped")),
+    }
+}
+
+/// Converts the error of a step that took longer than `timeout`.
+fn timed_out<E>(err: timeout::Error<E>, into_io: impl FnOnce(E) -> io::Error) -> io::Error {
+    if err.is_elapsed() {
+        return io::Error::new(io::ErrorKind::TimedOut, "connection timed out");
+    }
+    match err.into_inner() {
+        Some(err) => into_io(err),
+        None => io::Error::other("timer is unavailable"),
+    }
+}
+
+enum State {
+    Resolve(Timeout<oneshot::Receiver<Resolved>>),
+    Wait(Timeout<ConnectFuture>),
+    Fail(FutureResult<TcpStream, io::Error>),
+}
+
+/// Connects to the resolved addresses one by one, in the order they were resolved.
+///
+/// Resolving the host and connecting to each address may take up to `timeout`.
 pub(crate) struct ConnectingStream {
     state: State,
+    addresses: VecDeque<SocketAddr>,
+    cached_host: Option<String>,
+    timeout: Duration,
 }
 
 impl ConnectingStream {
-    pub(crate) fn new<S>(addr: S) -> Self
-    where
-        S: ToSocketAddrs,
-    {
-        match addr.to_socket_addrs() {
-            Ok(addresses) => {
-                let streams: Vec<_> = addresses
-                    .map(|address| TcpStream::connect(&address))
-                    .collect();
-
-                if streams.is_empty() {
-                    let err = io::Error::new(
-                        io::ErrorKind::InvalidInput,
-                        "Could not resolve to any address.",
-                    );
-                    Self {
-                        state: State::Fail(future::err(err)),
-                    }
-                } else {
-                    Self {
-                        state: State::Wait(future::select_ok(streams)),
-                    }
-                }
-            }
-            Err(err) => Self {
-                state: State::Fail(future::err(err)),
+    pub(crate) fn new(addr: &Address, dns_cache_ttl: Option<Duration>, timeout: Duration) -> Self {
+        let cached_host = match (addr, dns_cache_ttl) {
+            (Address::Url(host), Some(_)) => Some(host.clone()),
+            _ => None,
+        };
+
+        if let Some(addresses) = resolve_known(addr, dns_cache_ttl) {
+            return Self::from_addresses(addresses, cached_host, timeout);
+        }
+
+        match resolve_async(addr, dns_cache_ttl) {
+            Ok(rx) => Self {
+                state: State::Resolve(rx.timeout(timeout)),
+                addresses: VecDeque::new(),
+                cached_host,
+                timeout,
             },
+            Err(err) => Self::failed(err, timeout),
+        }
+    }
+
+    fn failed(err: io::Error, timeout: Duration) -> Self {
+        Self {
+            state: State::Fail(future::err(err)),
+            addresses: VecDeque::new(),
+            cached_host: None,
+            timeout,
+        }
+    }
+
+    fn connect(address: &SocketAddr, timeout: Duration) -> State {
+        State::Wait(TcpStream::connect(address).timeout(timeout))
+    }
+
+    fn from_addresses(
+        addresses: Vec<SocketAddr>,
+        cached_host: Option<String>,
+        timeout: Duration,
+    ) -> Self {
+        let mut addresses: VecDeque<_> = addresses.into();
+
+        let state = match addresses.pop_front() {
+            Some(address) => Self::connect(&address, timeout),
+            None => {
+                let err = io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Could not resolve to any address.",
+                );
+                State::Fail(future::err(err))
+            }
+        };
+
+        Self {
+            state,
+            addresses,
+            cached_host,
+            timeout,
         }
     }
 }
@@ -66,6 +214,130 @@ impl Future for ConnectingStream {
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        self.state.poll()
+        loop {
+            let err = match self.state {
+                State::Resolve(ref mut rx) => {
+                    let resolved = match rx.poll() {
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        Ok(Async::Ready(resolved)) => resolved,
+                        Err(err) => Err(timed_out(err, |_| {
+                            io::Error::other("host name resolution was interrupted")
+                        })),
+                    };
+                    let cached_host = self.cached_host.take();
+                    *self = match resolved {
+                        Ok(addresses) => Self::from_addresses(addresses, cached_host, self.timeout),
+                        Err(err) => Self::failed(err, self.timeout),
+                    };
+                    continue;
+                }
+                State::Wait(ref mut inner) => match inner.poll() {
+                    Err(err) => timed_out(err, |err| err),
+                    Ok(ready) => return Ok(ready),
+                },
+                State::Fail(ref mut inner) => match inner.poll() {
+                    Err(err) => return Err(err),
+                    _ => unreachable!(),
+                },
+            };
+
+            match self.addresses.pop_front() {
+                Some(address) => {
+                    warn!("[connect] {}, trying {}", err, address);
+                    self.state = Self::connect(&address, self.timeout);
+                }
+                None => {
+                    // All known addresses are unreachable,
+                    // the next attempt should ask the resolver again.
+                    if let Some(ref host) = self.cached_host {
+                        forget(host);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::TcpListener;
+
+    use tokio::runtime::current_thread;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_timed_out() {
+        let err = timed_out(timeout::Error::<io::Error>::elapsed(), |err| err);
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        let inner = io::Error::from(io::ErrorKind::ConnectionRefused);
+        let err = timed_out(timeout::Error::inner(inner), |err| err);
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+    }
+
+    #[test]
+    fn test_connect_in_order() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let reachable = listener.local_addr().unwrap();
+
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable = closed.local_addr().unwrap();
+        drop(closed);
+
+        let stream = ConnectingStream::from_addresses(vec![unreachable, reachable], None, TIMEOUT);
+        let tcp = current_thread::block_on_all(stream).unwrap();
+        assert_eq!(tcp.peer_addr().unwrap(), reachable);
+
+        let stream = ConnectingStream::from_addresses(vec![unreachable], None, TIMEOUT);
+        current_thread::block_on_all(stream).unwrap_err();
+    }
+
+    #[test]
+    fn test_resolve_with_cache() {
+        let addr = Address::from("localhost:9000");
+        let ttl = Some(Duration::from_secs(60));
+
+        let addresses = resolve(&addr, ttl).unwrap();
+        assert!(!addresses.is_empty());
+        assert!(DNS_CACHE.lock().unwrap().contains_key("localhost:9000"));
+        assert_eq!(resolve(&addr, ttl).unwrap(), addresses);
+
+        forget("localhost:9000");
+        assert!(!DNS_CACHE.lock().unwrap().contains_key("localhost:9000"));
+    }
+
+    #[test]
+    fn test_resolver_pool() {
+        let addr = Address::from("localhost:9000");
+        let lookups: Vec<_> = (0..RESOLVER_THREADS * 4)
+            .map(|_| resolve_async(&addr, None).unwrap())
+            .collect();
+        for rx in lookups {
+            assert!(!rx.wait().unwrap().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_resolve_off_reactor() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let ip = Address::from(format!("127.0.0.1:{}", port));
+        assert!(resolve_known(&ip, None).is_some());
+        match ConnectingStream::new(&ip, None, TIMEOUT).state {
+            State::Wait(_) => {}
+            _ => panic!("an IP address is expected to be connected right away"),
+        }
+
+        let host = Address::from(format!("localhost:{}", port));
+        assert!(resolve_known(&host, None).is_none());
+        let stream = ConnectingStream::new(&host, None, TIMEOUT);
+        assert!(matches!(stream.state, State::Resolve(_)));
+        let tcp = current_thread::block_on_all(stream).unwrap();
+        assert_eq!(tcp.peer_addr().unwrap().port(), port);
     }
 }
@@ -1,4 +1,4 @@
-use std::{borrow::Cow, io, mem, str::Utf8Error, string::FromUtf8Error, result};
+use std::{borrow::Cow, io, mem, str::Utf8Error, string::FromUtf8Error, result, sync::Arc};
 
 use failure::*;
 use tokio::prelude::*;
@@ -6,7 +6,7 @@ use tokio_timer::timeout::Error as TimeoutError;
 use tokio_timer::Error as TimerError;
 use url::ParseError;
 
-use crate::types::Packet;
+use crate::types::{DdlHostStatus, Packet, SqlType};
 
 /// Result type alias for this library.
 pub type Result<T> = result::Result<T, Error>;
@@ -80,8 +80,81 @@ pub enum DriverError {
     #[fail(display = "Timeout error.")]
     Timeout,
 
+    #[fail(display = "Packet decoding was interrupted.")]
+    DecodeFailed,
+
     #[fail(display = "Invalid utf-8 sequence.")]
     Utf8Error(Utf8Error),
+
+    #[fail(display = "Too many tasks are waiting for a pool connection.")]
+    PoolQueueFull,
+
+    #[fail(
+        display = "Query has {} placeholders, but {} parameters were given.",
+        placeholders, params
+    )]
+    PlaceholderMismatch { placeholders: usize, params: usize },
+
+    #[fail(display = "Unknown setting `{}'.", name)]
+    UnknownSetting { name: String },
+
+    #[fail(display = "Invalid value `{}' for setting `{}'.", value, name)]
+    InvalidSettingValue { name: String, value: String },
+
+    #[fail(display = "Query returned no rows.")]
+    NoRows,
+
+    #[fail(display = "Query returned more than one row.")]
+    TooManyRows,
+
+    #[fail(display = "Query returned {} columns instead of one.", columns)]
+    NotScalar { columns: usize },
+
+    #[fail(
+        display = "Result exceeds the limit of {} bytes, stream its blocks instead.",
+        limit
+    )]
+    ResultTooLarge { limit: usize },
+
+    #[fail(display = "Unknown column `{}'.", name)]
+    UnknownColumn { name: String },
+
+    #[fail(display = "Column `{}' is given more than once.", name)]
+    DuplicateColumn { name: String },
+
+    #[fail(display = "No value for column `{}'.", name)]
+    MissingColumn { name: String },
+
+    #[fail(display = "Invalid row at line {}: {}.", line, message)]
+    InvalidRow { line: usize, message: String },
+
+    #[fail(display = "Mutation `{}' failed: {}", mutation_id, reason)]
+    MutationFailed { mutation_id: String, reason: String },
+
+    #[fail(display = "Distributed DDL failed: {}.", message)]
+    DistributedDdlFailed {
+        message: String,
+        hosts: Vec<DdlHostStatus>,
+    },
+
+    #[fail(display = "Block doesn't match the table: {}.", message)]
+    SchemaMismatch {
+        message: String,
+        columns: Vec<ColumnMismatch>,
+    },
+}
+
+/// Column of an inserted block with a type that can't be stored
+/// in the table column, see `DriverError::SchemaMismatch`.
+#[derive(Clone, Debug)]
+pub struct ColumnMismatch {
+    pub name: String,
+    /// Type of the table column.
+    pub expected: SqlType,
+    /// Type of the block column.
+    pub actual: SqlType,
+    /// Why the column couldn't be cast to the table column type.
+    pub error: Arc<Error>,
 }
 
 /// This type enumerates cast from sql type errors.
@@ -93,11 +166,34 @@ pub enum FromSqlError {
         dst: Cow<'static, str>,
     },
 
+    #[fail(
+        display = "Column `{}' of type SqlType::{} cannot be read as {}.",
+        column, src, dst
+    )]
+    InvalidColumnValue {
+        column: String,
+        src: Cow<'static, str>,
+        dst: Cow<'static, str>,
+    },
+
     #[fail(display = "Out of range.")]
     OutOfRange,
 
     #[fail(display = "Unsupported operation.")]
     UnsupportedOperation,
+
+    #[fail(
+        display = "SqlType::{} cannot be stored in column `{}' of type {}.",
+        src, column, dst
+    )]
+    InvalidColumnType {
+        column: String,
+        src: Cow<'static, str>,
+        dst: Cow<'static, str>,
+    },
+
+    #[fail(display = "Deserialization error: {}", message)]
+    Deserialize { message: String },
 }
 
 impl From<DriverError> for Error {
@@ -0,0 +1,116 @@
+use std::{fmt::Write, str};
+
+use crate::types::ToSql;
+
+/// Quotes an identifier with backticks, e.g. a column or table name.
+///
+/// ```rust
+/// # use clickhouse_rs::escape::{escape_string_literal, quote_identifier, to_literal};
+/// let sql = format!(
+///     "SELECT {} FROM events WHERE name = {} AND id IN {}",
+///     quote_identifier("user id"),
+///     escape_string_literal("O'Brien"),
+///     to_literal(&vec![1_u32, 2]),
+/// );
+/// assert_eq!(
+///     sql,
+///     r"SELECT `user id` FROM events WHERE name = 'O\'Brien' AND id IN [1, 2]"
+/// );
+/// ```
+pub fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+}
+
+/// Quotes `table` or `database.table`.
+pub fn quote_table(name: &str) -> String {
+    name.splitn(2, '.')
+        .map(quote_identifier)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Formats a string as a quoted ClickHouse literal.
+pub fn escape_string_literal(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    write_string(value.as_bytes(), &mut out);
+    out
+}
+
+/// Formats a binary string as a quoted ClickHouse literal, bytes
+/// that aren't printable ASCII are written as `\xNN` escapes.
+pub fn escape_bytes_literal(value: &[u8]) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    write_bytes(value, &mut out);
+    out
+}
+
+/// Formats a value as a ClickHouse literal, the same way query parameters are.
+pub fn to_literal<T>(value: &T) -> String
+where
+    T: ToSql + ?Sized,
+{
+    let mut out = String::new();
+    value.write_sql(&mut out);
+    out
+}
+
+/// Writes a quoted literal of `source`, which is escaped as binary
+/// unless it's valid UTF-8.
+pub(crate) fn write_string(source: &[u8], out: &mut String) {
+    let text = match str::from_utf8(source) {
+        Ok(text) => text,
+        Err(_) => return write_bytes(source, out),
+    };
+
+    out.push('\'');
+    for ch in text.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\0' => out.push_str("\\0"),
+            _ => out.push(ch),
+        }
+    }
+    out.push('\'');
+}
+
+fn write_bytes(source: &[u8], out: &mut String) {
+    out.push('\'');
+    for b in source {
+        match *b {
+            b'\\' => out.push_str("\\\\"),
+            b'\'' => out.push_str("\\'"),
+            0x20..=0x7e => out.push(*b as char),
+            _ => write!(out, "\\x{:02X}", b).unwrap(),
+        }
+    }
+    out.push('\'');
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("analytics"), "`analytics`");
+        assert_eq!(quote_identifier("a`b\\c"), "`a\\`b\\\\c`");
+    }
+
+    #[test]
+    fn test_quote_table() {
+        assert_eq!(quote_table("events"), "`events`");
+        assert_eq!(quote_table("db.events"), "`db`.`events`");
+    }
+
+    #[test]
+    fn test_escape_literals() {
+        assert_eq!(escape_string_literal("it's\t\\"), r"'it\'s\t\\'");
+        assert_eq!(escape_bytes_literal(b"a\xff'\n"), r"'a\xFF\'\x0A'");
+        assert_eq!(to_literal(&Some(42_u8)), "42");
+        assert_eq!(to_literal("x"), "'x'");
+    }
+}
@@ -0,0 +1,539 @@
+//! Reading and writing of `JSONEachRow` data.
+//!
+//! Every row is a JSON object on a line of its own, keyed by column names,
+//! `read_array` and `write_array` handle the same objects in a JSON array.
+//! Values of types that have no JSON counterpart are strings in the same
+//! representation as in `formats::text`, numbers may be given as strings too.
+//! Missing fields of `Nullable` columns are `NULL`s.
+//!
+//! ```rust
+//! # use clickhouse_rs::{formats::json, types::{Block, SqlType}};
+//! let data = "{\"id\":1,\"name\":\"foo\"}\n{\"id\":\"2\",\"name\":\"bar\"}\n";
+//! let schema = vec![("id", SqlType::UInt64), ("name", SqlType::String)];
+//!
+//! let block = json::read(data.as_bytes(), schema).unwrap();
+//! assert_eq!(block.get::<u64, _>(1, "id").unwrap(), 2);
+//!
+//! let mut out = Vec::new();
+//! json::write(&block, &mut out).unwrap();
+//! assert_eq!(out, b"{\"id\":1,\"name\":\"foo\"}\n{\"id\":2,\"name\":\"bar\"}\n");
+//! ```
+
+use std::{
+    fmt::Write as _,
+    io::{BufRead, Write},
+    sync::Arc,
+};
+
+use chrono::prelude::*;
+
+use crate::{
+    errors::{DriverError, Result},
+    formats::text::parse_value,
+    types::{column::Either, Block, BlockBuilder, ColumnType, SqlType, Value},
+};
+
+type ParseResult<T> = std::result::Result<T, String>;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// Reads `JSONEachRow` data into a block, empty lines are skipped.
+///
+/// A row that can't be parsed is reported as `DriverError::InvalidRow`.
+pub fn read<R, S>(reader: R, schema: Vec<(S, SqlType)>) -> Result<Block>
+where
+    R: BufRead,
+    S: Into<String>,
+{
+    let schema: Vec<(String, SqlType)> = schema
+        .into_iter()
+        .map(|(name, sql_type)| (name.into(), sql_type))
+        .collect();
+    let mut builder = BlockBuilder::new(schema.clone())?;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let invalid = |message| DriverError::InvalidRow {
+            line: line_number,
+            message,
+        };
+        let row = parse_row(&line, &schema).map_err(invalid)?;
+        builder
+            .push(row)
+            .map_err(|err| invalid(err.to_string()))?;
+    }
+
+    Ok(builder.build())
+}
+
+/// Writes the rows of `block` as `JSONEachRow`.
+///
+/// `NaN` and infinite floats are written as `null`, strings that aren't
+/// valid UTF-8 have the invalid sequences replaced.
+pub fn write<K, W>(block: &Block<K>, mut writer: W) -> Result<()>
+where
+    K: ColumnType,
+    W: Write,
+{
+    let mut line = String::new();
+    for row in 0..block.row_count() {
+        line.clear();
+        write_row(block, row, &mut line);
+        line.push('\n');
+        writer.write_all(line.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Reads a JSON array of row objects into a block.
+///
+/// A row that can't be parsed is reported as `DriverError::InvalidRow`
+/// with its 1-based position in the array.
+///
+/// ```rust
+/// # use clickhouse_rs::{formats::json, types::SqlType};
+/// let data = r#"[{"id": 1}, {"id": 2}]"#;
+/// let block = json::read_array(data, vec![("id", SqlType::UInt32)]).unwrap();
+/// assert_eq!(json::write_array(&block), r#"[{"id":1},{"id":2}]"#);
+/// ```
+pub fn read_array<S>(text: &str, schema: Vec<(S, SqlType)>) -> Result<Block>
+where
+    S: Into<String>,
+{
+    let schema: Vec<(String, SqlType)> = schema
+        .into_iter()
+        .map(|(name, sql_type)| (name.into(), sql_type))
+        .collect();
+    let mut builder = BlockBuilder::new(schema.clone())?;
+
+    let rows = match Parser::new(text).parse() {
+        Ok(Json::Array(rows)) => rows,
+        Ok(_) => return Err(invalid_array("expected an array".into()).into()),
+        Err(message) => return Err(invalid_array(message).into()),
+    };
+
+    for (index, json) in rows.into_iter().enumerate() {
+        let invalid = |message| DriverError::InvalidRow {
+            line: index + 1,
+            message,
+        };
+        let row = row_values(json, &schema).map_err(invalid)?;
+        builder
+            .push(row)
+            .map_err(|err| invalid(err.to_string()))?;
+    }
+
+    Ok(builder.build())
+}
+
+/// Writes the rows of `block` as a JSON array of objects,
+/// values are written the same way as by `write`.
+pub fn write_array<K: ColumnType>(block: &Block<K>) -> String {
+    let mut out = String::from("[");
+    for row in 0..block.row_count() {
+        if row > 0 {
+            out.push(',');
+        }
+        write_row(block, row, &mut out);
+    }
+    out.push(']');
+    out
+}
+
+/// Error of data that isn't an array of rows.
+fn invalid_array(message: String) -> DriverError {
+    DriverError::InvalidRow { line: 1, message }
+}
+
+fn write_row<K: ColumnType>(block: &Block<K>, row: usize, out: &mut String) {
+    out.push('{');
+    for (i, column) in block.columns().iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_string(column.name(), out);
+        out.push(':');
+        write_value(&Value::from(column.at(row)), out);
+    }
+    out.push('}');
+}
+
+fn parse_row(line: &str, schema: &[(String, SqlType)]) -> ParseResult<Vec<(String, Value)>> {
+    row_values(Parser::new(line).parse()?, schema)
+}
+
+fn row_values(json: Json, schema: &[(String, SqlType)]) -> ParseResult<Vec<(String, Value)>> {
+    let fields = match json {
+        Json::Object(fields) => fields,
+        _ => return Err("expected an object".into()),
+    };
+
+    let mut row = Vec::with_capacity(schema.len());
+    for (name, json) in fields {
+        let sql_type = match schema.iter().find(|(column, _)| *column == name) {
+            Some((_, sql_type)) => *sql_type,
+            None => return Err(format!("unexpected column `{}'", name)),
+        };
+        let value =
+            to_value(json, sql_type).map_err(|message| format!("column `{}': {}", name, message))?;
+        row.push((name, value));
+    }
+
+    for (name, sql_type) in schema {
+        if let SqlType::Nullable(inner) = sql_type {
+            if !row.iter().any(|(column, _)| column == name) {
+                row.push((name.clone(), Value::Nullable(Either::Left(*inner))));
+            }
+        }
+    }
+    Ok(row)
+}
+
+fn to_value(json: Json, sql_type: SqlType) -> ParseResult<Value> {
+    match (json, sql_type) {
+        (Json::Null, SqlType::Nullable(inner)) => Ok(Value::Nullable(Either::Left(inner))),
+        (Json::Null, _) => Err("unexpected null".into()),
+        (json, SqlType::Nullable(inner)) => Ok(Value::Nullable(Either::Right(Box::new(
+            to_value(json, *inner)?,
+        )))),
+        (Json::Array(items), SqlType::Array(inner)) => {
+            let mut values = Vec::with_capacity(items.len());
+            for item in items {
+                values.push(to_value(item, *inner)?);
+            }
+            Ok(Value::Array(inner, Arc::new(values)))
+        }
+        (Json::Number(text), sql_type) | (Json::String(text), sql_type) => {
+            parse_value(&text, sql_type)
+        }
+        (Json::Bool(value), sql_type) => parse_value(if value { "1" } else { "0" }, sql_type),
+        (Json::Array(_), _) => Err("unexpected array".into()),
+        (Json::Object(_), _) => Err("unexpected object".into()),
+    }
+}
+
+fn write_value(value: &Value, out: &mut String) {
+    match value {
+        Value::String(bytes) => write_string(&String::from_utf8_lossy(bytes), out),
+        Value::Float32(v) if !v.is_finite() => out.push_str("null"),
+        Value::Float64(v) if !v.is_finite() => out.push_str("null"),
+        Value::Date(..) => write_string(&value.to_string(), out),
+        Value::DateTime(time, tz) => {
+            let time = tz.timestamp_opt(i64::from(*time), 0).unwrap();
+            write_string(&time.format("%Y-%m-%d %H:%M:%S").to_string(), out);
+        }
+        Value::Nullable(Either::Left(_)) => out.push_str("null"),
+        Value::Nullable(Either::Right(value)) => write_value(value, out),
+        Value::Array(_, values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(value, out);
+            }
+            out.push(']');
+        }
+        _ => write!(out, "{}", value).unwrap(),
+    }
+}
+
+fn write_string(text: &str, out: &mut String) {
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => write!(out, "\\u{:04x}", ch as u32).unwrap(),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+/// Parser of a single JSON value.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn parse(mut self) -> ParseResult<Json> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.pos < self.text.len() {
+            return Err(format!("unexpected data at {}", self.pos + 1));
+        }
+        Ok(value)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(ch) = self.peek().filter(|ch| ch.is_whitespace()) {
+            self.pos += ch.len_utf8();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> ParseResult<()> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(ch) if ch == expected => Ok(()),
+            Some(ch) => Err(format!("expected `{}', found `{}'", expected, ch)),
+            None => Err(format!("expected `{}', found the end of line", expected)),
+        }
+    }
+
+    fn parse_value(&mut self) -> ParseResult<Json> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('-') | Some('0'..='9') => Ok(self.parse_number()),
+            Some(_) => self.parse_literal(),
+            None => Err("unexpected end of line".into()),
+        }
+    }
+
+    fn parse_object(&mut self) -> ParseResult<Json> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let name = self.parse_string()?;
+            self.expect(':')?;
+            fields.push((name, self.parse_value()?));
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return Err("expected `,' or `}'".into()),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> ParseResult<Json> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Json::Array(items)),
+                _ => return Err("expected `,' or `]'".into()),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> ParseResult<String> {
+        if self.next() != Some('"') {
+            return Err("expected a string".into());
+        }
+
+        let mut result = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(result),
+                Some('\\') => match self.next() {
+                    Some('"') => result.push('"'),
+                    Some('\\') => result.push('\\'),
+                    Some('/') => result.push('/'),
+                    Some('b') => result.push('\u{8}'),
+                    Some('f') => result.push('\u{c}'),
+                    Some('n') => result.push('\n'),
+                    Some('r') => result.push('\r'),
+                    Some('t') => result.push('\t'),
+                    Some('u') => result.push(self.parse_unicode_escape()?),
+                    _ => return Err("invalid escape sequence".into()),
+                },
+                Some(ch) => result.push(ch),
+                None => return Err("unterminated string".into()),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> ParseResult<u32> {
+        let digits = self.text.get(self.pos..self.pos + 4).unwrap_or("");
+        let code = u32::from_str_radix(digits, 16).map_err(|_| "invalid escape sequence")?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    /// Parses `XXXX` of a `\uXXXX` escape, along with the low surrogate if any.
+    fn parse_unicode_escape(&mut self) -> ParseResult<char> {
+        let mut code = self.parse_hex4()?;
+        if (0xd800..0xdc00).contains(&code) && self.text[self.pos..].starts_with("\\u") {
+            self.pos += 2;
+            let low = self.parse_hex4()?;
+            code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+        }
+        std::char::from_u32(code).ok_or_else(|| "invalid escape sequence".into())
+    }
+
+    fn parse_number(&mut self) -> Json {
+        let start = self.pos;
+        while matches!(self.peek(), Some(ch) if ch.is_ascii_digit() || "+-.eE".contains(ch)) {
+            self.pos += 1;
+        }
+        Json::Number(self.text[start..self.pos].to_string())
+    }
+
+    fn parse_literal(&mut self) -> ParseResult<Json> {
+        for (literal, value) in &[
+            ("null", Json::Null),
+            ("true", Json::Bool(true)),
+            ("false", Json::Bool(false)),
+        ] {
+            if self.text[self.pos..].starts_with(literal) {
+                self.pos += literal.len();
+                return Ok(value.clone());
+            }
+        }
+        Err(format!("unexpected data at {}", self.pos + 1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono_tz::Tz;
+
+    use crate::{errors::Error, types::Decimal};
+
+    use super::*;
+
+    fn schema() -> Vec<(&'static str, SqlType)> {
+        vec![
+            ("id", SqlType::UInt64),
+            ("name", SqlType::Nullable(SqlType::String.into())),
+            ("tags", SqlType::Array(SqlType::String.into())),
+            ("price", SqlType::Decimal(9, 2)),
+            ("day", SqlType::Date),
+        ]
+    }
+
+    #[test]
+    fn test_read() {
+        let data = r#"{"id":1,"name":"a \"b\"\né😀","tags":["x","y"],"price":12.5,"day":"2019-03-14"}
+
+            { "day": "1970-01-02", "price": "-0.01", "tags": [], "id": "2" }
+        "#;
+
+        let block = read(data.as_bytes(), schema()).unwrap();
+        assert_eq!(block.row_count(), 2);
+        assert_eq!(
+            block.get::<Option<&str>, _>(0, "name").unwrap(),
+            Some("a \"b\"\né😀")
+        );
+        assert_eq!(block.get::<Option<&str>, _>(1, "name").unwrap(), None);
+        assert_eq!(block.get::<Vec<&str>, _>(0, "tags").unwrap(), vec!["x", "y"]);
+        assert_eq!(block.get::<u64, _>(1, "id").unwrap(), 2);
+        assert_eq!(block.get::<Decimal, _>(1, "price").unwrap(), Decimal::of(-0.01, 2));
+    }
+
+    #[test]
+    fn test_invalid_rows() {
+        let schema = vec![("id", SqlType::UInt8)];
+        for (data, expected_line) in &[
+            ("{\"id\":1}\n{\"id\":256}", 2),
+            ("{\"id\":1,\"x\":2}", 1),
+            ("{\"id\":1", 1),
+            ("[1]", 1),
+            ("\n{}", 2),
+        ] {
+            match read(data.as_bytes(), schema.clone()) {
+                Err(Error::Driver(DriverError::InvalidRow { line, .. })) => {
+                    assert_eq!(line, *expected_line)
+                }
+                result => panic!("unexpected result {:?}", result.is_ok()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec![Some("tab\t\"q\""), None])
+            .column("ratio", vec![0.5_f64, f64::NAN])
+            .column("tags", vec![vec!["a"], vec![]])
+            .column("day", vec![Tz::UTC.ymd(2019, 3, 14); 2]);
+
+        let mut out = Vec::new();
+        write(&block, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"id\":1,\"name\":\"tab\\t\\\"q\\\"\",\"ratio\":0.5,\"tags\":[\"a\"],\"day\":\"2019-03-14\"}\n\
+             {\"id\":2,\"name\":null,\"ratio\":null,\"tags\":[],\"day\":\"2019-03-14\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_array() {
+        let data = r#"[
+            {"id": 1, "name": "a", "tags": ["x"], "price": 1.5, "day": "2019-03-14"},
+            {"id": 2, "tags": [], "price": 0, "day": "2019-03-15"}
+        ]"#;
+
+        let block = Block::from_json(data, schema()).unwrap();
+        assert_eq!(block.row_count(), 2);
+        assert_eq!(block.get::<Option<&str>, _>(1, "name").unwrap(), None);
+        assert_eq!(
+            block.to_json(),
+            "[{\"id\":1,\"name\":\"a\",\"tags\":[\"x\"],\"price\":1.50,\"day\":\"2019-03-14\"},\
+             {\"id\":2,\"name\":null,\"tags\":[],\"price\":0.00,\"day\":\"2019-03-15\"}]"
+        );
+        assert_eq!(Block::new().to_json(), "[]");
+
+        for (data, expected_line) in &[("{\"id\":1}", 1), ("[{\"id\":1}, {}]", 2), ("[", 1)] {
+            match read_array(data, vec![("id", SqlType::UInt8)]) {
+                Err(Error::Driver(DriverError::InvalidRow { line, .. })) => {
+                    assert_eq!(line, *expected_line)
+                }
+                result => panic!("unexpected result {:?}", result.is_ok()),
+            }
+        }
+    }
+}
@@ -0,0 +1,6 @@
+/// `JSONEachRow` format.
+pub mod json;
+/// `RowBinary` and `RowBinaryWithNamesAndTypes` formats.
+pub mod rowbinary;
+/// `CSV` and `TabSeparated` formats.
+pub mod text;
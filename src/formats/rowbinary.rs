@@ -0,0 +1,381 @@
+//! Encoding of blocks and rows into the `RowBinary` format and back.
+//!
+//! `RowBinary` is the data written row by row, every value in the
+//! little-endian binary representation of its type. In the
+//! `RowBinaryWithNamesAndTypes` variant the data is preceded by the number
+//! of columns, their names and their types.
+//!
+//! ```rust
+//! # use clickhouse_rs::{formats::rowbinary, types::{Block, SqlType}};
+//! let block = Block::new()
+//!     .column("id", vec![1_u32, 2])
+//!     .column("name", vec!["foo", "bar"]);
+//!
+//! let data = rowbinary::encode_block_with_names_and_types(&block).unwrap();
+//! let decoded = rowbinary::decode_with_names_and_types(&data).unwrap();
+//! assert_eq!(decoded.get::<&str, _>(1, "name").unwrap(), "bar");
+//!
+//! let data = rowbinary::encode_block(&block).unwrap();
+//! let schema = vec![("id", SqlType::UInt32), ("name", SqlType::String)];
+//! let decoded = rowbinary::decode(&data, schema).unwrap();
+//! assert_eq!(decoded.get::<u32, _>(1, "id").unwrap(), 2);
+//! ```
+
+use std::{io, sync::Arc};
+
+use chrono_tz::Tz;
+
+use crate::{
+    binary::{Encoder, ReadEx},
+    errors::{Error, FromSqlError, Result},
+    types::{
+        column::{parse_sql_type, Either},
+        decimal::NoBits,
+        Block, BlockBuilder, ColumnType, Decimal, InsertRow, SqlType, Value,
+    },
+};
+
+/// Encodes `block` in the `RowBinary` format.
+pub fn encode_block<K: ColumnType>(block: &Block<K>) -> Result<Vec<u8>> {
+    let mut encoder = Encoder::new();
+    write_block(&mut encoder, block)?;
+    Ok(encoder.get_buffer())
+}
+
+/// Encodes `block` in the `RowBinaryWithNamesAndTypes` format.
+pub fn encode_block_with_names_and_types<K: ColumnType>(block: &Block<K>) -> Result<Vec<u8>> {
+    let mut encoder = Encoder::new();
+    let schema: Vec<_> = block
+        .columns()
+        .iter()
+        .map(|column| (column.name(), column.sql_type()))
+        .collect();
+    write_header(&mut encoder, &schema);
+    write_block(&mut encoder, block)?;
+    Ok(encoder.get_buffer())
+}
+
+/// Encodes `rows` in the `RowBinary` format.
+pub fn encode_rows<I, T>(rows: I) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = T>,
+    T: InsertRow,
+{
+    let mut encoder = Encoder::new();
+    write_rows(&mut encoder, rows)?;
+    Ok(encoder.get_buffer())
+}
+
+/// Encodes `rows` in the `RowBinaryWithNamesAndTypes` format.
+pub fn encode_rows_with_names_and_types<I, T>(rows: I) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = T>,
+    T: InsertRow,
+{
+    let mut encoder = Encoder::new();
+    write_header(&mut encoder, &T::columns());
+    write_rows(&mut encoder, rows)?;
+    Ok(encoder.get_buffer())
+}
+
+/// Decodes `RowBinary` data, the format doesn't carry the columns
+/// so they have to be given in `schema`.
+pub fn decode<S>(mut data: &[u8], schema: Vec<(S, SqlType)>) -> Result<Block>
+where
+    S: Into<String>,
+{
+    let schema: Vec<(String, SqlType)> = schema
+        .into_iter()
+        .map(|(name, sql_type)| (name.into(), sql_type))
+        .collect();
+    read_rows(&mut data, schema).map_err(map_eof)
+}
+
+/// Decodes `RowBinaryWithNamesAndTypes` data.
+pub fn decode_with_names_and_types(data: &[u8]) -> Result<Block> {
+    read_with_names_and_types(data).map_err(map_eof)
+}
+
+fn read_with_names_and_types(mut data: &[u8]) -> Result<Block> {
+    let count = data.read_uvarint()? as usize;
+
+    let mut names = Vec::with_capacity(count);
+    for _ in 0..count {
+        names.push(data.read_string()?);
+    }
+
+    let mut schema = Vec::with_capacity(count);
+    for name in names {
+        let type_name = data.read_string()?;
+        match parse_sql_type(&type_name) {
+            Some(sql_type) => schema.push((name, sql_type)),
+            None => {
+                let message = format!("Unsupported column type \"{}\".", type_name);
+                return Err(message.into());
+            }
+        }
+    }
+
+    read_rows(&mut data, schema)
+}
+
+fn write_header(encoder: &mut Encoder, schema: &[(&str, SqlType)]) {
+    encoder.uvarint(schema.len() as u64);
+    for (name, _) in schema {
+        encoder.string(name);
+    }
+    for (_, sql_type) in schema {
+        encoder.string(sql_type.to_string());
+    }
+}
+
+fn write_block<K: ColumnType>(encoder: &mut Encoder, block: &Block<K>) -> Result<()> {
+    for row in 0..block.row_count() {
+        for column in block.columns() {
+            write_value(encoder, column.sql_type(), &column.at(row).into())?;
+        }
+    }
+    Ok(())
+}
+
+fn write_rows<I, T>(encoder: &mut Encoder, rows: I) -> Result<()>
+where
+    I: IntoIterator<Item = T>,
+    T: InsertRow,
+{
+    let columns = T::columns();
+    for row in rows {
+        for ((_, sql_type), value) in columns.iter().zip(row.values()) {
+            write_value(encoder, *sql_type, &value)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_value(encoder: &mut Encoder, sql_type: SqlType, value: &Value) -> Result<()> {
+    match (sql_type, value) {
+        (SqlType::UInt8, Value::UInt8(v)) => encoder.write(*v),
+        (SqlType::UInt16, Value::UInt16(v)) => encoder.write(*v),
+        (SqlType::UInt32, Value::UInt32(v)) => encoder.write(*v),
+        (SqlType::UInt64, Value::UInt64(v)) => encoder.write(*v),
+        (SqlType::Int8, Value::Int8(v)) => encoder.write(*v),
+        (SqlType::Int16, Value::Int16(v)) => encoder.write(*v),
+        (SqlType::Int32, Value::Int32(v)) => encoder.write(*v),
+        (SqlType::Int64, Value::Int64(v)) => encoder.write(*v),
+        (SqlType::Float32, Value::Float32(v)) => encoder.write(*v),
+        (SqlType::Float64, Value::Float64(v)) => encoder.write(*v),
+        (SqlType::String, Value::String(v)) => encoder.byte_string(v.as_slice()),
+        (SqlType::FixedString(len), Value::String(v)) if v.len() <= len => {
+            encoder.write_bytes(v);
+            encoder.write_bytes(&vec![0_u8; len - v.len()]);
+        }
+        (SqlType::Date, Value::Date(v, _)) => encoder.write(*v),
+        (SqlType::DateTime, Value::DateTime(v, _)) => encoder.write(*v),
+        (SqlType::Nullable(_), Value::Nullable(Either::Left(_))) => encoder.write(1_u8),
+        (SqlType::Nullable(inner), Value::Nullable(Either::Right(v))) => {
+            encoder.write(0_u8);
+            write_value(encoder, *inner, v)?;
+        }
+        (SqlType::Array(inner), Value::Array(_, vs)) => {
+            encoder.uvarint(vs.len() as u64);
+            for v in vs.iter() {
+                write_value(encoder, *inner, v)?;
+            }
+        }
+        (SqlType::Decimal(precision, scale), Value::Decimal(v)) => {
+            let v = v.clone().set_scale(scale);
+            match NoBits::from_precision(precision) {
+                Some(NoBits::N32) => encoder.write(v.underlying as i32),
+                Some(NoBits::N64) => encoder.write(v.underlying),
+                None => return Err(Error::FromSql(FromSqlError::OutOfRange)),
+            }
+        }
+        _ => {
+            return Err(Error::FromSql(FromSqlError::InvalidType {
+                src: SqlType::from(value.clone()).to_string(),
+                dst: sql_type.to_string(),
+            }))
+        }
+    }
+    Ok(())
+}
+
+fn read_rows(data: &mut &[u8], schema: Vec<(String, SqlType)>) -> Result<Block> {
+    let types: Vec<SqlType> = schema.iter().map(|(_, sql_type)| *sql_type).collect();
+    let names: Vec<String> = schema.iter().map(|(name, _)| name.clone()).collect();
+    let mut builder = BlockBuilder::new(schema)?;
+
+    while !data.is_empty() {
+        let mut row = Vec::with_capacity(types.len());
+        for (name, sql_type) in names.iter().zip(&types) {
+            row.push((name, read_value(data, *sql_type)?));
+        }
+        builder.push(row)?;
+    }
+
+    Ok(builder.build())
+}
+
+fn read_value(data: &mut &[u8], sql_type: SqlType) -> Result<Value> {
+    Ok(match sql_type {
+        SqlType::UInt8 => Value::UInt8(data.read_scalar()?),
+        SqlType::UInt16 => Value::UInt16(data.read_scalar()?),
+        SqlType::UInt32 => Value::UInt32(data.read_scalar()?),
+        SqlType::UInt64 => Value::UInt64(data.read_scalar()?),
+        SqlType::Int8 => Value::Int8(data.read_scalar()?),
+        SqlType::Int16 => Value::Int16(data.read_scalar()?),
+        SqlType::Int32 => Value::Int32(data.read_scalar()?),
+        SqlType::Int64 => Value::Int64(data.read_scalar()?),
+        SqlType::Float32 => Value::Float32(data.read_scalar()?),
+        SqlType::Float64 => Value::Float64(data.read_scalar()?),
+        SqlType::String => {
+            let len = data.read_uvarint()? as usize;
+            Value::String(Arc::new(read_bytes(data, len)?))
+        }
+        SqlType::FixedString(len) => Value::String(Arc::new(read_bytes(data, len)?)),
+        SqlType::Date => Value::Date(data.read_scalar()?, Tz::UTC),
+        SqlType::DateTime => Value::DateTime(data.read_scalar()?, Tz::UTC),
+        SqlType::Nullable(inner) => {
+            let is_null: u8 = data.read_scalar()?;
+            if is_null != 0 {
+                Value::Nullable(Either::Left(inner))
+            } else {
+                Value::Nullable(Either::Right(Box::new(read_value(data, *inner)?)))
+            }
+        }
+        SqlType::Array(inner) => {
+            let len = data.read_uvarint()? as usize;
+            let mut values = Vec::with_capacity(len.min(data.len()));
+            for _ in 0..len {
+                values.push(read_value(data, *inner)?);
+            }
+            Value::Array(inner, Arc::new(values))
+        }
+        SqlType::Decimal(precision, scale) => {
+            let (nobits, underlying) = match NoBits::from_precision(precision) {
+                Some(NoBits::N32) => (NoBits::N32, i64::from(data.read_scalar::<i32>()?)),
+                Some(NoBits::N64) => (NoBits::N64, data.read_scalar::<i64>()?),
+                None => return Err(Error::FromSql(FromSqlError::OutOfRange)),
+            };
+            Value::Decimal(Decimal {
+                underlying,
+                nobits,
+                precision,
+                scale,
+            })
+        }
+    })
+}
+
+fn read_bytes(data: &mut &[u8], len: usize) -> Result<Vec<u8>> {
+    if data.len() < len {
+        return Err(truncated());
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Ok(head.to_vec())
+}
+
+fn truncated() -> Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated RowBinary data").into()
+}
+
+/// Reading past the end of a slice is reported as `WouldBlock`,
+/// for a complete buffer it means that the data is truncated.
+fn map_eof(err: Error) -> Error {
+    match err {
+        Error::Io(ref e) if e.kind() == io::ErrorKind::WouldBlock => truncated(),
+        err => err,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Point {
+        name: &'static str,
+        x: Option<f64>,
+        tags: Vec<u8>,
+    }
+
+    impl InsertRow for Point {
+        fn columns() -> Vec<(&'static str, SqlType)> {
+            vec![
+                ("name", SqlType::String),
+                ("x", SqlType::Nullable(SqlType::Float64.into())),
+                ("tags", SqlType::Array(SqlType::UInt8.into())),
+            ]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![
+                self.name.into(),
+                self.x.into(),
+                Value::Array(
+                    SqlType::UInt8.into(),
+                    Arc::new(self.tags.iter().map(|t| Value::UInt8(*t)).collect()),
+                ),
+            ]
+        }
+    }
+
+    #[test]
+    fn test_encode_values() {
+        let block = Block::new()
+            .column("id", vec![1_u16, 258])
+            .column("name", vec!["a", "bc"])
+            .column("x", vec![None, Some(-1_i8)]);
+
+        assert_eq!(
+            encode_block(&block).unwrap(),
+            vec![1, 0, 1, b'a', 1, 2, 1, 2, b'b', b'c', 0, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_rows_round_trip() {
+        let rows = vec![
+            Point {
+                name: "a",
+                x: Some(0.5),
+                tags: vec![1, 2],
+            },
+            Point {
+                name: "b",
+                x: None,
+                tags: vec![],
+            },
+        ];
+
+        let data = encode_rows_with_names_and_types(rows).unwrap();
+        let block = decode_with_names_and_types(&data).unwrap();
+
+        assert_eq!(block.row_count(), 2);
+        assert_eq!(block.get::<&str, _>(0, "name").unwrap(), "a");
+        assert_eq!(block.get::<Option<f64>, _>(0, "x").unwrap(), Some(0.5));
+        assert_eq!(block.get::<Option<f64>, _>(1, "x").unwrap(), None);
+        assert_eq!(block.get::<Vec<u8>, _>(0, "tags").unwrap(), vec![1, 2]);
+
+        assert_eq!(encode_block_with_names_and_types(&block).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decimal_and_fixed_string() {
+        let schema = vec![
+            ("code", SqlType::FixedString(3)),
+            ("price", SqlType::Decimal(9, 2)),
+        ];
+        let data = vec![b'a', b'b', 0, 0x39, 0x30, 0, 0];
+
+        let block = decode(&data, schema).unwrap();
+        assert_eq!(block.get::<Decimal, _>(0, "price").unwrap(), Decimal::of(123.45, 2));
+        assert_eq!(encode_block(&block).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        let schema = vec![("id", SqlType::UInt32), ("name", SqlType::String)];
+        decode(&[1, 0, 0, 0, 5, b'a'], schema).unwrap_err();
+    }
+}
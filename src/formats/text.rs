@@ -0,0 +1,618 @@
+//! Reading of `CSV` and `TabSeparated` data into blocks.
+//!
+//! Values are parsed according to the given schema, in the same
+//! representation ClickHouse uses for these formats: `\N` is `NULL`,
+//! dates are `YYYY-MM-DD`, times are `YYYY-MM-DD hh:mm:ss` in UTC and
+//! arrays are literals like `[1, 2]` or `['a', 'b']`.
+//!
+//! ```rust
+//! # use clickhouse_rs::{formats::text::TextFormat, types::SqlType};
+//! let data = "id,name\n1,foo\n2,\"bar, baz\"\n";
+//! let schema = vec![("id", SqlType::UInt32), ("name", SqlType::String)];
+//!
+//! let block = TextFormat::csv()
+//!     .with_header(true)
+//!     .read(data.as_bytes(), schema)
+//!     .unwrap();
+//! assert_eq!(block.get::<&str, _>(1, "name").unwrap(), "bar, baz");
+//! ```
+
+use std::{io::BufRead, str::FromStr, sync::Arc};
+
+use chrono::prelude::*;
+use chrono_tz::Tz;
+
+use crate::{
+    errors::{DriverError, Result},
+    types::{
+        column::Either,
+        decimal::NoBits,
+        Block, BlockBuilder, Decimal, SqlType, Value,
+    },
+};
+
+const UNIX_EPOCH_DAY: i64 = 719_163;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Csv,
+    Tsv,
+}
+
+/// Settings of a text format.
+#[derive(Clone, Copy, Debug)]
+pub struct TextFormat {
+    kind: Kind,
+    header: bool,
+    block_size: usize,
+}
+
+impl TextFormat {
+    /// `CSV` with `,` delimiter and `"` quoted fields.
+    pub fn csv() -> Self {
+        Self {
+            kind: Kind::Csv,
+            header: false,
+            block_size: 65_536,
+        }
+    }
+
+    /// `TabSeparated` with backslash escaped fields.
+    pub fn tsv() -> Self {
+        Self {
+            kind: Kind::Tsv,
+            ..Self::csv()
+        }
+    }
+
+    /// If `true` the first row holds column names, columns may be given
+    /// in any order then.
+    pub fn with_header(self, header: bool) -> Self {
+        Self { header, ..self }
+    }
+
+    /// Maximum number of rows in a block returned by `blocks`.
+    pub fn with_block_size(self, block_size: usize) -> Self {
+        Self {
+            block_size: block_size.max(1),
+            ..self
+        }
+    }
+
+    /// Reads the whole data into a single block.
+    pub fn read<R, S>(&self, reader: R, schema: Vec<(S, SqlType)>) -> Result<Block>
+    where
+        R: BufRead,
+        S: Into<String>,
+    {
+        let mut blocks = self.with_block_size(usize::MAX).blocks(reader, schema)?;
+        match blocks.next() {
+            Some(block) => block,
+            None => Ok(BlockBuilder::new(blocks.schema())?.build()),
+        }
+    }
+
+    /// Returns an iterator over blocks of at most `block_size` rows.
+    /// Empty lines are skipped.
+    ///
+    /// A row that can't be parsed is reported as `DriverError::InvalidRow`,
+    /// the iteration may be continued with the next row after that.
+    pub fn blocks<R, S>(&self, reader: R, schema: Vec<(S, SqlType)>) -> Result<Blocks<R>>
+    where
+        R: BufRead,
+        S: Into<String>,
+    {
+        let schema: Vec<(String, SqlType)> = schema
+            .into_iter()
+            .map(|(name, sql_type)| (name.into(), sql_type))
+            .collect();
+
+        let mut blocks = Blocks {
+            reader,
+            format: *self,
+            order: (0..schema.len()).collect(),
+            schema,
+            builder: None,
+            line: 0,
+        };
+
+        if self.header {
+            blocks.read_header()?;
+        }
+        Ok(blocks)
+    }
+}
+
+/// Iterator over blocks read from a text format.
+pub struct Blocks<R> {
+    reader: R,
+    format: TextFormat,
+    schema: Vec<(String, SqlType)>,
+    /// Column index of every field of a row.
+    order: Vec<usize>,
+    builder: Option<BlockBuilder>,
+    line: usize,
+}
+
+impl<R: BufRead> Blocks<R> {
+    fn schema(&self) -> Vec<(String, SqlType)> {
+        self.schema.clone()
+    }
+
+    fn read_header(&mut self) -> Result<()> {
+        let line = self.line + 1;
+        let fields = match self.read_record()? {
+            None => return Ok(()),
+            Some(fields) => fields,
+        };
+
+        let mut order = Vec::with_capacity(fields.len());
+        for field in fields {
+            let name = field.unwrap_or_default();
+            match self.schema.iter().position(|(column, _)| *column == name) {
+                Some(index) if !order.contains(&index) => order.push(index),
+                _ => {
+                    let message = format!("unexpected column `{}'", name);
+                    return Err(DriverError::InvalidRow { line, message }.into());
+                }
+            }
+        }
+
+        if order.len() != self.schema.len() {
+            let message = format!("expected {} columns", self.schema.len());
+            return Err(DriverError::InvalidRow { line, message }.into());
+        }
+
+        self.order = order;
+        Ok(())
+    }
+
+    /// Reads a record, `None` fields are `NULL`s.
+    fn read_record(&mut self) -> Result<Option<Vec<Option<String>>>> {
+        let mut line = String::new();
+        loop {
+            if self.reader.read_line(&mut line)? == 0 {
+                if line.is_empty() {
+                    return Ok(None);
+                }
+                break;
+            }
+            self.line += 1;
+
+            if self.format.kind == Kind::Tsv || !has_open_quote(&line) {
+                break;
+            }
+        }
+
+        while line.ends_with('\n') || line.ends_with('\r') {
+            line.pop();
+        }
+
+        Ok(Some(match self.format.kind {
+            Kind::Csv => split_csv(&line),
+            Kind::Tsv => line.split('\t').map(unescape_tsv).collect(),
+        }))
+    }
+
+    fn parse_row(&self, fields: Vec<Option<String>>) -> std::result::Result<Vec<Value>, String> {
+        if fields.len() != self.schema.len() {
+            return Err(format!(
+                "expected {} fields, found {}",
+                self.schema.len(),
+                fields.len()
+            ));
+        }
+
+        let mut values = vec![None; self.schema.len()];
+        for (field, &index) in fields.into_iter().zip(&self.order) {
+            let (name, sql_type) = &self.schema[index];
+            let value = parse_field(field, *sql_type)
+                .map_err(|message| format!("column `{}': {}", name, message))?;
+            values[index] = Some(value);
+        }
+        Ok(values.into_iter().map(Option::unwrap).collect())
+    }
+}
+
+impl<R: BufRead> Iterator for Blocks<R> {
+    type Item = Result<Block>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.line + 1;
+            let fields = match self.read_record() {
+                Ok(Some(fields)) => fields,
+                Ok(None) => return self.builder.take().map(|builder| Ok(builder.build())),
+                Err(err) => return Some(Err(err)),
+            };
+
+            if fields.len() == 1 && fields[0].as_deref() == Some("") {
+                continue;
+            }
+
+            let values = match self.parse_row(fields) {
+                Ok(values) => values,
+                Err(message) => return Some(Err(DriverError::InvalidRow { line, message }.into())),
+            };
+
+            if self.builder.is_none() {
+                match BlockBuilder::new(self.schema()) {
+                    Ok(builder) => self.builder = Some(builder),
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+
+            let builder = self.builder.as_mut().unwrap();
+            let row = self.schema.iter().map(|(name, _)| name).zip(values);
+            if let Err(err) = builder.push(row) {
+                let message = err.to_string();
+                return Some(Err(DriverError::InvalidRow { line, message }.into()));
+            }
+
+            if builder.row_count() >= self.format.block_size {
+                return self.builder.take().map(|builder| Ok(builder.build()));
+            }
+        }
+    }
+}
+
+/// Checks if the `CSV` record collected in `line` continues on the next line.
+fn has_open_quote(line: &str) -> bool {
+    let (mut open, mut field_start, mut closed) = (false, true, false);
+    for ch in line.chars() {
+        if open {
+            if ch == '"' {
+                open = false;
+                closed = true;
+            }
+            continue;
+        }
+        open = ch == '"' && (field_start || closed);
+        closed = false;
+        field_start = ch == ',';
+    }
+    open
+}
+
+fn split_csv(line: &str) -> Vec<Option<String>> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        let mut field = String::new();
+        let quoted = chars.peek() == Some(&'"');
+        if quoted {
+            chars.next();
+            while let Some(ch) = chars.next() {
+                if ch == '"' {
+                    if chars.peek() != Some(&'"') {
+                        break;
+                    }
+                    chars.next();
+                }
+                field.push(ch);
+            }
+        }
+
+        let mut delimited = false;
+        for ch in chars.by_ref() {
+            if ch == ',' {
+                delimited = true;
+                break;
+            }
+            field.push(ch);
+        }
+
+        fields.push(if !quoted && field == "\\N" {
+            None
+        } else {
+            Some(field)
+        });
+
+        if !delimited {
+            return fields;
+        }
+    }
+}
+
+fn unescape_tsv(field: &str) -> Option<String> {
+    if field == "\\N" {
+        return None;
+    }
+
+    let mut result = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => result.push('\x08'),
+            Some('f') => result.push('\x0c'),
+            Some('r') => result.push('\r'),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('0') => result.push('\0'),
+            Some(ch) => result.push(ch),
+            None => result.push('\\'),
+        }
+    }
+    Some(result)
+}
+
+type ParseResult<T> = std::result::Result<T, String>;
+
+fn parse_field(field: Option<String>, sql_type: SqlType) -> ParseResult<Value> {
+    match (field, sql_type) {
+        (None, SqlType::Nullable(inner)) => Ok(Value::Nullable(Either::Left(inner))),
+        (None, _) => Err("unexpected NULL".into()),
+        (Some(text), sql_type) => parse_value(&text, sql_type),
+    }
+}
+
+fn parse_number<T: FromStr>(text: &str) -> ParseResult<T> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("invalid number `{}'", text))
+}
+
+pub(crate) fn parse_value(text: &str, sql_type: SqlType) -> ParseResult<Value> {
+    Ok(match sql_type {
+        SqlType::UInt8 => Value::UInt8(parse_number(text)?),
+        SqlType::UInt16 => Value::UInt16(parse_number(text)?),
+        SqlType::UInt32 => Value::UInt32(parse_number(text)?),
+        SqlType::UInt64 => Value::UInt64(parse_number(text)?),
+        SqlType::Int8 => Value::Int8(parse_number(text)?),
+        SqlType::Int16 => Value::Int16(parse_number(text)?),
+        SqlType::Int32 => Value::Int32(parse_number(text)?),
+        SqlType::Int64 => Value::Int64(parse_number(text)?),
+        SqlType::Float32 => Value::Float32(parse_number(text)?),
+        SqlType::Float64 => Value::Float64(parse_number(text)?),
+        SqlType::String => Value::from(text),
+        SqlType::FixedString(len) if text.len() <= len => Value::from(text),
+        SqlType::FixedString(len) => {
+            return Err(format!("`{}' is longer than {} bytes", text, len));
+        }
+        SqlType::Date => {
+            let date = NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+                .map_err(|_| format!("invalid date `{}'", text))?;
+            let days = i64::from(date.num_days_from_ce()) - UNIX_EPOCH_DAY;
+            if days < 0 || days > i64::from(u16::MAX) {
+                return Err(format!("date `{}' is out of range", text));
+            }
+            Value::Date(days as u16, Tz::UTC)
+        }
+        SqlType::DateTime => {
+            let text = text.trim();
+            let time = match NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S") {
+                Ok(time) => Tz::UTC.from_utc_datetime(&time).timestamp(),
+                Err(_) => parse_number::<i64>(text)
+                    .map_err(|_| format!("invalid date and time `{}'", text))?,
+            };
+            if time < 0 || time > i64::from(u32::MAX) {
+                return Err(format!("date and time `{}' is out of range", text));
+            }
+            Value::DateTime(time as u32, Tz::UTC)
+        }
+        SqlType::Nullable(inner) => {
+            Value::Nullable(Either::Right(Box::new(parse_value(text, *inner)?)))
+        }
+        SqlType::Array(inner) => {
+            let mut values = Vec::new();
+            for item in split_array(text)? {
+                values.push(parse_field(item, *inner)?);
+            }
+            Value::Array(inner, Arc::new(values))
+        }
+        SqlType::Decimal(precision, scale) => parse_decimal(text, precision, scale)?,
+    })
+}
+
+fn parse_decimal(text: &str, precision: u8, scale: u8) -> ParseResult<Value> {
+    let invalid = || format!("invalid decimal `{}'", text);
+    let trimmed = text.trim();
+
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed),
+    };
+    let (int_part, frac_part) = match digits.find('.') {
+        Some(pos) => (&digits[..pos], &digits[pos + 1..]),
+        None => (digits, ""),
+    };
+
+    if int_part.is_empty()
+        || frac_part.len() > scale as usize
+        || !int_part.bytes().chain(frac_part.bytes()).all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let int_part = int_part.trim_start_matches('0');
+    if int_part.len() + scale as usize > precision as usize {
+        return Err(format!("decimal `{}' is out of range", text));
+    }
+
+    let nobits = NoBits::from_precision(precision).ok_or_else(invalid)?;
+    let mut underlying: i64 = 0;
+    let padding = scale as usize - frac_part.len();
+    for b in int_part.bytes().chain(frac_part.bytes()) {
+        underlying = underlying * 10 + i64::from(b - b'0');
+    }
+    for _ in 0..padding {
+        underlying *= 10;
+    }
+
+    Ok(Value::Decimal(Decimal {
+        underlying: if negative { -underlying } else { underlying },
+        nobits,
+        precision,
+        scale,
+    }))
+}
+
+/// Splits an array literal, `None` items are `NULL`s.
+fn split_array(text: &str) -> ParseResult<Vec<Option<String>>> {
+    let invalid = || format!("invalid array `{}'", text);
+    let inner = text
+        .trim()
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(invalid)?;
+
+    let mut items = Vec::new();
+    let mut chars = inner.chars().peekable();
+
+    loop {
+        while matches!(chars.peek(), Some(ch) if ch.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            if items.is_empty() {
+                return Ok(items);
+            }
+            return Err(invalid());
+        }
+
+        let mut item = String::new();
+        let mut quoted = false;
+        let mut depth = 0;
+        while let Some(&ch) = chars.peek() {
+            match ch {
+                '\'' if item.trim().is_empty() && depth == 0 => {
+                    quoted = true;
+                    chars.next();
+                    loop {
+                        match chars.next() {
+                            Some('\\') => item.push(chars.next().ok_or_else(invalid)?),
+                            Some('\'') => break,
+                            Some(ch) => item.push(ch),
+                            None => return Err(invalid()),
+                        }
+                    }
+                    continue;
+                }
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                ',' if depth == 0 => break,
+                _ => {}
+            }
+            item.push(ch);
+            chars.next();
+        }
+
+        let item = if quoted { item } else { item.trim().to_string() };
+        items.push(if !quoted && item == "NULL" {
+            None
+        } else {
+            Some(item)
+        });
+
+        if chars.next().is_none() {
+            return Ok(items);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::errors::Error;
+
+    use super::*;
+
+    fn schema() -> Vec<(&'static str, SqlType)> {
+        vec![
+            ("id", SqlType::UInt32),
+            ("name", SqlType::Nullable(SqlType::String.into())),
+            ("tags", SqlType::Array(SqlType::String.into())),
+            ("price", SqlType::Decimal(9, 2)),
+            ("day", SqlType::Date),
+        ]
+    }
+
+    #[test]
+    fn test_read_csv() {
+        let data = "\
+            1,\"multi\nline \"\"quoted\"\"\",\"['a', 'b\\'c']\",12.5,2019-03-14\r\n\
+            2,\\N,[],-0.01,1970-01-02\n";
+
+        let block = TextFormat::csv().read(data.as_bytes(), schema()).unwrap();
+        assert_eq!(block.row_count(), 2);
+        assert_eq!(
+            block.get::<Option<&str>, _>(0, "name").unwrap(),
+            Some("multi\nline \"quoted\"")
+        );
+        assert_eq!(block.get::<Option<&str>, _>(1, "name").unwrap(), None);
+        assert_eq!(
+            block.get::<Vec<&str>, _>(0, "tags").unwrap(),
+            vec!["a", "b'c"]
+        );
+        assert_eq!(block.get::<Decimal, _>(0, "price").unwrap(), Decimal::of(12.5, 2));
+        assert_eq!(block.get::<Decimal, _>(1, "price").unwrap(), Decimal::of(-0.01, 2));
+        assert_eq!(
+            block.get::<Date<Tz>, _>(0, "day").unwrap(),
+            Tz::UTC.ymd(2019, 3, 14)
+        );
+    }
+
+    #[test]
+    fn test_read_tsv_with_header() {
+        let data = "day\tprice\ttags\tname\tid\n\
+                    2019-03-14\t1\t[]\ttab\\there\t7\n";
+
+        let block = TextFormat::tsv()
+            .with_header(true)
+            .read(data.as_bytes(), schema())
+            .unwrap();
+        assert_eq!(block.get::<u32, _>(0, "id").unwrap(), 7);
+        assert_eq!(
+            block.get::<Option<&str>, _>(0, "name").unwrap(),
+            Some("tab\there")
+        );
+    }
+
+    #[test]
+    fn test_invalid_rows() {
+        let data = "1\tfoo\n\
+                    x\tbar\n\
+                    3\n\
+                    4\tbaz\n";
+        let schema = vec![("id", SqlType::UInt8), ("name", SqlType::String)];
+
+        let results: Vec<_> = TextFormat::tsv()
+            .with_block_size(1)
+            .blocks(data.as_bytes(), schema)
+            .unwrap()
+            .collect();
+        assert_eq!(results.len(), 4);
+
+        for (result, expected_line) in results[1..3].iter().zip(&[2, 3]) {
+            match result {
+                Err(Error::Driver(DriverError::InvalidRow { line, .. })) => {
+                    assert_eq!(line, expected_line)
+                }
+                result => panic!("unexpected result {:?}", result.is_ok()),
+            }
+        }
+
+        let block = results[3].as_ref().unwrap();
+        assert_eq!(block.get::<&str, _>(0, "name").unwrap(), "baz");
+    }
+
+    #[test]
+    fn test_parse_values() {
+        assert!(parse_value("256", SqlType::UInt8).is_err());
+        assert!(parse_value("abcd", SqlType::FixedString(3)).is_err());
+        assert!(parse_value("1.234", SqlType::Decimal(9, 2)).is_err());
+        assert!(parse_value("123456789", SqlType::Decimal(9, 2)).is_err());
+        assert_eq!(
+            parse_value("1970-01-01 00:01:40", SqlType::DateTime).unwrap(),
+            Value::DateTime(100, Tz::UTC)
+        );
+        assert_eq!(
+            split_array("[1, NULL, 'x,y']").unwrap(),
+            vec![Some("1".into()), None, Some("x,y".into())]
+        );
+    }
+}
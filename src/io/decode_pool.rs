@@ -0,0 +1,187 @@
+use std::{
+    io::Cursor,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono_tz::Tz;
+use futures::{sync::oneshot, Poll};
+
+use crate::{
+    binary::Parser,
+    errors::{DriverError, Error, Result},
+    types::{LoadOptions, Packet},
+};
+
+/// Most threads the pool starts.
+const MAX_THREADS: usize = 4;
+
+lazy_static! {
+    static ref POOL: Mutex<mpsc::Sender<Job>> = Mutex::new(start());
+}
+
+/// Packet parsed at the start of a buffer by the pool.
+pub(crate) struct Parsed {
+    pub(crate) buffer: Vec<u8>,
+    /// Number of bytes the packet takes.
+    pub(crate) pos: usize,
+    pub(crate) result: Poll<Option<Packet<()>>, Error>,
+    /// Time the parsing took.
+    pub(crate) elapsed: Duration,
+}
+
+struct Job {
+    buffer: Vec<u8>,
+    tz: Option<Tz>,
+    options: LoadOptions,
+    tx: oneshot::Sender<Parsed>,
+}
+
+/// Parses the packet at the start of `buffer`, `NotReady` means it's incomplete.
+/// Returns the number of bytes the packet takes along with it.
+pub(crate) fn parse(
+    buffer: &[u8],
+    tz: Option<Tz>,
+    options: LoadOptions,
+) -> (Poll<Option<Packet<()>>, Error>, usize) {
+    let mut cursor = Cursor::new(buffer);
+    let res = Parser::new(&mut cursor, tz, options).parse_packet();
+    let result = match res {
+        Ok(val) => Ok(futures::Async::Ready(Some(val))),
+        Err(e) => e.into(),
+    };
+    (result, cursor.position() as usize)
+}
+
+/// Runs `parse`, a panic while parsing is reported as `DecodeFailed`
+/// instead of taking the thread down.
+fn parse_caught<F>(parse: F) -> (Poll<Option<Packet<()>>, Error>, usize)
+where
+    F: FnOnce() -> (Poll<Option<Packet<()>>, Error>, usize),
+{
+    match panic::catch_unwind(AssertUnwindSafe(parse)) {
+        Ok(parsed) => parsed,
+        Err(_) => (Err(DriverError::DecodeFailed.into()), 0),
+    }
+}
+
+/// Parses the packet at the start of `buffer` on a thread of the pool,
+/// the buffer is given back with the result.
+pub(crate) fn spawn(
+    buffer: Vec<u8>,
+    tz: Option<Tz>,
+    options: LoadOptions,
+) -> Result<oneshot::Receiver<Parsed>> {
+    let (tx, rx) = oneshot::channel();
+    let job = Job {
+        buffer,
+        tz,
+        options,
+        tx,
+    };
+
+    match POOL.lock().unwrap().send(job) {
+        Ok(()) => Ok(rx),
+        Err(_) => Err(DriverError::DecodeFailed.into()),
+    }
+}
+
+fn start() -> mpsc::Sender<Job> {
+    let (tx, rx) = mpsc::channel::<Job>();
+    let rx = Arc::new(Mutex::new(rx));
+
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_THREADS);
+
+    for i in 0..threads {
+        let rx = rx.clone();
+        thread::Builder::new()
+            .name(format!("clickhouse-decode-{}", i))
+            .spawn(move || loop {
+                let job = match rx.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => return,
+                };
+
+                let start = Instant::now();
+                let (result, pos) = parse_caught(|| parse(&job.buffer, job.tz, job.options));
+                let parsed = Parsed {
+                    buffer: job.buffer,
+                    pos,
+                    result,
+                    elapsed: start.elapsed(),
+                };
+                // The transport may be gone already.
+                let _ = job.tx.send(parsed);
+            })
+            .expect("can't start a decode thread");
+    }
+
+    tx
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{Async, Future};
+
+    use super::*;
+    use crate::{
+        binary::{protocol, Encoder},
+        types::Block,
+    };
+
+    fn options(compress: bool) -> LoadOptions {
+        LoadOptions {
+            compress,
+            ..LoadOptions::default()
+        }
+    }
+
+    fn data_packet(block: &Block, compress: bool) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder.uvarint(protocol::SERVER_DATA);
+        encoder.string("");
+        block.write(&mut encoder, compress);
+        encoder.get_buffer()
+    }
+
+    #[test]
+    fn test_parse_on_pool() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2, 3])
+            .column("name", vec!["a", "b", "c"]);
+        let buffer = data_packet(&block, true);
+        let len = buffer.len();
+
+        let parsed = spawn(buffer, Some(Tz::UTC), options(true)).unwrap().wait().unwrap();
+        assert_eq!(parsed.pos, len);
+        match parsed.result {
+            Ok(Async::Ready(Some(Packet::Block(actual)))) => assert_eq!(actual, block),
+            _ => panic!("unexpected result"),
+        }
+    }
+
+    #[test]
+    fn test_parse_incomplete_packet() {
+        let block = Block::new().column("id", vec![1_u64; 100]);
+        let mut buffer = data_packet(&block, false);
+        buffer.truncate(buffer.len() - 1);
+
+        let parsed = spawn(buffer, Some(Tz::UTC), options(false)).unwrap().wait().unwrap();
+        assert!(matches!(parsed.result, Ok(Async::NotReady)));
+    }
+
+    #[test]
+    fn test_panic_is_reported() {
+        let (result, pos) = parse_caught(|| parse(&[], None, options(false)));
+        assert!(matches!(result, Ok(Async::NotReady)));
+        assert_eq!(pos, 0);
+
+        let (result, _) = parse_caught(|| panic!("decoding panicked"));
+        assert!(matches!(result, Err(Error::Driver(DriverError::DecodeFailed))));
+    }
+}
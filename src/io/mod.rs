@@ -4,4 +4,5 @@ pub(crate) use self::{
 };
 
 mod box_future;
+mod decode_pool;
 pub(crate) mod transport;
@@ -1,27 +1,40 @@
 use std::{
-    collections::VecDeque,
-    io::{self, Cursor},
+    collections::{HashMap, VecDeque},
+    io,
+    mem,
     ptr,
     sync::{
         self,
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    time::{Duration, Instant},
 };
 
 use chrono_tz::Tz;
-use futures::{Async, Poll, Stream};
+use futures::{sync::oneshot, task, Async, Poll, Stream};
 use tokio::{net::TcpStream, prelude::*};
+use tokio_timer::Delay;
 
 use crate::{
-    binary::Parser,
+    binary::{protocol, BufferPool, ReadEx, Segments},
     errors::{DriverError, Error},
-    io::BoxFuture,
+    io::{
+        decode_pool::{self, Parsed},
+        BoxFuture,
+    },
     pool::{Inner, PoolBinding},
-    types::{Block, Cmd, Context, Packet},
+    types::{
+        column::StringLayout, frame_size, Block, Cmd, Context, LoadOptions, Packet, QueryTimer,
+    },
     ClientHandle, Pool,
 };
 
+const MAX_CACHED_HEADERS: usize = 64;
+
+/// Read buffers that grew larger for a query are shrunk to this once it ends.
+const MAX_IDLE_READ_CAPACITY: usize = 1024 * 1024;
+
 /// Line transport
 pub(crate) struct ClickhouseTransport {
     // Inner socket
@@ -30,15 +43,38 @@ pub(crate) struct ClickhouseTransport {
     done: bool,
     // Buffered read data
     rd: Vec<u8>,
-    // Whether the buffer is known to be incomplete
-    buf_is_incomplete: bool,
+    // Length of the buffer when it was last found to hold an incomplete packet
+    parsed_len: usize,
+    // Length of the buffer the next packet is parsed at
+    parse_at: usize,
+    // Set if `parse_at` is the end of a compressed frame the packet can't be parsed without
+    awaits_frame: bool,
+    // Size of a single read from the socket
+    prefetch: usize,
+    // Whether data packets are parsed by the decode pool
+    parallel_decode: bool,
+    // Length of the buffer being parsed by the pool, `rd` holds the data read meanwhile
+    parsing: Option<(usize, oneshot::Receiver<Parsed>)>,
+    // Time spent parsing packets since it was last taken
+    decode_time: Duration,
     // Current buffer to write to the socket
-    wr: io::Cursor<Vec<u8>>,
+    wr: Segments,
+    // Buffers of written packets for the next ones
+    buffers: BufferPool,
     // Queued commands
     cmds: VecDeque<Cmd>,
     // Server time zone
     timezone: Option<Tz>,
-    compress: bool,
+    // How received blocks are read
+    load_options: LoadOptions,
+    // Set once a statement was sent, so the session may hold state
+    pinned: bool,
+    // Database selected by `USE`
+    database: Option<String>,
+    // Roles activated by `SET ROLE`
+    roles: Option<Vec<String>>,
+    // Headers of the tables by `INSERT` statement
+    headers: HashMap<String, Block>,
     status: Arc<TransportStatus>,
 }
 
@@ -58,19 +94,36 @@ pub(crate) struct PacketStream {
     inner: Option<ClickhouseTransport>,
     state: PacketStreamState,
     read_block: bool,
+    deadline: Option<Delay>,
+    cancelled: bool,
+    timer: Option<QueryTimer>,
 }
 
 impl ClickhouseTransport {
-    pub fn new(inner: TcpStream, compress: bool, pool: Option<Pool>) -> Self {
+    pub fn new(inner: TcpStream, compress: bool, prefetch: usize, pool: Option<Pool>) -> Self {
         ClickhouseTransport {
             inner,
             done: false,
             rd: vec![],
-            buf_is_incomplete: false,
-            wr: io::Cursor::new(vec![]),
+            parsed_len: 0,
+            parse_at: 0,
+            awaits_frame: false,
+            prefetch: prefetch.max(1),
+            parallel_decode: false,
+            parsing: None,
+            decode_time: Duration::default(),
+            wr: Segments::default(),
+            buffers: BufferPool::default(),
             cmds: VecDeque::new(),
             timezone: None,
-            compress,
+            load_options: LoadOptions {
+                compress,
+                ..LoadOptions::default()
+            },
+            pinned: false,
+            database: None,
+            roles: None,
+            headers: HashMap::new(),
             status: Arc::new(TransportStatus::new(pool)),
         }
     }
@@ -78,6 +131,72 @@ impl ClickhouseTransport {
     pub(crate) fn set_inside(&self, value: bool) {
         self.status.inside.store(value, Ordering::Relaxed);
     }
+
+    pub(crate) fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    pub(crate) fn set_pinned(&mut self, value: bool) {
+        self.pinned = value;
+    }
+
+    pub(crate) fn set_parallel_decode(&mut self, value: bool) {
+        self.parallel_decode = value;
+    }
+
+    pub(crate) fn set_lazy_blocks(&mut self, value: bool) {
+        self.load_options.lazy = value;
+    }
+
+    pub(crate) fn set_string_layout(&mut self, value: StringLayout) {
+        self.load_options.strings = value;
+    }
+
+    pub(crate) fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+
+    pub(crate) fn set_database(&mut self, database: Option<String>) {
+        self.database = database;
+        self.headers.clear();
+    }
+
+    pub(crate) fn roles(&self) -> Option<&[String]> {
+        self.roles.as_deref()
+    }
+
+    pub(crate) fn set_roles(&mut self, roles: Option<Vec<String>>) {
+        self.roles = roles;
+    }
+
+    /// Returns the header the server sent for the `INSERT` statement before.
+    pub(crate) fn cached_header(&self, sql: &str) -> Option<Block> {
+        self.headers.get(sql).cloned()
+    }
+
+    pub(crate) fn cache_header(&mut self, sql: String, header: Block) {
+        if self.headers.len() >= MAX_CACHED_HEADERS && !self.headers.contains_key(&sql) {
+            self.headers.clear();
+        }
+        self.headers.insert(sql, header);
+    }
+
+    /// Asks the server to stop the running query without waiting for the answer.
+    ///
+    /// It's a best effort: nothing is sent while another command is being written
+    /// or outside of a task.
+    pub(crate) fn cancel(&mut self) {
+        if !self.wr_is_empty() || !self.cmds.is_empty() || !task::is_in_task() {
+            return;
+        }
+
+        if let Ok(bytes) = Cmd::Cancel.get_packed_command() {
+            // The packet is a single byte, so it's either written or not.
+            if let Err(err) = self.inner.write(&bytes) {
+                trace!("transport cancel error; err={:?}", err);
+            }
+        }
+    }
 }
 
 impl Drop for TransportStatus {
@@ -110,37 +229,16 @@ impl TransportStatus {
 
 impl ClickhouseTransport {
     fn wr_is_empty(&self) -> bool {
-        self.wr_remaining() == 0
-    }
-
-    fn wr_remaining(&self) -> usize {
-        self.wr.get_ref().len() - self.wr_pos()
-    }
-
-    fn wr_pos(&self) -> usize {
-        self.wr.position() as usize
+        self.wr.is_empty()
     }
 
     fn wr_flush(&mut self) -> io::Result<bool> {
-        // Making the borrow checker happy
-        let res = {
-            let buf = {
-                let pos = self.wr.position() as usize;
-                let buf = &self.wr.get_ref()[pos..];
-
-                trace!("writing; remaining={:?}", buf);
-                buf
-            };
-
-            self.inner.write(buf)
-        };
-
-        match res {
-            Ok(mut n) => {
-                n += self.wr.position() as usize;
-                self.wr.set_position(n as u64);
+        match self.wr.write_to(&mut self.inner, &self.buffers) {
+            Ok(Async::Ready(n)) => {
+                trace!("written; bytes={}", n);
                 Ok(true)
             }
+            Ok(Async::NotReady) => Ok(false),
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
                     return Ok(false);
@@ -152,25 +250,17 @@ impl ClickhouseTransport {
         }
     }
 
-    fn try_parse_msg(&mut self) -> Poll<Option<Packet<()>>, Error> {
-        let pos;
-        let ret = {
-            let mut cursor = Cursor::new(&self.rd);
-            let res = {
-                let mut parser = Parser::new(&mut cursor, self.timezone, self.compress);
-                parser.parse_packet()
-            };
-            pos = cursor.position() as usize;
-
-            if let Ok(Packet::Hello(_, ref packet)) = res {
-                self.timezone = Some(packet.timezone);
-            }
+    fn take_decode_time(&mut self) -> Duration {
+        mem::take(&mut self.decode_time)
+    }
 
-            match res {
-                Ok(val) => Ok(Async::Ready(Some(val))),
-                Err(e) => e.into(),
-            }
-        };
+    fn try_parse_msg(&mut self) -> Poll<Option<Packet<()>>, Error> {
+        let start = Instant::now();
+        let (ret, pos) = decode_pool::parse(&self.rd, self.timezone, self.load_options);
+        self.decode_time += start.elapsed();
+        if let Ok(Async::Ready(Some(Packet::Hello(_, ref packet)))) = ret {
+            self.timezone = Some(packet.timezone);
+        }
 
         match ret {
             Ok(Async::NotReady) => (),
@@ -184,8 +274,106 @@ impl ClickhouseTransport {
             }
         }
 
+        // The buffer is kept for the next queries, unless a large result made it grow.
+        if matches!(
+            ret,
+            Ok(Async::Ready(Some(Packet::Eof(_)))) | Ok(Async::Ready(Some(Packet::Exception(_))))
+        ) {
+            self.rd.shrink_to(MAX_IDLE_READ_CAPACITY);
+        }
+
         ret
     }
+
+    /// Returns `true` if the buffer starts with a data packet
+    /// that should be parsed by the decode pool.
+    fn is_pool_packet(&self) -> bool {
+        if !self.parallel_decode || self.timezone.is_none() {
+            return false;
+        }
+
+        match self.rd.first() {
+            Some(&packet) => matches!(
+                packet as u64,
+                protocol::SERVER_DATA | protocol::SERVER_TOTALS | protocol::SERVER_EXTREMES
+            ),
+            None => false,
+        }
+    }
+
+    /// Returns the length the buffer needs to hold the first compressed frame
+    /// of the data packet it starts with, once the header of the frame is read.
+    fn frame_end(&self) -> Option<usize> {
+        if !self.load_options.compress {
+            return None;
+        }
+
+        let mut reader = self.rd.as_slice();
+        match reader.read_uvarint().ok()? {
+            protocol::SERVER_DATA | protocol::SERVER_TOTALS | protocol::SERVER_EXTREMES => {}
+            _ => return None,
+        }
+        reader.skip_string().ok()?;
+
+        let pos = self.rd.len() - reader.len();
+        frame_size(reader).map(|size| pos + size)
+    }
+
+    /// Postpones parsing the packet the buffer starts with, after `parsed_len`
+    /// bytes didn't hold it whole, until the first compressed frame of the
+    /// packet was read or until the buffer doubled.
+    fn defer_parsing(&mut self, parsed_len: usize) {
+        self.parsed_len = parsed_len;
+        let (parse_at, awaits_frame) = match self.frame_end() {
+            Some(end) if end > parsed_len => {
+                let len = self.rd.len();
+                if end > len {
+                    self.rd.reserve_exact(end + self.prefetch - len);
+                }
+                (end, true)
+            }
+            _ => (parsed_len * 2, false),
+        };
+        self.parse_at = parse_at;
+        self.awaits_frame = awaits_frame;
+    }
+
+    fn reset_parsing(&mut self) {
+        self.parsed_len = 0;
+        self.parse_at = 0;
+        self.awaits_frame = false;
+    }
+
+    /// Takes the buffer back from the pool, followed by the data read meanwhile.
+    fn finish_parsing(&mut self, parsed: Parsed) -> Poll<Option<Packet<()>>, Error> {
+        let Parsed {
+            mut buffer,
+            pos,
+            result,
+            elapsed,
+        } = parsed;
+        self.decode_time += elapsed;
+
+        let parsed_len = buffer.len();
+        let ready = !matches!(result, Ok(Async::NotReady));
+        if ready {
+            buffer.drain(..pos);
+        }
+
+        buffer.extend_from_slice(&self.rd);
+        // The pool is shared with the written packets, so it mustn't keep
+        // a buffer that grew larger for reading than an idle one would be.
+        let mut spare = mem::replace(&mut self.rd, buffer);
+        spare.clear();
+        spare.shrink_to(MAX_IDLE_READ_CAPACITY);
+        self.buffers.put(spare);
+        if ready {
+            self.reset_parsing();
+        } else {
+            self.defer_parsing(parsed_len);
+        }
+        result
+    }
 }
 
 impl ClickhouseTransport {
@@ -196,10 +384,7 @@ impl ClickhouseTransport {
                     None => {
                         return Ok(Async::Ready(()));
                     }
-                    Some(cmd) => {
-                        let bytes = cmd.get_packed_command()?;
-                        self.wr = Cursor::new(bytes)
-                    }
+                    Some(cmd) => self.wr = cmd.get_pooled_command(&self.buffers)?,
                 }
             }
 
@@ -216,40 +401,81 @@ impl Stream for ClickhouseTransport {
     type Error = Error;
 
     /// Read a message from the `Transport`
+    ///
+    /// The socket is read in chunks of `prefetch` bytes only until a packet
+    /// can be parsed, so a slow consumer leaves the data in the socket and
+    /// the server is held back by TCP flow control.
     fn poll(&mut self) -> Poll<Option<Packet<()>>, Error> {
-        // Check whether our currently buffered data is enough for a packet
-        // before reading any more data. This prevents the buffer from growing
-        // indefinitely when the sender is faster than we can consume the data
-        if !self.buf_is_incomplete && !self.rd.is_empty() {
-            if let ret @ Async::Ready(_) = self.try_parse_msg()? {
-                return Ok(ret);
+        loop {
+            if let Some((len, ref mut rx)) = self.parsing {
+                match rx.poll() {
+                    Ok(Async::Ready(parsed)) => {
+                        self.parsing = None;
+                        if let ret @ Async::Ready(_) = self.finish_parsing(parsed)? {
+                            return Ok(ret);
+                        }
+                    }
+                    Ok(Async::NotReady) => {
+                        // Data isn't read further ahead than the packet being parsed.
+                        if self.done || self.rd.len() >= len {
+                            return Ok(Async::NotReady);
+                        }
+                    }
+                    Err(_) => return Err(DriverError::DecodeFailed.into()),
+                }
+            // A packet that doesn't fit into the buffer is parsed again once its
+            // first compressed frame was read. Without a known frame end it is
+            // parsed once the buffer doubled, or once the socket has no more data
+            // for now, as the rest of the packet may already be in the buffer.
+            } else if self.rd.len() > self.parsed_len && self.rd.len() >= self.parse_at {
+                if self.is_pool_packet() {
+                    let buffer = mem::replace(&mut self.rd, self.buffers.take());
+                    let len = buffer.len();
+                    let rx = decode_pool::spawn(buffer, self.timezone, self.load_options)?;
+                    self.parsing = Some((len, rx));
+                    continue;
+                }
+
+                if let ret @ Async::Ready(_) = self.try_parse_msg()? {
+                    self.reset_parsing();
+                    return Ok(ret);
+                }
+                self.defer_parsing(self.rd.len());
             }
-        }
 
-        // Fill the buffer!
-        while !self.done {
-            match self.inner.read_to_end(&mut self.rd) {
+            if self.done {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed by server",
+                )
+                .into());
+            }
+
+            let len = self.rd.len();
+            self.rd.resize(len + self.prefetch, 0);
+            let res = self.inner.read(&mut self.rd[len..]);
+            self.rd.truncate(len + *res.as_ref().unwrap_or(&0));
+
+            match res {
                 Ok(0) => {
                     self.done = true;
-                    break;
+                    self.reset_parsing();
                 }
                 Ok(_) => {}
                 Err(e) => {
-                    if e.kind() == io::ErrorKind::WouldBlock {
-                        break;
+                    if e.kind() != io::ErrorKind::WouldBlock {
+                        return Err(e.into());
                     }
-
-                    return Err(e.into());
+                    if self.parsing.is_some()
+                        || self.rd.len() == self.parsed_len
+                        || self.awaits_frame
+                    {
+                        return Ok(Async::NotReady);
+                    }
+                    self.parse_at = 0;
                 }
             }
         }
-
-        // Try to parse the new data!
-        let ret = self.try_parse_msg();
-
-        self.buf_is_incomplete = if let Ok(Async::NotReady) = ret { true } else { false };
-
-        ret
     }
 }
 
@@ -280,17 +506,75 @@ impl PacketStream {
     }
 }
 
+impl PacketStream {
+    /// Fails the stream with a timeout error and cancels the query
+    /// once `deadline` is reached.
+    pub(crate) fn deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline.map(Delay::new);
+        self
+    }
+
+    /// Reports the phases of the query to `timer`, if any.
+    pub(crate) fn timed(mut self, timer: Option<QueryTimer>) -> Self {
+        if let Some(ref mut inner) = self.inner {
+            inner.take_decode_time();
+        }
+        self.timer = timer;
+        self
+    }
+
+    /// Asks the server to stop the query, the rest of the response
+    /// is skipped up to `Packet::Eof`.
+    pub(crate) fn cancel(&mut self) {
+        if let Some(ref mut inner) = self.inner {
+            inner.cancel();
+        }
+        self.cancelled = true;
+    }
+
+    fn poll_deadline(&mut self) -> Result<(), Error> {
+        let expired = match self.deadline {
+            Some(ref mut deadline) => deadline.poll()?.is_ready(),
+            None => false,
+        };
+
+        if expired {
+            warn!("[cancel] query deadline is exceeded");
+            if let Some(mut inner) = self.inner.take() {
+                inner.cancel();
+            }
+            return Err(DriverError::Timeout.into());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PacketStream {
+    fn drop(&mut self) {
+        // The stream is dropped before the end of the response,
+        // so the query shouldn't keep running on the server.
+        if let Some(ref mut inner) = self.inner {
+            inner.cancel();
+        }
+    }
+}
+
 impl Stream for PacketStream {
     type Item = Packet<ClickhouseTransport>;
     type Error = Error;
 
     fn poll(&mut self) -> Poll<Option<Packet<ClickhouseTransport>>, Self::Error> {
+        self.poll_deadline()?;
+
         loop {
             self.state = match self.state {
                 PacketStreamState::Ask => match self.inner {
                     None => PacketStreamState::Done,
                     Some(ref mut inner) => {
                         try_ready!(inner.send());
+                        if let Some(ref mut timer) = self.timer {
+                            timer.sent();
+                        }
                         PacketStreamState::Receive
                     }
                 },
@@ -300,6 +584,13 @@ impl Stream for PacketStream {
                         Some(ref mut inner) => try_ready!(inner.poll()),
                     };
 
+                    if let (Some(timer), Some(inner)) = (&mut self.timer, &mut self.inner) {
+                        timer.add_decode(inner.take_decode_time());
+                        if matches!(ret, Some(Packet::Block(ref block)) if !block.is_empty()) {
+                            timer.block_received();
+                        }
+                    }
+
                     match ret {
                         None => PacketStreamState::Done,
                         Some(packet) => {
@@ -310,6 +601,8 @@ impl Stream for PacketStream {
                 }
                 PacketStreamState::Yield(_) => PacketStreamState::Receive,
                 PacketStreamState::Done => {
+                    // The stats are reported once the result ends.
+                    self.timer = None;
                     return match self.inner.take() {
                         Some(inner) => Ok(Async::Ready(Some(Packet::Eof(inner)))),
                         _ => Ok(Async::Ready(None)),
@@ -326,6 +619,18 @@ impl Stream for PacketStream {
                 self.state = PacketStreamState::Done;
             }
 
+            if self.cancelled {
+                match package {
+                    Some(Packet::Eof(_)) | None => {}
+                    Some(Packet::Exception(_)) => {
+                        // The server reports the cancelled query as an exception.
+                        self.state = PacketStreamState::Done;
+                        continue;
+                    }
+                    Some(_) => continue,
+                }
+            }
+
             if package.is_some() {
                 return Ok(Async::Ready(package));
             }
@@ -334,14 +639,47 @@ impl Stream for PacketStream {
 }
 
 impl ClickhouseTransport {
-    pub(crate) fn call(mut self, req: Cmd) -> PacketStream {
-        self.cmds.push_back(req);
+    pub(crate) fn call(self, req: Cmd) -> PacketStream {
+        self.call_all(vec![req])
+    }
+
+    /// Sends the commands one after another, each is encoded right before it's written.
+    pub(crate) fn call_all(mut self, reqs: Vec<Cmd>) -> PacketStream {
+        self.cmds.extend(reqs);
         PacketStream {
             inner: Some(self),
             state: PacketStreamState::Ask,
             read_block: false,
+            deadline: None,
+            cancelled: false,
+            timer: None,
         }
     }
+
+    /// Writes the command to the socket without waiting for a response.
+    pub(crate) fn send_cmd(self, req: Cmd) -> SendCmd {
+        self.send_all(vec![req])
+    }
+
+    /// Writes the commands to the socket without waiting for a response.
+    pub(crate) fn send_all(mut self, reqs: Vec<Cmd>) -> SendCmd {
+        self.cmds.extend(reqs);
+        SendCmd { inner: Some(self) }
+    }
+}
+
+pub(crate) struct SendCmd {
+    inner: Option<ClickhouseTransport>,
+}
+
+impl Future for SendCmd {
+    type Item = ClickhouseTransport;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        try_ready!(self.inner.as_mut().unwrap().send());
+        Ok(Async::Ready(self.inner.take().unwrap()))
+    }
 }
 
 fn is_block<T>(packet: &Option<Packet<T>>) -> bool {
@@ -350,3 +688,238 @@ fn is_block<T>(packet: &Option<Packet<T>>) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{io::Write, net::TcpListener, thread};
+
+    use tokio::runtime::current_thread;
+
+    use crate::binary::Encoder;
+
+    use super::*;
+
+    #[test]
+    fn test_read_on_demand() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let pongs = vec![protocol::SERVER_PONG as u8; 100_000];
+            // The client stops reading after the first packet.
+            let _ = socket.write_all(&pongs);
+        });
+
+        let first_pong = TcpStream::connect(&addr).and_then(|stream| {
+            let mut transport = ClickhouseTransport::new(stream, false, 16, None);
+            future::poll_fn(move || {
+                let packet = try_ready!(transport.poll());
+                Ok(Async::Ready((packet, transport.rd.len())))
+            })
+        });
+
+        let (packet, buffered) = current_thread::block_on_all(first_pong).unwrap();
+        match packet {
+            Some(Packet::Pong(_)) => {}
+            _ => panic!("unexpected packet"),
+        }
+        assert!(buffered < 16);
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_parallel_decode() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let blocks: Vec<Block> = (0..10_u32)
+            .map(|i| Block::new().column("id", vec![i; 1000]))
+            .collect();
+        let mut encoder = Encoder::new();
+        for block in &blocks {
+            encoder.uvarint(protocol::SERVER_DATA);
+            encoder.string("");
+            block.write(&mut encoder, true);
+        }
+        encoder.uvarint(protocol::SERVER_END_OF_STREAM);
+        let response = encoder.get_buffer();
+
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            socket.write_all(&response).unwrap();
+        });
+
+        let packets = TcpStream::connect(&addr).and_then(|stream| {
+            let mut transport = ClickhouseTransport::new(stream, true, 64, None);
+            transport.set_parallel_decode(true);
+            transport.timezone = Some(Tz::UTC);
+            transport
+                .take_while(|packet| Ok(!matches!(packet, Packet::Eof(_))))
+                .collect()
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+        });
+
+        let packets = current_thread::block_on_all(packets).unwrap();
+        assert_eq!(packets.len(), blocks.len());
+        for (packet, expected) in packets.iter().zip(&blocks) {
+            match packet {
+                Packet::Block(block) => assert_eq!(block, expected),
+                _ => panic!("unexpected packet"),
+            }
+        }
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_defer_parsing_to_frame_end() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let stream = current_thread::block_on_all(TcpStream::connect(&addr)).unwrap();
+        let mut transport = ClickhouseTransport::new(stream, true, 16, None);
+
+        let block = Block::new().column("id", vec![7_u64; 1000]);
+        let mut encoder = Encoder::new();
+        encoder.uvarint(protocol::SERVER_DATA);
+        encoder.string("");
+        block.write(&mut encoder, true);
+        let packet = encoder.get_buffer();
+
+        transport.rd = packet[..40].to_vec();
+        assert_eq!(transport.frame_end(), Some(packet.len()));
+        transport.defer_parsing(40);
+        assert_eq!(transport.parse_at, packet.len());
+        assert!(transport.rd.capacity() >= packet.len() + 16);
+
+        transport.rd = packet[..20].to_vec();
+        assert_eq!(transport.frame_end(), None);
+        transport.defer_parsing(20);
+        assert_eq!(transport.parse_at, 40);
+    }
+
+    #[test]
+    fn test_keep_deferred_parsing_between_reads() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut runtime = current_thread::Runtime::new().unwrap();
+        let stream = runtime.block_on(TcpStream::connect(&addr)).unwrap();
+        let (mut socket, _) = listener.accept().unwrap();
+        let mut transport = ClickhouseTransport::new(stream, true, 64, None);
+        transport.timezone = Some(Tz::UTC);
+
+        let block = Block::new().column("id", (0..1000_u64).collect::<Vec<_>>());
+        let mut encoder = Encoder::new();
+        encoder.uvarint(protocol::SERVER_DATA);
+        encoder.string("");
+        block.write(&mut encoder, true);
+        let packet = encoder.get_buffer();
+
+        // Each piece is followed by WouldBlock, until the whole packet was sent.
+        let pieces = [40, packet.len() / 2, packet.len() - 1, packet.len()];
+        let mut sent = 0;
+        for &end in &pieces {
+            socket.write_all(&packet[sent..end]).unwrap();
+            sent = end;
+
+            let polled = &mut transport;
+            let ret = runtime
+                .block_on(future::poll_fn(move || -> Poll<_, Error> {
+                    let ret = polled.poll()?;
+                    if ret.is_ready() || polled.rd.len() == end {
+                        Ok(Async::Ready(ret))
+                    } else {
+                        Ok(Async::NotReady)
+                    }
+                }))
+                .unwrap();
+
+            if end < packet.len() {
+                assert!(ret.is_not_ready());
+                // The packet was parsed only once, with the first piece.
+                assert_eq!(transport.parsed_len, 40);
+                assert_eq!(transport.parse_at, packet.len());
+            } else {
+                match ret {
+                    Async::Ready(Some(Packet::Block(received))) => assert_eq!(received, block),
+                    _ => panic!("unexpected packet"),
+                }
+                assert_eq!((transport.parsed_len, transport.parse_at), (0, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_shrink_read_buffer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let stream = current_thread::block_on_all(TcpStream::connect(&addr)).unwrap();
+        let mut transport = ClickhouseTransport::new(stream, false, 16, None);
+
+        transport.rd = Vec::with_capacity(4 * MAX_IDLE_READ_CAPACITY);
+        transport.rd.push(protocol::SERVER_PONG as u8);
+        match transport.try_parse_msg() {
+            Ok(Async::Ready(Some(Packet::Pong(_)))) => {}
+            _ => panic!("expected Pong"),
+        }
+        assert_eq!(transport.rd.capacity(), 4 * MAX_IDLE_READ_CAPACITY);
+
+        transport.rd.push(protocol::SERVER_END_OF_STREAM as u8);
+        match transport.try_parse_msg() {
+            Ok(Async::Ready(Some(Packet::Eof(_)))) => {}
+            _ => panic!("expected Eof"),
+        }
+        assert!(transport.rd.is_empty());
+        assert!(transport.rd.capacity() <= MAX_IDLE_READ_CAPACITY);
+    }
+
+    #[test]
+    fn test_shrink_spare_buffer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let stream = current_thread::block_on_all(TcpStream::connect(&addr)).unwrap();
+        let mut transport = ClickhouseTransport::new(stream, false, 16, None);
+
+        transport.rd = Vec::with_capacity(4 * MAX_IDLE_READ_CAPACITY);
+        transport.rd.push(protocol::SERVER_PONG as u8);
+        let parsed = Parsed {
+            buffer: vec![protocol::SERVER_PONG as u8],
+            pos: 1,
+            result: Ok(Async::Ready(Some(Packet::Pong(())))),
+            elapsed: Duration::default(),
+        };
+        transport.finish_parsing(parsed).unwrap();
+        assert_eq!(transport.rd, [protocol::SERVER_PONG as u8]);
+
+        let spare = transport.buffers.take();
+        assert!(spare.is_empty());
+        assert!(spare.capacity() <= MAX_IDLE_READ_CAPACITY);
+    }
+
+    #[test]
+    fn test_header_cache() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let stream = current_thread::block_on_all(TcpStream::connect(&addr)).unwrap();
+        let mut transport = ClickhouseTransport::new(stream, false, 16, None);
+
+        let sql = |i| format!("INSERT INTO t{} (id) VALUES", i);
+        for i in 0..MAX_CACHED_HEADERS {
+            transport.cache_header(sql(i), Block::new());
+        }
+        assert!(transport.cached_header(&sql(0)).is_some());
+
+        transport.cache_header(sql(MAX_CACHED_HEADERS), Block::new());
+        assert!(transport.cached_header(&sql(0)).is_none());
+        assert!(transport.cached_header(&sql(MAX_CACHED_HEADERS)).is_some());
+
+        transport.set_database(Some("other".into()));
+        assert!(transport.cached_header(&sql(MAX_CACHED_HEADERS)).is_none());
+    }
+}
@@ -1,14 +1,20 @@
 use tokio::prelude::*;
 
-use crate::{errors::Error, pool::Pool, ClientHandle};
+use crate::{errors::Error, io::BoxFuture, pool::Pool, ClientHandle};
 
 pub struct GetHandle {
     pool: Pool,
+    ticket: Option<u64>,
+    checkout: Option<BoxFuture<ClientHandle>>,
 }
 
 impl GetHandle {
     pub fn new(pool: &Pool) -> Self {
-        Self { pool: pool.clone() }
+        Self {
+            pool: pool.clone(),
+            ticket: None,
+            checkout: None,
+        }
     }
 }
 
@@ -17,6 +23,38 @@ impl Future for GetHandle {
     type Error = Error;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        self.pool.poll()
+        if let Some(ref mut checkout) = self.checkout {
+            return checkout.poll();
+        }
+
+        let handle = try_ready!(self.pool.poll(&mut self.ticket));
+        let hook = self.pool.checkout_hook();
+
+        let database_changed = handle.is_database_changed();
+        let roles_changed = handle.roles().is_some();
+
+        if !database_changed && !roles_changed && hook.is_none() {
+            return Ok(Async::Ready(handle));
+        }
+
+        let mut checkout: BoxFuture<ClientHandle> = Box::new(future::ok(handle));
+        if roles_changed {
+            checkout = Box::new(checkout.and_then(ClientHandle::reset_roles));
+        }
+        if database_changed {
+            checkout = Box::new(checkout.and_then(ClientHandle::reset_database));
+        }
+        if let Some(hook) = hook {
+            checkout = Box::new(checkout.and_then(move |c| hook(c)));
+        }
+
+        self.checkout = Some(checkout);
+        self.poll()
+    }
+}
+
+impl Drop for GetHandle {
+    fn drop(&mut self) {
+        self.pool.cancel(&mut self.ticket);
     }
 }
@@ -1,39 +1,194 @@
 use std::{
+    collections::{HashSet, VecDeque},
     fmt, mem,
-    sync::{Arc, Mutex, MutexGuard},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
 };
 
+use rand::Rng;
 use tokio::prelude::{*, task::{self, Task}};
+use tokio_timer::Delay;
 
 use crate::{
     io::BoxFuture,
     pool::futures::GetHandle,
-    errors::Result,
-    types::{IntoOptions, OptionsSource},
+    errors::{DriverError, Error, Result},
+    types::{IntoOptions, Options, OptionsSource},
     Client, ClientHandle,
 };
 
 mod futures;
 
+type HandleHook = Arc<dyn Fn(ClientHandle) -> BoxFuture<ClientHandle> + Send + Sync>;
+type ReturnHook = Arc<dyn Fn(&ClientHandle) -> bool + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    on_connect: Option<HandleHook>,
+    on_checkout: Option<HandleHook>,
+    on_return: Option<ReturnHook>,
+}
+
+fn handle_hook<F, R>(hook: F) -> HandleHook
+where
+    F: Fn(ClientHandle) -> R + Send + Sync + 'static,
+    R: IntoFuture<Item = ClientHandle, Error = Error>,
+    R::Future: Send + 'static,
+{
+    Arc::new(move |c| Box::new(hook(c).into_future()))
+}
+
+/// Order in which tasks waiting for a connection are served.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum QueueOrder {
+    /// The task that has waited the longest is served first (default).
+    Fifo,
+    /// The task that started waiting most recently is served first.
+    Lifo,
+}
+
+/// What happens to a task that asks for a connection
+/// when the maximum number of waiting tasks is reached.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RejectionPolicy {
+    /// The new task fails with `DriverError::PoolQueueFull` (default).
+    Reject,
+    /// The task that has waited the longest fails with `DriverError::PoolQueueFull`
+    /// and the new task takes its place.
+    ShedOldest,
+}
+
 pub(crate) struct Inner {
     new: Option<BoxFuture<ClientHandle>>,
     idle: Vec<ClientHandle>,
-    tasks: Vec<Task>,
+    tasks: VecDeque<(u64, Task)>,
+    next_ticket: u64,
+    order: QueueOrder,
+    max_waiters: Option<usize>,
+    rejection: RejectionPolicy,
+    shed: HashSet<u64>,
     ongoing: usize,
+    min: usize,
+    max: usize,
+    failures: u32,
+    backoff: Option<Delay>,
 }
 
 impl Inner {
     pub(crate) fn release_conn(inner: &Mutex<Inner>) {
         let mut guard = inner.lock().unwrap();
         guard.ongoing -= 1;
-        while let Some(task) = guard.tasks.pop() {
-            task.notify()
-        }
+        guard.notify_next();
     }
 
     fn conn_count(&self) -> usize {
         self.new.is_some() as usize + self.idle.len() + self.ongoing
     }
+
+    fn next(&self) -> Option<&(u64, Task)> {
+        match self.order {
+            QueueOrder::Fifo => self.tasks.front(),
+            QueueOrder::Lifo => self.tasks.back(),
+        }
+    }
+
+    fn notify_next(&mut self) {
+        if let Some((_, task)) = self.next() {
+            task.notify()
+        }
+    }
+
+    /// Returns `true` if the waiter holding `ticket` may take a connection.
+    fn is_turn(&self, ticket: Option<u64>) -> bool {
+        match self.next() {
+            None => true,
+            Some((next, _)) => Some(*next) == ticket,
+        }
+    }
+
+    fn enqueue(&mut self, ticket: &mut Option<u64>) -> Result<()> {
+        if let Some(id) = *ticket {
+            if let Some(waiter) = self.tasks.iter_mut().find(|(t, _)| *t == id) {
+                waiter.1 = task::current();
+                return Ok(());
+            }
+        }
+
+        if let Some(max_waiters) = self.max_waiters {
+            if self.tasks.len() >= max_waiters {
+                match self.rejection {
+                    RejectionPolicy::Reject => return Err(DriverError::PoolQueueFull.into()),
+                    RejectionPolicy::ShedOldest => match self.tasks.pop_front() {
+                        Some((oldest, task)) => {
+                            self.shed.insert(oldest);
+                            task.notify();
+                        }
+                        None => return Err(DriverError::PoolQueueFull.into()),
+                    },
+                }
+            }
+        }
+
+        let id = self.next_ticket;
+        self.next_ticket = self.next_ticket.wrapping_add(1);
+        self.tasks.push_back((id, task::current()));
+        *ticket = Some(id);
+        Ok(())
+    }
+
+    fn dequeue(&mut self, ticket: &mut Option<u64>) {
+        if let Some(id) = ticket.take() {
+            self.tasks.retain(|(t, _)| *t != id);
+            self.shed.remove(&id);
+        }
+    }
+
+    /// Returns `true` if the waiter holding `ticket` was pushed out of the queue.
+    fn take_shed(&mut self, ticket: &mut Option<u64>) -> bool {
+        match *ticket {
+            Some(id) if self.shed.remove(&id) => {
+                *ticket = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` while new connections must not be opened
+    /// because of recent connection failures.
+    fn is_backing_off(&mut self) -> bool {
+        let elapsed = match self.backoff {
+            None => return false,
+            Some(ref mut delay) => match delay.poll() {
+                Ok(Async::NotReady) => false,
+                Ok(Async::Ready(())) | Err(_) => true,
+            },
+        };
+
+        if elapsed {
+            self.backoff = None;
+        }
+        !elapsed
+    }
+}
+
+fn backoff_delay(options: &Options, failures: u32) -> Duration {
+    let exp = options
+        .backoff_multiplier
+        .powi(failures.saturating_sub(1).min(i32::MAX as u32) as i32);
+    let secs = (options.backoff_base.as_secs_f64() * exp)
+        .min(options.backoff_max.as_secs_f64())
+        .max(0.0);
+    let delay = Duration::from_secs_f64(secs);
+
+    if options.backoff_jitter {
+        delay.mul_f64(rand::thread_rng().gen_range(0.5, 1.0))
+    } else {
+        delay
+    }
 }
 
 #[derive(Clone)]
@@ -87,6 +242,7 @@ impl PoolBinding {
     pub(crate) fn detach(&mut self) {
         match self.take() {
             PoolBinding::Attached(pool) => *self = PoolBinding::Detached(pool),
+            PoolBinding::None => (),
             _ => unreachable!(),
         }
     }
@@ -97,12 +253,15 @@ impl PoolBinding {
 pub struct Pool {
     options: OptionsSource,
     pub(crate) inner: Arc<Mutex<Inner>>,
-    min: usize,
-    max: usize,
+    hooks: Hooks,
+    replicas: Arc<Vec<Pool>>,
+    next_replica: Arc<AtomicUsize>,
 }
 
 #[derive(Debug)]
 struct PoolInfo {
+    min: usize,
+    max: usize,
     new_len: usize,
     idle_len: usize,
     tasks_len: usize,
@@ -113,31 +272,70 @@ impl fmt::Debug for Pool {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let info = self.info();
         f.debug_struct("Pool")
-            .field("min", &self.min)
-            .field("max", &self.max)
+            .field("min", &info.min)
+            .field("max", &info.max)
             .field("new connections count", &info.new_len)
             .field("idle connections count", &info.idle_len)
             .field("tasks count", &info.tasks_len)
             .field("ongoing connections count", &info.ongoing)
+            .field("read replicas count", &self.replicas.len())
             .finish()
     }
 }
 
 impl Pool {
     /// Constructs a new Pool.
+    ///
+    /// If `read_replicas` are specified, a separate set of connections
+    /// is maintained for each of them (see `get_read_handle`).
     pub fn new<O>(options: O) -> Self
     where
         O: IntoOptions,
     {
-        let inner = Arc::new(Mutex::new(Inner {
-            new: None,
-            idle: Vec::new(),
-            tasks: Vec::new(),
-            ongoing: 0,
-        }));
+        let options_src = options.into_options_src();
+
+        let replicas = match options_src.get() {
+            Ok(opt) => opt
+                .read_replicas
+                .iter()
+                .map(|addr| {
+                    let options = Options {
+                        addr: addr.clone(),
+                        read_replicas: Vec::new(),
+                        ..opt.as_ref().clone()
+                    };
+                    Self::from_source(options.into_options_src(), Vec::new())
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        Self::from_source(options_src, replicas)
+    }
 
+    /// Constructs a new Pool and makes sure it is usable: validates the options,
+    /// establishes a connection and pings the server.
+    ///
+    /// The established connection is kept by the pool, so `on_connect`
+    /// hooks set on the returned pool are not called for it.
+    pub fn connect<O>(options: O) -> BoxFuture<Self>
+    where
+        O: IntoOptions,
+    {
         let options_src = options.into_options_src();
+        if let Err(err) = options_src.get() {
+            return Box::new(future::err(err));
+        }
 
+        let pool = Self::new(options_src);
+        Box::new(
+            pool.get_handle()
+                .and_then(ClientHandle::ping)
+                .map(move |_| pool),
+        )
+    }
+
+    fn from_source(options_src: OptionsSource, replicas: Vec<Pool>) -> Self {
         let mut min = 5;
         let mut max = 10;
 
@@ -149,16 +347,156 @@ impl Pool {
             Err(err) => error!("{}", err),
         }
 
+        let inner = Arc::new(Mutex::new(Inner {
+            new: None,
+            idle: Vec::new(),
+            tasks: VecDeque::new(),
+            next_ticket: 0,
+            order: QueueOrder::Fifo,
+            max_waiters: None,
+            rejection: RejectionPolicy::Reject,
+            shed: HashSet::new(),
+            ongoing: 0,
+            min,
+            max,
+            failures: 0,
+            backoff: None,
+        }));
+
         Self {
             options: options_src,
             inner,
-            min,
-            max,
+            hooks: Hooks::default(),
+            replicas: Arc::new(replicas),
+            next_replica: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn with_hooks<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Hooks),
+    {
+        f(&mut self.hooks);
+        let mut replicas = self.replicas.as_ref().clone();
+        for replica in replicas.iter_mut() {
+            f(&mut replica.hooks);
+        }
+        self.replicas = Arc::new(replicas);
+        self
+    }
+
+    /// Changes the lower bound of idle connections kept by the pool.
+    ///
+    /// Surplus idle connections are closed immediately,
+    /// connections in use are closed as they are returned.
+    pub fn set_min(&self, min: usize) {
+        for replica in self.replicas.iter() {
+            replica.set_min(min);
         }
+
+        let surplus = self.with_inner(move |mut inner| {
+            inner.min = min;
+            let keep = inner.idle.len().min(min);
+            inner.idle.split_off(keep)
+        });
+        drop(surplus);
+    }
+
+    /// Changes the upper bound of opened connections.
+    ///
+    /// When the bound grows, pending `get_handle` calls may open new
+    /// connections right away. When it shrinks, surplus idle connections
+    /// are closed immediately and connections in use are closed as they are returned.
+    pub fn set_max(&self, max: usize) {
+        for replica in self.replicas.iter() {
+            replica.set_max(max);
+        }
+
+        let surplus = self.with_inner(move |mut inner| {
+            inner.max = max;
+            let excess = inner.conn_count().saturating_sub(max);
+            let keep = inner.idle.len().saturating_sub(excess);
+            let surplus = inner.idle.split_off(keep);
+            inner.notify_next();
+            surplus
+        });
+        drop(surplus);
+    }
+
+    /// Sets the order in which tasks waiting for a connection are served
+    /// (defaults to `QueueOrder::Fifo`).
+    pub fn queue_order(self, order: QueueOrder) -> Self {
+        for replica in self.replicas.iter() {
+            replica.with_inner(|mut inner| inner.order = order);
+        }
+        self.with_inner(|mut inner| inner.order = order);
+        self
+    }
+
+    /// Limits the number of tasks waiting for a connection (defaults to unlimited).
+    /// See `rejection_policy` for what happens when the limit is reached.
+    pub fn max_waiters(self, max_waiters: usize) -> Self {
+        for replica in self.replicas.iter() {
+            replica.with_inner(|mut inner| inner.max_waiters = Some(max_waiters));
+        }
+        self.with_inner(|mut inner| inner.max_waiters = Some(max_waiters));
+        self
+    }
+
+    /// Sets what happens when `max_waiters` tasks are already waiting for a connection
+    /// (defaults to `RejectionPolicy::Reject`).
+    pub fn rejection_policy(self, policy: RejectionPolicy) -> Self {
+        for replica in self.replicas.iter() {
+            replica.with_inner(|mut inner| inner.rejection = policy);
+        }
+        self.with_inner(|mut inner| inner.rejection = policy);
+        self
+    }
+
+    /// Sets a callback that is called once for every newly established
+    /// connection before it is handed out, e.g. to run `SET` statements.
+    ///
+    /// The callback must not use the pool itself.
+    pub fn on_connect<F, R>(self, hook: F) -> Self
+    where
+        F: Fn(ClientHandle) -> R + Send + Sync + 'static,
+        R: IntoFuture<Item = ClientHandle, Error = Error>,
+        R::Future: Send + 'static,
+    {
+        let hook = handle_hook(hook);
+        self.with_hooks(|hooks| hooks.on_connect = Some(hook.clone()))
+    }
+
+    /// Sets a callback that is called every time a connection is taken
+    /// from the pool, e.g. to validate or reset its state.
+    pub fn on_checkout<F, R>(self, hook: F) -> Self
+    where
+        F: Fn(ClientHandle) -> R + Send + Sync + 'static,
+        R: IntoFuture<Item = ClientHandle, Error = Error>,
+        R::Future: Send + 'static,
+    {
+        let hook = handle_hook(hook);
+        self.with_hooks(|hooks| hooks.on_checkout = Some(hook.clone()))
+    }
+
+    /// Sets a callback that is called every time a connection is returned
+    /// to the pool. The connection is discarded if the callback returns `false`.
+    pub fn on_return<F>(self, hook: F) -> Self
+    where
+        F: Fn(&ClientHandle) -> bool + Send + Sync + 'static,
+    {
+        let hook: ReturnHook = Arc::new(hook);
+        self.with_hooks(|hooks| hooks.on_return = Some(hook.clone()))
+    }
+
+    pub(crate) fn checkout_hook(&self) -> Option<HandleHook> {
+        self.hooks.on_checkout.clone()
     }
 
     fn info(&self) -> PoolInfo {
         self.with_inner(|inner| PoolInfo {
+            min: inner.min,
+            max: inner.max,
             new_len: inner.new.is_some() as usize,
             idle_len: inner.idle.len(),
             tasks_len: inner.tasks.len(),
@@ -171,6 +509,31 @@ impl Pool {
         GetHandle::new(self)
     }
 
+    /// Returns future that resolves to `ClientHandle` connected to the primary host.
+    /// Same as `get_handle`.
+    pub fn get_write_handle(&self) -> GetHandle {
+        self.get_handle()
+    }
+
+    /// Returns future that resolves to `ClientHandle` connected to one of the
+    /// `read_replicas`, chosen in a round-robin fashion. Falls back to
+    /// the primary host if no replicas are configured.
+    pub fn get_read_handle(&self) -> GetHandle {
+        match self.next_read_pool() {
+            None => self.get_handle(),
+            Some(replica) => replica.get_handle(),
+        }
+    }
+
+    fn next_read_pool(&self) -> Option<&Pool> {
+        if self.replicas.is_empty() {
+            return None;
+        }
+
+        let index = self.next_replica.fetch_add(1, Ordering::Relaxed);
+        self.replicas.get(index % self.replicas.len())
+    }
+
     fn with_inner<F, T>(&self, fun: F) -> T
     where
         F: FnOnce(MutexGuard<Inner>) -> T,
@@ -179,23 +542,31 @@ impl Pool {
         fun(self.inner.lock().unwrap())
     }
 
-    fn poll(&mut self) -> Result<Async<ClientHandle>> {
+    fn poll(&mut self, ticket: &mut Option<u64>) -> Result<Async<ClientHandle>> {
+        if self.with_inner(|mut inner| inner.take_shed(ticket)) {
+            return Err(DriverError::PoolQueueFull.into());
+        }
+
         self.handle_futures()?;
 
-        match self.take_conn() {
+        match self.take_conn(ticket) {
             Some(client) => Ok(Async::Ready(client)),
             None => {
-                let new_conn_created = self.with_inner(|mut inner| {
-                    if inner.new.is_none() && inner.conn_count() < self.max {
+                let new_conn_created = self.with_inner(|mut inner| -> Result<bool> {
+                    if inner.is_turn(*ticket)
+                        && inner.new.is_none()
+                        && inner.conn_count() < inner.max
+                        && !inner.is_backing_off()
+                    {
                         inner.new.replace(self.new_connection());
-                        true
+                        Ok(true)
                     } else {
-                        inner.tasks.push(task::current());
-                        false
+                        inner.enqueue(ticket)?;
+                        Ok(false)
                     }
-                });
+                })?;
                 if new_conn_created {
-                    self.poll()
+                    self.poll(ticket)
                 } else {
                     Ok(Async::NotReady)
                 }
@@ -204,7 +575,11 @@ impl Pool {
     }
 
     fn new_connection(&self) -> BoxFuture<ClientHandle> {
-        Client::open(&self.options, Some(self.clone()))
+        let future = Client::open(&self.options, Some(self.clone()));
+        match self.hooks.on_connect.clone() {
+            None => future,
+            Some(hook) => Box::new(future.and_then(move |c| hook(c))),
+        }
     }
 
     fn handle_futures(&mut self) -> Result<()> {
@@ -217,11 +592,19 @@ impl Pool {
             match result {
                 Ok(Async::Ready(client)) => {
                     inner.new = None;
+                    inner.failures = 0;
                     inner.idle.push(client);
+                    inner.notify_next();
                 }
                 Ok(Async::NotReady) => (),
                 Err(err) => {
                     inner.new = None;
+                    inner.failures = inner.failures.saturating_add(1);
+                    if let Ok(options) = self.options.get() {
+                        let delay = backoff_delay(&options, inner.failures);
+                        inner.backoff = Some(Delay::new(Instant::now() + delay));
+                    }
+                    inner.notify_next();
                     return Err(err);
                 }
             }
@@ -230,12 +613,19 @@ impl Pool {
         })
     }
 
-    fn take_conn(&mut self) -> Option<ClientHandle> {
+    fn take_conn(&mut self, ticket: &mut Option<u64>) -> Option<ClientHandle> {
         self.with_inner(|mut inner| {
+            if !inner.is_turn(*ticket) {
+                return None;
+            }
+
             if let Some(mut client) = inner.idle.pop() {
                 client.pool = PoolBinding::Attached(self.clone());
                 client.set_inside(false);
+                client.set_pinned(false);
                 inner.ongoing += 1;
+                inner.dequeue(ticket);
+                inner.notify_next();
                 Some(client)
             } else {
                 None
@@ -244,22 +634,41 @@ impl Pool {
     }
 
     fn return_conn(&mut self, mut client: ClientHandle) {
-        let min = self.min;
+        let is_valid = match self.hooks.on_return {
+            None => true,
+            Some(ref hook) => hook(&client),
+        };
 
-        self.with_inner(|mut inner| {
+        let surplus = self.with_inner(|mut inner| {
             let is_attached = client.pool.is_attached();
             client.pool = PoolBinding::None;
             client.set_inside(true);
+            inner.ongoing -= 1;
 
-            if inner.idle.len() < min && is_attached {
+            let surplus = if inner.idle.len() < inner.min
+                && inner.conn_count() < inner.max
+                && is_attached
+                && is_valid
+            {
                 inner.idle.push(client);
-            }
-            inner.ongoing -= 1;
+                None
+            } else {
+                Some(client)
+            };
 
-            while let Some(task) = inner.tasks.pop() {
-                task.notify()
-            }
-        })
+            inner.notify_next();
+            surplus
+        });
+        drop(surplus);
+    }
+
+    fn cancel(&self, ticket: &mut Option<u64>) {
+        if ticket.is_some() {
+            self.with_inner(|mut inner| {
+                inner.dequeue(ticket);
+                inner.notify_next();
+            })
+        }
     }
 }
 
@@ -286,7 +695,7 @@ mod test {
     use std::{
         str::FromStr,
         sync::{
-            atomic::{AtomicBool, Ordering},
+            atomic::{AtomicBool, AtomicUsize, Ordering},
             Arc,
         },
         thread::spawn,
@@ -296,14 +705,14 @@ mod test {
     use tokio::prelude::*;
 
     use crate::{
-        errors::Error,
+        errors::{DriverError, Error},
         io::BoxFuture,
         test_misc::DATABASE_URL,
         types::{Block, Options},
         ClientHandle,
     };
 
-    use super::Pool;
+    use super::{backoff_delay, Pool, QueueOrder, RejectionPolicy};
 
     /// Same as `tokio::run`, but will panic if future panics and will return the result
     /// of future execution.
@@ -337,6 +746,22 @@ mod test {
         run(done).unwrap();
     }
 
+    #[test]
+    fn test_eager_connect() {
+        let pool = run(Pool::connect(DATABASE_URL.as_str())).unwrap();
+        assert_eq!(pool.info().idle_len, 1);
+    }
+
+    #[test]
+    fn test_eager_connect_failure() {
+        match run(Pool::connect("tcp://localhost:9000?foo=bar")) {
+            Err(Error::Url(_)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        run(Pool::connect("tcp://127.0.0.1:1")).unwrap_err();
+    }
+
     #[test]
     fn test_detach() {
         let pool = Pool::new(DATABASE_URL.as_str());
@@ -353,6 +778,172 @@ mod test {
         assert_eq!(pool.info().idle_len, 0);
     }
 
+    #[test]
+    fn test_hooks() {
+        let checkouts = Arc::new(AtomicUsize::new(0));
+        let counter = checkouts.clone();
+
+        let pool = Pool::new(DATABASE_URL.as_str())
+            .on_connect(|c| c.execute("SET max_block_size = 1234"))
+            .on_checkout(move |c| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(c)
+            })
+            .on_return(|_| false);
+
+        let done = pool
+            .get_handle()
+            .and_then(|c| {
+                c.query("SELECT value FROM system.settings WHERE name = 'max_block_size'")
+                    .fetch_all()
+            })
+            .and_then(|(_, block)| {
+                let value: &str = block.get(0, 0)?;
+                assert_eq!(value, "1234");
+                Ok(())
+            });
+
+        run(done).unwrap();
+
+        assert_eq!(checkouts.load(Ordering::SeqCst), 1);
+        let info = pool.info();
+        assert_eq!(info.ongoing, 0);
+        assert_eq!(info.idle_len, 0);
+    }
+
+    #[test]
+    fn test_resize() {
+        let options = Options::from_str(DATABASE_URL.as_str())
+            .unwrap()
+            .pool_min(2)
+            .pool_max(4);
+        let pool = Pool::new(options);
+        let other = pool.clone();
+
+        other.set_min(1);
+        other.set_max(8);
+
+        let info = pool.info();
+        assert_eq!(info.min, 1);
+        assert_eq!(info.max, 8);
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let options = Options::default()
+            .backoff_base(Duration::from_millis(100))
+            .backoff_multiplier(2.0)
+            .backoff_max(Duration::from_secs(1))
+            .backoff_jitter(false);
+
+        assert_eq!(backoff_delay(&options, 1), Duration::from_millis(100));
+        assert_eq!(backoff_delay(&options, 3), Duration::from_millis(400));
+        assert_eq!(backoff_delay(&options, 10), Duration::from_secs(1));
+        assert_eq!(backoff_delay(&options, u32::MAX), Duration::from_secs(1));
+
+        let options = options.backoff_jitter(true);
+        for failures in 1..5 {
+            let delay = backoff_delay(&options, failures);
+            assert!(delay <= Duration::from_millis(100 << (failures - 1)));
+            assert!(delay >= Duration::from_millis(50 << (failures - 1)));
+        }
+    }
+
+    #[test]
+    fn test_queue_order() {
+        use tokio::runtime::current_thread;
+
+        fn tickets(order: QueueOrder) -> (Vec<Option<u64>>, Pool) {
+            let pool = Pool::new(DATABASE_URL.as_str()).queue_order(order);
+            let mut tickets = vec![None; 3];
+            current_thread::block_on_all(future::lazy(|| {
+                pool.with_inner(|mut inner| {
+                    for ticket in tickets.iter_mut() {
+                        inner.enqueue(ticket).unwrap();
+                    }
+                });
+                Ok::<_, ()>(())
+            }))
+            .unwrap();
+            (tickets, pool)
+        }
+
+        let (mut fifo, pool) = tickets(QueueOrder::Fifo);
+        pool.with_inner(move |mut inner| {
+            assert!(inner.is_turn(fifo[0]));
+            assert!(!inner.is_turn(fifo[2]));
+            assert!(!inner.is_turn(None));
+            inner.dequeue(&mut fifo[0]);
+            assert!(inner.is_turn(fifo[1]));
+        });
+
+        let (lifo, pool) = tickets(QueueOrder::Lifo);
+        pool.with_inner(move |inner| {
+            assert!(inner.is_turn(lifo[2]));
+            assert!(!inner.is_turn(lifo[0]));
+        });
+        assert_eq!(pool.info().tasks_len, 3);
+    }
+
+    #[test]
+    fn test_read_replicas() {
+        let options = Options::from_str(DATABASE_URL.as_str())
+            .unwrap()
+            .read_replicas(vec!["replica1:9000", "replica2:9000"]);
+        let pool = Pool::new(options);
+
+        let first = pool.next_read_pool().unwrap().inner.clone();
+        let second = pool.next_read_pool().unwrap().inner.clone();
+        let third = pool.next_read_pool().unwrap().inner.clone();
+
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert!(Arc::ptr_eq(&first, &third));
+        assert!(!Arc::ptr_eq(&first, &pool.inner));
+
+        let pool = Pool::new(DATABASE_URL.as_str());
+        assert!(pool.next_read_pool().is_none());
+    }
+
+    #[test]
+    fn test_max_waiters() {
+        use tokio::runtime::current_thread;
+
+        fn enqueue(pool: &Pool, policy: RejectionPolicy) -> Vec<Result<Option<u64>, Error>> {
+            let pool = pool.clone().max_waiters(2).rejection_policy(policy);
+            current_thread::block_on_all(future::lazy(move || {
+                let results = pool.with_inner(|mut inner| {
+                    (0..3)
+                        .map(|_| {
+                            let mut ticket = None;
+                            inner.enqueue(&mut ticket).map(|_| ticket)
+                        })
+                        .collect()
+                });
+                Ok::<_, ()>(results)
+            }))
+            .unwrap()
+        }
+
+        let pool = Pool::new(DATABASE_URL.as_str());
+        let results = enqueue(&pool, RejectionPolicy::Reject);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_ok());
+        match results[2] {
+            Err(Error::Driver(DriverError::PoolQueueFull)) => {}
+            ref other => panic!("unexpected result {:?}", other),
+        }
+
+        let pool = Pool::new(DATABASE_URL.as_str());
+        let mut results = enqueue(&pool, RejectionPolicy::ShedOldest);
+        assert!(results.iter().all(Result::is_ok));
+        let mut oldest = results.remove(0).unwrap();
+        pool.with_inner(move |mut inner| {
+            assert_eq!(inner.tasks.len(), 2);
+            assert!(inner.take_shed(&mut oldest));
+            assert!(oldest.is_none());
+        });
+    }
+
     #[test]
     fn test_many_connection() {
         let options = Options::from_str(DATABASE_URL.as_str())
@@ -432,14 +1023,14 @@ mod test {
 
             for _ in 0..100 {
                 let local_barer = barer.clone();
-                let mut local_pool = pool.clone();
+                let local_pool = pool.clone();
 
                 let thread = spawn(|| {
                     current_thread::block_on_all(lazy(|| {
                         current_thread::spawn(lazy(move || {
                             while local_barer.load(Ordering::SeqCst) {}
 
-                            match local_pool.poll() {
+                            match local_pool.get_handle().poll() {
                                 Ok(_) => Ok(()),
                                 Err(_) => Err(()),
                             }
@@ -0,0 +1,274 @@
+use std::{convert::TryFrom, marker, sync::Arc};
+
+use chrono_tz::Tz;
+
+use crate::{
+    errors::{DriverError, Error, FromSqlError, Result},
+    types::{
+        column::{ArcColumnWrapper, ColumnData, Either},
+        decimal::NoBits,
+        Block, Column, SqlType, Value,
+    },
+};
+
+use super::DEFAULT_CAPACITY;
+
+/// Builds a block for a known set of columns.
+///
+/// Pushed values are converted to the column types, e.g. an `u8` is stored
+/// into an `Int64` column and a plain value into a `Nullable` one. A value
+/// that can't be converted is rejected with the name of its column.
+pub struct BlockBuilder {
+    block: Block,
+}
+
+impl BlockBuilder {
+    /// Creates a builder for columns with the given names and types.
+    pub fn new<S>(schema: Vec<(S, SqlType)>) -> Result<Self>
+    where
+        S: Into<String>,
+    {
+        Self::with_capacity(schema, DEFAULT_CAPACITY)
+    }
+
+    /// Creates a builder with columns preallocated for `rows` rows.
+    pub fn with_capacity<S>(schema: Vec<(S, SqlType)>, rows: usize) -> Result<Self>
+    where
+        S: Into<String>,
+    {
+        let mut block = Block::with_capacity(rows);
+        for (name, sql_type) in schema {
+            block.columns.push(Column {
+                name: name.into(),
+                data: <dyn ColumnData>::from_type::<ArcColumnWrapper>(
+                    sql_type,
+                    Tz::UTC,
+                    block.capacity,
+                )?,
+                _marker: marker::PhantomData,
+                stats: Default::default(),
+            });
+        }
+        Ok(Self { block })
+    }
+
+    /// Names and types of the columns.
+    pub fn schema(&self) -> Vec<(&str, SqlType)> {
+        self.block
+            .columns()
+            .iter()
+            .map(|column| (column.name(), column.sql_type()))
+            .collect()
+    }
+
+    /// Appends a row given as pairs of column names and values.
+    ///
+    /// Every column has to be given exactly once. Nothing is appended
+    /// if any of the values is rejected.
+    pub fn push<I, K, V>(&mut self, row: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<Value>,
+    {
+        let columns = &self.block.columns;
+        let mut values: Vec<Option<Value>> = vec![None; columns.len()];
+
+        for (name, value) in row {
+            let name = name.as_ref();
+            let index = match columns.iter().position(|column| column.name() == name) {
+                Some(index) => index,
+                None => return Err(DriverError::UnknownColumn { name: name.into() }.into()),
+            };
+
+            if values[index].is_some() {
+                return Err(DriverError::DuplicateColumn { name: name.into() }.into());
+            }
+
+            let value = value.into();
+            let dst_type = columns[index].sql_type();
+            values[index] = match coerce(value.clone(), dst_type) {
+                Some(value) => Some(value),
+                None => {
+                    return Err(Error::FromSql(FromSqlError::InvalidColumnType {
+                        column: name.into(),
+                        src: SqlType::from(value).to_string(),
+                        dst: dst_type.to_string(),
+                    }))
+                }
+            };
+        }
+
+        if let Some(index) = values.iter().position(Option::is_none) {
+            let name = columns[index].name().into();
+            return Err(DriverError::MissingColumn { name }.into());
+        }
+
+        for (column, value) in self.block.columns.iter_mut().zip(values) {
+            column.push(value.unwrap());
+        }
+        Ok(())
+    }
+
+    /// Returns the number of rows pushed so far.
+    pub fn row_count(&self) -> usize {
+        self.block.row_count()
+    }
+
+    /// Returns the built block.
+    pub fn build(self) -> Block {
+        self.block
+    }
+}
+
+macro_rules! coerce_int {
+    ( $value:expr, $dst:expr, $( $t:ident: $k:ident ),* ) => {
+        match $dst {
+            $( SqlType::$k => $t::try_from($value).ok().map(Value::$k), )*
+            SqlType::Float32 => Some(Value::Float32($value as f32)),
+            SqlType::Float64 => Some(Value::Float64($value as f64)),
+            _ => None,
+        }
+    };
+}
+
+/// Converts `value` to a value of `dst` type, returns `None` if it doesn't fit.
+pub(super) fn coerce(value: Value, dst: SqlType) -> Option<Value> {
+    let int = match value {
+        Value::UInt8(v) => Some(i128::from(v)),
+        Value::UInt16(v) => Some(i128::from(v)),
+        Value::UInt32(v) => Some(i128::from(v)),
+        Value::UInt64(v) => Some(i128::from(v)),
+        Value::Int8(v) => Some(i128::from(v)),
+        Value::Int16(v) => Some(i128::from(v)),
+        Value::Int32(v) => Some(i128::from(v)),
+        Value::Int64(v) => Some(i128::from(v)),
+        _ => None,
+    };
+
+    if let Some(int) = int {
+        if let SqlType::Nullable(inner) = dst {
+            return coerce(value, *inner).map(|v| Value::Nullable(Either::Right(Box::new(v))));
+        }
+        return coerce_int!(
+            int, dst,
+            u8: UInt8, u16: UInt16, u32: UInt32, u64: UInt64,
+            i8: Int8, i16: Int16, i32: Int32, i64: Int64
+        );
+    }
+
+    match (dst, value) {
+        (SqlType::Nullable(inner), Value::Nullable(Either::Left(_))) => {
+            Some(Value::Nullable(Either::Left(inner)))
+        }
+        (SqlType::Nullable(inner), Value::Nullable(Either::Right(v))) => {
+            coerce(*v, *inner).map(|v| Value::Nullable(Either::Right(Box::new(v))))
+        }
+        (SqlType::Nullable(inner), v) => {
+            coerce(v, *inner).map(|v| Value::Nullable(Either::Right(Box::new(v))))
+        }
+        (SqlType::Float32, Value::Float32(v)) => Some(Value::Float32(v)),
+        (SqlType::Float64, Value::Float32(v)) => Some(Value::Float64(f64::from(v))),
+        (SqlType::Float64, Value::Float64(v)) => Some(Value::Float64(v)),
+        (SqlType::String, v @ Value::String(_)) => Some(v),
+        (SqlType::FixedString(len), Value::String(v)) if v.len() <= len => {
+            Some(Value::String(v))
+        }
+        (SqlType::Date, v @ Value::Date(..)) => Some(v),
+        (SqlType::DateTime, v @ Value::DateTime(..)) => Some(v),
+        (SqlType::Decimal(precision, scale), Value::Decimal(v)) => {
+            let mut v = v.set_scale(scale);
+            v.precision = precision;
+            v.nobits = NoBits::from_precision(precision)?;
+            Some(Value::Decimal(v))
+        }
+        (SqlType::Array(inner), Value::Array(_, vs)) => {
+            let mut values = Vec::with_capacity(vs.len());
+            for v in vs.iter() {
+                values.push(coerce(v.clone(), *inner)?);
+            }
+            Some(Value::Array(inner, Arc::new(values)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn builder() -> BlockBuilder {
+        BlockBuilder::new(vec![
+            ("id", SqlType::Int64),
+            ("name", SqlType::FixedString(4)),
+            ("score", SqlType::Nullable(SqlType::Float64.into())),
+            ("tags", SqlType::Array(SqlType::UInt16.into())),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_coerce_values() {
+        let mut builder = builder();
+        builder
+            .push(vec![
+                ("id", Value::from(1_u8)),
+                ("name", Value::from("abc")),
+                ("score", Value::from(0.5_f32)),
+                ("tags", Value::Array(SqlType::UInt8.into(), Arc::new(vec![Value::UInt8(7)]))),
+            ])
+            .unwrap();
+        builder
+            .push(vec![
+                ("tags", Value::Array(SqlType::UInt8.into(), Arc::new(vec![]))),
+                ("score", Value::from(None::<f64>)),
+                ("name", Value::from("abcd")),
+                ("id", Value::from(-2_i32)),
+            ])
+            .unwrap();
+
+        let block = builder.build();
+        assert_eq!(block.row_count(), 2);
+        assert_eq!(block.get::<i64, _>(0, "id").unwrap(), 1);
+        assert_eq!(block.get::<i64, _>(1, "id").unwrap(), -2);
+        assert_eq!(block.get::<Option<f64>, _>(0, "score").unwrap(), Some(0.5));
+        assert_eq!(block.get::<Option<f64>, _>(1, "score").unwrap(), None);
+        assert_eq!(block.get::<Vec<u16>, _>(0, "tags").unwrap(), vec![7]);
+    }
+
+    #[test]
+    fn test_reject_values() {
+        let mut builder = builder();
+
+        let row = |id: Value, name: &str| {
+            vec![
+                ("id", id),
+                ("name", Value::from(name)),
+                ("score", Value::from(None::<f64>)),
+                ("tags", Value::Array(SqlType::UInt16.into(), Arc::new(vec![]))),
+            ]
+        };
+
+        match builder.push(row(Value::from("1"), "abc")) {
+            Err(Error::FromSql(FromSqlError::InvalidColumnType { column, .. })) => {
+                assert_eq!(column, "id")
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        builder.push(row(Value::from(u64::MAX), "abc")).unwrap_err();
+        builder.push(row(Value::from(1_u8), "abcde")).unwrap_err();
+
+        builder.push(vec![("id", Value::from(1_u8))]).unwrap_err();
+        builder.push(vec![("foo", Value::from(1_u8))]).unwrap_err();
+
+        assert_eq!(builder.row_count(), 0);
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let schema = vec![("id", SqlType::UInt64)];
+        let builder = BlockBuilder::with_capacity(schema, 1000).unwrap();
+        assert!(builder.block.byte_size() >= 8000);
+        assert_eq!(builder.row_count(), 0);
+    }
+}
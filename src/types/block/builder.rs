@@ -97,6 +97,7 @@ fn put_param<K: ColumnType>(
                         block.capacity,
                     )?,
                     _marker: marker::PhantomData,
+                    stats: Default::default(),
                 };
 
                 block.columns.push(column);
@@ -2,6 +2,7 @@ use std::cmp;
 
 use crate::types::{Block, ColumnType};
 
+/// Iterator over chunks of a block, see `Block::chunks`.
 pub struct ChunkIterator<'a, K: ColumnType> {
     position: usize,
     size: usize,
@@ -38,7 +39,7 @@ impl<'a, K: ColumnType> Iterator for ChunkIterator<'a, K> {
 }
 
 impl<'a, K: ColumnType> ChunkIterator<'a, K> {
-    pub fn new(size: usize, block: &Block<K>) -> ChunkIterator<K> {
+    pub(crate) fn new(size: usize, block: &Block<K>) -> ChunkIterator<K> {
         ChunkIterator {
             position: 0,
             size,
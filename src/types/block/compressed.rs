@@ -1,11 +1,11 @@
 use std::{
     io,
-    io::Read,
+    io::{BufRead, Read},
     mem,
     os::raw::{c_char, c_int},
 };
 
-use byteorder::{LittleEndian, WriteBytesExt};
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
 use clickhouse_rs_cityhash_sys::{city_hash_128, UInt128};
 use lz4::liblz4::LZ4_decompress_safe;
 
@@ -13,21 +13,32 @@ use crate::{binary::ReadEx, errors::{Error, Result}};
 
 const DBMS_MAX_COMPRESSED_SIZE: u32 = 0x4000_0000; // 1GB
 
+/// Method byte of frames with data compressed by LZ4.
+pub(crate) const LZ4_METHOD: u8 = 0x82;
+
+/// Method byte of frames with uncompressed data.
+pub(crate) const NONE_METHOD: u8 = 0x02;
+
+/// Reads the data of a block frame by frame: each compressed frame is
+/// decompressed only once the previous one was consumed by the decoder.
 pub(crate) struct CompressedReader<'a, R> {
     reader: &'a mut R,
     cursor: io::Cursor<Vec<u8>>,
+    // Compressed frame, used only if the reader can't lend it whole
+    scratch: Vec<u8>,
 }
 
 pub(crate) fn make<R>(reader: &mut R) -> CompressedReader<R> {
     CompressedReader {
         reader,
         cursor: io::Cursor::new(Vec::new()),
+        scratch: Vec::new(),
     }
 }
 
 impl<'a, R> CompressedReader<'a, R>
 where
-    R: Read + ReadEx,
+    R: BufRead,
 {
     fn is_empty(&self) -> bool {
         let len = self.cursor.get_ref().len();
@@ -36,18 +47,16 @@ where
     }
 
     fn fill(&mut self) -> Result<()> {
-        let cursor = mem::replace(&mut self.cursor, io::Cursor::new(Vec::new()));
-        let buffer = cursor.into_inner();
-
-        let tmp = decompress_buffer(&mut self.reader, buffer)?;
-        self.cursor = io::Cursor::new(tmp);
+        let mut data = mem::take(self.cursor.get_mut());
+        decompress_frame(self.reader, &mut self.scratch, &mut data)?;
+        self.cursor = io::Cursor::new(data);
         Ok(())
     }
 }
 
 impl<'a, R> Read for CompressedReader<'a, R>
 where
-    R: Read + ReadEx,
+    R: BufRead,
 {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         if self.is_empty() {
@@ -58,46 +67,106 @@ where
     }
 }
 
-fn decompress_buffer<R>(reader: &mut R, mut buffer: Vec<u8>) -> Result<Vec<u8>>
+/// Returns the size of the compressed frame that starts at `header`,
+/// `None` if its header wasn't read whole.
+pub(crate) fn frame_size(header: &[u8]) -> Option<usize> {
+    if header.len() < 25 {
+        return None;
+    }
+
+    let compressed = LittleEndian::read_u32(&header[17..21]);
+    if compressed > DBMS_MAX_COMPRESSED_SIZE {
+        return None;
+    }
+    Some(16 + compressed as usize)
+}
+
+/// Decompresses the next frame into `data`, reusing its allocation.
+///
+/// The frame is decompressed straight from the buffer of `reader`
+/// if it's there whole, and copied to `scratch` otherwise.
+fn decompress_frame<R>(reader: &mut R, scratch: &mut Vec<u8>, data: &mut Vec<u8>) -> Result<()>
 where
-    R: ReadEx,
+    R: BufRead,
 {
     let h = UInt128 {
         lo: reader.read_scalar()?,
         hi: reader.read_scalar()?,
     };
 
-    let method: u8 = reader.read_scalar()?;
-    if method != 0x82 {
-        let message: String = format!("unsupported compression method {}", method);
-        return Err(raise_error(message));
+    let available = reader.fill_buf()?;
+    if available.len() >= 9 {
+        let compressed = LittleEndian::read_u32(&available[1..5]);
+        check_frame_size(compressed)?;
+        let compressed = compressed as usize;
+        if available.len() >= compressed {
+            decompress_buffer(h, &available[..compressed], data)?;
+            reader.consume(compressed);
+            return Ok(());
+        }
     }
 
+    let method: u8 = reader.read_scalar()?;
     let compressed: u32 = reader.read_scalar()?;
     let original: u32 = reader.read_scalar()?;
+    check_frame_size(compressed)?;
 
+    scratch.resize(compressed as usize, 0_u8);
+    {
+        let mut cursor = io::Cursor::new(&mut *scratch);
+        cursor.write_u8(method)?;
+        cursor.write_u32::<LittleEndian>(compressed)?;
+        cursor.write_u32::<LittleEndian>(original)?;
+    }
+    reader.read_bytes(&mut scratch[9..])?;
+
+    decompress_buffer(h, scratch, data)
+}
+
+/// Fails unless a frame of `compressed` bytes holds at least its own header.
+fn check_frame_size(compressed: u32) -> Result<()> {
     if compressed > DBMS_MAX_COMPRESSED_SIZE {
         return Err(raise_error("compressed data too big".to_string()));
     }
+    if compressed < 9 {
+        return Err(raise_error("data was corrupted".to_string()));
+    }
+    Ok(())
+}
 
-    buffer.resize(compressed as usize, 0_u8);
-    {
-        let mut cursor = io::Cursor::new(&mut buffer);
-        cursor.write_u8(0x82)?;
-        cursor.write_u32::<LittleEndian>(compressed)?;
-        cursor.write_u32::<LittleEndian>(original)?;
+fn decompress_buffer(h: UInt128, frame: &[u8], data: &mut Vec<u8>) -> Result<()> {
+    if frame.len() < 9 {
+        return Err(raise_error("data was corrupted".to_string()));
+    }
+
+    let method = frame[0];
+    if method != LZ4_METHOD && method != NONE_METHOD {
+        let message: String = format!("unsupported compression method {}", method);
+        return Err(raise_error(message));
     }
-    reader.read_bytes(&mut buffer[9..])?;
 
-    if h != city_hash_128(&buffer) {
+    let compressed = LittleEndian::read_u32(&frame[1..5]);
+    let original = LittleEndian::read_u32(&frame[5..9]);
+
+    check_frame_size(compressed)?;
+    if compressed as usize != frame.len() || h != city_hash_128(frame) {
         return Err(raise_error("data was corrupted".to_string()));
     }
 
-    let data = vec![0_u8; original as usize];
+    data.clear();
+    if method == NONE_METHOD {
+        if original != compressed - 9 {
+            return Err(raise_error("data was corrupted".to_string()));
+        }
+        data.extend_from_slice(&frame[9..]);
+        return Ok(());
+    }
+
+    data.resize(original as usize, 0_u8);
     let status = unsafe {
         LZ4_decompress_safe(
-            (buffer.as_mut_ptr() as *const c_char).add(9),
-            data.as_ptr() as *mut i8,
+            (frame.as_ptr() as *const c_char).add(9),
+            data.as_mut_ptr() as *mut c_char,
             (compressed - 9) as c_int,
             original as c_int,
         )
@@ -107,7 +176,7 @@ where
         return Err(raise_error("can't decompress data".to_string()));
     }
 
-    Ok(data)
+    Ok(())
 }
 
 fn raise_error(message: String) -> Error {
@@ -116,7 +185,13 @@ fn raise_error(message: String) -> Error {
 
 #[cfg(test)]
 mod test {
+    use chrono_tz::Tz;
+
     use super::*;
+    use crate::{
+        binary::Encoder,
+        types::{Block, Simple},
+    };
 
     #[test]
     fn test_decompress() {
@@ -132,8 +207,69 @@ mod test {
         ];
 
         let mut cursor = io::Cursor::new(&source[..]);
-        let actual = decompress_buffer(&mut cursor, Vec::new()).unwrap();
+        let mut actual = Vec::new();
+        decompress_frame(&mut cursor, &mut Vec::new(), &mut actual).unwrap();
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_decompress_split_frame() {
+        let block = Block::<Simple>::new().column("id", vec![7_u64; 1000]);
+        let mut encoder = Encoder::new();
+        block.write(&mut encoder, true);
+        let source = encoder.get_buffer();
+
+        let mut reader = io::BufReader::with_capacity(16, &source[..]);
+        let actual = Block::load(&mut reader, Tz::UTC, true).unwrap();
+        assert_eq!(actual, block);
+
+        let mut truncated = io::Cursor::new(&source[..source.len() - 1]);
+        Block::load(&mut truncated, Tz::UTC, true).unwrap_err();
+    }
+
+    #[test]
+    fn test_invalid_frame_size() {
+        for compressed in &[0_u32, 8, DBMS_MAX_COMPRESSED_SIZE + 1] {
+            let mut source = vec![0_u8; 16];
+            source.push(LZ4_METHOD);
+            source.extend_from_slice(&compressed.to_le_bytes());
+            source.extend_from_slice(&[0; 4]);
+            source.extend_from_slice(&[0; 32]);
+
+            let mut cursor = io::Cursor::new(&source[..]);
+            decompress_frame(&mut cursor, &mut Vec::new(), &mut Vec::new()).unwrap_err();
+
+            // Frames that aren't in the buffer whole are read field by field.
+            let mut reader = io::BufReader::with_capacity(20, &source[..]);
+            decompress_frame(&mut reader, &mut Vec::new(), &mut Vec::new()).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_frame_size() {
+        let block = Block::<Simple>::new().column("id", vec![7_u64; 1000]);
+        let mut encoder = Encoder::new();
+        block.write(&mut encoder, true);
+        let source = encoder.get_buffer();
+
+        assert_eq!(frame_size(&source), Some(source.len()));
+        assert_eq!(frame_size(&source[..24]), None);
+    }
+
+    #[test]
+    fn test_uncompressed_frame() {
+        let block = Block::<Simple>::new().column("id", vec![7_u64; 10]);
+        let mut encoder = Encoder::new();
+        block.write_frame(&mut encoder, 1024);
+        let source = encoder.get_buffer();
+        assert_eq!(source[16], NONE_METHOD);
+
+        let actual = Block::load(&mut io::Cursor::new(&source[..]), Tz::UTC, true).unwrap();
+        assert_eq!(actual, block);
+
+        let mut encoder = Encoder::new();
+        block.write_frame(&mut encoder, 10);
+        assert_eq!(encoder.get_buffer()[16], LZ4_METHOD);
+    }
 }
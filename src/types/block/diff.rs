@@ -0,0 +1,193 @@
+use std::{cmp, fmt};
+
+use crate::types::{Block, ColumnType, SqlType, Value};
+
+/// Number of differing values shown by `Display`.
+const MAX_SHOWN_CELLS: usize = 20;
+
+/// Differences between two blocks, see `Block::diff`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlockDiff {
+    /// Columns at the same position with a different name or type,
+    /// or present in one block only.
+    pub columns: Vec<ColumnDiff>,
+    /// Row counts of both blocks, if they differ.
+    pub rows: Option<(usize, usize)>,
+    /// Differing values of the columns of the same type,
+    /// within the rows of both blocks.
+    pub cells: Vec<CellDiff>,
+}
+
+/// Column at `index` that differs between two blocks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnDiff {
+    pub index: usize,
+    /// Name and type in the left block, `None` if it has fewer columns.
+    pub left: Option<(String, SqlType)>,
+    /// Name and type in the right block, `None` if it has fewer columns.
+    pub right: Option<(String, SqlType)>,
+}
+
+/// Value that differs between two blocks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellDiff {
+    pub row: usize,
+    pub column: String,
+    pub left: Value,
+    pub right: Value,
+}
+
+impl BlockDiff {
+    pub(crate) fn new<L: ColumnType, R: ColumnType>(left: &Block<L>, right: &Block<R>) -> Self {
+        let mut diff = BlockDiff::default();
+
+        let column_count = cmp::max(left.column_count(), right.column_count());
+        for index in 0..column_count {
+            let (l, r) = (schema(left, index), schema(right, index));
+            if l != r {
+                diff.columns.push(ColumnDiff {
+                    index,
+                    left: l,
+                    right: r,
+                });
+            }
+        }
+
+        if left.row_count() != right.row_count() {
+            diff.rows = Some((left.row_count(), right.row_count()));
+        }
+
+        let row_count = cmp::min(left.row_count(), right.row_count());
+        for (l, r) in left.columns().iter().zip(right.columns()) {
+            if l.sql_type() != r.sql_type() {
+                continue;
+            }
+
+            for row in 0..row_count {
+                if l.at(row) != r.at(row) {
+                    diff.cells.push(CellDiff {
+                        row,
+                        column: l.name().to_string(),
+                        left: Value::from(l.at(row)),
+                        right: Value::from(r.at(row)),
+                    });
+                }
+            }
+        }
+
+        diff.cells.sort_by_key(|cell| cell.row);
+        diff
+    }
+
+    /// Returns `true` if the blocks are the same.
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty() && self.rows.is_none() && self.cells.is_empty()
+    }
+}
+
+fn schema<K: ColumnType>(block: &Block<K>, index: usize) -> Option<(String, SqlType)> {
+    let column = block.columns().get(index)?;
+    Some((column.name().to_string(), column.sql_type()))
+}
+
+fn fmt_column(f: &mut fmt::Formatter, column: &Option<(String, SqlType)>) -> fmt::Result {
+    match column {
+        Some((name, sql_type)) => write!(f, "`{}' {}", name, sql_type),
+        None => write!(f, "none"),
+    }
+}
+
+impl fmt::Display for BlockDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for column in &self.columns {
+            write!(f, "column {}: ", column.index)?;
+            fmt_column(f, &column.left)?;
+            write!(f, " != ")?;
+            fmt_column(f, &column.right)?;
+            writeln!(f)?;
+        }
+
+        if let Some((left, right)) = self.rows {
+            writeln!(f, "row count: {} != {}", left, right)?;
+        }
+
+        for cell in self.cells.iter().take(MAX_SHOWN_CELLS) {
+            writeln!(
+                f,
+                "row {}, column `{}': {} != {}",
+                cell.row, cell.column, cell.left, cell.right
+            )?;
+        }
+
+        if self.cells.len() > MAX_SHOWN_CELLS {
+            writeln!(f, "... {} more values differ", self.cells.len() - MAX_SHOWN_CELLS)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Simple;
+
+    #[test]
+    fn test_diff() {
+        let left = Block::<Simple>::new()
+            .column("id", vec![1_u32, 2, 3])
+            .column("name", vec!["a", "b", "c"])
+            .column("score", vec![1.0_f64, 2.0, 3.0]);
+        let right = Block::<Simple>::new()
+            .column("id", vec![1_u32, 5])
+            .column("name", vec!["a", "x"])
+            .column("score", vec![1_u8, 2]);
+
+        let diff = left.diff(&right);
+        assert_eq!(
+            diff.columns,
+            vec![ColumnDiff {
+                index: 2,
+                left: Some(("score".into(), SqlType::Float64)),
+                right: Some(("score".into(), SqlType::UInt8)),
+            }]
+        );
+        assert_eq!(diff.rows, Some((3, 2)));
+        assert_eq!(
+            diff.cells[0],
+            CellDiff {
+                row: 1,
+                column: "id".into(),
+                left: Value::UInt32(2),
+                right: Value::UInt32(5),
+            }
+        );
+        assert_eq!(diff.cells.len(), 2);
+        assert_eq!(
+            diff.to_string(),
+            "column 2: `score' Float64 != `score' UInt8\n\
+             row count: 3 != 2\n\
+             row 1, column `id': 2 != 5\n\
+             row 1, column `name': b != x\n"
+        );
+
+        assert!(left.diff(&left.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_of_column_count() {
+        let left = Block::<Simple>::new().column("id", vec![1_u32]);
+        let right = left.clone().column("name", vec!["a"]);
+
+        let diff = left.diff(&right);
+        assert_eq!(diff.to_string(), "column 1: none != `name' String\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "row 0, column `id': 1 != 2")]
+    fn test_assert_blocks_eq() {
+        let left = Block::<Simple>::new().column("id", vec![1_u32]);
+        let right = Block::<Simple>::new().column("id", vec![2_u32]);
+        crate::assert_blocks_eq!(left, right);
+    }
+}
@@ -1,7 +1,8 @@
 use std::{
     cmp, fmt,
-    io::{Cursor, Read},
+    io::{BufRead, Cursor, Write},
     marker::PhantomData,
+    sync::Arc,
 };
 
 use byteorder::{LittleEndian, WriteBytesExt};
@@ -11,29 +12,45 @@ use lz4::liblz4::{LZ4_compressBound, LZ4_compress_default};
 
 use crate::{
     binary::{protocol, Encoder, ReadEx},
-    errors::{Error, FromSqlError, Result},
+    errors::{ColumnMismatch, DriverError, Error, FromSqlError, Result},
+    formats::json,
     types::{
-        column::{self, ArcColumnWrapper, Column, ColumnFrom},
-        FromSql, ColumnType, Simple,
+        column::{self, ArcColumnWrapper, Column, ColumnData, ColumnFrom, StringLayout},
+        FromSql, ColumnType, InsertRow, PushTyped, Simple, SqlType, Value, ValueRef,
     },
 };
 
-use self::chunk_iterator::ChunkIterator;
-pub(crate) use self::row::BlockRef;
+pub(crate) use self::{compressed::frame_size, row::BlockRef, sort::compare};
 pub use self::{
+    block_builder::BlockBuilder,
     block_info::BlockInfo,
+    diff::{BlockDiff, CellDiff, ColumnDiff},
+    chunk_iterator::ChunkIterator,
     builder::{RCons, RNil, RowBuilder},
+    pretty::PrettyTable,
     row::{Row, Rows},
+    sort::{NullsOrder, SortOrder},
 };
 use crate::types::Complex;
 
+/// How received blocks are read, see `Block::load_with`.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct LoadOptions {
+    pub(crate) compress: bool,
+    /// Whether columns are kept encoded until they're accessed.
+    pub(crate) lazy: bool,
+    pub(crate) strings: StringLayout,
+}
+
+mod block_builder;
 mod block_info;
 mod builder;
 mod chunk_iterator;
 mod compressed;
+mod diff;
+mod pretty;
 mod row;
-
-const INSERT_BLOCK_SIZE: usize = 1_048_576;
+mod sort;
 
 const DEFAULT_CAPACITY: usize = 100;
 
@@ -83,7 +100,10 @@ impl<K: ColumnType> AsRef<Block<K>> for Block<K> {
 
 impl ColumnIdx for usize {
     #[inline(always)]
-    fn get_index<K: ColumnType>(&self, _: &[Column<K>]) -> Result<usize> {
+    fn get_index<K: ColumnType>(&self, columns: &[Column<K>]) -> Result<usize> {
+        if *self >= columns.len() {
+            return Err(Error::FromSql(FromSqlError::OutOfRange));
+        }
         Ok(*self)
     }
 }
@@ -118,6 +138,8 @@ impl Block {
     }
 
     /// Constructs a new, empty `Block` with the specified capacity.
+    ///
+    /// Columns created by `push` are preallocated for `capacity` rows.
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             info: Default::default(),
@@ -126,19 +148,151 @@ impl Block {
         }
     }
 
+    /// Adds a column of an explicit type instead of the one inferred from
+    /// the values, see `column`.
+    ///
+    /// Values are converted the same way as by `BlockBuilder`, a value that
+    /// doesn't fit the type is rejected.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::{Block, SqlType};
+    /// let score = SqlType::Nullable(SqlType::Float64.into());
+    /// let block = Block::new()
+    ///     .column_typed("id", SqlType::UInt64, vec![1_u8, 2, 3])?
+    ///     .column_typed("score", score, vec![Some(0.5_f32), None, None])?;
+    /// # Ok::<(), clickhouse_rs::errors::Error>(())
+    /// ```
+    pub fn column_typed<I, V>(self, name: &str, sql_type: SqlType, values: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        let mut column: Column<Simple> = Column {
+            name: name.into(),
+            data: <dyn ColumnData>::from_type::<ArcColumnWrapper>(
+                sql_type,
+                Tz::UTC,
+                self.capacity,
+            )?,
+            _marker: PhantomData,
+            stats: Default::default(),
+        };
+
+        for value in values {
+            let value = value.into();
+            match block_builder::coerce(value.clone(), sql_type) {
+                Some(value) => column.push(value),
+                None => {
+                    return Err(Error::FromSql(FromSqlError::InvalidColumnType {
+                        column: name.into(),
+                        src: SqlType::from(value).to_string(),
+                        dst: sql_type.to_string(),
+                    }))
+                }
+            }
+        }
+
+        if !self.columns.is_empty() && self.row_count() != column.len() {
+            let message = format!(
+                "Column `{}' has {} rows instead of {}.",
+                name,
+                column.len(),
+                self.row_count()
+            );
+            return Err(message.into());
+        }
+
+        let mut block = self;
+        block.append_column(column);
+        Ok(block)
+    }
+
+    /// Reads a JSON array of row objects with columns of the given names
+    /// and types, see `formats::json`.
+    pub fn from_json<S>(rows: &str, schema: Vec<(S, SqlType)>) -> Result<Self>
+    where
+        S: Into<String>,
+    {
+        json::read_array(rows, schema)
+    }
+
+    /// Reads `JSONEachRow` data with columns of the given names and types,
+    /// see `formats::json`.
+    pub fn from_json_each_row<R, S>(reader: R, schema: Vec<(S, SqlType)>) -> Result<Self>
+    where
+        R: BufRead,
+        S: Into<String>,
+    {
+        json::read(reader, schema)
+    }
+
+    /// Constructs a new `Block` from rows of a type that implements `InsertRow`.
+    pub fn from_rows<T: InsertRow>(rows: &[T]) -> Result<Self> {
+        let names = T::columns();
+        let mut block = Self::with_capacity(cmp::max(rows.len(), DEFAULT_CAPACITY));
+
+        for row in rows {
+            let values: Vec<(String, Value)> = names
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .zip(row.values())
+                .collect();
+            block.push(values)?;
+        }
+
+        Ok(block)
+    }
+
+    /// Appends values to a column without converting them to `Value`,
+    /// see `Column::extend_from_slice`.
+    ///
+    /// Every column has to be extended by the same number of values
+    /// before the block is used.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::Block;
+    /// let mut block = Block::new()
+    ///     .column("id", vec![1_u64])
+    ///     .column("name", vec!["a"]);
+    /// block.extend_column("id", &[2_u64, 3])?;
+    /// block.extend_column("name", &["b", "c"])?;
+    /// assert_eq!(block.get_column("id")?.as_slice::<u64>()?, &[1, 2, 3]);
+    /// # Ok::<(), clickhouse_rs::errors::Error>(())
+    /// ```
+    pub fn extend_column<I, T>(&mut self, col: I, values: &[T]) -> Result<()>
+    where
+        I: ColumnIdx + Copy,
+        T: PushTyped,
+    {
+        let column_index = col.get_index(self.columns())?;
+        self.columns[column_index].extend_from_slice(values)
+    }
+
+    #[cfg(test)]
     pub(crate) fn load<R>(reader: &mut R, tz: Tz, compress: bool) -> Result<Self>
     where
-        R: Read + ReadEx,
+        R: BufRead,
     {
-        if compress {
+        let options = LoadOptions {
+            compress,
+            ..LoadOptions::default()
+        };
+        Self::load_with(reader, tz, options)
+    }
+
+    pub(crate) fn load_with<R>(reader: &mut R, tz: Tz, options: LoadOptions) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        if options.compress {
             let mut cr = compressed::make(reader);
-            Self::raw_load(&mut cr, tz)
+            Self::raw_load(&mut cr, tz, options)
         } else {
-            Self::raw_load(reader, tz)
+            Self::raw_load(reader, tz, options)
         }
     }
 
-    fn raw_load<R>(reader: &mut R, tz: Tz) -> Result<Block<Simple>>
+    fn raw_load<R>(reader: &mut R, tz: Tz, options: LoadOptions) -> Result<Block<Simple>>
     where
         R: ReadEx,
     {
@@ -149,7 +303,11 @@ impl Block {
         let num_rows = reader.read_uvarint()?;
 
         for _ in 0..num_columns {
-            let column = Column::read(reader, num_rows as usize, tz)?;
+            let column = if options.lazy {
+                Column::read_lazy(reader, num_rows as usize, tz, options.strings)?
+            } else {
+                Column::read(reader, num_rows as usize, tz, options.strings)?
+            };
             block.append_column(column);
         }
 
@@ -171,6 +329,12 @@ impl<K: ColumnType> Block<K> {
         self.columns.len()
     }
 
+    /// Approximate heap footprint of the block data in bytes,
+    /// see `Column::byte_size`.
+    pub fn byte_size(&self) -> usize {
+        self.columns.iter().map(Column::byte_size).sum()
+    }
+
     /// This method returns a slice of columns.
     pub fn columns(&self) -> &[Column<K>] {
         &self.columns
@@ -187,13 +351,28 @@ impl<K: ColumnType> Block<K> {
     }
 
     /// Get the value of a particular cell of the block.
+    ///
+    /// It's an error if there's no such cell or its column has a type
+    /// that can't be converted to `T`.
     pub fn get<'a, T, I>(&'a self, row: usize, col: I) -> Result<T>
     where
         T: FromSql<'a>,
         I: ColumnIdx + Copy,
     {
-        let column_index = col.get_index(self.columns())?;
-        T::from_sql(self.columns[column_index].at(row))
+        let column = self.get_column(col)?;
+        if row >= column.len() {
+            return Err(Error::FromSql(FromSqlError::OutOfRange));
+        }
+        T::from_sql(column.at(row)).map_err(|err| match err {
+            Error::FromSql(FromSqlError::InvalidType { src, dst }) => {
+                Error::FromSql(FromSqlError::InvalidColumnValue {
+                    column: column.name().to_string(),
+                    src,
+                    dst,
+                })
+            }
+            err => err,
+        })
     }
 
     /// Add new column into this block
@@ -216,6 +395,24 @@ impl<K: ColumnType> Block<K> {
         self
     }
 
+    /// Adds a column of the values taken from an iterator, e.g. of `Option`s
+    /// for a `Nullable` column.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::Block;
+    /// let values = (0_u32..4).map(|i| Some(i).filter(|i| i % 2 == 0));
+    /// let block = Block::new().column_from_iter("x", values);
+    /// assert_eq!(block.get::<Option<u32>, _>(1, "x")?, None);
+    /// # Ok::<(), clickhouse_rs::errors::Error>(())
+    /// ```
+    pub fn column_from_iter<I>(self, name: &str, values: I) -> Self
+    where
+        I: IntoIterator,
+        Vec<I::Item>: ColumnFrom,
+    {
+        self.column(name, values.into_iter().collect::<Vec<_>>())
+    }
+
     /// Returns true if the block contains no elements.
     pub fn is_empty(&self) -> bool {
         self.columns.is_empty()
@@ -223,14 +420,238 @@ impl<K: ColumnType> Block<K> {
 
     /// This method returns a iterator of rows.
     pub fn rows(&self) -> Rows<K> {
-        Rows {
-            row: 0,
+        Rows::new(BlockRef::Borrowed(self))
+    }
+
+    /// Returns the row at `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn row(&self, index: usize) -> Row<K> {
+        assert!(
+            index < self.row_count(),
+            "row index {} is out of bounds, the block has {} rows",
+            index,
+            self.row_count()
+        );
+        Row {
+            row: index,
             block_ref: BlockRef::Borrowed(self),
             kind: PhantomData,
         }
     }
 
+    /// Returns a block of the given columns in the given order,
+    /// the column data is shared with this block.
+    pub fn select<S: AsRef<str>>(&self, names: &[S]) -> Result<Self> {
+        let mut columns = Vec::with_capacity(names.len());
+        for name in names {
+            let name = name.as_ref();
+            match self.columns.iter().find(|column| column.name() == name) {
+                Some(column) => columns.push(column.clone()),
+                None => return Err(DriverError::UnknownColumn { name: name.into() }.into()),
+            }
+        }
+        Ok(Self {
+            info: self.info,
+            columns,
+            capacity: self.capacity,
+        })
+    }
+
+    /// Returns a block without the given columns, the data of the other
+    /// columns is shared with this block. Names of missing columns are ignored.
+    pub fn drop_columns<S: AsRef<str>>(&self, names: &[S]) -> Self {
+        let columns = self
+            .columns
+            .iter()
+            .filter(|column| !names.iter().any(|name| name.as_ref() == column.name()))
+            .cloned()
+            .collect();
+        Self {
+            info: self.info,
+            columns,
+            capacity: self.capacity,
+        }
+    }
+
+    /// Renames the column `from` to `to`.
+    pub fn rename_column(self, from: &str, to: &str) -> Result<Self> {
+        self.rename_columns(&[(from, to)])
+    }
+
+    /// Renames columns given as pairs of the current and the new name.
+    ///
+    /// It's an error if a column is missing or the new names clash.
+    pub fn rename_columns<S, T>(mut self, renames: &[(S, T)]) -> Result<Self>
+    where
+        S: AsRef<str>,
+        T: AsRef<str>,
+    {
+        let mut names: Vec<String> = self.columns.iter().map(|c| c.name().to_string()).collect();
+        for (from, to) in renames {
+            let from = from.as_ref();
+            match self.columns.iter().position(|column| column.name() == from) {
+                Some(index) => names[index] = to.as_ref().to_string(),
+                None => return Err(DriverError::UnknownColumn { name: from.into() }.into()),
+            }
+        }
+
+        for (index, name) in names.iter().enumerate() {
+            if names[..index].contains(name) {
+                return Err(DriverError::DuplicateColumn { name: name.clone() }.into());
+            }
+        }
+
+        for (column, name) in self.columns.iter_mut().zip(names) {
+            column.name = name;
+        }
+        Ok(self)
+    }
+
+    /// Returns a block of the rows `predicate` returns `true` for.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::Block;
+    /// let block = Block::new().column("id", vec![1_u32, 2, 3, 4]);
+    /// let even = block.filter(|row| row.get::<u32, _>("id").unwrap() % 2 == 0);
+    /// assert_eq!(even.row_count(), 2);
+    /// ```
+    pub fn filter<F>(&self, mut predicate: F) -> Self
+    where
+        F: FnMut(&Row<K>) -> bool,
+    {
+        let indices: Vec<usize> = self
+            .rows()
+            .enumerate()
+            .filter(|(_, row)| predicate(row))
+            .map(|(index, _)| index)
+            .collect();
+        self.take(&indices)
+    }
+
+    /// Returns a block of the rows `mask` is `true` for.
+    ///
+    /// Panics if the length of `mask` isn't the number of rows.
+    pub fn filter_mask(&self, mask: &[bool]) -> Self {
+        assert_eq!(
+            mask.len(),
+            self.row_count(),
+            "mask length doesn't match the number of rows"
+        );
+        let indices: Vec<usize> = mask
+            .iter()
+            .enumerate()
+            .filter(|(_, &keep)| keep)
+            .map(|(index, _)| index)
+            .collect();
+        self.take(&indices)
+    }
+
+    /// Returns the block sorted by the given columns, `NULL`s come last.
+    ///
+    /// The sort is stable, so rows with equal keys keep their order.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::{Block, SortOrder::{Asc, Desc}};
+    /// let block = Block::new()
+    ///     .column("ts", vec![1_u32, 2, 2])
+    ///     .column("id", vec![7_u64, 9, 8]);
+    /// let sorted = block.sort_by(&[("ts", Desc), ("id", Asc)])?;
+    /// assert_eq!(sorted.get::<u64, _>(0, "id")?, 8);
+    /// # Ok::<(), clickhouse_rs::errors::Error>(())
+    /// ```
+    pub fn sort_by<I>(&self, keys: &[(I, SortOrder)]) -> Result<Self>
+    where
+        I: ColumnIdx + Copy,
+    {
+        let keys: Vec<_> = keys
+            .iter()
+            .map(|&(column, order)| (column, order, NullsOrder::Last))
+            .collect();
+        self.sort_by_with_nulls(&keys)
+    }
+
+    /// Returns the block sorted by the given columns with an explicit
+    /// placement of `NULL`s for each of them.
+    pub fn sort_by_with_nulls<I>(&self, keys: &[(I, SortOrder, NullsOrder)]) -> Result<Self>
+    where
+        I: ColumnIdx + Copy,
+    {
+        let mut columns = Vec::with_capacity(keys.len());
+        for &(column, order, nulls) in keys {
+            columns.push((self.get_column(column)?, order, nulls));
+        }
+
+        let mut indices: Vec<usize> = (0..self.row_count()).collect();
+        indices.sort_by(|&a, &b| {
+            columns
+                .iter()
+                .map(|(column, order, nulls)| {
+                    sort::compare(&column.at(a), &column.at(b), *order, *nulls)
+                })
+                .find(|ordering| *ordering != cmp::Ordering::Equal)
+                .unwrap_or(cmp::Ordering::Equal)
+        });
+        Ok(self.take(&indices))
+    }
+
+    /// Returns a block of the rows at `indices`, the column data is shared
+    /// if every row is taken in order.
+    fn take(&self, indices: &[usize]) -> Self {
+        Self {
+            info: self.info,
+            columns: self.columns.iter().map(|column| column.take(indices)).collect(),
+            capacity: self.capacity,
+        }
+    }
+
+    /// Compares the block with `other` column by column. Unlike `==`,
+    /// the names of the columns are compared too.
+    pub fn diff<R: ColumnType>(&self, other: &Block<R>) -> BlockDiff {
+        BlockDiff::new(self, other)
+    }
+
+    /// Renders the block as a table like the `PrettyCompact` format of
+    /// `clickhouse-client`, the same as `Display` unless limits are set.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::Block;
+    /// let block = Block::new().column("id", (0_u64..1000).collect::<Vec<_>>());
+    /// println!("{}", block.fmt_table().max_rows(10).max_cell_width(40));
+    /// ```
+    pub fn fmt_table(&self) -> PrettyTable<K> {
+        PrettyTable::new(self)
+    }
+
+    /// Returns the values row by row.
+    pub fn to_rows(&self) -> Vec<Vec<Value>> {
+        (0..self.row_count())
+            .map(|row| self.columns.iter().map(|c| Value::from(c.at(row))).collect())
+            .collect()
+    }
+
+    /// Returns the values row by row, borrowing strings from the block.
+    pub fn to_row_refs(&self) -> Vec<Vec<ValueRef>> {
+        (0..self.row_count())
+            .map(|row| self.columns.iter().map(|c| c.at(row)).collect())
+            .collect()
+    }
+
+    /// Returns the rows as a JSON array of objects keyed by column names,
+    /// see `formats::json`.
+    pub fn to_json(&self) -> String {
+        json::write_array(self)
+    }
+
+    /// Writes the rows as `JSONEachRow`, see `formats::json`.
+    pub fn to_json_each_row<W: Write>(&self, writer: W) -> Result<()> {
+        json::write(self, writer)
+    }
+
     /// This method is a convenient way to pass row into a block.
+    ///
+    /// A column shared with clones of the block is copied once, when the first
+    /// value is pushed to it (see `Column::make_mut`).
     pub fn push<B: RowBuilder>(&mut self, row: B) -> Result<()> {
         row.apply(self)
     }
@@ -245,27 +666,86 @@ impl<K: ColumnType> Block<K> {
         Ok(column)
     }
 
-    pub(crate) fn chunks(&self, n: usize) -> ChunkIterator<K> {
+    /// Splits the block into blocks of at most `n` rows, the chunks share
+    /// column data with the block instead of copying it.
+    ///
+    /// An empty block yields a single empty block. Panics if `n` is zero.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::Block;
+    /// let block = Block::new().column("id", (0_u64..1000).collect::<Vec<_>>());
+    /// let sizes: Vec<_> = block.chunks(300).map(|chunk| chunk.row_count()).collect();
+    /// assert_eq!(sizes, vec![300, 300, 300, 100]);
+    /// ```
+    pub fn chunks(&self, n: usize) -> ChunkIterator<K> {
+        assert!(n > 0, "chunk size must be positive");
+
         ChunkIterator::new(n, self)
     }
 }
 
 impl Block {
+    /// Casts the block to the insert `header` of the server. Columns are
+    /// matched by name and put in the order of the header.
     pub(crate) fn cast_to(self, header: &Block) -> Result<Self> {
         let info = self.info;
-        let mut columns = self.columns;
-        columns.reverse();
-
-        if header.column_count() != columns.len() {
-            return Err(Error::FromSql(FromSqlError::OutOfRange));
+        let mut columns: Vec<_> = self.columns.into_iter().map(Some).collect();
+
+        for (i, column) in columns.iter().flatten().enumerate() {
+            if columns[..i].iter().flatten().any(|c| c.name == column.name) {
+                return Err(DriverError::DuplicateColumn {
+                    name: column.name.clone(),
+                }
+                .into());
+            }
         }
 
-        let mut new_columns = Vec::with_capacity(columns.len());
+        let mut matched = Vec::with_capacity(header.column_count());
         for column in header.columns() {
-            let dst_type = column.sql_type();
-            let old_column = columns.pop().unwrap();
-            let new_column = old_column.cast_to(dst_type)?;
-            new_columns.push(new_column);
+            let found = columns
+                .iter_mut()
+                .find(|c| matches!(c, Some(c) if c.name == column.name));
+            match found.and_then(Option::take) {
+                Some(old_column) => matched.push((old_column, column.sql_type())),
+                None => {
+                    return Err(DriverError::MissingColumn {
+                        name: column.name.clone(),
+                    }
+                    .into())
+                }
+            }
+        }
+
+        if let Some(column) = columns.into_iter().flatten().next() {
+            return Err(DriverError::UnknownColumn { name: column.name }.into());
+        }
+
+        let mut new_columns = Vec::with_capacity(matched.len());
+        let mut mismatches = Vec::new();
+        for (old_column, dst_type) in matched {
+            let name = old_column.name.clone();
+            let src_type = old_column.sql_type();
+            match old_column.cast_to(dst_type) {
+                Ok(new_column) => new_columns.push(new_column),
+                Err(err) => mismatches.push(ColumnMismatch {
+                    name,
+                    expected: dst_type,
+                    actual: src_type,
+                    error: Arc::new(err),
+                }),
+            }
+        }
+
+        if !mismatches.is_empty() {
+            let message: Vec<_> = mismatches
+                .iter()
+                .map(|c| format!("column `{}' is {} instead of {}", c.name, c.actual, c.expected))
+                .collect();
+            return Err(DriverError::SchemaMismatch {
+                message: message.join("; "),
+                columns: mismatches,
+            }
+            .into());
         }
 
         Ok(Block {
@@ -277,11 +757,32 @@ impl Block {
 
     pub(crate) fn write(&self, encoder: &mut Encoder, compress: bool) {
         if compress {
-            let mut tmp_encoder = Encoder::new();
-            self.write(&mut tmp_encoder, false);
-            let tmp = tmp_encoder.get_buffer();
+            self.write_frame(encoder, 0);
+        } else {
+            self.info.write(encoder);
+            encoder.uvarint(self.column_count() as u64);
+            encoder.uvarint(self.row_count() as u64);
 
-            let mut buf = Vec::new();
+            for column in &self.columns {
+                column.write(encoder);
+            }
+        }
+    }
+
+    /// Writes the block as a compressed frame, the data of blocks smaller
+    /// than `threshold` bytes is put into the frame as is.
+    fn write_frame(&self, encoder: &mut Encoder, threshold: usize) {
+        let mut tmp_encoder = encoder.scratch();
+        self.write(&mut tmp_encoder, false);
+        let tmp = tmp_encoder.get_buffer_ref();
+
+        let mut compressed = encoder.scratch();
+        let buf = compressed.get_buffer_mut();
+        let method = if tmp.len() < threshold {
+            buf.resize(9, 0_u8);
+            buf.extend_from_slice(tmp);
+            compressed::NONE_METHOD
+        } else {
             let size;
             unsafe {
                 buf.resize(9 + LZ4_compressBound(tmp.len() as i32) as usize, 0_u8);
@@ -293,47 +794,104 @@ impl Block {
                 );
             }
             buf.resize(9 + size as usize, 0_u8);
+            compressed::LZ4_METHOD
+        };
 
-            let buf_len = buf.len() as u32;
-            {
-                let mut cursor = Cursor::new(&mut buf);
-                cursor.write_u8(0x82).unwrap();
-                cursor.write_u32::<LittleEndian>(buf_len).unwrap();
-                cursor.write_u32::<LittleEndian>(tmp.len() as u32).unwrap();
-            }
+        let buf_len = buf.len() as u32;
+        {
+            let mut cursor = Cursor::new(&mut *buf);
+            cursor.write_u8(method).unwrap();
+            cursor.write_u32::<LittleEndian>(buf_len).unwrap();
+            cursor.write_u32::<LittleEndian>(tmp.len() as u32).unwrap();
+        }
 
-            let hash = city_hash_128(&buf);
-            encoder.write(hash.lo);
-            encoder.write(hash.hi);
-            encoder.write_bytes(buf.as_ref());
-        } else {
-            self.info.write(encoder);
-            encoder.uvarint(self.column_count() as u64);
-            encoder.uvarint(self.row_count() as u64);
+        let hash = city_hash_128(&buf[..]);
+        encoder.write(hash.lo);
+        encoder.write(hash.hi);
+        encoder.write_bytes(buf.as_ref());
+    }
 
-            for column in &self.columns {
-                column.write(encoder);
+    /// Writes the block as a data packet. A block whose data is larger
+    /// than `max_bytes` (see `byte_size`) is split into several packets
+    /// with the same number of rows.
+    ///
+    /// Blocks smaller than `threshold` bytes are sent uncompressed
+    /// even if `compress` is set.
+    pub(crate) fn send_data(
+        &self,
+        encoder: &mut Encoder,
+        compress: bool,
+        threshold: usize,
+        max_bytes: Option<usize>,
+    ) {
+        if let Some(max_bytes) = max_bytes {
+            let rows = self.row_count();
+            let size = self.byte_size();
+            if rows > 1 && size > max_bytes {
+                let parts = size.div_ceil(max_bytes.max(1));
+                for chunk in self.chunks(rows.div_ceil(parts)) {
+                    chunk.send_table_data(encoder, "", compress, threshold);
+                }
+                return;
             }
         }
+
+        self.send_table_data(encoder, "", compress, threshold);
     }
 
-    pub(crate) fn send_data(&self, encoder: &mut Encoder, compress: bool) {
+    /// Writes the block as data of the external table `table`,
+    /// the data of the query itself has an empty name.
+    pub(crate) fn send_table_data(
+        &self,
+        encoder: &mut Encoder,
+        table: &str,
+        compress: bool,
+        threshold: usize,
+    ) {
         encoder.uvarint(protocol::CLIENT_DATA);
-        encoder.string(""); // temporary table
-        for chunk in self.chunks(INSERT_BLOCK_SIZE) {
-            chunk.write(encoder, compress);
+        encoder.string(table);
+        if compress {
+            self.write_frame(encoder, threshold);
+        } else {
+            self.write(encoder, false);
         }
     }
 
-    pub(crate) fn concat(blocks: &[Self]) -> Block<Complex> {
-        let first = blocks.first().expect("blocks should not be empty.");
-
-        for block in blocks {
-            assert_eq!(
-                first.column_count(),
-                block.column_count(),
-                "all block should have the same columns."
-            );
+    /// Concatenates the rows of `blocks` without copying column data.
+    ///
+    /// The blocks must have the same column names and types in the same order,
+    /// an empty slice gives an empty block.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::Block;
+    /// let a = Block::new().column("id", vec![1_u32, 2]);
+    /// let b = Block::new().column("id", vec![3_u32]);
+    /// let block = Block::concat(&[a, b])?;
+    /// assert_eq!(block.row_count(), 3);
+    /// # Ok::<(), clickhouse_rs::errors::Error>(())
+    /// ```
+    pub fn concat(blocks: &[Self]) -> Result<Block<Complex>> {
+        let first = match blocks.first() {
+            Some(first) => first,
+            None => {
+                return Ok(Block {
+                    info: Default::default(),
+                    columns: Vec::new(),
+                    capacity: DEFAULT_CAPACITY,
+                })
+            }
+        };
+
+        for (index, block) in blocks.iter().enumerate().skip(1) {
+            if !has_same_columns(first, block) {
+                let message = format!(
+                    "Block {} has columns ({}) instead of ({}).",
+                    index,
+                    schema_string(block),
+                    schema_string(first)
+                );
+                return Err(message.into());
+            }
         }
 
         let num_columns = first.column_count();
@@ -343,79 +901,46 @@ impl Block {
             columns.push(Column::concat(chunks));
         }
 
-        Block {
+        Ok(Block {
             info: first.info,
             columns,
             capacity: blocks.iter().map(|b| b.capacity).sum(),
-        }
+        })
     }
 }
 
-impl<K: ColumnType> fmt::Debug for Block<K> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let titles: Vec<&str> = self.columns.iter().map(Column::name).collect();
-
-        let cells: Vec<_> = self.columns.iter().map(|col| text_cells(&col)).collect();
-
-        let titles_len: Vec<_> = titles
-            .iter()
-            .map(|t| t.chars().count())
-            .zip(cells.iter().map(|w| column_width(w)))
-            .map(|(a, b)| cmp::max(a, b))
-            .collect();
-
-        print_line(f, &titles_len, "\n\u{250c}", '┬', "\u{2510}\n")?;
-
-        for (i, title) in titles.iter().enumerate() {
-            write!(f, "\u{2502}{:>width$} ", title, width = titles_len[i] + 1)?;
-        }
-        write!(f, "\u{2502}")?;
-
-        if self.row_count() > 0 {
-            print_line(f, &titles_len, "\n\u{251c}", '┼', "\u{2524}\n")?;
-        }
-
-        for j in 0..self.row_count() {
-            for (i, col) in cells.iter().enumerate() {
-                write!(f, "\u{2502}{:>width$} ", col[j], width = titles_len[i] + 1)?;
-            }
-
-            let new_line = (j + 1) != self.row_count();
-            write!(f, "\u{2502}{}", if new_line { "\n" } else { "" })?;
-        }
-
-        print_line(f, &titles_len, "\n\u{2514}", '┴', "\u{2518}")
-    }
+fn has_same_columns<K: ColumnType>(a: &Block<K>, b: &Block<K>) -> bool {
+    a.column_count() == b.column_count()
+        && a.columns.iter().zip(b.columns.iter()).all(|(a, b)| {
+            a.name() == b.name() && a.sql_type() == b.sql_type()
+        })
 }
 
-fn column_width(column: &[String]) -> usize {
-    column.iter().map(String::len).max().unwrap_or(0)
+fn schema_string<K: ColumnType>(block: &Block<K>) -> String {
+    block
+        .columns
+        .iter()
+        .map(|column| format!("{} {}", column.name(), column.sql_type()))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
-fn print_line(
-    f: &mut fmt::Formatter,
-    lens: &[usize],
-    left: &str,
-    center: char,
-    right: &str,
-) -> fmt::Result {
-    write!(f, "{}", left)?;
-    for (i, len) in lens.iter().enumerate() {
-        if i != 0 {
-            write!(f, "{}", center)?;
-        }
-
-        write!(f, "{:\u{2500}>width$}", "", width = len + 2)?;
+impl<K: ColumnType> fmt::Debug for Block<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.fmt_table(), f)
     }
-    write!(f, "{}", right)
 }
 
-fn text_cells<K: ColumnType>(data: &Column<K>) -> Vec<String> {
-    (0..data.len()).map(|i| format!("{}", data.at(i))).collect()
+impl<K: ColumnType> fmt::Display for Block<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.fmt_table(), f)
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use chrono::prelude::*;
+
     use super::*;
 
     #[test]
@@ -489,7 +1014,7 @@ mod test {
         let block_a = make_block();
         let block_b = make_block();
 
-        let actual = Block::concat(&[block_a, block_b]);
+        let actual = Block::concat(&[block_a, block_b]).unwrap();
         assert_eq!(actual.row_count(), 4);
         assert_eq!(actual.column_count(), 1);
 
@@ -521,6 +1046,74 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_concat_mismatch() {
+        assert!(Block::concat(&[]).unwrap().is_empty());
+
+        let a = Block::new().column("id", vec![1_u32]);
+        let b = Block::new().column("id", vec![1_u64]);
+        let c = Block::new().column("key", vec![1_u32]);
+        Block::concat(&[a.clone(), b]).unwrap_err();
+        Block::concat(&[a, c]).unwrap_err();
+    }
+
+    #[test]
+    fn test_push_to_shared_columns() {
+        let mut block = Block::<Simple>::new();
+        block.push(vec![("id".to_string(), Value::UInt32(1))]).unwrap();
+        let shared = block.clone();
+
+        block.push(vec![("id".to_string(), Value::UInt32(2))]).unwrap();
+        block.push(vec![("id".to_string(), Value::UInt32(3))]).unwrap();
+        assert_eq!(Arc::strong_count(&block.columns[0].data), 1);
+        assert!(!Arc::ptr_eq(&block.columns[0].data, &shared.columns[0].data));
+
+        assert_eq!(block.row_count(), 3);
+        assert_eq!(shared.row_count(), 1);
+        assert_eq!(block.get::<u32, _>(2, "id").unwrap(), 3);
+
+        // A row that doesn't fit the block leaves its columns shared.
+        let shared = block.clone();
+        block.push(vec![("name".to_string(), Value::UInt32(4))]).unwrap_err();
+        assert!(Arc::ptr_eq(&block.columns[0].data, &shared.columns[0].data));
+    }
+
+    #[test]
+    fn test_select() {
+        let block = Block::new()
+            .column("a", vec![1_u32])
+            .column("b", vec!["x"])
+            .column("c", vec![2_u8]);
+
+        let selected = block.select(&["c", "a"]).unwrap();
+        let names: Vec<&str> = selected.columns().iter().map(Column::name).collect();
+        assert_eq!(names, vec!["c", "a"]);
+        assert!(Arc::ptr_eq(&selected.columns[1].data, &block.columns[0].data));
+        block.select(&["missing"]).unwrap_err();
+
+        let dropped = block.drop_columns(&["b", "missing"]);
+        let names: Vec<&str> = dropped.columns().iter().map(Column::name).collect();
+        assert_eq!(names, vec!["a", "c"]);
+        assert_eq!(dropped.row_count(), 1);
+    }
+
+    #[test]
+    fn test_rename_columns() {
+        let block = Block::new()
+            .column("a", vec![1_u32])
+            .column("b", vec!["x"])
+            .rename_column("a", "id")
+            .unwrap();
+        assert_eq!(block.get::<u32, _>(0, "id").unwrap(), 1);
+
+        let block = block.rename_columns(&[("id", "b"), ("b", "name")]).unwrap();
+        let names: Vec<&str> = block.columns().iter().map(Column::name).collect();
+        assert_eq!(names, vec!["b", "name"]);
+
+        block.clone().rename_column("missing", "c").unwrap_err();
+        block.rename_column("b", "name").unwrap_err();
+    }
+
     #[test]
     fn test_chunks() {
         let first = Block::new().column("A", vec![1, 2]);
@@ -536,6 +1129,241 @@ mod test {
         assert_eq!(None, iter.next());
     }
 
+    #[test]
+    #[should_panic(expected = "chunk size must be positive")]
+    fn test_chunks_of_zero_rows() {
+        let block = Block::<Simple>::new().column("A", vec![1, 2]);
+        block.chunks(0);
+    }
+
+    #[test]
+    fn test_byte_size() {
+        let block = Block::<Simple>::new().column("A", vec![1_u64, 2, 3, 4]);
+        assert_eq!(block.byte_size(), 32);
+
+        let chunk = block.chunks(2).next().unwrap();
+        assert_eq!(chunk.byte_size(), 16);
+
+        let nullable = Block::<Simple>::new().column("A", vec![Some(1_u64), None, Some(3), None]);
+        assert_eq!(nullable.byte_size(), 36);
+
+        let strings = Block::<Simple>::new().column("A", vec!["foo", "bar"]);
+        assert!(strings.byte_size() >= 6);
+        assert_eq!(Block::<Simple>::new().byte_size(), 0);
+    }
+
+    #[test]
+    fn test_column_as_slice() {
+        let block = Block::<Simple>::new()
+            .column("f", vec![0.5_f64, 1.5, 2.5])
+            .column("n", vec![1_u32, 2, 3]);
+
+        let column = block.get_column("f").unwrap();
+        assert_eq!(column.as_slice::<f64>().unwrap(), &[0.5, 1.5, 2.5]);
+        column.as_slice::<f32>().unwrap_err();
+
+        let column = block.get_column("n").unwrap().clone();
+        let ptr = column.as_slice::<u32>().unwrap().as_ptr();
+        assert_eq!(column.clone().into_vec::<u32>().unwrap(), vec![1, 2, 3]);
+
+        drop(block);
+        let values = column.into_vec::<u32>().unwrap();
+        assert_eq!(values, vec![1, 2, 3]);
+        assert_eq!(values.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_column_slice() {
+        let block = Block::<Simple>::new().column("A", vec![1_u32, 2, 3, 4]);
+        let column = block.get_column("A").unwrap();
+
+        let slice = column.slice(1..3);
+        assert_eq!(slice.name(), "A");
+        assert_eq!(slice.len(), 2);
+        assert!(slice == Block::<Simple>::new().column("A", vec![2_u32, 3]).columns()[0]);
+
+        let tail = slice.slice(1..2);
+        assert!(tail == Block::<Simple>::new().column("A", vec![3_u32]).columns()[0]);
+        assert!(column.slice(4..4).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_column_slice_out_of_range() {
+        let block = Block::<Simple>::new().column("A", vec![1_u32, 2]);
+        block.get_column("A").unwrap().slice(1..3);
+    }
+
+    #[test]
+    fn test_cast_to_header_order() {
+        let header = Block::new()
+            .column("id", vec![0_u64])
+            .column("name", vec![""]);
+
+        let block = Block::new()
+            .column("name", vec!["foo", "bar"])
+            .column("id", vec![1_u64, 2]);
+        let expected = Block::new()
+            .column("id", vec![1_u64, 2])
+            .column("name", vec!["foo", "bar"]);
+        assert_eq!(block.cast_to(&header).unwrap(), expected);
+
+        let missing = Block::new().column("id", vec![1_u64]);
+        match missing.cast_to(&header) {
+            Err(Error::Driver(DriverError::MissingColumn { name })) => assert_eq!(name, "name"),
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let extra = Block::new()
+            .column("id", vec![1_u64])
+            .column("name", vec!["foo"])
+            .column("note", vec!["bar"]);
+        match extra.cast_to(&header) {
+            Err(Error::Driver(DriverError::UnknownColumn { name })) => assert_eq!(name, "note"),
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let duplicate = Block::new()
+            .column("id", vec![1_u64])
+            .column("id", vec![2_u64]);
+        match duplicate.cast_to(&header) {
+            Err(Error::Driver(DriverError::DuplicateColumn { name })) => assert_eq!(name, "id"),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_cast_to_reports_every_mismatch() {
+        let header = Block::new()
+            .column("id", vec![0_u64])
+            .column("name", vec![""])
+            .column("score", vec![0.0_f64]);
+
+        let block = Block::new()
+            .column("id", vec!["foo"])
+            .column("name", vec!["bar"])
+            .column("score", vec![1_i8]);
+
+        match block.cast_to(&header) {
+            Err(Error::Driver(DriverError::SchemaMismatch { message, columns })) => {
+                assert_eq!(
+                    message,
+                    "column `id' is String instead of UInt64; \
+                     column `score' is Int8 instead of Float64"
+                );
+                assert_eq!(columns.len(), 2);
+                assert_eq!(columns[1].name, "score");
+                assert_eq!(columns[1].expected, SqlType::Float64);
+                assert_eq!(columns[1].actual, SqlType::Int8);
+                assert!(matches!(
+                    *columns[1].error,
+                    Error::FromSql(FromSqlError::InvalidType { .. })
+                ));
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_to_rows() {
+        let block = Block::<Simple>::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec![Some("foo"), None]);
+
+        assert_eq!(
+            block.to_rows(),
+            vec![
+                vec![Value::UInt32(1), Value::from(Some("foo"))],
+                vec![Value::UInt32(2), Value::from(None::<&str>)],
+            ]
+        );
+
+        let rows = block.to_row_refs();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][0], ValueRef::UInt32(2));
+        assert!(Block::<Simple>::new().to_rows().is_empty());
+    }
+
+    #[test]
+    fn test_nullable_columns() {
+        let block = Block::<Simple>::new()
+            .column("n", vec![vec![Some(1_u32), None], vec![]])
+            .column("s", vec![vec![None, Some("foo")], vec![Some("bar")]])
+            .column("b", vec![Some(&b"foo"[..]), None])
+            .column_from_iter("i", (0_i64..2).map(|i| Some(i).filter(|&i| i > 0)));
+
+        let types: Vec<_> = block.columns().iter().map(|c| c.sql_type().to_string()).collect();
+        assert_eq!(
+            types,
+            vec![
+                "Array(Nullable(UInt32))",
+                "Array(Nullable(String))",
+                "Nullable(String)",
+                "Nullable(Int64)"
+            ]
+        );
+
+        let rows = block.to_rows();
+        assert_eq!(rows[0][0].to_string(), "[1, NULL]");
+        assert_eq!(rows[0][1].to_string(), "[NULL, foo]");
+        assert_eq!(rows[1][1].to_string(), "[bar]");
+        let b: Option<String> = block.get(0, "b").unwrap();
+        assert_eq!(b, Some("foo".to_string()));
+        let i: Option<i64> = block.get(0, "i").unwrap();
+        assert_eq!(i, None);
+    }
+
+    #[test]
+    fn test_iter_str() {
+        let block = Block::<Simple>::new()
+            .column("s", vec!["foo", "bar"])
+            .column("o", vec![Some("baz"), None])
+            .column("a", vec![vec!["x", "y"], vec![]])
+            .column("b", vec![&b"ok"[..], &[0xff, 0xfe][..]]);
+
+        let s: Vec<&str> = block.get_column("s").unwrap().iter::<&str>().unwrap().collect();
+        assert_eq!(s, vec!["foo", "bar"]);
+
+        let o: Vec<_> = block.get_column("o").unwrap().iter::<Option<&str>>().unwrap().collect();
+        assert_eq!(o, vec![Some("baz"), None]);
+
+        let a: Vec<_> = block.get_column("a").unwrap().iter::<Vec<&str>>().unwrap().collect();
+        assert_eq!(a, vec![vec!["x", "y"], vec![]]);
+
+        assert!(block.get_column("b").unwrap().iter::<&str>().is_err());
+    }
+
+    #[test]
+    fn test_send_data_splits_by_bytes() {
+        let block = Block::<Simple>::new().column("A", vec![1_u64, 2, 3, 4]);
+
+        let mut expected = Encoder::new();
+        for chunk in block.chunks(1) {
+            chunk.send_data(&mut expected, false, 0, None);
+        }
+
+        let mut actual = Encoder::new();
+        block.send_data(&mut actual, false, 0, Some(1));
+        assert_eq!(expected.get_buffer(), actual.get_buffer());
+
+        let mut expected = Encoder::new();
+        block.send_data(&mut expected, false, 0, None);
+
+        let mut actual = Encoder::new();
+        block.send_data(&mut actual, false, 0, Some(1_000));
+        assert_eq!(expected.get_buffer(), actual.get_buffer());
+
+        let block = Block::<Simple>::new().column("A", (0..100_u64).collect::<Vec<_>>());
+        let mut expected = Encoder::new();
+        for chunk in block.chunks(25) {
+            chunk.send_data(&mut expected, false, 0, None);
+        }
+
+        let mut actual = Encoder::new();
+        block.send_data(&mut actual, false, 0, Some(200));
+        assert_eq!(expected.get_buffer(), actual.get_buffer());
+    }
+
     #[test]
     fn test_chunks_of_empty_block() {
         let block = Block::default();
@@ -551,6 +1379,106 @@ mod test {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_column_typed() {
+        let block = Block::new()
+            .column_typed("id", SqlType::Int64, vec![1_u8, 2])
+            .unwrap()
+            .column_typed("name", SqlType::FixedString(3), vec!["ab", "abc"])
+            .unwrap();
+        assert_eq!(block.columns()[0].sql_type(), SqlType::Int64);
+        assert_eq!(block.get::<i64, _>(1, "id").unwrap(), 2);
+        assert_eq!(block.columns()[1].sql_type(), SqlType::FixedString(3));
+
+        let rejected = Block::new().column_typed("id", SqlType::UInt8, vec![1_u64, 256]);
+        match rejected {
+            Err(Error::FromSql(FromSqlError::InvalidColumnType { column, .. })) => {
+                assert_eq!(column, "id")
+            }
+            result => panic!("unexpected result {:?}", result.map(|_| ())),
+        }
+
+        block.column_typed("day", SqlType::UInt8, vec![1_u8]).unwrap_err();
+    }
+
+    #[test]
+    fn test_filter() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2, 3, 4])
+            .column("name", vec![Some("a"), None, Some("c"), None]);
+
+        let filtered = block.filter(|row| row.get::<Option<&str>, _>("name").unwrap().is_some());
+        assert_eq!(filtered.row_count(), 2);
+        assert_eq!(filtered.get::<u32, _>(1, "id").unwrap(), 3);
+        assert_eq!(filtered.get::<Option<&str>, _>(1, "name").unwrap(), Some("c"));
+
+        let filtered = block.filter_mask(&[false, true, false, false]);
+        assert_eq!(filtered.get::<u32, _>(0, "id").unwrap(), 2);
+        assert_eq!(filtered.get::<Option<&str>, _>(0, "name").unwrap(), None);
+
+        let all = block.filter(|_| true);
+        assert!(Arc::ptr_eq(&all.columns[0].data, &block.columns[0].data));
+        assert_eq!(block.filter(|_| false).row_count(), 0);
+    }
+
+    #[test]
+    fn test_filter_keeps_timezone() {
+        let tz: Tz = "Europe/Moscow".parse().unwrap();
+        let time = tz.with_ymd_and_hms(2020, 1, 1, 12, 0, 0).unwrap();
+        let block = Block::new().column("time", vec![time, time]);
+
+        let filtered = block.filter_mask(&[false, true]);
+        assert_eq!(filtered.get::<DateTime<Tz>, _>(0, "time").unwrap(), time);
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let block = Block::new()
+            .column("ts", vec![Some(2_u32), None, Some(1), Some(2)])
+            .column("name", vec!["b", "x", "c", "a"]);
+
+        let names = |block: &Block| -> Vec<String> {
+            block.rows().map(|row| row.get("name").unwrap()).collect()
+        };
+
+        let sorted = block.sort_by(&[("ts", SortOrder::Desc), ("name", SortOrder::Asc)]);
+        assert_eq!(names(&sorted.unwrap()), vec!["a", "b", "c", "x"]);
+
+        let sorted = block.sort_by(&[("ts", SortOrder::Asc)]).unwrap();
+        assert_eq!(names(&sorted), vec!["c", "b", "a", "x"]);
+
+        let sorted = block
+            .sort_by_with_nulls(&[("ts", SortOrder::Asc, NullsOrder::First)])
+            .unwrap();
+        assert_eq!(names(&sorted), vec!["x", "c", "b", "a"]);
+
+        block.sort_by(&[("missing", SortOrder::Asc)]).unwrap_err();
+    }
+
+    #[test]
+    fn test_sort_by_array() {
+        let block = Block::new().column("tags", vec![vec![1_u8, 2], vec![1], vec![0, 5]]);
+        let sorted = block.sort_by(&[("tags", SortOrder::Asc)]).unwrap();
+        let tags: Vec<Vec<u8>> = sorted.rows().map(|row| row.get("tags").unwrap()).collect();
+        assert_eq!(tags, vec![vec![0, 5], vec![1], vec![1, 2]]);
+    }
+
+    #[test]
+    fn test_get() {
+        let block = Block::new().column("count", vec![3_u64]);
+        assert_eq!(block.get::<u64, _>(0, "count").unwrap(), 3);
+
+        let err = block.get::<i32, _>(0, "count").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "From SQL error: `Column `count' of type SqlType::UInt64 cannot be read as i32.`"
+        );
+
+        block.get::<u64, _>(1, "count").unwrap_err();
+        block.get::<u64, _>(0, 1).unwrap_err();
+        block.get::<u64, _>(0, "missing").unwrap_err();
+    }
+
     #[test]
     fn test_write_and_read() {
         let block = Block::<Simple>::new().column("y", vec![Some(1_u8), None]);
@@ -0,0 +1,164 @@
+use std::{cmp, fmt};
+
+use crate::types::{Block, ColumnType};
+
+/// Block rendered as a table like the `PrettyCompact` format of
+/// `clickhouse-client`, see `Block::fmt_table`.
+pub struct PrettyTable<'a, K: ColumnType> {
+    block: &'a Block<K>,
+    max_rows: Option<usize>,
+    max_cell_width: Option<usize>,
+}
+
+impl<'a, K: ColumnType> PrettyTable<'a, K> {
+    pub(crate) fn new(block: &'a Block<K>) -> Self {
+        Self {
+            block,
+            max_rows: None,
+            max_cell_width: None,
+        }
+    }
+
+    /// Renders only the first `max_rows` rows (defaults to all of them).
+    pub fn max_rows(self, max_rows: usize) -> Self {
+        Self {
+            max_rows: Some(max_rows),
+            ..self
+        }
+    }
+
+    /// Truncates cells longer than `max_cell_width` characters
+    /// (defaults to no limit).
+    pub fn max_cell_width(self, max_cell_width: usize) -> Self {
+        Self {
+            max_cell_width: Some(max_cell_width.max(1)),
+            ..self
+        }
+    }
+
+    fn cell(&self, text: String) -> String {
+        match self.max_cell_width {
+            Some(width) if text.chars().count() > width => {
+                let mut text: String = text.chars().take(width - 1).collect();
+                text.push('…');
+                text
+            }
+            _ => text,
+        }
+    }
+}
+
+impl<'a, K: ColumnType> fmt::Display for PrettyTable<'a, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let columns = self.block.columns();
+        let row_count = self.block.row_count();
+        let shown_rows = cmp::min(row_count, self.max_rows.unwrap_or(row_count));
+
+        let titles: Vec<String> = columns
+            .iter()
+            .map(|column| self.cell(column.name().to_string()))
+            .collect();
+        let cells: Vec<Vec<String>> = columns
+            .iter()
+            .map(|column| {
+                (0..shown_rows)
+                    .map(|row| self.cell(column.at(row).to_string()))
+                    .collect()
+            })
+            .collect();
+
+        let widths: Vec<usize> = titles
+            .iter()
+            .zip(cells.iter())
+            .map(|(title, cells)| {
+                cells
+                    .iter()
+                    .chain(Some(title))
+                    .map(|text| text.chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        print_line(f, &widths, "\n\u{250c}", '┬', "\u{2510}\n")?;
+
+        for (i, title) in titles.iter().enumerate() {
+            write!(f, "\u{2502}{:>width$} ", title, width = widths[i] + 1)?;
+        }
+        write!(f, "\u{2502}")?;
+
+        if shown_rows > 0 {
+            print_line(f, &widths, "\n\u{251c}", '┼', "\u{2524}\n")?;
+        }
+
+        for j in 0..shown_rows {
+            for (i, column) in cells.iter().enumerate() {
+                write!(f, "\u{2502}{:>width$} ", column[j], width = widths[i] + 1)?;
+            }
+
+            let new_line = (j + 1) != shown_rows;
+            write!(f, "\u{2502}{}", if new_line { "\n" } else { "" })?;
+        }
+
+        print_line(f, &widths, "\n\u{2514}", '┴', "\u{2518}")?;
+
+        if shown_rows < row_count {
+            write!(f, "\nShowed first {} of {} rows.", shown_rows, row_count)?;
+        }
+        Ok(())
+    }
+}
+
+fn print_line(
+    f: &mut fmt::Formatter,
+    lens: &[usize],
+    left: &str,
+    center: char,
+    right: &str,
+) -> fmt::Result {
+    write!(f, "{}", left)?;
+    for (i, len) in lens.iter().enumerate() {
+        if i != 0 {
+            write!(f, "{}", center)?;
+        }
+
+        write!(f, "{:\u{2500}>width$}", "", width = len + 2)?;
+    }
+    write!(f, "{}", right)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2, 3])
+            .column("name", vec!["a", "long name", "c"]);
+
+        assert_eq!(
+            block.to_string(),
+            "
+┌────┬───────────┐
+│ id │      name │
+├────┼───────────┤
+│  1 │         a │
+│  2 │ long name │
+│  3 │         c │
+└────┴───────────┘"
+        );
+
+        assert_eq!(
+            block.fmt_table().max_rows(2).max_cell_width(4).to_string(),
+            "
+┌────┬──────┐
+│ id │ name │
+├────┼──────┤
+│  1 │    a │
+│  2 │ lon… │
+└────┴──────┘
+Showed first 2 of 3 rows."
+        );
+    }
+}
@@ -1,8 +1,13 @@
 use std::{marker, sync::Arc};
 
+use serde::de::Deserialize;
+
 use crate::{
     errors::Result,
-    types::{block::ColumnIdx, Block, Column, FromSql, SqlType, ColumnType},
+    types::{
+        block::ColumnIdx, de::RowDeserializer, Block, Column, ColumnType, FromSql, SqlType,
+        ValueRef,
+    },
 };
 
 /// A row from Clickhouse
@@ -22,6 +27,14 @@ impl<'a, K: ColumnType> Row<'a, K> {
         self.block_ref.get(self.row, col)
     }
 
+    /// Get the value of the cell at `index`, the same as `get` with a column index.
+    pub fn get_at<T>(&'a self, index: usize) -> Result<T>
+    where
+        T: FromSql<'a>,
+    {
+        self.get(index)
+    }
+
     /// Return the number of cells in the current row.
     pub fn len(&self) -> usize {
         self.block_ref.column_count()
@@ -41,6 +54,22 @@ impl<'a, K: ColumnType> Row<'a, K> {
     pub fn sql_type<I: ColumnIdx + Copy>(&self, col: I) -> Result<SqlType> {
         Ok(self.block_ref.get_column(col)?.sql_type())
     }
+
+    /// Deserialize the row into `T`.
+    ///
+    /// Structs and maps are filled by column name, tuples by column position.
+    /// A column that can't be represented as the field type is an error.
+    /// Decimals are read as strings in their exact form, or as floats.
+    pub fn deserialize<'s, T>(&'s self) -> Result<T>
+    where
+        T: Deserialize<'s>,
+    {
+        Ok(T::deserialize(RowDeserializer::new(self))?)
+    }
+
+    pub(crate) fn value(&self, index: usize) -> Result<ValueRef<'_>> {
+        Ok(self.block_ref.get_column(index)?.at(self.row))
+    }
 }
 
 pub(crate) enum BlockRef<'a, K: ColumnType> {
@@ -99,31 +128,68 @@ impl<'a, K: ColumnType> BlockRef<'a, K> {
 
 /// Immutable rows iterator
 pub struct Rows<'a, K: ColumnType> {
-    pub(crate) row: usize,
-    pub(crate) block_ref: BlockRef<'a, K>,
-    pub(crate) kind: marker::PhantomData<K>,
+    row: usize,
+    end: usize,
+    block_ref: BlockRef<'a, K>,
+    kind: marker::PhantomData<K>,
+}
+
+impl<'a, K: ColumnType> Rows<'a, K> {
+    pub(crate) fn new(block_ref: BlockRef<'a, K>) -> Self {
+        Self {
+            row: 0,
+            end: block_ref.row_count(),
+            block_ref,
+            kind: marker::PhantomData,
+        }
+    }
+
+    fn row_at(&self, row: usize) -> Row<'a, K> {
+        Row {
+            row,
+            block_ref: self.block_ref.clone(),
+            kind: marker::PhantomData,
+        }
+    }
 }
 
 impl<'a, K: ColumnType> Iterator for Rows<'a, K> {
     type Item = Row<'a, K>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.row >= self.block_ref.row_count() {
+        if self.row >= self.end {
             return None;
         }
-        let result = Some(Row {
-            row: self.row,
-            block_ref: self.block_ref.clone(),
-            kind: marker::PhantomData,
-        });
+        let result = Some(self.row_at(self.row));
         self.row += 1;
         result
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.row;
+        (len, Some(len))
+    }
+}
+
+impl<'a, K: ColumnType> DoubleEndedIterator for Rows<'a, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.row >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(self.row_at(self.end))
+    }
 }
 
+impl<'a, K: ColumnType> ExactSizeIterator for Rows<'a, K> {}
+
 #[cfg(test)]
 mod test {
-    use crate::{row, types::Block};
+    use crate::{
+        errors::{Error, FromSqlError},
+        row,
+        types::Block,
+    };
 
     #[test]
     fn test_len() {
@@ -144,4 +210,45 @@ mod test {
             assert!(!row.is_empty());
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_get_at() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec!["a", "b"]);
+
+        let row = block.row(1);
+        assert_eq!(row.get::<u32, _>("id").unwrap(), 2);
+        assert_eq!(row.get_at::<&str>(1).unwrap(), "b");
+
+        match row.get_at::<u64>(0) {
+            Err(Error::FromSql(FromSqlError::InvalidColumnValue { column, src, dst })) => {
+                assert_eq!(column, "id");
+                assert_eq!((src.as_ref(), dst.as_ref()), ("UInt32", "u64"));
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        row.get_at::<u32>(2).unwrap_err();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_row_out_of_bounds() {
+        let block = Block::new().column("id", vec![1_u32]);
+        block.row(1);
+    }
+
+    #[test]
+    fn test_rows_both_ends() {
+        let block = Block::new().column("id", vec![1_u32, 2, 3, 4]);
+
+        let mut rows = block.rows();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows.next().unwrap().get_at::<u32>(0).unwrap(), 1);
+        assert_eq!(rows.next_back().unwrap().get_at::<u32>(0).unwrap(), 4);
+        assert_eq!(rows.len(), 2);
+
+        let rest: Vec<u32> = rows.rev().map(|row| row.get_at(0).unwrap()).collect();
+        assert_eq!(rest, vec![3, 2]);
+    }
+}
@@ -0,0 +1,73 @@
+use std::cmp::Ordering;
+
+use crate::types::{column::Either, ValueRef};
+
+/// Direction of sorting by a column, see `Block::sort_by`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// Placement of `NULL`s when sorting by a column, see `Block::sort_by_with_nulls`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+/// Compares two values of the same column.
+pub(crate) fn compare(a: &ValueRef, b: &ValueRef, order: SortOrder, nulls: NullsOrder) -> Ordering {
+    let (a, b) = match (unwrap_null(a), unwrap_null(b)) {
+        (None, None) => return Ordering::Equal,
+        (None, Some(_)) if nulls == NullsOrder::First => return Ordering::Less,
+        (None, Some(_)) => return Ordering::Greater,
+        (Some(_), None) if nulls == NullsOrder::First => return Ordering::Greater,
+        (Some(_), None) => return Ordering::Less,
+        (Some(a), Some(b)) => (a, b),
+    };
+
+    let ordering = compare_values(a, b);
+    match order {
+        SortOrder::Asc => ordering,
+        SortOrder::Desc => ordering.reverse(),
+    }
+}
+
+/// Returns `None` for `NULL`, the value otherwise.
+fn unwrap_null<'a, 'b>(value: &'b ValueRef<'a>) -> Option<&'b ValueRef<'a>> {
+    match value {
+        ValueRef::Nullable(Either::Left(_)) => None,
+        ValueRef::Nullable(Either::Right(inner)) => Some(inner),
+        value => Some(value),
+    }
+}
+
+fn compare_values(a: &ValueRef, b: &ValueRef) -> Ordering {
+    match (a, b) {
+        (ValueRef::UInt8(a), ValueRef::UInt8(b)) => a.cmp(b),
+        (ValueRef::UInt16(a), ValueRef::UInt16(b)) => a.cmp(b),
+        (ValueRef::UInt32(a), ValueRef::UInt32(b)) => a.cmp(b),
+        (ValueRef::UInt64(a), ValueRef::UInt64(b)) => a.cmp(b),
+        (ValueRef::Int8(a), ValueRef::Int8(b)) => a.cmp(b),
+        (ValueRef::Int16(a), ValueRef::Int16(b)) => a.cmp(b),
+        (ValueRef::Int32(a), ValueRef::Int32(b)) => a.cmp(b),
+        (ValueRef::Int64(a), ValueRef::Int64(b)) => a.cmp(b),
+        (ValueRef::String(a), ValueRef::String(b)) => a.cmp(b),
+        (ValueRef::Float32(a), ValueRef::Float32(b)) => a.total_cmp(b),
+        (ValueRef::Float64(a), ValueRef::Float64(b)) => a.total_cmp(b),
+        (ValueRef::Date(a, _), ValueRef::Date(b, _)) => a.cmp(b),
+        (ValueRef::DateTime(a, _), ValueRef::DateTime(b, _)) => a.cmp(b),
+        (ValueRef::Decimal(a), ValueRef::Decimal(b)) => a.underlying.cmp(&b.underlying),
+        (ValueRef::Array(_, a), ValueRef::Array(_, b)) => {
+            for (a, b) in a.iter().zip(b.iter()) {
+                match compare(a, b, SortOrder::Asc, NullsOrder::Last) {
+                    Ordering::Equal => continue,
+                    ordering => return ordering,
+                }
+            }
+            a.len().cmp(&b.len())
+        }
+        _ => Ordering::Equal,
+    }
+}
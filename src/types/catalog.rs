@@ -0,0 +1,189 @@
+use crate::{
+    errors::Result,
+    types::{
+        cluster::get_maybe_null,
+        describe::{parse_default_kind, parse_type},
+        Block, ColumnType, DefaultKind, SqlType,
+    },
+};
+
+pub(crate) const DATABASES_SQL: &str = "SELECT name, engine FROM system.databases ORDER BY name";
+
+pub(crate) const TABLES_SQL: &str = "SELECT database, name, engine, total_rows, total_bytes \
+     FROM system.tables WHERE database = ? ORDER BY name";
+
+pub(crate) const COLUMNS_SQL: &str = "SELECT name, type, position, default_kind, \
+     default_expression, comment, is_in_primary_key, \
+     data_compressed_bytes, data_uncompressed_bytes \
+     FROM system.columns WHERE database = ? AND table = ? ORDER BY position";
+
+pub(crate) const PARTITIONS_SQL: &str = "SELECT partition_id, partition, \
+     count() AS part_count, sum(rows) AS row_count, sum(bytes_on_disk) AS byte_count \
+     FROM system.parts WHERE database = ? AND table = ? AND active \
+     GROUP BY partition_id, partition ORDER BY partition_id";
+
+/// Database, as reported by `system.databases`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatabaseInfo {
+    pub name: String,
+    pub engine: String,
+}
+
+/// Table, as reported by `system.tables`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableInfo {
+    pub database: String,
+    pub name: String,
+    pub engine: String,
+    /// `None` if the engine doesn't keep track of it.
+    pub total_rows: Option<u64>,
+    /// `None` if the engine doesn't keep track of it.
+    pub total_bytes: Option<u64>,
+}
+
+/// Table column, as reported by `system.columns`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub sql_type: SqlType,
+    /// 1-based position of the column in the table.
+    pub position: u64,
+    pub default_kind: Option<DefaultKind>,
+    pub default_expression: String,
+    pub comment: String,
+    pub is_in_primary_key: bool,
+    pub compressed_bytes: u64,
+    pub uncompressed_bytes: u64,
+}
+
+/// Active parts of a table partition, as reported by `system.parts`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartitionInfo {
+    pub partition_id: String,
+    /// Value of the partition key, e.g. `202401`.
+    pub partition: String,
+    pub parts: u64,
+    pub rows: u64,
+    pub bytes_on_disk: u64,
+}
+
+pub(crate) fn databases<K: ColumnType>(block: &Block<K>) -> Result<Vec<DatabaseInfo>> {
+    block
+        .rows()
+        .map(|row| {
+            Ok(DatabaseInfo {
+                name: row.get("name")?,
+                engine: row.get("engine")?,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn tables<K: ColumnType>(block: &Block<K>) -> Result<Vec<TableInfo>> {
+    block
+        .rows()
+        .map(|row| {
+            Ok(TableInfo {
+                database: row.get("database")?,
+                name: row.get("name")?,
+                engine: row.get("engine")?,
+                total_rows: get_maybe_null(&row, "total_rows")?,
+                total_bytes: get_maybe_null(&row, "total_bytes")?,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn columns<K: ColumnType>(block: &Block<K>) -> Result<Vec<ColumnInfo>> {
+    block
+        .rows()
+        .map(|row| {
+            Ok(ColumnInfo {
+                name: row.get("name")?,
+                sql_type: parse_type(row.get("type")?)?,
+                position: row.get("position")?,
+                default_kind: parse_default_kind(row.get("default_kind")?)?,
+                default_expression: row.get("default_expression")?,
+                comment: row.get("comment")?,
+                is_in_primary_key: row.get::<u8, _>("is_in_primary_key")? != 0,
+                compressed_bytes: row.get("data_compressed_bytes")?,
+                uncompressed_bytes: row.get("data_uncompressed_bytes")?,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn partitions<K: ColumnType>(block: &Block<K>) -> Result<Vec<PartitionInfo>> {
+    block
+        .rows()
+        .map(|row| {
+            Ok(PartitionInfo {
+                partition_id: row.get("partition_id")?,
+                partition: row.get("partition")?,
+                parts: row.get("part_count")?,
+                rows: row.get("row_count")?,
+                bytes_on_disk: row.get("byte_count")?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tables() {
+        let block = Block::new()
+            .column("database", vec!["default", "default"])
+            .column("name", vec!["events", "events_view"])
+            .column("engine", vec!["MergeTree", "View"])
+            .column("total_rows", vec![Some(10_u64), None])
+            .column("total_bytes", vec![Some(512_u64), None]);
+
+        let tables = tables(&block).unwrap();
+        assert_eq!(
+            tables[0],
+            TableInfo {
+                database: "default".into(),
+                name: "events".into(),
+                engine: "MergeTree".into(),
+                total_rows: Some(10),
+                total_bytes: Some(512),
+            }
+        );
+        assert_eq!(tables[1].total_rows, None);
+    }
+
+    #[test]
+    fn test_columns() {
+        let block = Block::new()
+            .column("name", vec!["id", "day"])
+            .column("type", vec!["UInt64", "Date"])
+            .column("position", vec![1_u64, 2])
+            .column("default_kind", vec!["", "MATERIALIZED"])
+            .column("default_expression", vec!["", "today()"])
+            .column("comment", vec!["key", ""])
+            .column("is_in_primary_key", vec![1_u8, 0])
+            .column("data_compressed_bytes", vec![100_u64, 20])
+            .column("data_uncompressed_bytes", vec![800_u64, 40]);
+
+        let columns = columns(&block).unwrap();
+        assert_eq!(
+            columns[0],
+            ColumnInfo {
+                name: "id".into(),
+                sql_type: SqlType::UInt64,
+                position: 1,
+                default_kind: None,
+                default_expression: "".into(),
+                comment: "key".into(),
+                is_in_primary_key: true,
+                compressed_bytes: 100,
+                uncompressed_bytes: 800,
+            }
+        );
+        assert_eq!(columns[1].default_kind, Some(DefaultKind::Materialized));
+        assert!(!columns[1].is_in_primary_key);
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use crate::{
+    errors::{DriverError, Result},
+    types::{Block, ColumnType, FromSql, Query, Row, SqlType},
+};
+
+/// Makes the server report every host in the result, with a `NULL` status
+/// for unfinished ones, instead of rethrowing the first failure.
+const DDL_OUTPUT_MODE: &str = "never_throw";
+
+/// Status of a host that executed an `ON CLUSTER` statement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DdlHostStatus {
+    pub host: String,
+    pub port: u16,
+    /// Zero on success, the code of the exception otherwise.
+    /// `None` if the host didn't finish in time.
+    pub status: Option<i64>,
+    pub error: String,
+}
+
+impl DdlHostStatus {
+    /// Returns `true` if the statement succeeded on the host.
+    pub fn is_ok(&self) -> bool {
+        self.status == Some(0)
+    }
+}
+
+/// Reads a column that is `Nullable` in some server versions only.
+pub(crate) fn get_maybe_null<'a, T, K>(row: &'a Row<'a, K>, name: &str) -> Result<Option<T>>
+where
+    T: FromSql<'a>,
+    K: ColumnType,
+{
+    match row.sql_type(name)? {
+        SqlType::Nullable(_) => row.get(name),
+        _ => row.get(name).map(Some),
+    }
+}
+
+/// Prepares an `ON CLUSTER` statement so that its result has the status
+/// of every host, see `from_block`.
+pub(crate) fn query(query: Query, timeout: Option<Duration>) -> Query {
+    let query = query.setting("distributed_ddl_output_mode", DDL_OUTPUT_MODE);
+    match timeout {
+        Some(timeout) => query.setting("distributed_ddl_task_timeout", timeout.as_secs().max(1)),
+        None => query,
+    }
+}
+
+/// Parses the result of an `ON CLUSTER` statement, fails unless it
+/// succeeded on every host.
+pub(crate) fn from_block<K: ColumnType>(block: &Block<K>) -> Result<Vec<DdlHostStatus>> {
+    let mut hosts = Vec::with_capacity(block.row_count());
+    for row in block.rows() {
+        hosts.push(DdlHostStatus {
+            host: row.get("host")?,
+            port: row.get("port")?,
+            status: get_maybe_null(&row, "status")?,
+            error: get_maybe_null::<String, _>(&row, "error")?.unwrap_or_default(),
+        });
+    }
+
+    let failures: Vec<String> = hosts
+        .iter()
+        .filter(|host| !host.is_ok())
+        .map(|host| match host.status {
+            Some(_) => format!("{}:{}: {}", host.host, host.port, host.error),
+            None => format!("{}:{}: not finished", host.host, host.port),
+        })
+        .collect();
+
+    if !failures.is_empty() {
+        return Err(DriverError::DistributedDdlFailed {
+            message: failures.join("; "),
+            hosts,
+        }
+        .into());
+    }
+
+    Ok(hosts)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{errors::Error, types::SettingValue};
+
+    use super::*;
+
+    #[test]
+    fn test_query_settings() {
+        let ddl = || Query::new("DROP TABLE t ON CLUSTER main");
+
+        let prepared = query(ddl(), None);
+        let settings = prepared.get_settings();
+        assert_eq!(
+            settings.get("distributed_ddl_output_mode"),
+            Some(&SettingValue::String("never_throw".into()))
+        );
+        assert_eq!(settings.get("distributed_ddl_task_timeout"), None);
+        for (name, value) in settings {
+            crate::types::settings::validate(name, value).unwrap();
+        }
+
+        let prepared = query(ddl(), Some(Duration::from_millis(1)));
+        assert_eq!(
+            prepared.get_settings().get("distributed_ddl_task_timeout"),
+            Some(&SettingValue::UInt64(1))
+        );
+    }
+
+    #[test]
+    fn test_all_hosts_succeeded() {
+        let block = Block::new()
+            .column("host", vec!["ch1", "ch2"])
+            .column("port", vec![9000_u16, 9000])
+            .column("status", vec![0_i64, 0])
+            .column("error", vec!["", ""]);
+
+        let hosts = from_block(&block).unwrap();
+        assert_eq!(hosts.len(), 2);
+        assert!(hosts.iter().all(DdlHostStatus::is_ok));
+    }
+
+    #[test]
+    fn test_host_failed() {
+        let block = Block::new()
+            .column("host", vec!["ch1", "ch2", "ch3"])
+            .column("port", vec![9000_u16, 9000, 9000])
+            .column("status", vec![Some(0_i64), Some(57), None])
+            .column("error", vec![None, Some("Table already exists"), None]);
+
+        match from_block(&block) {
+            Err(Error::Driver(DriverError::DistributedDdlFailed { message, hosts })) => {
+                assert_eq!(
+                    message,
+                    "ch2:9000: Table already exists; ch3:9000: not finished"
+                );
+                assert_eq!(hosts.len(), 3);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_host_failed_not_null() {
+        let block = Block::new()
+            .column("host", vec!["ch1", "ch2"])
+            .column("port", vec![9000_u16, 9440])
+            .column("status", vec![0_i64, 60])
+            .column("error", vec!["", "Table doesn't exist"]);
+
+        match from_block(&block) {
+            Err(Error::Driver(DriverError::DistributedDdlFailed { message, hosts })) => {
+                assert_eq!(message, "ch2:9440: Table doesn't exist");
+                assert!(hosts[0].is_ok());
+                assert_eq!(hosts[1].status, Some(60));
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use crate::{
-    binary::{protocol, Encoder},
+    binary::{protocol, BufferPool, Encoder, Segments},
     client_info,
     errors::Result,
-    types::{Block, Context, Query, Simple},
+    types::{settings, Block, Context, Query, Simple},
 };
 
 /// Represents clickhouse commands.
@@ -11,31 +11,43 @@ pub(crate) enum Cmd {
     Ping,
     SendQuery(Query, Context),
     SendData(Block, Context),
-    Union(Box<Cmd>, Box<Cmd>),
+    Cancel,
 }
 
 impl Cmd {
     /// Returns the packed command as a byte vector.
     #[inline]
     pub(crate) fn get_packed_command(&self) -> Result<Vec<u8>> {
-        encode_command(self)
+        encode_command(self, Encoder::new())
+    }
+
+    /// Packs the command into buffers taken from `pool`, large column data
+    /// is written from the storage of the columns instead.
+    pub(crate) fn get_pooled_command(&self, pool: &BufferPool) -> Result<Segments> {
+        match self {
+            Cmd::SendData(block, context) => {
+                let mut encoder = Encoder::vectored(pool);
+                write_data(block, context, &mut encoder)?;
+                Ok(encoder.into_segments())
+            }
+            _ => encode_command(self, Encoder::pooled(pool)).map(Segments::from),
+        }
     }
 }
 
-fn encode_command(cmd: &Cmd) -> Result<Vec<u8>> {
+fn encode_command(cmd: &Cmd, encoder: Encoder) -> Result<Vec<u8>> {
     match cmd {
-        Cmd::Hello(context) => encode_hello(context),
-        Cmd::Ping => encode_ping(),
-        Cmd::SendQuery(query, context) => encode_query(query, context),
-        Cmd::SendData(block, context) => encode_data(&block, context),
-        Cmd::Union(first, second) => encode_union(first.as_ref(), second.as_ref()),
+        Cmd::Hello(context) => encode_hello(context, encoder),
+        Cmd::Ping => encode_ping(encoder),
+        Cmd::SendQuery(query, context) => encode_query(query, context, encoder),
+        Cmd::SendData(block, context) => encode_data(&block, context, encoder),
+        Cmd::Cancel => encode_cancel(encoder),
     }
 }
 
-fn encode_hello(context: &Context) -> Result<Vec<u8>> {
+fn encode_hello(context: &Context, mut encoder: Encoder) -> Result<Vec<u8>> {
     trace!("[hello]        -> {}", client_info::description());
 
-    let mut encoder = Encoder::new();
     encoder.uvarint(protocol::CLIENT_HELLO);
     client_info::write(&mut encoder);
 
@@ -48,18 +60,23 @@ fn encode_hello(context: &Context) -> Result<Vec<u8>> {
     Ok(encoder.get_buffer())
 }
 
-fn encode_ping() -> Result<Vec<u8>> {
+fn encode_ping(mut encoder: Encoder) -> Result<Vec<u8>> {
     trace!("[ping]         -> ping");
 
-    let mut encoder = Encoder::new();
     encoder.uvarint(protocol::CLIENT_PING);
     Ok(encoder.get_buffer())
 }
 
-fn encode_query(query: &Query, context: &Context) -> Result<Vec<u8>> {
+fn encode_cancel(mut encoder: Encoder) -> Result<Vec<u8>> {
+    trace!("[cancel]");
+
+    encoder.uvarint(protocol::CLIENT_CANCEL);
+    Ok(encoder.get_buffer())
+}
+
+fn encode_query(query: &Query, context: &Context, mut encoder: Encoder) -> Result<Vec<u8>> {
     trace!("[send query] {}", query.get_sql());
 
-    let mut encoder = Encoder::new();
     encoder.uvarint(protocol::CLIENT_QUERY);
     encoder.string("");
 
@@ -79,34 +96,92 @@ fn encode_query(query: &Query, context: &Context) -> Result<Vec<u8>> {
         encoder.string("");
     }
 
-    encoder.string(""); // settings
-    encoder.uvarint(protocol::STATE_COMPLETE);
-
     let options = context.options.get()?;
 
+    for (name, value) in &options.settings {
+        if !query.get_settings().contains_key(name) {
+            settings::write(&mut encoder, name, value)?;
+        }
+    }
+    for (name, value) in query.get_settings() {
+        settings::write(&mut encoder, name, value)?;
+    }
+    encoder.string(""); // end of settings
+    encoder.uvarint(protocol::STATE_COMPLETE);
+
     encoder.uvarint(if options.compression {
         protocol::COMPRESS_ENABLE
     } else {
         protocol::COMPRESS_DISABLE
     });
 
-    let options = context.options.get()?;
+    let bound = query.get_bound_sql(options.max_in_list_literals)?;
+    encoder.string(&bound.sql);
+    for (name, block) in &bound.tables {
+        block.send_table_data(
+            &mut encoder,
+            name,
+            options.compression,
+            options.compression_threshold,
+        );
+    }
+    Block::<Simple>::default().send_data(
+        &mut encoder,
+        options.compression,
+        options.compression_threshold,
+        None,
+    );
 
-    encoder.string(&query.get_sql());
-    Block::<Simple>::default().send_data(&mut encoder, options.compression);
+    Ok(encoder.get_buffer())
+}
 
+fn encode_data(block: &Block, context: &Context, mut encoder: Encoder) -> Result<Vec<u8>> {
+    write_data(block, context, &mut encoder)?;
     Ok(encoder.get_buffer())
 }
 
-fn encode_data(block: &Block, context: &Context) -> Result<Vec<u8>> {
-    let mut encoder = Encoder::new();
+fn write_data(block: &Block, context: &Context, encoder: &mut Encoder) -> Result<()> {
     let options = context.options.get()?;
-    block.send_data(&mut encoder, options.compression);
-    Ok(encoder.get_buffer())
+    block.send_data(
+        encoder,
+        options.compression,
+        options.compression_threshold,
+        options.insert_block_bytes,
+    );
+    Ok(())
 }
 
-fn encode_union(first: &Cmd, second: &Cmd) -> Result<Vec<u8>> {
-    let mut result = encode_command(first)?;
-    result.extend((encode_command(second)?).iter());
-    Ok(result)
+#[cfg(test)]
+mod test {
+    use crate::types::{IntoOptions, Options};
+
+    use super::*;
+
+    #[test]
+    fn test_encode_cancel() {
+        assert_eq!(Cmd::Cancel.get_packed_command().unwrap(), vec![3]);
+    }
+
+    #[test]
+    fn test_query_settings_override_options() {
+        let mut context = Context::default();
+        context.options = Options::default()
+            .with_setting("max_threads", 4)
+            .unwrap()
+            .with_setting("readonly", 1)
+            .unwrap()
+            .into_options_src();
+        let query = Query::new("SELECT 1").setting("max_threads", 8);
+
+        let buffer = encode_query(&query, &context, Encoder::new()).unwrap();
+        let positions: Vec<_> = buffer
+            .windows(11)
+            .enumerate()
+            .filter(|(_, window)| window == b"max_threads")
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(buffer[positions[0] + 11], 8);
+        assert!(buffer.windows(8).any(|window| window == b"readonly"));
+    }
 }
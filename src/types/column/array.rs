@@ -2,7 +2,9 @@ use crate::{
     binary::{Encoder, ReadEx},
     errors::Result,
     types::{
-        column::{column_data::BoxColumnData, list::List, BoxColumnWrapper, ColumnData},
+        column::{
+            column_data::BoxColumnData, list::List, BoxColumnWrapper, ColumnData, StringLayout,
+        },
         SqlType, Value, ValueRef,
     },
 };
@@ -20,6 +22,7 @@ impl ArrayColumnData {
         type_name: &str,
         rows: usize,
         tz: Tz,
+        strings: StringLayout,
     ) -> Result<Self> {
         let mut offsets = List::with_capacity(rows);
         offsets.resize(rows, 0_u64);
@@ -29,7 +32,8 @@ impl ArrayColumnData {
             0 => 0,
             _ => offsets.at(rows - 1) as usize,
         };
-        let inner = ColumnData::load_data::<BoxColumnWrapper, _>(reader, type_name, size, tz)?;
+        let inner =
+            ColumnData::load_data::<BoxColumnWrapper, _>(reader, type_name, size, tz, strings)?;
 
         Ok(ArrayColumnData { inner, offsets })
     }
@@ -56,6 +60,10 @@ impl ColumnData for ArrayColumnData {
         self.offsets.len()
     }
 
+    fn byte_size(&self) -> usize {
+        self.offsets.byte_size() + self.inner.byte_size()
+    }
+
     fn push(&mut self, value: Value) {
         if let Value::Array(_, vs) = value {
             let offsets_len = self.offsets.len();
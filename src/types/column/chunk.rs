@@ -16,7 +16,15 @@ pub struct ChunkColumnData {
 }
 
 impl ChunkColumnData {
+    /// Creates a view of `range` of `data`, a view of a view refers
+    /// to the viewed data directly.
     pub(crate) fn new(data: ArcColumnData, range: ops::Range<usize>) -> Self {
+        if let Some((inner, outer)) = data.as_chunk() {
+            return Self {
+                data: inner.clone(),
+                range: outer.start + range.start..outer.start + range.end,
+            };
+        }
         Self { data, range }
     }
 }
@@ -34,10 +42,24 @@ impl ColumnData for ChunkColumnData {
         )
     }
 
+    fn wire_bytes(&self, start: usize, end: usize) -> Option<&[u8]> {
+        self.data.wire_bytes(
+            self.range.start + start,
+            cmp::min(self.range.end, self.range.start + end),
+        )
+    }
+
     fn len(&self) -> usize {
         self.range.len()
     }
 
+    fn byte_size(&self) -> usize {
+        match self.data.len() {
+            0 => 0,
+            total => self.data.byte_size() * self.range.len() / total,
+        }
+    }
+
     fn push(&mut self, _value: Value) {
         unimplemented!()
     }
@@ -53,4 +75,27 @@ impl ColumnData for ChunkColumnData {
     fn clone_instance(&self) -> BoxColumnData {
         unimplemented!()
     }
+
+    fn as_chunk(&self) -> Option<(&ArcColumnData, ops::Range<usize>)> {
+        Some((&self.data, self.range.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::types::{Block, Simple, ValueRef};
+
+    #[test]
+    fn test_chunk_of_chunk() {
+        let block = Block::<Simple>::new().column("id", vec![1_u32, 2, 3, 4, 5]);
+        let column = block.get_column("id").unwrap();
+
+        let tail = column.slice(1..5).slice(1..3).slice(1..2);
+        let (data, range) = tail.data.as_chunk().unwrap();
+        assert!(Arc::ptr_eq(data, &column.data));
+        assert_eq!(range, 3..4);
+        assert_eq!(tail.at(0), ValueRef::UInt32(4));
+    }
 }
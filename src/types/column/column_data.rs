@@ -1,4 +1,4 @@
-use std::{convert, sync::Arc};
+use std::{any::Any, convert, ops, sync::Arc};
 
 use crate::{
     binary::Encoder,
@@ -14,11 +14,29 @@ pub trait ColumnData {
     fn sql_type(&self) -> SqlType;
     fn save(&self, encoder: &mut Encoder, start: usize, end: usize);
     fn len(&self) -> usize;
+    /// Approximate heap footprint of the data in bytes.
+    fn byte_size(&self) -> usize;
     fn push(&mut self, value: Value);
     fn at(&self, index: usize) -> ValueRef;
 
     fn clone_instance(&self) -> BoxColumnData;
 
+    /// Returns the values from `start` to `end` the way they're sent,
+    /// if they're stored that way.
+    fn wire_bytes(&self, _start: usize, _end: usize) -> Option<&[u8]> {
+        None
+    }
+
+    /// Returns the data and the range of it, if this is a view of other data.
+    fn as_chunk(&self) -> Option<(&ArcColumnData, ops::Range<usize>)> {
+        None
+    }
+
+    /// Gives access to the concrete data type, if it can be taken apart.
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
+
     unsafe fn get_internal(&self, _pointers: &[*mut *const u8], _level: u8) -> Result<()> {
         Err(Error::FromSql(FromSqlError::UnsupportedOperation))
     }
@@ -51,6 +51,10 @@ impl ColumnData for ConcatColumnData {
         *self.index.last().unwrap()
     }
 
+    fn byte_size(&self) -> usize {
+        self.data.iter().map(|data| data.byte_size()).sum()
+    }
+
     fn push(&mut self, _value: Value) {
         unimplemented!()
     }
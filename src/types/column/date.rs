@@ -217,6 +217,10 @@ where
         self.data.len()
     }
 
+    fn byte_size(&self) -> usize {
+        self.data.byte_size()
+    }
+
     fn push(&mut self, value: Value) {
         self.data.push(T::get_stamp(value));
     }
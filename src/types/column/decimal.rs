@@ -7,7 +7,7 @@ use crate::{
         column::{
             column_data::BoxColumnData, column_data::ColumnData, list::List,
             nullable::NullableColumnData, BoxColumnWrapper, ColumnFrom, ColumnWrapper, Either,
-            VectorColumnData,
+            StringLayout, VectorColumnData,
         },
         decimal::{Decimal, NoBits},
         from_sql::FromSql,
@@ -44,12 +44,14 @@ impl DecimalColumnData {
         nobits: NoBits,
         size: usize,
         tz: Tz,
+        strings: StringLayout,
     ) -> Result<Self> {
         let type_name = match nobits {
             NoBits::N32 => "Int32",
             NoBits::N64 => "Int64",
         };
-        let inner = ColumnData::load_data::<BoxColumnWrapper, _>(reader, type_name, size, tz)?;
+        let inner =
+            ColumnData::load_data::<BoxColumnWrapper, _>(reader, type_name, size, tz, strings)?;
 
         Ok(DecimalColumnData {
             inner,
@@ -145,6 +147,10 @@ impl ColumnData for DecimalColumnData {
         self.inner.len()
     }
 
+    fn byte_size(&self) -> usize {
+        self.inner.byte_size()
+    }
+
     fn push(&mut self, value: Value) {
         if let Value::Decimal(decimal) = value {
             match self.nobits {
@@ -221,6 +227,10 @@ impl<K: ColumnType> ColumnData for DecimalAdapter<K> {
         self.column.len()
     }
 
+    fn byte_size(&self) -> usize {
+        self.column.byte_size()
+    }
+
     fn push(&mut self, _: Value) {
         unimplemented!()
     }
@@ -278,6 +288,10 @@ impl<K: ColumnType> ColumnData for NullableDecimalAdapter<K> {
         self.column.len()
     }
 
+    fn byte_size(&self) -> usize {
+        self.column.byte_size()
+    }
+
     fn push(&mut self, _: Value) {
         unimplemented!()
     }
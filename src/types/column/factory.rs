@@ -1,13 +1,13 @@
 use chrono_tz::Tz;
 
 use crate::{
-    binary::ReadEx,
+    binary::{put_uvarint, ReadEx},
     errors::Result,
     types::column::{
         array::ArrayColumnData, column_data::ColumnData, date::DateColumnData,
         decimal::DecimalColumnData, fixed_string::FixedStringColumnData, list::List,
         nullable::NullableColumnData, numeric::VectorColumnData, string::StringColumnData,
-        BoxColumnWrapper, ColumnWrapper, SqlType,
+        BoxColumnWrapper, ColumnWrapper, SqlType, StringLayout,
     },
     types::decimal::NoBits,
 };
@@ -18,6 +18,7 @@ impl dyn ColumnData {
         type_name: &str,
         size: usize,
         tz: Tz,
+        strings: StringLayout,
     ) -> Result<W::Wrapper> {
         Ok(match type_name {
             "UInt8" => W::wrap(VectorColumnData::<u8>::load(reader, size)?),
@@ -30,19 +31,19 @@ impl dyn ColumnData {
             "Int64" => W::wrap(VectorColumnData::<i64>::load(reader, size)?),
             "Float32" => W::wrap(VectorColumnData::<f32>::load(reader, size)?),
             "Float64" => W::wrap(VectorColumnData::<f64>::load(reader, size)?),
-            "String" => W::wrap(StringColumnData::load(reader, size)?),
+            "String" => W::wrap(StringColumnData::load(reader, size, strings)?),
             "Date" => W::wrap(DateColumnData::<u16>::load(reader, size, tz)?),
             "DateTime" => W::wrap(DateColumnData::<u32>::load(reader, size, tz)?),
             _ => {
                 if let Some(inner_type) = parse_nullable_type(type_name) {
-                    W::wrap(NullableColumnData::load(reader, inner_type, size, tz)?)
+                    W::wrap(NullableColumnData::load(reader, inner_type, size, tz, strings)?)
                 } else if let Some(str_len) = parse_fixed_string(type_name) {
                     W::wrap(FixedStringColumnData::load(reader, size, str_len)?)
                 } else if let Some(inner_type) = parse_array_type(type_name) {
-                    W::wrap(ArrayColumnData::load(reader, inner_type, size, tz)?)
+                    W::wrap(ArrayColumnData::load(reader, inner_type, size, tz, strings)?)
                 } else if let Some((precision, scale, nobits)) = parse_decimal(type_name) {
                     W::wrap(DecimalColumnData::load(
-                        reader, precision, scale, nobits, size, tz,
+                        reader, precision, scale, nobits, size, tz, strings,
                     )?)
                 } else {
                     let message = format!("Unsupported column type \"{}\".", type_name);
@@ -52,6 +53,68 @@ impl dyn ColumnData {
         })
     }
 
+    /// Copies the encoded data of a column to `raw` without decoding it.
+    ///
+    /// The data is read the way `load_data` reads it, so `load_data` can't fail
+    /// on what was copied.
+    pub(crate) fn copy_data<T: ReadEx>(
+        reader: &mut T,
+        type_name: &str,
+        size: usize,
+        raw: &mut Vec<u8>,
+    ) -> Result<()> {
+        let width = match type_name {
+            "UInt8" | "Int8" => 1,
+            "UInt16" | "Int16" | "Date" => 2,
+            "UInt32" | "Int32" | "Float32" | "DateTime" => 4,
+            "UInt64" | "Int64" | "Float64" => 8,
+            "String" => {
+                raw.reserve(size);
+                for _ in 0..size {
+                    let len = reader.read_uvarint()?;
+                    let mut scratch = [0_u8; 10];
+                    let n = put_uvarint(&mut scratch[..], len);
+                    raw.extend_from_slice(&scratch[..n]);
+                    copy_bytes(reader, len as usize, raw)?;
+                }
+                return Ok(());
+            }
+            _ => {
+                if let Some(inner_type) = parse_nullable_type(type_name) {
+                    copy_bytes(reader, size, raw)?;
+                    return <dyn ColumnData>::copy_data(reader, inner_type, size, raw);
+                } else if let Some(str_len) = parse_fixed_string(type_name) {
+                    str_len
+                } else if let Some(inner_type) = parse_array_type(type_name) {
+                    let mut inner_size = 0_u64;
+                    raw.reserve(size * 8);
+                    for _ in 0..size {
+                        let offset: u64 = reader.read_scalar()?;
+                        // Decoded arrays are sliced by their offsets.
+                        if offset < inner_size {
+                            let message = format!("Decreasing offsets of \"{}\".", type_name);
+                            return Err(message.into());
+                        }
+                        inner_size = offset;
+                        raw.extend_from_slice(&inner_size.to_le_bytes());
+                    }
+                    let inner_size = inner_size as usize;
+                    return <dyn ColumnData>::copy_data(reader, inner_type, inner_size, raw);
+                } else if let Some((_, _, nobits)) = parse_decimal(type_name) {
+                    match nobits {
+                        NoBits::N32 => 4,
+                        NoBits::N64 => 8,
+                    }
+                } else {
+                    let message = format!("Unsupported column type \"{}\".", type_name);
+                    return Err(message.into());
+                }
+            }
+        };
+
+        copy_bytes(reader, size * width, raw)
+    }
+
     pub(crate) fn from_type<W: ColumnWrapper>(
         sql_type: SqlType,
         timezone: Tz,
@@ -103,6 +166,44 @@ impl dyn ColumnData {
     }
 }
 
+fn copy_bytes<T: ReadEx>(reader: &mut T, len: usize, raw: &mut Vec<u8>) -> Result<()> {
+    let start = raw.len();
+    raw.resize(start + len, 0);
+    reader.read_bytes(&mut raw[start..])
+}
+
+/// Parses a type name as returned by `DESCRIBE TABLE`.
+pub(crate) fn parse_sql_type(source: &str) -> Option<SqlType> {
+    Some(match source {
+        "UInt8" => SqlType::UInt8,
+        "UInt16" => SqlType::UInt16,
+        "UInt32" => SqlType::UInt32,
+        "UInt64" => SqlType::UInt64,
+        "Int8" => SqlType::Int8,
+        "Int16" => SqlType::Int16,
+        "Int32" => SqlType::Int32,
+        "Int64" => SqlType::Int64,
+        "Float32" => SqlType::Float32,
+        "Float64" => SqlType::Float64,
+        "String" => SqlType::String,
+        "Date" => SqlType::Date,
+        _ if source == "DateTime" || source.starts_with("DateTime(") => SqlType::DateTime,
+        _ => {
+            if let Some(inner_type) = parse_nullable_type(source) {
+                SqlType::Nullable(parse_sql_type(inner_type)?.into())
+            } else if let Some(str_len) = parse_fixed_string(source) {
+                SqlType::FixedString(str_len)
+            } else if let Some(inner_type) = parse_array_type(source) {
+                SqlType::Array(parse_sql_type(inner_type)?.into())
+            } else if let Some((precision, scale, _)) = parse_decimal(source) {
+                SqlType::Decimal(precision, scale)
+            } else {
+                return None;
+            }
+        }
+    })
+}
+
 fn parse_fixed_string(source: &str) -> Option<usize> {
     if !source.starts_with("FixedString") {
         return None;
@@ -210,6 +311,18 @@ mod test {
         assert_eq!(parse_nullable_type("Nullable(Nullable(Int8))"), None);
     }
 
+    #[test]
+    fn test_parse_sql_type() {
+        assert_eq!(parse_sql_type("UInt32"), Some(SqlType::UInt32));
+        assert_eq!(parse_sql_type("DateTime('Europe/Moscow')"), Some(SqlType::DateTime));
+        assert_eq!(
+            parse_sql_type("Array(Nullable(String))"),
+            Some(SqlType::Array(SqlType::Nullable(SqlType::String.into()).into()))
+        );
+        assert_eq!(parse_sql_type("Decimal(9, 4)"), Some(SqlType::Decimal(9, 4)));
+        assert_eq!(parse_sql_type("Enum8('a' = 1)"), None);
+    }
+
     #[test]
     fn test_parse_fixed_string() {
         assert_eq!(parse_fixed_string("FixedString(8)"), Some(8_usize));
@@ -33,13 +33,8 @@ impl FixedStringColumnData {
 
     pub(crate) fn load<T: ReadEx>(reader: &mut T, size: usize, str_len: usize) -> Result<Self> {
         let mut instance = Self::with_capacity(size, str_len);
-
-        for _ in 0..size {
-            let old_len = instance.buffer.len();
-            instance.buffer.resize(old_len + str_len, 0_u8);
-            reader.read_bytes(&mut instance.buffer[old_len..old_len + str_len])?;
-        }
-
+        instance.buffer.resize(size * str_len, 0_u8);
+        reader.read_bytes(&mut instance.buffer)?;
         Ok(instance)
     }
 }
@@ -55,16 +50,24 @@ impl ColumnData for FixedStringColumnData {
         encoder.write_bytes(&self.buffer[start_index..end_index]);
     }
 
+    fn wire_bytes(&self, start: usize, end: usize) -> Option<&[u8]> {
+        Some(&self.buffer[start * self.str_len..end * self.str_len])
+    }
+
     fn len(&self) -> usize {
         self.buffer.len() / self.str_len
     }
 
+    fn byte_size(&self) -> usize {
+        self.buffer.capacity()
+    }
+
     fn push(&mut self, value: Value) {
         let bs: String = String::from(value);
         let l = cmp::min(bs.len(), self.str_len);
         let old_len = self.buffer.len();
         self.buffer.extend_from_slice(&bs.as_bytes()[0..l]);
-        self.buffer.resize(old_len + (self.str_len - l), 0_u8);
+        self.buffer.resize(old_len + self.str_len, 0_u8);
     }
 
     fn at(&self, index: usize) -> ValueRef {
@@ -122,6 +125,10 @@ impl<K: ColumnType> ColumnData for FixedStringAdapter<K> {
         self.column.len()
     }
 
+    fn byte_size(&self) -> usize {
+        self.column.byte_size()
+    }
+
     fn push(&mut self, _value: Value) {
         unimplemented!()
     }
@@ -169,6 +176,10 @@ impl<K: ColumnType> ColumnData for NullableFixedStringAdapter<K> {
         self.column.len()
     }
 
+    fn byte_size(&self) -> usize {
+        self.column.byte_size()
+    }
+
     fn push(&mut self, _value: Value) {
         unimplemented!()
     }
@@ -2,7 +2,7 @@
 
 use std::{
     iter::{FusedIterator},
-    marker, mem, ptr, slice,
+    marker, mem, ptr, slice, str,
 };
 
 use chrono::{prelude::*, Date};
@@ -127,6 +127,11 @@ pub struct StringIterator<'a> {
     size: usize,
 }
 
+/// Iterator over the values of a `String` column borrowed as `&str`.
+pub struct StrIterator<'a> {
+    inner: StringIterator<'a>,
+}
+
 pub struct DecimalIterator<'a> {
     ptr: *const u8,
     end: *const u8,
@@ -459,6 +464,51 @@ impl<'a> SimpleIterable<'a> for &[u8] {
     }
 }
 
+impl<'a> Iterator for StrIterator<'a> {
+    type Item = &'a str;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // The strings are checked to be valid UTF-8 before iterating.
+        self.inner
+            .next()
+            .map(|bytes| unsafe { str::from_utf8_unchecked(bytes) })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.inner
+            .nth(n)
+            .map(|bytes| unsafe { str::from_utf8_unchecked(bytes) })
+    }
+}
+
+impl FusedIterator for StrIterator<'_> {}
+
+impl<'a> SimpleIterable<'a> for &str {
+    type Iter = StrIterator<'a>;
+
+    fn iter(column: &'a Column<Simple>, column_type: SqlType) -> Result<Self::Iter> {
+        for bytes in <&[u8]>::iter(column, column_type)? {
+            str::from_utf8(bytes)?;
+        }
+
+        Ok(StrIterator {
+            inner: <&[u8]>::iter(column, column_type)?,
+        })
+    }
+}
+
 impl<'a> SimpleIterable<'a> for Decimal {
     type Iter = DecimalIterator<'a>;
 
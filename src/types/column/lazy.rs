@@ -0,0 +1,202 @@
+use std::{
+    any::Any,
+    mem,
+    sync::{Mutex, OnceLock},
+};
+
+use chrono_tz::Tz;
+
+use crate::{
+    binary::{Encoder, ReadEx},
+    errors::Result,
+    types::{
+        column::{column_data::BoxColumnData, BoxColumnWrapper, ColumnData, StringLayout},
+        SqlType, Value, ValueRef,
+    },
+};
+
+/// Column data kept encoded as received and decoded on first access.
+pub(crate) struct LazyColumnData {
+    type_name: String,
+    size: usize,
+    tz: Tz,
+    strings: StringLayout,
+    raw: Mutex<Vec<u8>>,
+    /// Empty column of the same type, so the type is known without decoding.
+    empty: BoxColumnData,
+    data: OnceLock<BoxColumnData>,
+}
+
+impl LazyColumnData {
+    pub(crate) fn load<R: ReadEx>(
+        reader: &mut R,
+        type_name: &str,
+        size: usize,
+        tz: Tz,
+        strings: StringLayout,
+    ) -> Result<Self> {
+        let mut nothing: &[u8] = &[];
+        let empty = <dyn ColumnData>::load_data::<BoxColumnWrapper, _>(
+            &mut nothing,
+            type_name,
+            0,
+            tz,
+            strings,
+        )?;
+
+        let mut raw = Vec::new();
+        <dyn ColumnData>::copy_data(reader, type_name, size, &mut raw)?;
+
+        Ok(LazyColumnData {
+            type_name: type_name.to_string(),
+            size,
+            tz,
+            strings,
+            raw: Mutex::new(raw),
+            empty,
+            data: OnceLock::new(),
+        })
+    }
+
+    fn decoded(&self) -> &BoxColumnData {
+        self.data.get_or_init(|| {
+            let raw = mem::take(&mut *self.raw.lock().unwrap());
+            <dyn ColumnData>::load_data::<BoxColumnWrapper, _>(
+                &mut raw.as_slice(),
+                &self.type_name,
+                self.size,
+                self.tz,
+                self.strings,
+            )
+            .expect("column data is checked by copy_data when it's received")
+        })
+    }
+
+    fn decoded_mut(&mut self) -> &mut BoxColumnData {
+        self.decoded();
+        self.data.get_mut().unwrap()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_decoded(&self) -> bool {
+        self.data.get().is_some()
+    }
+}
+
+impl ColumnData for LazyColumnData {
+    fn sql_type(&self) -> SqlType {
+        self.empty.sql_type()
+    }
+
+    fn save(&self, encoder: &mut Encoder, start: usize, end: usize) {
+        self.decoded().save(encoder, start, end)
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+
+    fn byte_size(&self) -> usize {
+        match self.data.get() {
+            Some(data) => data.byte_size(),
+            None => self.raw.lock().unwrap().capacity(),
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        self.decoded_mut().push(value);
+        self.size += 1;
+    }
+
+    fn at(&self, index: usize) -> ValueRef<'_> {
+        self.decoded().at(index)
+    }
+
+    fn clone_instance(&self) -> BoxColumnData {
+        self.decoded().clone_instance()
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        self.decoded_mut().as_any_mut()
+    }
+
+    unsafe fn get_internal(&self, pointers: &[*mut *const u8], level: u8) -> Result<()> {
+        self.decoded().get_internal(pointers, level)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::types::{Block, Decimal, LoadOptions, Simple};
+
+    #[test]
+    fn test_decode_on_access() {
+        let block = Block::<Simple>::new().column("name", vec!["a", "bc", ""]);
+        let mut encoder = Encoder::new();
+        block.get_column("name").unwrap().data.save(&mut encoder, 0, 3);
+        let buffer = encoder.get_buffer();
+
+        let mut reader = &buffer[..];
+        let data = LazyColumnData::load(&mut reader, "String", 3, Tz::UTC, StringLayout::default());
+        let data = data.unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(data.sql_type(), SqlType::String);
+        assert_eq!(data.len(), 3);
+        assert!(!data.is_decoded());
+
+        assert_eq!(data.at(1), ValueRef::String(b"bc"));
+        assert!(data.is_decoded());
+    }
+
+    #[test]
+    fn test_check_on_receive() {
+        let mut encoder = Encoder::new();
+        for offset in &[2_u64, 1] {
+            encoder.write(*offset);
+        }
+        encoder.write_bytes(&[1, 2]);
+        let buffer = encoder.get_buffer();
+
+        let mut reader = &buffer[..];
+        let strings = StringLayout::default();
+        assert!(LazyColumnData::load(&mut reader, "Array(UInt8)", 2, Tz::UTC, strings).is_err());
+
+        let mut reader = &buffer[..buffer.len() - 1];
+        assert!(LazyColumnData::load(&mut reader, "UInt64", 3, Tz::UTC, strings).is_err());
+    }
+
+    #[test]
+    fn test_lazy_block() {
+        let date = Tz::UTC.with_ymd_and_hms(2020, 1, 2, 3, 4, 5).unwrap();
+        let block = Block::<Simple>::new()
+            .column("id", vec![1_u32, 2, 3])
+            .column("name", vec!["a", "bc", ""])
+            .column("score", vec![Some(1.5_f64), None, Some(3.0)])
+            .column("tags", vec![vec!["x"], vec![], vec!["y", "z"]])
+            .column("price", vec![Decimal::of(1.25, 2); 3])
+            .column("time", vec![date; 3]);
+
+        let mut encoder = Encoder::new();
+        block.write(&mut encoder, true);
+        let buffer = encoder.get_buffer();
+
+        let options = LoadOptions {
+            compress: true,
+            lazy: true,
+            ..LoadOptions::default()
+        };
+        let actual = Block::load_with(&mut Cursor::new(&buffer[..]), Tz::UTC, options).unwrap();
+        assert_eq!(actual.row_count(), 3);
+        let tags = actual.get_column("tags").unwrap();
+        assert_eq!(tags.sql_type(), SqlType::Array(SqlType::String.into()));
+
+        let name: &str = actual.get(1, "name").unwrap();
+        assert_eq!(name, "bc");
+        assert_eq!(actual, block);
+    }
+}
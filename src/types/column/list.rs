@@ -22,10 +22,18 @@ where
         self.data[index]
     }
 
+    pub fn byte_size(&self) -> usize {
+        self.data.capacity() * mem::size_of::<T>()
+    }
+
     pub fn push(&mut self, value: T) {
         self.data.push(value);
     }
 
+    pub(super) fn extend_from_slice(&mut self, values: &[T]) {
+        self.data.extend_from_slice(values);
+    }
+
     #[cfg(test)]
     pub fn new() -> List<T> {
         List { data: Vec::new() }
@@ -41,6 +49,10 @@ where
         self.data.resize(new_len, value);
     }
 
+    pub(super) fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+
     pub(super) unsafe fn set_len(&mut self, new_len: usize) {
         self.data.set_len(new_len);
     }
@@ -50,6 +62,15 @@ where
     }
 }
 
+impl<T> From<Vec<T>> for List<T>
+where
+    T: StatBuffer + Unmarshal<T> + Marshal + Copy + Sync + 'static,
+{
+    fn from(data: Vec<T>) -> Self {
+        Self { data }
+    }
+}
+
 impl<T> fmt::Debug for List<T>
 where
     T: StatBuffer + Unmarshal<T> + Marshal + Copy + Sync + 'static + fmt::Debug,
@@ -115,4 +136,14 @@ mod test {
             vs.push(k);
         }
     }
+    #[test]
+    fn test_from_vec() {
+        let vs = vec![1_u64, 2, 3];
+        let ptr = vs.as_ptr();
+
+        let list = List::from(vs);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.at(2), 3);
+        assert_eq!(unsafe { list.as_ptr() }, ptr);
+    }
 }
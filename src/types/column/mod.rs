@@ -1,4 +1,4 @@
-use std::{fmt, ops, sync::Arc, marker};
+use std::{convert, fmt, marker, ops, ptr, slice, sync::Arc};
 
 use chrono_tz::Tz;
 
@@ -7,20 +7,25 @@ use crate::{
     errors::{Error, FromSqlError, Result},
     types::{
         column::{
-            column_data::ArcColumnData,
             decimal::{DecimalAdapter, NullableDecimalAdapter},
             fixed_string::{FixedStringAdapter, NullableFixedStringAdapter},
             string::StringAdapter,
             iter::SimpleIterable,
+            lazy::LazyColumnData,
         },
         decimal::NoBits,
-        SqlType, Value, ValueRef,
+        Marshal, SqlType, StatBuffer, Unmarshal, Value, ValueRef,
     },
 };
 
 use self::chunk::ChunkColumnData;
-pub(crate) use self::string_pool::StringPool;
-pub use self::{column_data::ColumnData, concat::ConcatColumnData, numeric::VectorColumnData};
+pub(crate) use self::string_pool::{StringLayout, StringPool, DEFAULT_MAX_POOLED};
+pub(crate) use self::{column_data::ArcColumnData, factory::parse_sql_type};
+pub use self::{
+    column_data::ColumnData, concat::ConcatColumnData, numeric::VectorColumnData,
+    stats::ColumnStats, typed::PushTyped,
+};
+use self::stats::StatsCache;
 
 mod array;
 mod chunk;
@@ -31,17 +36,21 @@ mod decimal;
 mod factory;
 pub(crate) mod fixed_string;
 mod iter;
+mod lazy;
 mod list;
 mod nullable;
 mod numeric;
 mod string;
+mod stats;
 mod string_pool;
+mod typed;
 
 /// Represents Clickhouse Column
 pub struct Column<K: ColumnType> {
     pub(crate) name: String,
     pub(crate) data: ArcColumnData,
     pub(crate) _marker: marker::PhantomData<K>,
+    pub(crate) stats: StatsCache,
 }
 
 pub trait ColumnFrom {
@@ -108,6 +117,7 @@ impl<K: ColumnType> Clone for Column<K> {
             name: self.name.clone(),
             data: self.data.clone(),
             _marker: marker::PhantomData,
+            stats: self.stats.clone(),
         }
     }
 }
@@ -128,6 +138,7 @@ impl Column<Simple> {
                     name,
                     data: Arc::new(data),
                     _marker: marker::PhantomData,
+                    stats: Default::default(),
                 }
             }
         }
@@ -173,21 +184,128 @@ impl Column<Simple> {
     {
         T::iter(self, self.sql_type())
     }
+
+    /// Returns the values of a numeric column as a slice, without copying.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::Block;
+    /// let block = Block::new().column("n", vec![1_u64, 2, 3]);
+    /// let values: &[u64] = block.get_column("n")?.as_slice()?;
+    /// assert_eq!(values, &[1, 2, 3]);
+    /// # Ok::<(), clickhouse_rs::errors::Error>(())
+    /// ```
+    pub fn as_slice<T>(&self) -> Result<&[T]>
+    where
+        T: StatBuffer + Copy,
+    {
+        let sql_type = self.sql_type();
+        if sql_type != T::sql_type() {
+            return Err(Error::FromSql(FromSqlError::InvalidType {
+                src: sql_type.to_string(),
+                dst: T::sql_type().to_string(),
+            }));
+        }
+
+        unsafe {
+            let mut ptr: *const u8 = ptr::null();
+            let mut size: usize = 0;
+            self.get_internal(&[&mut ptr, &mut size as *mut usize as *mut *const u8], 0)?;
+            assert_ne!(ptr, ptr::null());
+            Ok(slice::from_raw_parts(ptr as *const T, size))
+        }
+    }
+
+    /// Appends a value to a column of the same type without converting it to `Value`.
+    pub fn push_typed<T: PushTyped>(&mut self, value: T) -> Result<()> {
+        self.extend_from_slice(slice::from_ref(&value))
+    }
+
+    /// Appends values to a column of the same type, numbers are copied at once.
+    pub fn extend_from_slice<T: PushTyped>(&mut self, values: &[T]) -> Result<()> {
+        let sql_type = self.sql_type();
+        if sql_type != T::column_type() {
+            return Err(Error::FromSql(FromSqlError::InvalidType {
+                src: T::column_type().to_string(),
+                dst: sql_type.to_string(),
+            }));
+        }
+
+        let data = self.make_mut();
+        let extended = match data.as_any_mut() {
+            Some(any) => T::extend_data(any, values),
+            None => false,
+        };
+        if !extended {
+            for value in values {
+                data.push(value.clone().into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the values of a numeric column, the data is moved out
+    /// without copying if the column isn't shared.
+    pub fn into_vec<T>(mut self) -> Result<Vec<T>>
+    where
+        T: StatBuffer
+            + Unmarshal<T>
+            + Marshal
+            + Copy
+            + convert::Into<Value>
+            + convert::From<Value>
+            + Sync
+            + Default
+            + 'static,
+    {
+        self.as_slice::<T>()?;
+
+        if let Some(data) = Arc::get_mut(&mut self.data) {
+            if let Some(values) = VectorColumnData::<T>::take_from(data) {
+                return Ok(values);
+            }
+        }
+        Ok(self.as_slice::<T>()?.to_vec())
+    }
 }
 
 impl<K: ColumnType> Column<K> {
-    pub(crate) fn read<R: ReadEx>(reader: &mut R, size: usize, tz: Tz) -> Result<Column<K>> {
+    pub(crate) fn read<R: ReadEx>(
+        reader: &mut R,
+        size: usize,
+        tz: Tz,
+        strings: StringLayout,
+    ) -> Result<Column<K>> {
         let name = reader.read_string()?;
         let type_name = reader.read_string()?;
-        let data = ColumnData::load_data::<ArcColumnWrapper, _>(reader, &type_name, size, tz)?;
+        let data =
+            ColumnData::load_data::<ArcColumnWrapper, _>(reader, &type_name, size, tz, strings)?;
         let column = Self {
             name,
             data,
             _marker: marker::PhantomData,
+            stats: Default::default(),
         };
         Ok(column)
     }
 
+    /// Reads a column that is decoded on first access.
+    pub(crate) fn read_lazy<R: ReadEx>(
+        reader: &mut R,
+        size: usize,
+        tz: Tz,
+        strings: StringLayout,
+    ) -> Result<Column<K>> {
+        let name = reader.read_string()?;
+        let type_name = reader.read_string()?;
+        let data = LazyColumnData::load(reader, &type_name, size, tz, strings)?;
+        Ok(Self {
+            name,
+            data: Arc::new(data),
+            _marker: marker::PhantomData,
+            stats: Default::default(),
+        })
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -196,6 +314,18 @@ impl<K: ColumnType> Column<K> {
         self.data.sql_type()
     }
 
+    /// Returns the min and max values, the number of `NULL`s and of distinct
+    /// values of the column. They're computed once and shared by clones.
+    pub fn stats(&self) -> ColumnStats {
+        self.stats.get(self.data.as_ref())
+    }
+
+    /// Approximate heap footprint of the column data in bytes, including
+    /// string pools and null maps. A slice reports its share of the data.
+    pub fn byte_size(&self) -> usize {
+        self.data.byte_size()
+    }
+
     pub(crate) fn at(&self, index: usize) -> ValueRef {
         self.data.at(index)
     }
@@ -204,19 +334,81 @@ impl<K: ColumnType> Column<K> {
         encoder.string(&self.name);
         encoder.string(self.data.sql_type().to_string().as_ref());
         let len = self.data.len();
-        self.data.save(encoder, 0, len);
+        if !encoder.write_shared(&self.data, 0, len) {
+            self.data.save(encoder, 0, len);
+        }
     }
 
-    pub(crate) fn len(&self) -> usize {
+    /// Returns the number of values in the column.
+    pub fn len(&self) -> usize {
         self.data.len()
     }
 
-    pub(crate) fn slice(&self, range: ops::Range<usize>) -> Column<Complex> {
+    /// Returns `true` if the column has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a column of the values at `indices`, the data is shared
+    /// if the indices take every value in order.
+    pub(crate) fn take(&self, indices: &[usize]) -> Self {
+        if indices.len() == self.len() && indices.iter().enumerate().all(|(i, &j)| i == j) {
+            return self.clone();
+        }
+
+        let sql_type = self.sql_type();
+        let timezone = if has_timezone(sql_type) {
+            (0..self.len())
+                .find_map(|index| value_timezone(&self.at(index)))
+                .unwrap_or(Tz::UTC)
+        } else {
+            Tz::UTC
+        };
+
+        // The type is one of an existing column, so it's supported.
+        let mut data =
+            <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, timezone, indices.len())
+                .unwrap();
+        let target = Arc::get_mut(&mut data).unwrap();
+        for &index in indices {
+            target.push(Value::from(self.at(index)));
+        }
+
+        Column {
+            name: self.name.clone(),
+            data,
+            _marker: marker::PhantomData,
+            stats: Default::default(),
+        }
+    }
+
+    /// Returns a view of the values in `range`, the data is shared with
+    /// the column instead of being copied.
+    ///
+    /// Panics if the range is out of bounds.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::Block;
+    /// let block = Block::new().column("n", vec![1_u64, 2, 3, 4]);
+    /// let column = block.get_column("n")?;
+    /// let (head, tail) = (column.slice(0..2), column.slice(2..column.len()));
+    /// assert_eq!((head.len(), tail.len()), (2, 2));
+    /// # Ok::<(), clickhouse_rs::errors::Error>(())
+    /// ```
+    pub fn slice(&self, range: ops::Range<usize>) -> Column<Complex> {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "slice {:?} out of range for column of {} values",
+            range,
+            self.len()
+        );
+
         let data = ChunkColumnData::new(self.data.clone(), range);
         Column {
             name: self.name.clone(),
             data: Arc::new(data),
             _marker: marker::PhantomData,
+            stats: Default::default(),
         }
     }
 
@@ -238,6 +430,7 @@ impl<K: ColumnType> Column<K> {
                     name,
                     data: Arc::new(adapter),
                     _marker: marker::PhantomData,
+                    stats: Default::default(),
                 })
             }
             (
@@ -253,6 +446,7 @@ impl<K: ColumnType> Column<K> {
                     name,
                     data: Arc::new(adapter),
                     _marker: marker::PhantomData,
+                    stats: Default::default(),
                 })
             }
             (SqlType::String, SqlType::Array(SqlType::UInt8)) => {
@@ -262,6 +456,7 @@ impl<K: ColumnType> Column<K> {
                     name,
                     data: Arc::new(adapter),
                     _marker: marker::PhantomData,
+                    stats: Default::default(),
                 })
             }
             (SqlType::FixedString(n), SqlType::Array(SqlType::UInt8)) => {
@@ -281,6 +476,7 @@ impl<K: ColumnType> Column<K> {
                     name,
                     data: Arc::new(adapter),
                     _marker: marker::PhantomData,
+                    stats: Default::default(),
                 })
             }
             (
@@ -299,6 +495,7 @@ impl<K: ColumnType> Column<K> {
                     name,
                     data: Arc::new(adapter),
                     _marker: marker::PhantomData,
+                    stats: Default::default(),
                 })
             }
             _ => Err(Error::FromSql(FromSqlError::InvalidType {
@@ -309,17 +506,20 @@ impl<K: ColumnType> Column<K> {
     }
 
     pub(crate) fn push(&mut self, value: Value) {
-        loop {
-            match Arc::get_mut(&mut self.data) {
-                None => {
-                    self.data = Arc::from(self.data.clone_instance());
-                }
-                Some(data) => {
-                    data.push(value);
-                    break;
-                }
-            }
+        self.make_mut().push(value);
+    }
+
+    /// Returns the data for a change, copying it once first if it's shared
+    /// with clones of the column or with the blocks it was taken from.
+    ///
+    /// Values can then be pushed one by one without checking the sharing again,
+    /// the cached stats of the column are dropped.
+    pub fn make_mut(&mut self) -> &mut (dyn ColumnData + Send + Sync) {
+        if Arc::get_mut(&mut self.data).is_none() {
+            self.data = Arc::from(self.data.clone_instance());
         }
+        self.stats = StatsCache::default();
+        Arc::get_mut(&mut self.data).unwrap()
     }
 
     pub(crate) unsafe fn get_internal(&self, pointers: &[*mut *const u8], level: u8) -> Result<()> {
@@ -335,6 +535,7 @@ pub(crate) fn new_column<K: ColumnType>(
         name: name.to_string(),
         data,
         _marker: marker::PhantomData,
+        stats: Default::default(),
     }
 }
 
@@ -382,3 +583,20 @@ impl ColumnWrapper for BoxColumnWrapper {
         unimplemented!()
     }
 }
+
+fn has_timezone(sql_type: SqlType) -> bool {
+    match sql_type {
+        SqlType::Date | SqlType::DateTime => true,
+        SqlType::Nullable(inner) | SqlType::Array(inner) => has_timezone(*inner),
+        _ => false,
+    }
+}
+
+fn value_timezone(value: &ValueRef) -> Option<Tz> {
+    match value {
+        ValueRef::Date(_, tz) | ValueRef::DateTime(_, tz) => Some(*tz),
+        ValueRef::Nullable(Either::Right(inner)) => value_timezone(inner),
+        ValueRef::Array(_, values) => values.iter().find_map(value_timezone),
+        _ => None,
+    }
+}
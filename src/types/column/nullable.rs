@@ -4,7 +4,7 @@ use crate::{
     binary::{Encoder, ReadEx},
     errors::Result,
     types::{
-        column::{column_data::BoxColumnData, Either},
+        column::{column_data::BoxColumnData, Either, StringLayout},
         SqlType, Value, ValueRef,
     },
 };
@@ -23,11 +23,13 @@ impl NullableColumnData {
         type_name: &str,
         size: usize,
         tz: Tz,
+        strings: StringLayout,
     ) -> Result<Self> {
         let mut nulls = vec![0; size];
         reader.read_bytes(nulls.as_mut())?;
 
-        let inner = ColumnData::load_data::<BoxColumnWrapper, _>(reader, type_name, size, tz)?;
+        let inner =
+            ColumnData::load_data::<BoxColumnWrapper, _>(reader, type_name, size, tz, strings)?;
 
         Ok(NullableColumnData { inner, nulls })
     }
@@ -50,6 +52,10 @@ impl ColumnData for NullableColumnData {
         self.inner.len()
     }
 
+    fn byte_size(&self) -> usize {
+        self.nulls.capacity() + self.inner.byte_size()
+    }
+
     fn push(&mut self, value: Value) {
         if let Value::Nullable(e) = value {
             match e {
@@ -1,12 +1,10 @@
-use std::{convert, mem, sync::Arc};
+use std::{any::Any, convert, mem, sync::Arc};
 
 use crate::{
     binary::{Encoder, ReadEx},
     errors::Result,
     types::{
-        column::{
-            array::ArrayColumnData, nullable::NullableColumnData, BoxColumnWrapper, ColumnWrapper,
-        },
+        column::{array::ArrayColumnData, nullable::NullableColumnData, ColumnWrapper},
         Marshal, SqlType, StatBuffer, Unmarshal, Value, ValueRef,
     },
 };
@@ -46,11 +44,9 @@ where
         + 'static,
 {
     fn column_from<W: ColumnWrapper>(source: Self) -> W::Wrapper {
-        let mut data = List::with_capacity(source.len());
-        for s in source {
-            data.push(s);
-        }
-        W::wrap(VectorColumnData { data })
+        W::wrap(VectorColumnData {
+            data: List::from(source),
+        })
     }
 }
 
@@ -69,8 +65,7 @@ where
         + 'static,
 {
     fn column_from<W: ColumnWrapper>(source: Self) -> W::Wrapper {
-        let fake: Vec<T> = Vec::with_capacity(source.len());
-        let inner = Vec::column_from::<BoxColumnWrapper>(fake);
+        let inner = Box::new(VectorColumnData::<T>::with_capacity(source.len()));
 
         let mut data = NullableColumnData {
             inner,
@@ -100,8 +95,8 @@ where
         + 'static,
 {
     fn column_from<W: ColumnWrapper>(source: Self) -> W::Wrapper {
-        let fake: Vec<T> = Vec::with_capacity(source.len());
-        let inner = Vec::column_from::<BoxColumnWrapper>(fake);
+        let size = source.iter().map(Vec::len).sum();
+        let inner: BoxColumnData = Box::new(VectorColumnData::<T>::with_capacity(size));
         let sql_type = inner.sql_type();
 
         let mut data = ArrayColumnData {
@@ -117,6 +112,42 @@ where
     }
 }
 
+impl<T> ColumnFrom for Vec<Vec<Option<T>>>
+where
+    Value: convert::From<T>,
+    T: StatBuffer
+        + Unmarshal<T>
+        + Marshal
+        + Copy
+        + convert::Into<Value>
+        + convert::From<Value>
+        + Send
+        + Sync
+        + Default
+        + 'static,
+{
+    fn column_from<W: ColumnWrapper>(source: Self) -> W::Wrapper {
+        let size = source.iter().map(Vec::len).sum();
+        let inner: BoxColumnData = Box::new(NullableColumnData {
+            inner: Box::new(VectorColumnData::<T>::with_capacity(size)),
+            nulls: Vec::with_capacity(size),
+        });
+        let sql_type = inner.sql_type();
+
+        let mut data = ArrayColumnData {
+            inner,
+            offsets: List::with_capacity(source.len()),
+        };
+
+        for array in source {
+            let values: Vec<Value> = array.into_iter().map(convert::Into::into).collect();
+            data.push(Value::Array(sql_type.into(), Arc::new(values)));
+        }
+
+        W::wrap(data)
+    }
+}
+
 fn to_array<T>(sql_type: SqlType, vs: Vec<T>) -> Value
 where
     Value: convert::From<T>,
@@ -157,6 +188,17 @@ where
         }
     }
 
+    /// Takes the values out of `data` if it's a column of `T`.
+    pub(crate) fn take_from(data: &mut (dyn ColumnData + Send + Sync)) -> Option<Vec<T>> {
+        let vector = data.as_any_mut()?.downcast_mut::<Self>()?;
+        let values = mem::replace(&mut vector.data, List::with_capacity(0));
+        Some(values.into_vec())
+    }
+
+    pub(crate) fn extend_from_slice(&mut self, values: &[T]) {
+        self.data.extend_from_slice(values);
+    }
+
     pub(crate) fn load<R: ReadEx>(reader: &mut R, size: usize) -> Result<VectorColumnData<T>> {
         let mut data = List::with_capacity(size);
         unsafe {
@@ -188,10 +230,19 @@ where
         save_data::<T>(self.data.as_ref(), encoder, start, end);
     }
 
+    fn wire_bytes(&self, start: usize, end: usize) -> Option<&[u8]> {
+        let size = mem::size_of::<T>();
+        Some(&self.data.as_ref()[start * size..end * size])
+    }
+
     fn len(&self) -> usize {
         self.data.len()
     }
 
+    fn byte_size(&self) -> usize {
+        self.data.byte_size()
+    }
+
     fn push(&mut self, value: Value) {
         self.data.push(T::from(value));
     }
@@ -222,6 +273,10 @@ where
         })
     }
 
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+
     unsafe fn get_internal(&self, pointers: &[*mut *const u8], level: u8) -> Result<()> {
         assert_eq!(level, 0);
         *pointers[0] = self.data.as_ptr() as *const u8;
@@ -0,0 +1,191 @@
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, BTreeSet},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use crate::types::{
+    block::{compare, NullsOrder, SortOrder},
+    column::{ColumnData, Either},
+    Value, ValueRef,
+};
+
+/// Number of distinct values counted exactly, see `ColumnStats::distinct_count`.
+const SKETCH_SIZE: usize = 1024;
+
+/// Summary of the values of a column, see `Column::stats`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnStats {
+    /// Smallest value other than `NULL`, `None` if there are no such values.
+    pub min: Option<Value>,
+    /// Largest value other than `NULL`, `None` if there are no such values.
+    pub max: Option<Value>,
+    pub null_count: usize,
+    /// Number of distinct values other than `NULL`, exact up to 1024
+    /// and estimated beyond that.
+    pub distinct_count: usize,
+}
+
+/// Statistics computed on the first request and shared by clones of the column.
+#[derive(Clone, Default)]
+pub(crate) struct StatsCache(Arc<Mutex<Option<ColumnStats>>>);
+
+impl StatsCache {
+    pub(crate) fn get(&self, data: &dyn ColumnData) -> ColumnStats {
+        let mut guard = self.0.lock().unwrap();
+        guard.get_or_insert_with(|| ColumnStats::new(data)).clone()
+    }
+}
+
+impl ColumnStats {
+    fn new(data: &dyn ColumnData) -> Self {
+        let mut min: Option<ValueRef> = None;
+        let mut max: Option<ValueRef> = None;
+        let mut null_count = 0;
+        let mut sketch = BTreeSet::new();
+
+        for index in 0..data.len() {
+            let value = match data.at(index) {
+                ValueRef::Nullable(Either::Left(_)) => {
+                    null_count += 1;
+                    continue;
+                }
+                ValueRef::Nullable(Either::Right(inner)) => *inner,
+                value => value,
+            };
+
+            let mut hasher = DefaultHasher::new();
+            hash_value(&value, &mut hasher);
+            insert_hash(&mut sketch, hasher.finish());
+
+            if is_beyond(&value, &min, Ordering::Less) {
+                min = Some(value.clone());
+            }
+            if is_beyond(&value, &max, Ordering::Greater) {
+                max = Some(value);
+            }
+        }
+
+        Self {
+            min: min.map(Value::from),
+            max: max.map(Value::from),
+            null_count,
+            distinct_count: estimate(&sketch),
+        }
+    }
+}
+
+/// Returns `true` if `value` is to the `side` of the current bound.
+fn is_beyond(value: &ValueRef, bound: &Option<ValueRef>, side: Ordering) -> bool {
+    match bound {
+        None => true,
+        Some(bound) => compare(value, bound, SortOrder::Asc, NullsOrder::Last) == side,
+    }
+}
+
+/// Keeps the `SKETCH_SIZE` smallest hashes (the k minimum values sketch).
+fn insert_hash(sketch: &mut BTreeSet<u64>, hash: u64) {
+    if sketch.len() < SKETCH_SIZE {
+        sketch.insert(hash);
+    } else if hash < *sketch.iter().next_back().unwrap() && sketch.insert(hash) {
+        let largest = *sketch.iter().next_back().unwrap();
+        sketch.remove(&largest);
+    }
+}
+
+fn estimate(sketch: &BTreeSet<u64>) -> usize {
+    if sketch.len() < SKETCH_SIZE {
+        return sketch.len();
+    }
+
+    let largest = *sketch.iter().next_back().unwrap() as f64 + 1.0;
+    ((SKETCH_SIZE - 1) as f64 * (u64::MAX as f64 / largest)) as usize
+}
+
+fn hash_value<H: Hasher>(value: &ValueRef, state: &mut H) {
+    match value {
+        ValueRef::UInt8(v) => v.hash(state),
+        ValueRef::UInt16(v) => v.hash(state),
+        ValueRef::UInt32(v) => v.hash(state),
+        ValueRef::UInt64(v) => v.hash(state),
+        ValueRef::Int8(v) => v.hash(state),
+        ValueRef::Int16(v) => v.hash(state),
+        ValueRef::Int32(v) => v.hash(state),
+        ValueRef::Int64(v) => v.hash(state),
+        ValueRef::String(v) => v.hash(state),
+        ValueRef::Float32(v) => v.to_bits().hash(state),
+        ValueRef::Float64(v) => v.to_bits().hash(state),
+        ValueRef::Date(v, _) => v.hash(state),
+        ValueRef::DateTime(v, _) => v.hash(state),
+        ValueRef::Decimal(v) => v.underlying.hash(state),
+        ValueRef::Nullable(Either::Left(_)) => 0_u8.hash(state),
+        ValueRef::Nullable(Either::Right(inner)) => {
+            1_u8.hash(state);
+            hash_value(inner, state);
+        }
+        ValueRef::Array(_, vs) => {
+            vs.len().hash(state);
+            for v in vs.iter() {
+                hash_value(v, state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{Block, Simple};
+
+    #[test]
+    fn test_stats() {
+        let block = Block::<Simple>::new()
+            .column("n", vec![Some(3_u32), None, Some(1), Some(3), None])
+            .column("s", vec!["b", "a", "c", "a", "b"]);
+
+        let stats = block.get_column("n").unwrap().stats();
+        assert_eq!(
+            stats,
+            ColumnStats {
+                min: Some(Value::UInt32(1)),
+                max: Some(Value::UInt32(3)),
+                null_count: 2,
+                distinct_count: 2,
+            }
+        );
+
+        let stats = block.get_column("s").unwrap().stats();
+        assert_eq!(stats.min, Some(Value::from("a")));
+        assert_eq!(stats.max, Some(Value::from("c")));
+        assert_eq!(stats.distinct_count, 3);
+    }
+
+    #[test]
+    fn test_stats_of_empty_column() {
+        let block = Block::<Simple>::new().column("n", Vec::<Option<u8>>::new());
+        let stats = block.get_column("n").unwrap().stats();
+        assert_eq!(stats.min, None);
+        assert_eq!(stats.null_count, 0);
+        assert_eq!(stats.distinct_count, 0);
+    }
+
+    #[test]
+    fn test_distinct_estimate() {
+        let values: Vec<u64> = (0..100_000).map(|i| i % 20_000).collect();
+        let block = Block::<Simple>::new().column("n", values);
+
+        let estimate = block.get_column("n").unwrap().stats().distinct_count;
+        assert!(estimate > 18_000 && estimate < 22_000, "{}", estimate);
+    }
+
+    #[test]
+    fn test_stats_after_push() {
+        let mut block = Block::<Simple>::new();
+        block.push(vec![("n".to_string(), Value::UInt8(1))]).unwrap();
+        assert_eq!(block.get_column("n").unwrap().stats().max, Some(Value::UInt8(1)));
+
+        block.push(vec![("n".to_string(), Value::UInt8(5))]).unwrap();
+        assert_eq!(block.get_column("n").unwrap().stats().max, Some(Value::UInt8(5)));
+    }
+}
@@ -1,12 +1,12 @@
-use std::{io::Write, string::ToString, sync::Arc};
+use std::{any::Any, io::Write, sync::Arc};
 
 use crate::{
     binary::{Encoder, ReadEx},
     errors::Result,
     types::{
         column::{
-            array::ArrayColumnData, list::List, nullable::NullableColumnData, BoxColumnWrapper,
-            ColumnWrapper, Either, StringPool,
+            array::ArrayColumnData, list::List, nullable::NullableColumnData, ColumnWrapper,
+            Either, StringLayout, StringPool,
         },
         Column, FromSql, SqlType, Value, ValueRef, ColumnType,
     },
@@ -32,8 +32,18 @@ impl StringColumnData {
         }
     }
 
-    pub(crate) fn load<T: ReadEx>(reader: &mut T, size: usize) -> Result<Self> {
-        let mut data = Self::with_capacity(size);
+    pub(crate) fn push_bytes(&mut self, value: &[u8]) {
+        self.pool.allocate(value.len()).copy_from_slice(value);
+    }
+
+    pub(crate) fn load<T: ReadEx>(
+        reader: &mut T,
+        size: usize,
+        strings: StringLayout,
+    ) -> Result<Self> {
+        let mut data = Self {
+            pool: StringPool::with_layout(size, strings),
+        };
 
         for _ in 0..size {
             reader.read_str_into_buffer(&mut data.pool)?;
@@ -45,14 +55,17 @@ impl StringColumnData {
 
 impl ColumnFrom for Vec<String> {
     fn column_from<W: ColumnWrapper>(data: Self) -> W::Wrapper {
-        W::wrap(StringColumnData { pool: data.into() })
+        W::wrap(StringColumnData {
+            pool: StringPool::adopt(data),
+        })
     }
 }
 
 impl<'a> ColumnFrom for Vec<&'a str> {
     fn column_from<W: ColumnWrapper>(source: Self) -> W::Wrapper {
-        let data: Vec<_> = source.iter().map(ToString::to_string).collect();
-        W::wrap(StringColumnData { pool: data.into() })
+        W::wrap(StringColumnData {
+            pool: source.into(),
+        })
     }
 }
 
@@ -84,6 +97,12 @@ impl StringSource for Vec<u8> {
     }
 }
 
+impl StringSource for &[u8] {
+    fn to_value(self) -> Value {
+        Value::String(Arc::new(self.to_vec()))
+    }
+}
+
 impl ColumnFrom for Vec<Vec<String>> {
     fn column_from<W: ColumnWrapper>(source: Self) -> <W as ColumnWrapper>::Wrapper {
         make_array_of_array::<W, String>(source)
@@ -99,8 +118,8 @@ impl ColumnFrom for Vec<Vec<&str>> {
 fn make_array_of_array<W: ColumnWrapper, S: StringSource>(
     source: Vec<Vec<S>>,
 ) -> <W as ColumnWrapper>::Wrapper {
-    let fake: Vec<String> = Vec::with_capacity(source.len());
-    let inner = Vec::column_from::<BoxColumnWrapper>(fake);
+    let size = source.iter().map(Vec::len).sum();
+    let inner: BoxColumnData = Box::new(StringColumnData::with_capacity(size));
     let sql_type = inner.sql_type();
 
     let mut data = ArrayColumnData {
@@ -120,6 +139,46 @@ fn make_array_of_array<W: ColumnWrapper, S: StringSource>(
     W::wrap(data)
 }
 
+impl ColumnFrom for Vec<Vec<Option<String>>> {
+    fn column_from<W: ColumnWrapper>(source: Self) -> W::Wrapper {
+        make_array_of_opt::<W, String>(source)
+    }
+}
+
+impl ColumnFrom for Vec<Vec<Option<&str>>> {
+    fn column_from<W: ColumnWrapper>(source: Self) -> W::Wrapper {
+        make_array_of_opt::<W, &str>(source)
+    }
+}
+
+fn make_array_of_opt<W: ColumnWrapper, S: StringSource>(source: Vec<Vec<Option<S>>>) -> W::Wrapper {
+    let size = source.iter().map(Vec::len).sum();
+    let inner: BoxColumnData = Box::new(NullableColumnData {
+        inner: Box::new(StringColumnData::with_capacity(size)),
+        nulls: Vec::with_capacity(size),
+    });
+    let sql_type = inner.sql_type();
+
+    let mut data = ArrayColumnData {
+        inner,
+        offsets: List::with_capacity(source.len()),
+    };
+
+    for vs in source {
+        let inner: Vec<_> = vs.into_iter().map(to_nullable_string).collect();
+        data.push(Value::Array(sql_type.into(), Arc::new(inner)));
+    }
+
+    W::wrap(data)
+}
+
+fn to_nullable_string<S: StringSource>(value: Option<S>) -> Value {
+    match value {
+        Some(v) => Value::Nullable(Either::Right(Box::new(v.to_value()))),
+        None => Value::Nullable(Either::Left(SqlType::String.into())),
+    }
+}
+
 impl ColumnFrom for Vec<Option<Vec<u8>>> {
     fn column_from<W: ColumnWrapper>(source: Self) -> W::Wrapper {
         make_opt_column::<W, Vec<u8>>(source)
@@ -138,6 +197,12 @@ impl ColumnFrom for Vec<Option<String>> {
     }
 }
 
+impl<'a> ColumnFrom for Vec<Option<&'a [u8]>> {
+    fn column_from<W: ColumnWrapper>(source: Self) -> W::Wrapper {
+        make_opt_column::<W, &[u8]>(source)
+    }
+}
+
 fn make_opt_column<W: ColumnWrapper, S: StringSource>(source: Vec<Option<S>>) -> W::Wrapper {
     let inner = Box::new(StringColumnData::with_capacity(source.len()));
 
@@ -147,13 +212,7 @@ fn make_opt_column<W: ColumnWrapper, S: StringSource>(source: Vec<Option<S>>) ->
     };
 
     for value in source {
-        let item = if let Some(v) = value {
-            let inner = v.to_value();
-            Value::Nullable(Either::Right(Box::new(inner)))
-        } else {
-            Value::Nullable(Either::Left(SqlType::String.into()))
-        };
-        data.push(item);
+        data.push(to_nullable_string(value));
     }
 
     W::wrap(data)
@@ -175,6 +234,10 @@ impl ColumnData for StringColumnData {
         self.pool.len()
     }
 
+    fn byte_size(&self) -> usize {
+        self.pool.byte_size()
+    }
+
     fn push(&mut self, value: Value) {
         let s: Vec<u8> = value.into();
         let mut b = self.pool.allocate(s.len());
@@ -192,6 +255,10 @@ impl ColumnData for StringColumnData {
         })
     }
 
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+
     unsafe fn get_internal(&self, pointers: &[*mut *const u8], level: u8) -> Result<()> {
         assert_eq!(level, 0);
         *pointers[0] = &self.pool as *const StringPool as *const u8;
@@ -216,6 +283,10 @@ impl<K: ColumnType> ColumnData for StringAdapter<K> {
         self.column.len()
     }
 
+    fn byte_size(&self) -> usize {
+        self.column.byte_size()
+    }
+
     fn push(&mut self, _value: Value) {
         unimplemented!()
     }
@@ -1,20 +1,56 @@
-use std::{io::Write, slice};
+use std::{convert::TryFrom, io::Write, mem, slice};
 
 const AVG_STR_SIZE: usize = 80;
 
+/// Longest string stored in its pointer instead of a chunk.
+const INLINE_CAPACITY: usize = 22;
+
+/// Longest string stored in a shared chunk by default.
+pub(crate) const DEFAULT_MAX_POOLED: usize = 1024;
+
+/// How the strings of a pool are laid out in memory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct StringLayout {
+    /// Size of the chunks strings share, `None` to size them by the number of strings.
+    pub(crate) chunk_size: Option<usize>,
+    /// Longest string stored in a shared chunk, longer ones get an allocation of their own.
+    pub(crate) max_pooled: usize,
+}
+
+impl Default for StringLayout {
+    fn default() -> Self {
+        Self {
+            chunk_size: None,
+            max_pooled: DEFAULT_MAX_POOLED,
+        }
+    }
+}
+
+/// Location of a string, short strings are kept in it inline.
 #[derive(Copy, Clone)]
-struct StringPtr {
-    chunk: usize,
-    shift: usize,
-    len: usize,
+enum StringPtr {
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Chunk {
+        chunk: u32,
+        shift: usize,
+        len: usize,
+    },
 }
 
 #[derive(Clone)]
 pub(crate) struct StringPool {
+    // Strings taken over from the caller, they come before the allocated ones
+    owned: Vec<String>,
     chunks: Vec<Vec<u8>>,
     pointers: Vec<StringPtr>,
     position: usize,
     capacity: usize,
+    // Chunk shared by the strings that are allocated next
+    current: Option<usize>,
+    layout: StringLayout,
 }
 
 pub(crate) struct StringIter<'a> {
@@ -41,7 +77,19 @@ where
     T: AsRef<[u8]>,
 {
     fn from(source: Vec<T>) -> Self {
-        let mut pool = StringPool::with_capacity(source.len());
+        let size = source
+            .iter()
+            .map(|s| s.as_ref().len())
+            .filter(|&len| len > INLINE_CAPACITY)
+            .sum();
+        let layout = StringLayout {
+            chunk_size: Some(size),
+            max_pooled: usize::MAX,
+        };
+        let mut pool = StringPool::with_layout(source.len(), layout);
+        if size > 0 {
+            pool.reserve(size);
+        }
         for s in source.iter() {
             let mut b = pool.allocate(s.as_ref().len());
             b.write_all(s.as_ref()).unwrap();
@@ -51,81 +99,128 @@ where
 }
 
 impl StringPool {
+    /// Makes a pool that keeps `strings` as they are instead of copying them.
+    pub(crate) fn adopt(strings: Vec<String>) -> StringPool {
+        StringPool {
+            owned: strings,
+            ..StringPool::with_capacity(0)
+        }
+    }
+
     pub(crate) fn with_capacity(capacity: usize) -> StringPool {
+        Self::with_layout(capacity, StringLayout::default())
+    }
+
+    pub(crate) fn with_layout(capacity: usize, layout: StringLayout) -> StringPool {
         StringPool {
+            owned: Vec::new(),
             pointers: Vec::with_capacity(capacity),
             chunks: Vec::new(),
             position: 0,
             capacity,
+            current: None,
+            layout,
         }
     }
 
     pub(crate) fn allocate(&mut self, size: usize) -> &mut [u8] {
-        if self.free_space() < size || self.chunks.is_empty() {
-            self.reserve(size);
-            return self.allocate(size);
+        if size <= INLINE_CAPACITY {
+            self.pointers.push(StringPtr::Inline {
+                len: size as u8,
+                bytes: [0; INLINE_CAPACITY],
+            });
+            return match self.pointers.last_mut() {
+                Some(StringPtr::Inline { bytes, .. }) => &mut bytes[..size],
+                _ => unreachable!(),
+            };
         }
 
-        self.try_allocate(size).unwrap()
-    }
+        if size > self.layout.max_pooled || self.layout.max_pooled == 0 {
+            // A separate allocation doesn't leave the rest of a chunk unused.
+            self.chunks.push(vec![0_u8; size]);
+            return self.push_pointer(self.chunks.len() - 1, 0, size);
+        }
 
-    fn free_space(&self) -> usize {
-        if let Some(buffer) = self.chunks.last() {
-            return buffer.len() - self.position;
+        if self.current.is_none() || self.free_space() < size {
+            self.reserve(size);
         }
 
-        0
+        let chunk = self.current.unwrap();
+        let position = self.position;
+        self.position += size;
+        self.push_pointer(chunk, position, size)
     }
 
-    fn try_allocate(&mut self, size: usize) -> Option<&mut [u8]> {
-        if !self.chunks.is_empty() {
-            let chunk = self.chunks.len() - 1;
-
-            let position = self.position;
-            self.position += size;
-            self.pointers.push(StringPtr {
-                len: size,
-                shift: position,
-                chunk,
-            });
+    fn push_pointer(&mut self, chunk: usize, shift: usize, len: usize) -> &mut [u8] {
+        let index = u32::try_from(chunk).expect("too many string chunks");
+        self.pointers.push(StringPtr::Chunk {
+            chunk: index,
+            shift,
+            len,
+        });
+        &mut self.chunks[chunk][shift..shift + len]
+    }
 
-            let buffer = &mut self.chunks[chunk];
-            return Some(&mut buffer[position..position + size]);
+    fn free_space(&self) -> usize {
+        match self.current {
+            Some(chunk) => self.chunks[chunk].len() - self.position,
+            None => 0,
         }
-
-        None
     }
 
     fn reserve(&mut self, size: usize) {
         use std::cmp::max;
+        let chunk_size = match self.layout.chunk_size {
+            Some(chunk_size) => chunk_size,
+            None => self.capacity * AVG_STR_SIZE,
+        };
+
         self.position = 0;
-        self.chunks
-            .push(vec![0_u8; max(self.capacity * AVG_STR_SIZE, size)]);
+        self.current = Some(self.chunks.len());
+        self.chunks.push(vec![0_u8; max(chunk_size, size)]);
     }
 
     #[inline(always)]
     pub(crate) fn get(&self, index: usize) -> &[u8] {
-        let pointer = &self.pointers[index];
-        unsafe { self.get_by_pointer(pointer) }
+        match index.checked_sub(self.owned.len()) {
+            None => self.owned[index].as_bytes(),
+            Some(index) => unsafe { self.get_by_pointer(&self.pointers[index]) },
+        }
     }
 
     #[inline(always)]
     pub(crate) unsafe fn get_unchecked(&self, index: usize) -> &[u8] {
-        let pointer = self.pointers.get_unchecked(index);
-        self.get_by_pointer(pointer)
+        match index.checked_sub(self.owned.len()) {
+            None => self.owned.get_unchecked(index).as_bytes(),
+            Some(index) => self.get_by_pointer(self.pointers.get_unchecked(index)),
+        }
     }
 
     #[inline(always)]
-    unsafe fn get_by_pointer(&self, pointer: &StringPtr) -> &[u8] {
-        let chunk = &self.chunks.get_unchecked(pointer.chunk);
+    unsafe fn get_by_pointer<'a>(&'a self, pointer: &'a StringPtr) -> &'a [u8] {
+        match pointer {
+            StringPtr::Inline { len, bytes } => bytes.get_unchecked(..*len as usize),
+            StringPtr::Chunk { chunk, shift, len } => {
+                let chunk = &self.chunks.get_unchecked(*chunk as usize);
 
-        let ptr = chunk.as_ptr().add(pointer.shift);
-        slice::from_raw_parts(ptr, pointer.len)
+                let ptr = chunk.as_ptr().add(*shift);
+                slice::from_raw_parts(ptr, *len)
+            }
+        }
     }
 
     #[inline(always)]
     pub(crate) fn len(&self) -> usize {
-        self.pointers.len()
+        self.owned.len() + self.pointers.len()
+    }
+
+    pub(crate) fn byte_size(&self) -> usize {
+        let chunks: usize = self.chunks.iter().map(Vec::capacity).sum();
+        let owned: usize = self.owned.iter().map(String::capacity).sum();
+        chunks
+            + owned
+            + self.owned.capacity() * mem::size_of::<String>()
+            + self.pointers.capacity() * mem::size_of::<StringPtr>()
     }
 
     pub(crate) fn strings(&self) -> StringIter {
@@ -169,4 +264,80 @@ mod test {
             assert_eq!(s, format!("text-{}", i));
         }
     }
+
+    #[test]
+    fn test_from_allocates_once() {
+        let long = "a".repeat(30);
+        let pool = StringPool::from(vec![long.as_str(), "", "barbaz", long.as_str()]);
+        assert_eq!(pool.chunks.len(), 1);
+        assert_eq!(pool.chunks[0].len(), 60);
+        assert_eq!(pool.get(2), b"barbaz");
+        assert_eq!(pool.get(1), b"");
+        assert_eq!(pool.get(3), long.as_bytes());
+    }
+
+    #[test]
+    fn test_large_strings() {
+        let layout = StringLayout {
+            chunk_size: Some(64),
+            max_pooled: 40,
+        };
+        let mut pool = StringPool::with_layout(10, layout);
+        pool.allocate(30).copy_from_slice(&[1; 30]);
+        pool.allocate(1000).copy_from_slice(&[2; 1000]);
+        pool.allocate(30).copy_from_slice(&[3; 30]);
+
+        assert_eq!(pool.chunks.len(), 2);
+        assert_eq!(pool.chunks[0].len(), 64);
+        assert_eq!(pool.chunks[1].len(), 1000);
+        assert_eq!(pool.get(1), &[2; 1000][..]);
+        assert_eq!(pool.get(2), &[3; 30]);
+    }
+
+    #[test]
+    fn test_bypass_pool() {
+        let layout = StringLayout {
+            chunk_size: None,
+            max_pooled: 0,
+        };
+        let mut pool = StringPool::with_layout(10, layout);
+        for _ in 0..3 {
+            pool.allocate(30).copy_from_slice(&[1; 30]);
+        }
+        pool.allocate(0);
+
+        assert_eq!(pool.chunks.len(), 3);
+        assert_eq!(pool.chunks[0].capacity(), 30);
+        assert_eq!(pool.get(2), &[1; 30]);
+        assert_eq!(pool.get(3), b"");
+    }
+
+    #[test]
+    fn test_inline_strings() {
+        assert!(mem::size_of::<StringPtr>() <= 24);
+
+        let mut pool = StringPool::with_capacity(10);
+        pool.allocate(22).copy_from_slice(&[1; 22]);
+        pool.allocate(2).copy_from_slice(b"de");
+        assert!(pool.chunks.is_empty());
+        assert_eq!(pool.get(0), &[1; 22]);
+        assert_eq!(pool.get(1), b"de");
+
+        pool.allocate(23).copy_from_slice(&[2; 23]);
+        assert_eq!(pool.chunks.len(), 1);
+        assert_eq!(pool.get(2), &[2; 23]);
+    }
+
+    #[test]
+    fn test_adopt() {
+        let strings = vec!["foo".to_string(), "a".repeat(100)];
+        let ptr = strings[1].as_ptr();
+
+        let mut pool = StringPool::adopt(strings);
+        pool.allocate(3).copy_from_slice(b"bar");
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.get(1).as_ptr(), ptr);
+        assert_eq!(pool.get(0), b"foo");
+        assert_eq!(unsafe { pool.get_unchecked(2) }, b"bar");
+    }
 }
@@ -0,0 +1,115 @@
+use std::{any::Any, convert};
+
+use crate::types::{
+    column::{numeric::VectorColumnData, string::StringColumnData},
+    Marshal, SqlType, StatBuffer, Unmarshal, Value,
+};
+
+/// Values appended to a column of the matching type without going
+/// through `Value`, see `Column::push_typed`.
+pub trait PushTyped: Clone + convert::Into<Value> {
+    /// Type of the columns the values are appended to.
+    fn column_type() -> SqlType;
+
+    /// Appends `values` to `data` if it stores them as they are,
+    /// returns `false` otherwise.
+    #[doc(hidden)]
+    fn extend_data(data: &mut dyn Any, values: &[Self]) -> bool;
+}
+
+impl<T> PushTyped for T
+where
+    T: StatBuffer
+        + Unmarshal<T>
+        + Marshal
+        + Copy
+        + convert::Into<Value>
+        + convert::From<Value>
+        + Send
+        + Sync
+        + Default
+        + 'static,
+{
+    fn column_type() -> SqlType {
+        T::sql_type()
+    }
+
+    fn extend_data(data: &mut dyn Any, values: &[Self]) -> bool {
+        match data.downcast_mut::<VectorColumnData<T>>() {
+            Some(vector) => {
+                vector.extend_from_slice(values);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl PushTyped for &str {
+    fn column_type() -> SqlType {
+        SqlType::String
+    }
+
+    fn extend_data(data: &mut dyn Any, values: &[Self]) -> bool {
+        match data.downcast_mut::<StringColumnData>() {
+            Some(strings) => {
+                for value in values {
+                    strings.push_bytes(value.as_bytes());
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl PushTyped for String {
+    fn column_type() -> SqlType {
+        SqlType::String
+    }
+
+    fn extend_data(data: &mut dyn Any, values: &[Self]) -> bool {
+        match data.downcast_mut::<StringColumnData>() {
+            Some(strings) => {
+                for value in values {
+                    strings.push_bytes(value.as_bytes());
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::types::{Block, Simple};
+
+    #[test]
+    fn test_push_typed() {
+        let mut block = Block::<Simple>::new()
+            .column("id", vec![1_u32])
+            .column("name", vec!["a"]);
+
+        block.extend_column("id", &[2_u32, 3]).unwrap();
+        block.extend_column("name", &["b", "c"]).unwrap();
+        assert_eq!(block.row_count(), 3);
+
+        let shared = block.clone();
+        block.extend_column("id", &[4_u32]).unwrap();
+        block.extend_column("name", &["d".to_string()]).unwrap();
+        assert_eq!(shared.row_count(), 3);
+
+        let ids: &[u32] = block.get_column("id").unwrap().as_slice().unwrap();
+        assert_eq!(ids, &[1, 2, 3, 4]);
+        let name: &str = block.get(3, "name").unwrap();
+        assert_eq!(name, "d");
+
+        let mut column = block.get_column("id").unwrap().clone();
+        column.push_typed(5_u32).unwrap();
+        assert_eq!(column.len(), 5);
+
+        let err = block.extend_column("id", &[5_u64]).unwrap_err();
+        assert_eq!(err.to_string(), "From SQL error: `SqlType::UInt64 cannot be cast to UInt32.`");
+    }
+}
@@ -0,0 +1,463 @@
+use std::{error, fmt, str, sync::Arc};
+
+use chrono::prelude::*;
+use chrono_tz::Tz;
+use serde::de::{
+    self, value::BorrowedStrDeserializer, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor,
+};
+
+use crate::{
+    errors::{Error, FromSqlError},
+    types::{column::Either, ColumnType, FromSql, Row, ValueRef},
+};
+
+#[derive(Debug)]
+pub(crate) struct DeError(String);
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DeError(msg.to_string())
+    }
+}
+
+impl From<DeError> for Error {
+    fn from(err: DeError) -> Self {
+        Error::FromSql(FromSqlError::Deserialize { message: err.0 })
+    }
+}
+
+impl From<Error> for DeError {
+    fn from(err: Error) -> Self {
+        DeError(err.to_string())
+    }
+}
+
+type DeResult<T> = Result<T, DeError>;
+
+/// Deserializes a row: structs and maps are filled by column name,
+/// tuples and sequences by column position.
+pub(crate) struct RowDeserializer<'a, 'r, K: ColumnType> {
+    row: &'a Row<'r, K>,
+}
+
+impl<'a, 'r, K: ColumnType> RowDeserializer<'a, 'r, K> {
+    pub(crate) fn new(row: &'a Row<'r, K>) -> Self {
+        Self { row }
+    }
+
+    fn value(&self, index: usize) -> DeResult<ValueRef<'a>> {
+        Ok(self.row.value(index)?)
+    }
+
+    fn name(&self, index: usize) -> DeResult<&'a str> {
+        Ok(self.row.name(index)?)
+    }
+}
+
+impl<'de, 'r, K: ColumnType> Deserializer<'de> for RowDeserializer<'de, 'r, K> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        visitor.visit_map(Columns {
+            row: self,
+            index: 0,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        visitor.visit_seq(Columns {
+            row: self,
+            index: 0,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> DeResult<V::Value> {
+        if len != self.row.len() {
+            return Err(DeError(format!(
+                "expected a tuple of {} elements, the row has {} columns",
+                len,
+                self.row.len()
+            )));
+        }
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct enum identifier ignored_any
+    }
+}
+
+struct Columns<'a, 'r, K: ColumnType> {
+    row: RowDeserializer<'a, 'r, K>,
+    index: usize,
+}
+
+impl<'a, 'r, K: ColumnType> Columns<'a, 'r, K> {
+    fn next_value<'de, T>(&mut self, seed: T) -> DeResult<T::Value>
+    where
+        'a: 'de,
+        T: DeserializeSeed<'de>,
+    {
+        let index = self.index;
+        self.index += 1;
+
+        let value = self.row.value(index)?;
+        seed.deserialize(ValueDeserializer::new(value))
+            .map_err(|err| match self.row.name(index) {
+                Ok(name) => DeError(format!("column `{}`: {}", name, err)),
+                Err(_) => err,
+            })
+    }
+}
+
+impl<'de, 'r, K: ColumnType> MapAccess<'de> for Columns<'de, 'r, K> {
+    type Error = DeError;
+
+    fn next_key_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> DeResult<Option<T::Value>> {
+        if self.index >= self.row.row.len() {
+            return Ok(None);
+        }
+        let name = self.row.name(self.index)?;
+        seed.deserialize(BorrowedStrDeserializer::new(name))
+            .map(Some)
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> DeResult<T::Value> {
+        self.next_value(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.row.row.len() - self.index)
+    }
+}
+
+impl<'de, 'r, K: ColumnType> SeqAccess<'de> for Columns<'de, 'r, K> {
+    type Error = DeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> DeResult<Option<T::Value>> {
+        if self.index >= self.row.row.len() {
+            return Ok(None);
+        }
+        self.next_value(seed).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.row.row.len() - self.index)
+    }
+}
+
+/// Deserializes a single cell. The Rust type is checked against the
+/// column type by the visitor, so e.g. a `String` column cannot be read
+/// into an `u32` field.
+struct ValueDeserializer<'a> {
+    value: ValueRef<'a>,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    fn new(value: ValueRef<'a>) -> Self {
+        Self { value }
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        match self.value {
+            ValueRef::UInt8(v) => visitor.visit_u8(v),
+            ValueRef::UInt16(v) => visitor.visit_u16(v),
+            ValueRef::UInt32(v) => visitor.visit_u32(v),
+            ValueRef::UInt64(v) => visitor.visit_u64(v),
+            ValueRef::Int8(v) => visitor.visit_i8(v),
+            ValueRef::Int16(v) => visitor.visit_i16(v),
+            ValueRef::Int32(v) => visitor.visit_i32(v),
+            ValueRef::Int64(v) => visitor.visit_i64(v),
+            ValueRef::Float32(v) => visitor.visit_f32(v),
+            ValueRef::Float64(v) => visitor.visit_f64(v),
+            ValueRef::String(v) => match str::from_utf8(v) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => visitor.visit_borrowed_bytes(v),
+            },
+            ValueRef::Date(..) => {
+                let date: Date<Tz> = FromSql::from_sql(self.value).map_err(DeError::from)?;
+                visitor.visit_string(date.format("%Y-%m-%d").to_string())
+            }
+            ValueRef::DateTime(..) => {
+                let time: DateTime<Tz> = FromSql::from_sql(self.value).map_err(DeError::from)?;
+                visitor.visit_string(time.to_rfc3339())
+            }
+            ValueRef::Nullable(Either::Left(_)) => visitor.visit_none(),
+            ValueRef::Nullable(Either::Right(v)) => visitor.visit_some(ValueDeserializer::new(*v)),
+            ValueRef::Array(_, vs) => visitor.visit_seq(Values { values: vs, index: 0 }),
+            // The exact value, floats are asked for with `deserialize_f64`.
+            ValueRef::Decimal(v) => visitor.visit_string(v.to_string()),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        match self.value {
+            ValueRef::Decimal(v) => visitor.visit_f32(v.into()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        match self.value {
+            ValueRef::Decimal(v) => visitor.visit_f64(v.into()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        // ClickHouse has no boolean type, flags are stored as UInt8.
+        match self.value {
+            ValueRef::UInt8(v) => visitor.visit_bool(v != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        match self.value {
+            ValueRef::Nullable(Either::Left(_)) => visitor.visit_none(),
+            ValueRef::Nullable(Either::Right(v)) => visitor.visit_some(ValueDeserializer::new(*v)),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        match self.value {
+            ValueRef::String(v) => match str::from_utf8(v) {
+                Ok(s) => visitor.visit_enum(BorrowedStrDeserializer::new(s)),
+                Err(_) => self.deserialize_any(visitor),
+            },
+            ValueRef::Nullable(Either::Right(v)) => ValueDeserializer::new(*v)
+                .deserialize_enum(name, variants, visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct Values<'a> {
+    values: Arc<Vec<ValueRef<'a>>>,
+    index: usize,
+}
+
+impl<'de> SeqAccess<'de> for Values<'de> {
+    type Error = DeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> DeResult<Option<T::Value>> {
+        match self.values.get(self.index) {
+            None => Ok(None),
+            Some(value) => {
+                self.index += 1;
+                seed.deserialize(ValueDeserializer::new(value.clone()))
+                    .map(Some)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len() - self.index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+
+    use crate::{
+        errors::{Error, FromSqlError},
+        row,
+        types::{Block, Decimal},
+    };
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        id: u32,
+        name: String,
+        email: Option<String>,
+    }
+
+    // Written by hand, the way `#[derive(Deserialize)]` would do it.
+    impl<'de> Deserialize<'de> for User {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct UserVisitor;
+
+            impl<'de> Visitor<'de> for UserVisitor {
+                type Value = User;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("struct User")
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<User, A::Error> {
+                    let (mut id, mut name, mut email) = (None, None, None);
+                    while let Some(key) = map.next_key::<&str>()? {
+                        match key {
+                            "id" => id = Some(map.next_value()?),
+                            "name" => name = Some(map.next_value()?),
+                            "email" => email = Some(map.next_value()?),
+                            _ => {
+                                map.next_value::<de::IgnoredAny>()?;
+                            }
+                        }
+                    }
+                    Ok(User {
+                        id: id.ok_or_else(|| de::Error::missing_field("id"))?,
+                        name: name.ok_or_else(|| de::Error::missing_field("name"))?,
+                        email: email.unwrap_or(None),
+                    })
+                }
+            }
+
+            deserializer.deserialize_struct("User", &["id", "name", "email"], UserVisitor)
+        }
+    }
+
+    #[test]
+    fn test_deserialize_struct() {
+        let mut block = Block::new();
+        block
+            .push(row! { name: "Alice", extra: 1_u8, id: 1_u32, email: Some("a@b.c") })
+            .unwrap();
+        block
+            .push(row! { name: "Bob", extra: 2_u8, id: 2_u32, email: None::<&str> })
+            .unwrap();
+
+        let users: Vec<User> = block.rows().map(|row| row.deserialize().unwrap()).collect();
+        assert_eq!(
+            users,
+            vec![
+                User {
+                    id: 1,
+                    name: "Alice".into(),
+                    email: Some("a@b.c".into())
+                },
+                User {
+                    id: 2,
+                    name: "Bob".into(),
+                    email: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_tuple() {
+        let block = Block::new()
+            .add_column("id", vec![7_u64])
+            .add_column("tags", vec![vec!["a", "b"]])
+            .add_column("flag", vec![1_u8]);
+
+        let row = block.rows().next().unwrap();
+        let value: (u64, Vec<String>, bool) = row.deserialize().unwrap();
+        assert_eq!(value, (7, vec!["a".to_string(), "b".to_string()], true));
+
+        let mut block = Block::new();
+        block.push(row! { a: 1_u64, b: 2_u64 }).unwrap();
+        let columns: HashMap<String, u64> = block.rows().next().unwrap().deserialize().unwrap();
+        assert_eq!(columns.get("a"), Some(&1));
+        assert_eq!(columns.get("b"), Some(&2));
+
+        row.deserialize::<(u64, Vec<String>)>().unwrap_err();
+    }
+
+    #[test]
+    fn test_deserialize_decimal() {
+        // More digits than a float holds.
+        let price = Decimal {
+            underlying: 1_234_567_890_123_456_789,
+            ..Decimal::of(0_i64, 9)
+        };
+        let block = Block::new().add_column("price", vec![price]);
+
+        let row = block.rows().next().unwrap();
+        let (price,): (String,) = row.deserialize().unwrap();
+        assert_eq!(price, "1234567890.123456789");
+
+        let (price,): (f64,) = row.deserialize().unwrap();
+        assert!((price - 1_234_567_890.123_456_8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        let mut block = Block::new();
+        block.push(row! { id: "one", name: "Alice" }).unwrap();
+
+        let row = block.rows().next().unwrap();
+        match row.deserialize::<User>() {
+            Err(Error::FromSql(FromSqlError::Deserialize { message })) => {
+                assert!(message.starts_with("column `id`"), "{}", message)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
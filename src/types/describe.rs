@@ -0,0 +1,133 @@
+use crate::{
+    errors::Result,
+    types::{column::parse_sql_type, Block, ColumnType, SqlType},
+};
+
+/// Kind of the default value of a column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DefaultKind {
+    /// `DEFAULT expr`, used when the column is omitted on insert.
+    Default,
+    /// `MATERIALIZED expr`, always computed, can't be inserted.
+    Materialized,
+    /// `ALIAS expr`, computed on read and not stored.
+    Alias,
+}
+
+/// Description of a table column, as reported by `DESCRIBE TABLE`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnDescriptor {
+    pub name: String,
+    pub sql_type: SqlType,
+    pub default_kind: Option<DefaultKind>,
+    pub default_expression: String,
+    pub comment: String,
+}
+
+impl ColumnDescriptor {
+    /// Returns `true` if the column can be given in an `INSERT`.
+    pub fn is_insertable(&self) -> bool {
+        match self.default_kind {
+            None | Some(DefaultKind::Default) => true,
+            Some(DefaultKind::Materialized) | Some(DefaultKind::Alias) => false,
+        }
+    }
+}
+
+/// Parses the result of `DESCRIBE TABLE`.
+pub(crate) fn from_block<K: ColumnType>(block: &Block<K>) -> Result<Vec<ColumnDescriptor>> {
+    let has_column = |name: &str| block.columns().iter().any(|c| c.name() == name);
+    let has_comment = has_column("comment");
+    let has_default = has_column("default_type");
+
+    let mut columns = Vec::with_capacity(block.row_count());
+    for row in block.rows() {
+        let sql_type = parse_type(row.get("type")?)?;
+
+        let (default_kind, default_expression) = if has_default {
+            let kind = parse_default_kind(row.get("default_type")?)?;
+            (kind, row.get("default_expression")?)
+        } else {
+            (None, String::new())
+        };
+
+        columns.push(ColumnDescriptor {
+            name: row.get("name")?,
+            sql_type,
+            default_kind,
+            default_expression,
+            comment: if has_comment {
+                row.get("comment")?
+            } else {
+                String::new()
+            },
+        });
+    }
+
+    Ok(columns)
+}
+
+pub(crate) fn parse_type(type_name: &str) -> Result<SqlType> {
+    match parse_sql_type(type_name) {
+        Some(sql_type) => Ok(sql_type),
+        None => {
+            let message = format!("Unsupported column type \"{}\".", type_name);
+            Err(message.into())
+        }
+    }
+}
+
+pub(crate) fn parse_default_kind(kind: &str) -> Result<Option<DefaultKind>> {
+    match kind {
+        "" => Ok(None),
+        "DEFAULT" => Ok(Some(DefaultKind::Default)),
+        "MATERIALIZED" => Ok(Some(DefaultKind::Materialized)),
+        "ALIAS" => Ok(Some(DefaultKind::Alias)),
+        kind => {
+            let message = format!("Unsupported default kind \"{}\".", kind);
+            Err(message.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_block() {
+        let block = Block::new()
+            .column("name", vec!["id", "day", "total"])
+            .column("type", vec!["UInt64", "Date", "Nullable(Decimal(9, 2))"])
+            .column("default_type", vec!["", "MATERIALIZED", "DEFAULT"])
+            .column("default_expression", vec!["", "today()", "0"])
+            .column("comment", vec!["key", "", ""]);
+
+        let columns = from_block(&block).unwrap();
+        assert_eq!(
+            columns[0],
+            ColumnDescriptor {
+                name: "id".into(),
+                sql_type: SqlType::UInt64,
+                default_kind: None,
+                default_expression: "".into(),
+                comment: "key".into(),
+            }
+        );
+        assert_eq!(columns[1].default_kind, Some(DefaultKind::Materialized));
+        assert!(!columns[1].is_insertable());
+        assert_eq!(
+            columns[2].sql_type,
+            SqlType::Nullable(SqlType::Decimal(9, 2).into())
+        );
+        assert!(columns[2].is_insertable());
+    }
+
+    #[test]
+    fn test_unsupported_type() {
+        let block = Block::new()
+            .column("name", vec!["id"])
+            .column("type", vec!["Tuple(UInt8)"]);
+        from_block(&block).unwrap_err();
+    }
+}
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use chrono::prelude::*;
+use chrono_tz::Tz;
+
+use crate::{
+    escape::{quote_identifier, quote_table},
+    types::{column::Either, SqlType, Value},
+};
+
+/// Rust types that are stored in a column of a fixed ClickHouse type.
+pub trait ColumnValue {
+    /// Type of the column.
+    fn sql_type() -> SqlType;
+
+    /// Converts the value into a cell of that column.
+    fn to_value(&self) -> Value;
+}
+
+/// Rows that can be inserted with `ClientHandle::insert_rows`.
+///
+/// Usually implemented with `#[derive(Row)]`.
+pub trait InsertRow {
+    /// Names and types of the columns, in the order of `values`.
+    fn columns() -> Vec<(&'static str, SqlType)>;
+
+    /// Cells of the row.
+    fn values(&self) -> Vec<Value>;
+
+    /// Engine of the table created by `create_table_sql`.
+    fn engine() -> &'static str {
+        "MergeTree"
+    }
+
+    /// `ORDER BY` expression of the table created by `create_table_sql`.
+    fn order_by() -> Option<&'static str> {
+        None
+    }
+
+    /// `PARTITION BY` expression of the table created by `create_table_sql`.
+    fn partition_by() -> Option<&'static str> {
+        None
+    }
+
+    /// Returns the `CREATE TABLE` statement for a table holding these rows.
+    ///
+    /// `MergeTree` family engines without `order_by` are ordered by `tuple()`.
+    /// `table` may be qualified by a database, the names are quoted.
+    fn create_table_sql(table: &str) -> String {
+        let columns: Vec<String> = Self::columns()
+            .into_iter()
+            .map(|(name, sql_type)| format!("{} {}", quote_identifier(name), sql_type))
+            .collect();
+
+        let mut sql = format!(
+            "CREATE TABLE {} ({}) ENGINE = {}",
+            quote_table(table),
+            columns.join(", "),
+            Self::engine()
+        );
+        if let Some(partition_by) = Self::partition_by() {
+            sql.push_str(" PARTITION BY ");
+            sql.push_str(partition_by);
+        }
+        match Self::order_by() {
+            Some(order_by) => {
+                sql.push_str(" ORDER BY ");
+                sql.push_str(order_by);
+            }
+            None if Self::engine().contains("MergeTree") => sql.push_str(" ORDER BY tuple()"),
+            None => {}
+        }
+        sql
+    }
+}
+
+macro_rules! column_value {
+    ( $( $t:ty : $k:ident ),* ) => {
+        $(
+            impl ColumnValue for $t {
+                fn sql_type() -> SqlType {
+                    SqlType::$k
+                }
+
+                fn to_value(&self) -> Value {
+                    self.clone().into()
+                }
+            }
+        )*
+    };
+}
+
+column_value! {
+    u8: UInt8,
+    u16: UInt16,
+    u32: UInt32,
+    u64: UInt64,
+
+    i8: Int8,
+    i16: Int16,
+    i32: Int32,
+    i64: Int64,
+
+    f32: Float32,
+    f64: Float64,
+
+    String: String,
+    Date<Tz>: Date,
+    DateTime<Tz>: DateTime
+}
+
+impl ColumnValue for &str {
+    fn sql_type() -> SqlType {
+        SqlType::String
+    }
+
+    fn to_value(&self) -> Value {
+        (*self).into()
+    }
+}
+
+impl ColumnValue for Vec<u8> {
+    fn sql_type() -> SqlType {
+        SqlType::String
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(Arc::new(self.clone()))
+    }
+}
+
+impl<T> ColumnValue for Option<T>
+where
+    T: ColumnValue,
+{
+    fn sql_type() -> SqlType {
+        SqlType::Nullable(T::sql_type().into())
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            None => Value::Nullable(Either::Left(T::sql_type().into())),
+            Some(v) => Value::Nullable(Either::Right(Box::new(v.to_value()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::types::Block;
+
+    use super::*;
+
+    struct User {
+        id: u32,
+        name: &'static str,
+        email: Option<String>,
+    }
+
+    impl InsertRow for User {
+        fn columns() -> Vec<(&'static str, SqlType)> {
+            vec![
+                ("id", u32::sql_type()),
+                ("name", <&str>::sql_type()),
+                ("email", Option::<String>::sql_type()),
+            ]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.id.to_value(), self.name.to_value(), self.email.to_value()]
+        }
+    }
+
+    #[test]
+    fn test_from_rows() {
+        let rows = [
+            User {
+                id: 1,
+                name: "Alice",
+                email: None,
+            },
+            User {
+                id: 2,
+                name: "Bob",
+                email: Some("bob@example.com".into()),
+            },
+        ];
+
+        let block = Block::from_rows(&rows).unwrap();
+        assert_eq!(block.row_count(), 2);
+
+        for (column, (name, sql_type)) in block.columns().iter().zip(User::columns()) {
+            assert_eq!(column.name(), name);
+            assert_eq!(column.sql_type(), sql_type);
+        }
+
+        let email: Option<&str> = block.get(0, "email").unwrap();
+        assert_eq!(email, None);
+        let email: Option<&str> = block.get(1, "email").unwrap();
+        assert_eq!(email, Some("bob@example.com"));
+    }
+
+    #[test]
+    fn test_create_table_sql() {
+        assert_eq!(
+            User::create_table_sql("users"),
+            "CREATE TABLE `users` (`id` UInt32, `name` String, `email` Nullable(String)) \
+             ENGINE = MergeTree ORDER BY tuple()"
+        );
+    }
+
+    #[test]
+    fn test_from_no_rows() {
+        let block = Block::from_rows::<User>(&[]).unwrap();
+        assert!(block.is_empty());
+    }
+}
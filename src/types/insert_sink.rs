@@ -0,0 +1,269 @@
+use std::{
+    mem,
+    time::{Duration, Instant},
+};
+
+use futures::{AsyncSink, StartSend};
+use tokio::prelude::*;
+use tokio_timer::Delay;
+
+use crate::{
+    errors::{Error, Result},
+    io::{transport::SendCmd, BoxFuture, ClickhouseTransport},
+    pool::PoolBinding,
+    types::{Block, Cmd, Context, InsertRow},
+    ClientHandle,
+};
+
+/// `INSERT` that stays open while blocks are pushed into it,
+/// see `ClientHandle::insert_sink`.
+///
+/// Blocks are buffered and sent to the server once `flush_rows` rows are
+/// buffered or `flush_interval` has passed since the first buffered block,
+/// whichever comes first. The rest is sent by `commit`, which completes
+/// the insert.
+pub struct InsertSink {
+    transport: Option<ClickhouseTransport>,
+    sending: Option<SendCmd>,
+    header: Block,
+    context: Context,
+    pool: PoolBinding,
+    buffer: Vec<Block>,
+    buffered_rows: usize,
+    flush_rows: usize,
+    flush_interval: Option<Duration>,
+    timer: Option<Delay>,
+}
+
+impl InsertSink {
+    pub(crate) fn new(
+        transport: ClickhouseTransport,
+        header: Block,
+        context: Context,
+        pool: PoolBinding,
+        flush_rows: usize,
+    ) -> Self {
+        Self {
+            transport: Some(transport),
+            sending: None,
+            header,
+            context,
+            pool,
+            buffer: Vec::new(),
+            buffered_rows: 0,
+            flush_rows,
+            flush_interval: None,
+            timer: None,
+        }
+    }
+
+    /// Number of buffered rows that triggers sending (defaults to `insert_block_size`).
+    pub fn flush_rows(self, flush_rows: usize) -> Self {
+        Self {
+            flush_rows: flush_rows.max(1),
+            ..self
+        }
+    }
+
+    /// Longest time blocks are buffered before they're sent (defaults to `None`).
+    pub fn flush_interval(self, flush_interval: Option<Duration>) -> Self {
+        Self {
+            flush_interval,
+            ..self
+        }
+    }
+
+    /// Sends the buffered blocks and completes the insert.
+    pub fn commit(self) -> BoxFuture<ClientHandle> {
+        let mut sink = Some(self);
+        Box::new(
+            future::poll_fn(move || {
+                try_ready!(sink.as_mut().unwrap().poll_sending());
+                Ok(Async::Ready(sink.take().unwrap()))
+            })
+            .and_then(|mut sink| {
+                let mut cmds = sink.take_data_cmds();
+                cmds.push(Cmd::SendData(Block::default(), sink.context.clone()));
+
+                sink.transport
+                    .take()
+                    .unwrap()
+                    .call_all(cmds)
+                    .read_block(sink.context, sink.pool)
+                    .map(|(c, _)| c)
+            }),
+        )
+    }
+
+    /// Cancels the insert and closes the connection.
+    ///
+    /// Blocks that were already sent may have been written to the table.
+    pub fn abort(mut self) -> BoxFuture<()> {
+        Box::new(future::lazy(move || {
+            if let Some(ref mut transport) = self.transport {
+                transport.cancel();
+            }
+            Ok(())
+        }))
+    }
+
+    pub(crate) fn push_block(&mut self, block: Block) -> Result<()> {
+        if block.is_empty() {
+            return Ok(());
+        }
+
+        let block = block.cast_to(&self.header)?;
+        if self.buffer.is_empty() {
+            self.timer = self
+                .flush_interval
+                .map(|interval| Delay::new(Instant::now() + interval));
+        }
+        self.buffered_rows += block.row_count();
+        self.buffer.push(block);
+        Ok(())
+    }
+
+    fn is_full(&self) -> bool {
+        self.buffered_rows >= self.flush_rows
+    }
+
+    /// Waits until the blocks that are being sent are written to the socket.
+    fn poll_sending(&mut self) -> Poll<(), Error> {
+        if let Some(ref mut sending) = self.sending {
+            let transport = try_ready!(sending.poll());
+            self.transport = Some(transport);
+        }
+        self.sending = None;
+        Ok(Async::Ready(()))
+    }
+
+    fn is_due(&mut self) -> Result<bool> {
+        if self.is_full() {
+            return Ok(true);
+        }
+        match self.timer {
+            Some(ref mut timer) => Ok(timer.poll()?.is_ready()),
+            None => Ok(false),
+        }
+    }
+
+    fn take_data_cmds(&mut self) -> Vec<Cmd> {
+        self.buffered_rows = 0;
+        self.timer = None;
+        mem::take(&mut self.buffer)
+            .into_iter()
+            .map(|block| Cmd::SendData(block, self.context.clone()))
+            .collect()
+    }
+}
+
+impl Sink for InsertSink {
+    type SinkItem = Block;
+    type SinkError = Error;
+
+    fn start_send(&mut self, block: Block) -> StartSend<Block, Error> {
+        if self.is_full() {
+            self.poll_complete()?;
+            if self.is_full() {
+                return Ok(AsyncSink::NotReady(block));
+            }
+        }
+
+        self.push_block(block)?;
+        Ok(AsyncSink::Ready)
+    }
+
+    /// Sends the buffered blocks if a threshold is reached.
+    fn poll_complete(&mut self) -> Poll<(), Error> {
+        loop {
+            try_ready!(self.poll_sending());
+            if self.buffer.is_empty() || !self.is_due()? {
+                return Ok(Async::Ready(()));
+            }
+
+            let cmds = self.take_data_cmds();
+            let transport = self.transport.take().unwrap();
+            self.sending = Some(transport.send_all(cmds));
+        }
+    }
+}
+
+/// Row-level façade of `InsertSink`, see `ClientHandle::insert_row_sink`.
+pub struct RowSink<T> {
+    inner: InsertSink,
+    rows: Vec<T>,
+}
+
+impl<T: InsertRow> RowSink<T> {
+    pub(crate) fn new(inner: InsertSink) -> Self {
+        Self {
+            inner,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Number of buffered rows that triggers sending (defaults to `insert_block_size`).
+    pub fn flush_rows(self, flush_rows: usize) -> Self {
+        Self {
+            inner: self.inner.flush_rows(flush_rows),
+            ..self
+        }
+    }
+
+    /// Longest time rows are buffered before they're sent (defaults to `None`).
+    pub fn flush_interval(self, flush_interval: Option<Duration>) -> Self {
+        Self {
+            inner: self.inner.flush_interval(flush_interval),
+            ..self
+        }
+    }
+
+    /// Sends the buffered rows and completes the insert.
+    pub fn commit(mut self) -> BoxFuture<ClientHandle> {
+        match self.move_rows() {
+            Ok(()) => self.inner.commit(),
+            Err(err) => Box::new(future::err(err)),
+        }
+    }
+
+    /// Cancels the insert and closes the connection.
+    ///
+    /// Rows that were already sent may have been written to the table.
+    pub fn abort(self) -> BoxFuture<()> {
+        self.inner.abort()
+    }
+
+    fn move_rows(&mut self) -> Result<()> {
+        if !self.rows.is_empty() {
+            let block = Block::from_rows(&self.rows)?;
+            self.rows.clear();
+            self.inner.push_block(block)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: InsertRow> Sink for RowSink<T> {
+    type SinkItem = T;
+    type SinkError = Error;
+
+    fn start_send(&mut self, row: T) -> StartSend<T, Error> {
+        if self.rows.len() >= self.inner.flush_rows {
+            if self.inner.is_full() {
+                self.inner.poll_complete()?;
+                if self.inner.is_full() {
+                    return Ok(AsyncSink::NotReady(row));
+                }
+            }
+            self.move_rows()?;
+        }
+
+        self.rows.push(row);
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Error> {
+        self.move_rows()?;
+        self.inner.poll_complete()
+    }
+}
@@ -6,23 +6,44 @@ use hostname::get_hostname;
 use crate::errors::ServerError;
 
 pub use self::{
-    block::{Block, RCons, RNil, Row, RowBuilder, Rows},
-    column::{Column, ColumnType, Simple, Complex},
+    block::{
+        Block, BlockBuilder, BlockDiff, CellDiff, ChunkIterator, ColumnDiff, NullsOrder,
+        PrettyTable, RCons, RNil, Row, RowBuilder, Rows, SortOrder,
+    },
+    catalog::{ColumnInfo, DatabaseInfo, PartitionInfo, TableInfo},
+    cluster::DdlHostStatus,
+    column::{Column, ColumnStats, ColumnType, PushTyped, Simple, Complex},
     decimal::Decimal,
+    describe::{ColumnDescriptor, DefaultKind},
     from_sql::FromSql,
+    insert_row::{ColumnValue, InsertRow},
+    insert_sink::{InsertSink, RowSink},
+    mutation::Mutation,
+    optimize::Optimize,
     options::Options,
+    param::{Param, ToSql},
+    processes::RunningQuery,
     query::Query,
-    query_result::QueryResult,
+    query_result::{Pages, QueryResult, QueryStats},
+    retry::RetryPolicy,
+    select::{col, raw, Expr, Select},
+    settings::{SettingValue, Settings},
+    system::SystemCommand,
     value::Value,
+    value_ref::ValueRef,
 };
+#[cfg(feature = "transactions")]
+pub use self::transaction::Transaction;
 pub(crate) use self::{
+    block::{frame_size, LoadOptions},
     cmd::Cmd,
     date_converter::DateConverter,
     marshal::Marshal,
-    options::{IntoOptions, OptionsSource},
+    options::{Address, IntoOptions, OptionsSource},
+    query_result::QueryTimer,
     stat_buffer::StatBuffer,
     unmarshal::Unmarshal,
-    value_ref::ValueRef,
+    watch::Watch,
 };
 
 pub(crate) mod column;
@@ -31,18 +52,35 @@ mod stat_buffer;
 mod unmarshal;
 
 mod from_sql;
+mod insert_row;
+mod insert_sink;
 mod value;
 mod value_ref;
 
 mod block;
+pub(crate) mod catalog;
+pub(crate) mod cluster;
 mod cmd;
 
 mod date_converter;
+mod de;
+pub(crate) mod describe;
+mod param;
+pub(crate) mod processes;
 mod query;
+mod mutation;
+mod optimize;
 mod query_result;
+pub(crate) mod retry;
+mod select;
+mod system;
+#[cfg(feature = "transactions")]
+mod transaction;
+mod watch;
 
-mod decimal;
+pub(crate) mod decimal;
 mod options;
+pub(crate) mod settings;
 
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub(crate) struct Progress {
@@ -0,0 +1,240 @@
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::future::Loop;
+use tokio::prelude::*;
+use tokio_timer::Delay;
+
+use crate::{
+    errors::{DriverError, Error, Result},
+    io::BoxFuture,
+    types::{Param, Query},
+    ClientHandle,
+};
+
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `mutation_id`, `is_done` and `latest_fail_reason` of `system.mutations`.
+type MutationRow = (String, u8, String);
+
+/// `ALTER TABLE ... DELETE/UPDATE` statement, see `ClientHandle::mutate`.
+pub struct Mutation {
+    pub(crate) client: ClientHandle,
+    pub(crate) query: Query,
+}
+
+impl Mutation {
+    /// Issues the mutation without waiting for it to be applied.
+    pub fn execute(self) -> BoxFuture<ClientHandle> {
+        self.client.execute(self.query)
+    }
+
+    /// Issues the mutation and waits until it's applied to all parts.
+    ///
+    /// Mutations started for the table by the statement are polled in
+    /// `system.mutations` with an increasing interval. A mutation that fails
+    /// is reported as `DriverError::MutationFailed` with its
+    /// `latest_fail_reason`.
+    pub fn wait(self) -> BoxFuture<ClientHandle> {
+        let (database, table) = match parse_table(self.query.get_sql()) {
+            Some(name) => name,
+            None => {
+                let message = format!(
+                    "Can't find the table of mutation \"{}\".",
+                    self.query.get_sql()
+                );
+                return Box::new(future::err(message.into()));
+            }
+        };
+
+        let query = self.query;
+        let list = MutationList::new(database, table);
+        let poll_list = list.clone();
+
+        Box::new(
+            list.fetch(self.client)
+                .and_then(move |(c, existing)| {
+                    c.execute(query).map(move |c| {
+                        let existing: HashSet<_> =
+                            existing.into_iter().map(|(id, _, _)| id).collect();
+                        (c, Arc::new(existing))
+                    })
+                })
+                .and_then(move |(c, existing)| {
+                    future::loop_fn(
+                        (c, MIN_POLL_INTERVAL),
+                        move |(c, interval)| -> BoxFuture<Loop<ClientHandle, _>> {
+                            let existing = existing.clone();
+                            Box::new(poll_list.fetch(c).and_then(move |(c, mutations)| {
+                                poll_status(c, interval, &existing, mutations)
+                            }))
+                        },
+                    )
+                }),
+        )
+    }
+}
+
+fn poll_status(
+    c: ClientHandle,
+    interval: Duration,
+    existing: &HashSet<String>,
+    mutations: Vec<MutationRow>,
+) -> BoxFuture<Loop<ClientHandle, (ClientHandle, Duration)>> {
+    match check_status(existing, mutations) {
+        Err(err) => Box::new(future::err(err)),
+        Ok(false) => Box::new(future::ok(Loop::Break(c))),
+        Ok(true) => {
+            let next_interval = (interval * 2).min(MAX_POLL_INTERVAL);
+            Box::new(
+                Delay::new(Instant::now() + interval)
+                    .map_err(Error::from)
+                    .map(move |_| Loop::Continue((c, next_interval))),
+            )
+        }
+    }
+}
+
+/// Returns `true` if some of the new mutations are still running.
+fn check_status(existing: &HashSet<String>, mutations: Vec<MutationRow>) -> Result<bool> {
+    let mut pending = false;
+    for (mutation_id, is_done, reason) in mutations {
+        if existing.contains(&mutation_id) {
+            continue;
+        }
+        if !reason.is_empty() {
+            return Err(DriverError::MutationFailed {
+                mutation_id,
+                reason,
+            }
+            .into());
+        }
+        pending |= is_done == 0;
+    }
+    Ok(pending)
+}
+
+/// Query of the mutations of a table.
+#[derive(Clone)]
+struct MutationList {
+    sql: String,
+    params: Vec<Param>,
+}
+
+impl MutationList {
+    fn new(database: Option<String>, table: String) -> Self {
+        let (database_expr, mut params) = match database {
+            Some(database) => ("?", vec![Param::new(&database)]),
+            None => ("currentDatabase()", vec![]),
+        };
+        params.push(Param::new(&table));
+
+        let sql = format!(
+            "SELECT mutation_id, is_done, latest_fail_reason FROM system.mutations \
+             WHERE database = {} AND table = ?",
+            database_expr
+        );
+        Self { sql, params }
+    }
+
+    fn fetch(&self, c: ClientHandle) -> BoxFuture<(ClientHandle, Vec<MutationRow>)> {
+        c.query_with_params(self.sql.as_str(), self.params.clone())
+            .fetch_all_as()
+    }
+}
+
+/// Returns the database and the table of an `ALTER TABLE` statement.
+fn parse_table(sql: &str) -> Option<(Option<String>, String)> {
+    let mut rest = sql.trim_start();
+    for keyword in &["ALTER", "TABLE"] {
+        let (word, tail) = rest.split_at(rest.find(char::is_whitespace)?);
+        if !word.eq_ignore_ascii_case(keyword) {
+            return None;
+        }
+        rest = tail.trim_start();
+    }
+
+    let (first, tail) = parse_identifier(rest)?;
+    match tail.strip_prefix('.') {
+        Some(tail) => {
+            let (second, _) = parse_identifier(tail)?;
+            Some((Some(first), second))
+        }
+        None => Some((None, first)),
+    }
+}
+
+/// Reads a plain, backquoted or double-quoted identifier.
+fn parse_identifier(source: &str) -> Option<(String, &str)> {
+    let mut chars = source.char_indices();
+    match chars.next()? {
+        (_, quote) if quote == '`' || quote == '"' => {
+            let mut name = String::new();
+            while let Some((i, ch)) = chars.next() {
+                match ch {
+                    '\\' => name.push(chars.next()?.1),
+                    _ if ch == quote => return Some((name, &source[i + 1..])),
+                    _ => name.push(ch),
+                }
+            }
+            None
+        }
+        _ => {
+            let end = source
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .unwrap_or(source.len());
+            if end == 0 {
+                return None;
+            }
+            Some((source[..end].to_string(), &source[end..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_table() {
+        assert_eq!(
+            parse_table("ALTER TABLE events DELETE WHERE id = 1"),
+            Some((None, "events".into()))
+        );
+        assert_eq!(
+            parse_table("alter  table\n`my db`.\"my`table\" UPDATE x = 1 WHERE 1"),
+            Some((Some("my db".into()), "my`table".into()))
+        );
+        assert_eq!(
+            parse_table("ALTER TABLE db.t ON CLUSTER c DELETE WHERE 1"),
+            Some((Some("db".into()), "t".into()))
+        );
+        assert_eq!(parse_table("SELECT 1"), None);
+        assert_eq!(parse_table("ALTER TABLE `unterminated"), None);
+    }
+
+    #[test]
+    fn test_check_status() {
+        let existing: HashSet<String> = vec!["mutation_1.txt".to_string()].into_iter().collect();
+        let failed = vec![
+            ("mutation_1.txt".to_string(), 0, "old failure".to_string()),
+            ("mutation_2.txt".to_string(), 0, "Code: 36".to_string()),
+        ];
+
+        let reason = match check_status(&existing, failed) {
+            Err(Error::Driver(DriverError::MutationFailed { reason, .. })) => reason,
+            result => panic!("unexpected result {:?}", result.is_ok()),
+        };
+        assert_eq!(reason, "Code: 36");
+
+        let pending = vec![("mutation_2.txt".to_string(), 0, String::new())];
+        assert!(check_status(&existing, pending).unwrap());
+
+        let done = vec![("mutation_2.txt".to_string(), 1, String::new())];
+        assert!(!check_status(&existing, done).unwrap());
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+
+use crate::{
+    escape::{quote_identifier, quote_table},
+    types::Query,
+};
+
+/// `OPTIMIZE TABLE` statement, run with `ClientHandle::optimize`.
+///
+/// ```rust
+/// # use clickhouse_rs::types::Optimize;
+/// let optimize = Optimize::table("db.events")
+///     .partition("202403")
+///     .final_merge()
+///     .deduplicate_by(vec!["user_id", "time"]);
+///
+/// assert_eq!(
+///     optimize.to_string(),
+///     "OPTIMIZE TABLE `db`.`events` PARTITION 202403 FINAL DEDUPLICATE BY `user_id`, `time`"
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Optimize {
+    table: String,
+    partition: Option<String>,
+    final_merge: bool,
+    deduplicate: Option<Vec<String>>,
+    wait: bool,
+}
+
+impl Optimize {
+    /// Optimizes `table`, `database.table` is also accepted.
+    pub fn table(table: impl AsRef<str>) -> Self {
+        Self {
+            table: quote_table(table.as_ref()),
+            partition: None,
+            final_merge: false,
+            deduplicate: None,
+            wait: false,
+        }
+    }
+
+    /// Merges only the partition given by the expression, e.g. `202403`,
+    /// `tuple()` or `ID 'all'`.
+    pub fn partition(self, partition: impl Into<String>) -> Self {
+        Self {
+            partition: Some(partition.into()),
+            ..self
+        }
+    }
+
+    /// Merges parts even if they are already merged into one (`FINAL`).
+    pub fn final_merge(self) -> Self {
+        Self {
+            final_merge: true,
+            ..self
+        }
+    }
+
+    /// Removes rows that are equal in all columns (`DEDUPLICATE`).
+    pub fn deduplicate(self) -> Self {
+        Self {
+            deduplicate: Some(Vec::new()),
+            ..self
+        }
+    }
+
+    /// Removes rows that are equal in the given columns (`DEDUPLICATE BY`).
+    pub fn deduplicate_by<I, S>(self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let columns = columns
+            .into_iter()
+            .map(|column| quote_identifier(column.as_ref()))
+            .collect();
+        Self {
+            deduplicate: Some(columns),
+            ..self
+        }
+    }
+
+    /// Waits until the merge is done on all replicas of a replicated table.
+    ///
+    /// Non-replicated tables are always optimized synchronously.
+    pub fn wait(self, wait: bool) -> Self {
+        Self { wait, ..self }
+    }
+}
+
+impl fmt::Display for Optimize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "OPTIMIZE TABLE {}", self.table)?;
+        if let Some(ref partition) = self.partition {
+            write!(f, " PARTITION {}", partition)?;
+        }
+        if self.final_merge {
+            f.write_str(" FINAL")?;
+        }
+        match self.deduplicate {
+            None => {}
+            Some(ref columns) if columns.is_empty() => f.write_str(" DEDUPLICATE")?,
+            Some(ref columns) => write!(f, " DEDUPLICATE BY {}", columns.join(", "))?,
+        }
+        Ok(())
+    }
+}
+
+impl From<Optimize> for Query {
+    fn from(optimize: Optimize) -> Self {
+        let query = Query::new(optimize.to_string());
+        if optimize.wait {
+            query.setting("replication_alter_partitions_sync", 2)
+        } else {
+            query
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_optimize_sql() {
+        assert_eq!(
+            Optimize::table("events").to_string(),
+            "OPTIMIZE TABLE `events`"
+        );
+        assert_eq!(
+            Optimize::table("events").deduplicate().to_string(),
+            "OPTIMIZE TABLE `events` DEDUPLICATE"
+        );
+        assert_eq!(
+            Optimize::table("events")
+                .partition("ID 'all'")
+                .final_merge()
+                .to_string(),
+            "OPTIMIZE TABLE `events` PARTITION ID 'all' FINAL"
+        );
+    }
+
+    #[test]
+    fn test_optimize_wait() {
+        let query = Query::from(Optimize::table("events"));
+        assert!(query.get_settings().is_empty());
+
+        let query = Query::from(Optimize::table("events").wait(true));
+        assert!(query
+            .get_settings()
+            .contains_key("replication_alter_partitions_sync"));
+    }
+}
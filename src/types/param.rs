@@ -0,0 +1,439 @@
+use std::{
+    borrow::Cow,
+    fmt::Write,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str,
+};
+
+use chrono::prelude::*;
+use chrono_tz::Tz;
+
+use crate::{
+    errors::{DriverError, Result},
+    escape::write_string,
+    types::{column::ColumnFrom, column::Either, decimal::NoBits, Block, Decimal, Value},
+};
+
+/// Column of the external table an `IN` list is sent as.
+const IN_LIST_COLUMN: &str = "value";
+
+/// Values that can be formatted as a ClickHouse literal.
+pub trait ToSql {
+    /// Appends the literal representation of the value to `out`.
+    fn write_sql(&self, out: &mut String);
+}
+
+/// Query parameter, a value already formatted as a ClickHouse literal.
+///
+/// Parameters are usually created with the `params!` macro.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param {
+    literal: String,
+    /// Values of an `IN` list, see `Param::in_list`.
+    list: Option<Block>,
+}
+
+impl Param {
+    pub fn new<T>(value: &T) -> Self
+    where
+        T: ToSql + ?Sized,
+    {
+        let mut literal = String::new();
+        value.write_sql(&mut literal);
+        Param {
+            literal,
+            list: None,
+        }
+    }
+
+    /// Values of an `IN (?)` clause, the placeholder is replaced with
+    /// a comma-separated list of their literals.
+    ///
+    /// Lists longer than `max_in_list_literals` are sent along with the query
+    /// as an external table and replaced with a subquery reading from it.
+    ///
+    /// ```rust
+    /// # use std::collections::HashSet;
+    /// # use clickhouse_rs::types::{Param, Query};
+    /// let ids: HashSet<u32> = vec![1, 2, 3].into_iter().collect();
+    /// let query = Query::new("SELECT * FROM events WHERE id IN (?)")
+    ///     .params(vec![Param::in_list(ids)]);
+    /// ```
+    pub fn in_list<I, T>(values: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: ToSql,
+        Vec<T>: ColumnFrom,
+    {
+        let values: Vec<T> = values.into_iter().collect();
+
+        let mut literal = String::new();
+        for (i, value) in values.iter().enumerate() {
+            if i > 0 {
+                literal.push_str(", ");
+            }
+            value.write_sql(&mut literal);
+        }
+        if values.is_empty() {
+            // Matches nothing, as `IN ()` isn't valid.
+            literal.push_str("NULL");
+        }
+
+        Param {
+            literal,
+            list: Some(Block::new().column(IN_LIST_COLUMN, values)),
+        }
+    }
+
+    /// Returns the formatted literal.
+    pub fn as_str(&self) -> &str {
+        &self.literal
+    }
+
+    /// Returns the values if the parameter is an `IN` list longer than `max_literals`.
+    fn external_list(&self, max_literals: usize) -> Option<&Block> {
+        self.list
+            .as_ref()
+            .filter(|list| list.row_count() > max_literals)
+    }
+}
+
+/// Query with parameters substituted, see `bind`.
+#[derive(Debug)]
+pub(crate) struct BoundSql<'a> {
+    pub(crate) sql: Cow<'a, str>,
+    /// External tables holding the long `IN` lists.
+    pub(crate) tables: Vec<(String, Block)>,
+}
+
+fn write_list<'a, T, I>(items: I, out: &mut String)
+where
+    T: ToSql + 'a + ?Sized,
+    I: Iterator<Item = &'a T>,
+{
+    out.push('[');
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        item.write_sql(out);
+    }
+    out.push(']');
+}
+
+fn write_date(date: &Date<Tz>, out: &mut String) {
+    write!(out, "toDate('{}')", date.format("%Y-%m-%d")).unwrap();
+}
+
+fn write_date_time(time: &DateTime<Tz>, out: &mut String) {
+    write!(
+        out,
+        "toDateTime('{}', '{}')",
+        time.format("%Y-%m-%d %H:%M:%S"),
+        time.timezone().name()
+    )
+    .unwrap();
+}
+
+macro_rules! to_sql_display {
+    ( $( $t:ty ),* ) => {
+        $(
+            impl ToSql for $t {
+                fn write_sql(&self, out: &mut String) {
+                    write!(out, "{}", self).unwrap();
+                }
+            }
+        )*
+    };
+}
+
+to_sql_display! { u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, bool }
+
+macro_rules! to_sql_ip {
+    ( $( $t:ty ),* ) => {
+        $(
+            impl ToSql for $t {
+                fn write_sql(&self, out: &mut String) {
+                    write_string(self.to_string().as_bytes(), out);
+                }
+            }
+        )*
+    };
+}
+
+to_sql_ip! { IpAddr, Ipv4Addr, Ipv6Addr }
+
+impl ToSql for str {
+    fn write_sql(&self, out: &mut String) {
+        write_string(self.as_bytes(), out);
+    }
+}
+
+impl ToSql for String {
+    fn write_sql(&self, out: &mut String) {
+        write_string(self.as_bytes(), out);
+    }
+}
+
+impl ToSql for Date<Tz> {
+    fn write_sql(&self, out: &mut String) {
+        write_date(self, out);
+    }
+}
+
+impl ToSql for DateTime<Tz> {
+    fn write_sql(&self, out: &mut String) {
+        write_date_time(self, out);
+    }
+}
+
+impl ToSql for Decimal {
+    fn write_sql(&self, out: &mut String) {
+        let function = match self.nobits {
+            NoBits::N32 => "toDecimal32",
+            NoBits::N64 => "toDecimal64",
+        };
+        write!(out, "{}('{}', {})", function, self, self.scale).unwrap();
+    }
+}
+
+impl ToSql for Value {
+    fn write_sql(&self, out: &mut String) {
+        match self {
+            Value::String(v) => write_string(v, out),
+            Value::Date(..) => write_date(&self.clone().into(), out),
+            Value::DateTime(..) => write_date_time(&self.clone().into(), out),
+            Value::Nullable(Either::Left(_)) => out.push_str("NULL"),
+            Value::Nullable(Either::Right(v)) => v.write_sql(out),
+            Value::Array(_, vs) => write_list(vs.iter(), out),
+            Value::Decimal(v) => v.write_sql(out),
+            _ => write!(out, "{}", self).unwrap(),
+        }
+    }
+}
+
+impl<T> ToSql for Option<T>
+where
+    T: ToSql,
+{
+    fn write_sql(&self, out: &mut String) {
+        match self {
+            None => out.push_str("NULL"),
+            Some(v) => v.write_sql(out),
+        }
+    }
+}
+
+impl<T> ToSql for [T]
+where
+    T: ToSql,
+{
+    fn write_sql(&self, out: &mut String) {
+        write_list(self.iter(), out);
+    }
+}
+
+impl<T> ToSql for Vec<T>
+where
+    T: ToSql,
+{
+    fn write_sql(&self, out: &mut String) {
+        write_list(self.iter(), out);
+    }
+}
+
+impl<T> ToSql for &T
+where
+    T: ToSql + ?Sized,
+{
+    fn write_sql(&self, out: &mut String) {
+        (*self).write_sql(out);
+    }
+}
+
+/// Copies a quoted literal or identifier that starts at the current position.
+fn copy_quoted<I>(quote: char, chars: &mut I, out: &mut String)
+where
+    I: Iterator<Item = char>,
+{
+    out.push(quote);
+    while let Some(ch) = chars.next() {
+        out.push(ch);
+        if ch == '\\' {
+            if let Some(escaped) = chars.next() {
+                out.push(escaped);
+            }
+        } else if ch == quote {
+            return;
+        }
+    }
+}
+
+/// Substitutes `?` placeholders in `sql` with `params`.
+/// Placeholders inside of quoted literals, identifiers and comments are ignored.
+///
+/// `IN` lists longer than `max_literals` are moved to external tables.
+pub(crate) fn bind(
+    sql: &str,
+    params: &[Param],
+    max_literals: usize,
+) -> Result<BoundSql<'static>> {
+    let mut result = String::with_capacity(sql.len());
+    let mut tables = Vec::new();
+    let mut params_iter = params.iter();
+    let mut placeholders = 0;
+    let mut chars = sql.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' | '"' | '`' => copy_quoted(ch, &mut chars, &mut result),
+            '-' if chars.peek() == Some(&'-') => {
+                result.push(ch);
+                for ch in chars.by_ref() {
+                    result.push(ch);
+                    if ch == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                result.push(ch);
+                result.push(chars.next().unwrap());
+                let mut prev = None;
+                for ch in chars.by_ref() {
+                    result.push(ch);
+                    if prev == Some('*') && ch == '/' {
+                        break;
+                    }
+                    prev = Some(ch);
+                }
+            }
+            '?' => {
+                placeholders += 1;
+                if let Some(param) = params_iter.next() {
+                    match param.external_list(max_literals) {
+                        Some(list) => {
+                            let table = format!("_in_list_{}", tables.len());
+                            write!(result, "SELECT {} FROM {}", IN_LIST_COLUMN, table).unwrap();
+                            tables.push((table, list.clone()));
+                        }
+                        None => result.push_str(param.as_str()),
+                    }
+                }
+            }
+            _ => result.push(ch),
+        }
+    }
+
+    if placeholders != params.len() {
+        return Err(DriverError::PlaceholderMismatch {
+            placeholders,
+            params: params.len(),
+        }
+        .into());
+    }
+
+    Ok(BoundSql {
+        sql: Cow::Owned(result),
+        tables,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use chrono_tz::Tz;
+
+    use super::*;
+
+    fn literal<T: ToSql + ?Sized>(value: &T) -> String {
+        Param::new(value).literal
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(literal(&42_u32), "42");
+        assert_eq!(literal(&-7_i64), "-7");
+        assert_eq!(literal(&1.5_f64), "1.5");
+        assert_eq!(literal(&true), "true");
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(literal("O'Brien"), r"'O\'Brien'");
+        assert_eq!(literal("a\\b\nc"), r"'a\\b\nc'");
+        assert_eq!(literal(&Value::from(vec![0xff_u8, b'a'])), r"'\xFFa'");
+    }
+
+    #[test]
+    fn test_complex() {
+        assert_eq!(literal(&vec![1_u8, 2, 3]), "[1, 2, 3]");
+        assert_eq!(literal(&vec!["a", "b"]), "['a', 'b']");
+        assert_eq!(literal(&None::<u8>), "NULL");
+        assert_eq!(literal(&Some("x")), "'x'");
+        assert_eq!(literal(&Ipv4Addr::new(10, 0, 0, 1)), "'10.0.0.1'");
+        assert_eq!(literal(&"::1".parse::<IpAddr>().unwrap()), "'::1'");
+        assert_eq!(literal(&Decimal::of(1.23, 2)), "toDecimal64('1.23', 2)");
+    }
+
+    #[test]
+    fn test_dates() {
+        let date = Tz::UTC.ymd(2019, 3, 14);
+        assert_eq!(literal(&date), "toDate('2019-03-14')");
+
+        let time = Tz::Europe__Moscow.ymd(2019, 3, 14).and_hms(10, 20, 30);
+        assert_eq!(
+            literal(&time),
+            "toDateTime('2019-03-14 10:20:30', 'Europe/Moscow')"
+        );
+        assert_eq!(literal(&Value::from(time)), literal(&time));
+    }
+
+    #[test]
+    fn test_bind() {
+        let params = vec![Param::new(&42), Param::new("O'Brien")];
+        let sql = bind("SELECT * FROM t WHERE id = ? AND name = ?", &params, 0)
+            .unwrap()
+            .sql;
+        assert_eq!(sql, r"SELECT * FROM t WHERE id = 42 AND name = 'O\'Brien'");
+    }
+
+    #[test]
+    fn test_bind_skips_literals_and_comments() {
+        let params = vec![Param::new(&1)];
+        let sql = "SELECT '?', `?`, \"?\", 'it\\'s ?' -- ?\n /* ? */ WHERE x = ?";
+        assert_eq!(
+            bind(sql, &params, 0).unwrap().sql,
+            "SELECT '?', `?`, \"?\", 'it\\'s ?' -- ?\n /* ? */ WHERE x = 1"
+        );
+    }
+
+    #[test]
+    fn test_bind_mismatch() {
+        bind("SELECT ?, ?", &[Param::new(&1)], 0).unwrap_err();
+        bind("SELECT 1", &[Param::new(&1)], 0).unwrap_err();
+    }
+
+    #[test]
+    fn test_bind_in_list() {
+        let sql = "SELECT * FROM t WHERE id IN (?) AND name IN (?)";
+        let params = vec![Param::in_list(vec![1_u32, 2, 3]), Param::in_list(vec!["a'b"])];
+        let bound = bind(sql, &params, 3).unwrap();
+        assert_eq!(
+            bound.sql,
+            r"SELECT * FROM t WHERE id IN (1, 2, 3) AND name IN ('a\'b')"
+        );
+        assert!(bound.tables.is_empty());
+
+        let bound = bind(sql, &params, 2).unwrap();
+        assert_eq!(
+            bound.sql,
+            r"SELECT * FROM t WHERE id IN (SELECT value FROM _in_list_0) AND name IN ('a\'b')"
+        );
+        assert_eq!(bound.tables.len(), 1);
+        assert_eq!(bound.tables[0].0, "_in_list_0");
+        assert_eq!(bound.tables[0].1.row_count(), 3);
+
+        let empty = Param::in_list(Vec::<u32>::new());
+        assert_eq!(bind("SELECT 1 IN (?)", &[empty], 2).unwrap().sql, "SELECT 1 IN (NULL)");
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use crate::{
+    errors::Result,
+    types::{Block, ColumnType},
+};
+
+pub(crate) const PROCESSES_SQL: &str = "SELECT query_id, user, elapsed, memory_usage, query \
+     FROM system.processes ORDER BY elapsed DESC";
+
+/// Query that is being executed, as reported by `system.processes`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunningQuery {
+    pub query_id: String,
+    pub user: String,
+    pub elapsed: Duration,
+    /// Memory used by the query in bytes.
+    pub memory_usage: i64,
+    pub query: String,
+}
+
+pub(crate) fn from_block<K: ColumnType>(block: &Block<K>) -> Result<Vec<RunningQuery>> {
+    block
+        .rows()
+        .map(|row| {
+            let elapsed: f64 = row.get("elapsed")?;
+            Ok(RunningQuery {
+                query_id: row.get("query_id")?,
+                user: row.get("user")?,
+                elapsed: Duration::from_secs_f64(elapsed.max(0.0)),
+                memory_usage: row.get("memory_usage")?,
+                query: row.get("query")?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_block() {
+        let block = Block::new()
+            .column("query_id", vec!["a1"])
+            .column("user", vec!["default"])
+            .column("elapsed", vec![1.5_f64])
+            .column("memory_usage", vec![4096_i64])
+            .column("query", vec!["SELECT 1"]);
+
+        assert_eq!(
+            from_block(&block).unwrap(),
+            vec![RunningQuery {
+                query_id: "a1".into(),
+                user: "default".into(),
+                elapsed: Duration::from_millis(1500),
+                memory_usage: 4096,
+                query: "SELECT 1".into(),
+            }]
+        );
+    }
+}
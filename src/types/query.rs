@@ -1,7 +1,29 @@
+use std::{
+    borrow::Cow,
+    convert::TryFrom,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    errors::{DriverError, Result},
+    types::{
+        param::{self, BoundSql},
+        query_result::{QueryTimer, StatsHook},
+        retry, settings, Param, RetryPolicy, SettingValue, Settings,
+    },
+};
+
 #[derive(Clone, Debug)]
 pub struct Query {
     sql: String,
     id: String,
+    params: Option<Vec<Param>>,
+    settings: Settings,
+    retry_policy: Option<RetryPolicy>,
+    idempotent: Option<bool>,
+    timeout: Option<Duration>,
+    max_result_bytes: Option<usize>,
+    stats_hook: Option<StatsHook>,
 }
 
 impl Query {
@@ -9,6 +31,25 @@ impl Query {
         Self {
             sql: sql.as_ref().to_string(),
             id: "".to_string(),
+            params: None,
+            settings: Settings::new(),
+            retry_policy: None,
+            idempotent: None,
+            timeout: None,
+            max_result_bytes: None,
+            stats_hook: None,
+        }
+    }
+
+    /// Values substituted for `?` placeholders in the query.
+    ///
+    /// Server-side `{name:Type}` parameters aren't supported: they are sent in a
+    /// section of the query packet that needs protocol revision 54459, while the
+    /// driver negotiates revision 54213.
+    pub fn params(self, params: Vec<Param>) -> Self {
+        Self {
+            params: Some(params),
+            ..self
         }
     }
 
@@ -19,10 +60,188 @@ impl Query {
         }
     }
 
+    /// Setting applied to this query only, it overrides the one given in `Options`.
+    pub fn setting(mut self, name: impl AsRef<str>, value: impl Into<SettingValue>) -> Self {
+        self.settings
+            .insert(name.as_ref().to_string(), value.into());
+        self
+    }
+
+    /// Same as `setting`, but fails right away if the setting is unknown
+    /// or the value doesn't fit its type.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::Query;
+    /// # fn main() -> Result<(), clickhouse_rs::errors::Error> {
+    /// let query = Query::new("SELECT * FROM events")
+    ///     .with_setting("max_execution_time", 30)?
+    ///     .with_setting("max_result_rows", 1_000_000)?;
+    ///
+    /// assert!(Query::new("SELECT 1").with_setting("max_exection_time", 30).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_setting(
+        self,
+        name: impl AsRef<str>,
+        value: impl Into<SettingValue>,
+    ) -> Result<Self> {
+        let value = value.into();
+        settings::validate(name.as_ref(), &value)?;
+        Ok(self.setting(name, value))
+    }
+
+    /// Retry policy of this query, it overrides the one given in `Options`.
+    pub fn retry(self, policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy: Some(policy),
+            ..self
+        }
+    }
+
+    /// Marks the statement as safe to send more than once.
+    ///
+    /// Queries are considered idempotent if they start with `SELECT`, `WITH`,
+    /// `SHOW`, `DESCRIBE`, `EXISTS` or `EXPLAIN`. Inserts marked idempotent
+    /// are retried only if they have a deduplication token (see `dedup_token`).
+    pub fn idempotent(self, idempotent: bool) -> Self {
+        Self {
+            idempotent: Some(idempotent),
+            ..self
+        }
+    }
+
+    /// Sets `insert_deduplication_token`, so the server skips the data
+    /// of an insert with the same token that was already written
+    /// (e.g. when the insert is retried). Marks the query as idempotent.
+    ///
+    /// An insert that is sent in several blocks (see `insert_block_size`)
+    /// is deduplicated block by block, so a retried insert must be split
+    /// the same way. Deduplication is done by `Replicated*MergeTree` tables
+    /// and by `MergeTree` tables with `non_replicated_deduplication_window`.
+    ///
+    /// ```rust
+    /// # use clickhouse_rs::types::Query;
+    /// let query = Query::new("events").dedup_token("batch-123");
+    /// ```
+    pub fn dedup_token(self, token: impl AsRef<str>) -> Self {
+        Self {
+            idempotent: Some(true),
+            ..self
+        }
+        .setting("insert_deduplication_token", token.as_ref())
+    }
+
+    /// Sets `insert_quorum`, so an insert succeeds only once the data is
+    /// written to `replicas` replicas (`0` disables the quorum).
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use clickhouse_rs::types::Query;
+    /// let query = Query::new("events")
+    ///     .insert_quorum(2)
+    ///     .insert_quorum_timeout(Duration::from_secs(30));
+    /// ```
+    pub fn insert_quorum(self, replicas: u64) -> Self {
+        self.setting("insert_quorum", replicas)
+    }
+
+    /// Sets `insert_quorum_timeout`, the time to wait for the quorum
+    /// of an insert, rounded up to milliseconds.
+    pub fn insert_quorum_timeout(self, timeout: Duration) -> Self {
+        let millis = timeout.as_nanos().div_ceil(1_000_000);
+        self.setting(
+            "insert_quorum_timeout",
+            u64::try_from(millis).unwrap_or(u64::MAX),
+        )
+    }
+
+    /// Sets `insert_distributed_sync`, so an insert into a `Distributed`
+    /// table returns only after the data is written to all shards.
+    pub fn insert_distributed_sync(self, sync: bool) -> Self {
+        self.setting("insert_distributed_sync", sync)
+    }
+
+    /// Returns the retry policy if the query may be retried.
+    pub(crate) fn get_retry_policy(&self, default: &RetryPolicy) -> Option<RetryPolicy> {
+        let policy = self.retry_policy.as_ref().unwrap_or(default);
+        if policy.is_enabled() && self.is_idempotent() {
+            Some(policy.clone())
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn is_idempotent(&self) -> bool {
+        self.idempotent
+            .unwrap_or_else(|| retry::is_read_only(&self.sql))
+    }
+
+    /// Limits the execution time on the server and sets the deadline
+    /// the client waits for the result.
+    pub(crate) fn with_timeout(self, timeout: Duration) -> Self {
+        let secs = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+        .setting("max_execution_time", secs.max(1))
+    }
+
+    pub(crate) fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub(crate) fn with_max_result_bytes(self, max_result_bytes: usize) -> Self {
+        Self {
+            max_result_bytes: Some(max_result_bytes),
+            ..self
+        }
+    }
+
+    /// Fails with `DriverError::ResultTooLarge` if `size` bytes
+    /// of the result exceed the limit of the query.
+    pub(crate) fn check_result_size(&self, size: usize) -> Result<()> {
+        match self.max_result_bytes {
+            Some(limit) if size > limit => Err(DriverError::ResultTooLarge { limit }.into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn with_stats_hook(self, hook: StatsHook) -> Self {
+        Self {
+            stats_hook: Some(hook),
+            ..self
+        }
+    }
+
+    /// Starts measuring the query if its stats are asked for,
+    /// `start` is when the query was asked for.
+    pub(crate) fn timer(&self, start: Instant) -> Option<QueryTimer> {
+        let hook = self.stats_hook.clone()?;
+        Some(QueryTimer::new(hook, start))
+    }
+
     pub(crate) fn get_sql(&self) -> &str {
         &self.sql
     }
 
+    /// Returns sql with substituted parameters and the external tables
+    /// of `IN` lists longer than `max_in_list_literals`.
+    pub(crate) fn get_bound_sql(&self, max_in_list_literals: usize) -> Result<BoundSql<'_>> {
+        match self.params {
+            None => Ok(BoundSql {
+                sql: Cow::Borrowed(&self.sql),
+                tables: Vec::new(),
+            }),
+            Some(ref params) => param::bind(&self.sql, params, max_in_list_literals),
+        }
+    }
+
+    pub(crate) fn get_settings(&self) -> &Settings {
+        &self.settings
+    }
+
     pub(crate) fn get_id(&self) -> &str {
         &self.id
     }
@@ -46,3 +265,120 @@ where
         Self::new(source)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::errors::{DriverError, Error};
+
+    use super::*;
+
+    #[test]
+    fn test_with_setting() {
+        let query = Query::new("SELECT 1")
+            .with_setting("max_execution_time", 30_u64)
+            .unwrap()
+            .with_setting("join_use_nulls", true)
+            .unwrap();
+        assert_eq!(
+            query.get_settings().get("max_execution_time"),
+            Some(&SettingValue::UInt64(30))
+        );
+
+        match Query::new("SELECT 1").with_setting("max_exection_time", 30) {
+            Err(Error::Driver(DriverError::UnknownSetting { name })) => {
+                assert_eq!(name, "max_exection_time")
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        match Query::new("SELECT 1").with_setting("max_execution_time", "soon") {
+            Err(Error::Driver(DriverError::InvalidSettingValue { .. })) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_with_timeout() {
+        let query = Query::new("SELECT 1").with_timeout(Duration::from_millis(2500));
+        assert_eq!(query.get_timeout(), Some(Duration::from_millis(2500)));
+        assert_eq!(
+            query.get_settings().get("max_execution_time"),
+            Some(&SettingValue::UInt64(3))
+        );
+
+        let query = Query::new("SELECT 1").with_timeout(Duration::from_millis(10));
+        assert_eq!(
+            query.get_settings().get("max_execution_time"),
+            Some(&SettingValue::UInt64(1))
+        );
+    }
+
+    #[test]
+    fn test_insert_durability() {
+        let query = Query::new("events")
+            .insert_quorum(2)
+            .insert_quorum_timeout(Duration::from_micros(1500))
+            .insert_distributed_sync(true);
+
+        let settings = query.get_settings();
+        assert_eq!(settings.get("insert_quorum"), Some(&SettingValue::UInt64(2)));
+        assert_eq!(
+            settings.get("insert_quorum_timeout"),
+            Some(&SettingValue::UInt64(2))
+        );
+        assert_eq!(
+            settings.get("insert_distributed_sync"),
+            Some(&SettingValue::Bool(true))
+        );
+        for (name, value) in settings {
+            settings::validate(name, value).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_dedup_token() {
+        let query = Query::new("events").dedup_token("batch-123");
+        assert!(query.is_idempotent());
+        assert_eq!(
+            query.get_settings().get("insert_deduplication_token"),
+            Some(&SettingValue::String("batch-123".into()))
+        );
+    }
+
+    #[test]
+    fn test_get_retry_policy() {
+        let disabled = RetryPolicy::none();
+        let enabled = RetryPolicy::new(3);
+
+        assert_eq!(Query::new("SELECT 1").get_retry_policy(&disabled), None);
+        assert_eq!(
+            Query::new("SELECT 1").get_retry_policy(&enabled),
+            Some(enabled.clone())
+        );
+        assert_eq!(
+            Query::new("SELECT 1")
+                .retry(disabled.clone())
+                .get_retry_policy(&enabled),
+            None
+        );
+        assert_eq!(Query::new("DROP TABLE t").get_retry_policy(&enabled), None);
+        assert_eq!(
+            Query::new("CREATE TABLE IF NOT EXISTS t (x UInt8) ENGINE = Memory")
+                .idempotent(true)
+                .get_retry_policy(&enabled),
+            Some(enabled.clone())
+        );
+    }
+
+    #[test]
+    fn test_check_result_size() {
+        Query::new("SELECT 1").check_result_size(usize::MAX).unwrap();
+
+        let query = Query::new("SELECT 1").with_max_result_bytes(100);
+        query.check_result_size(100).unwrap();
+        match query.check_result_size(101) {
+            Err(Error::Driver(DriverError::ResultTooLarge { limit: 100 })) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::mem;
+
+use futures::{Async, Poll, Stream};
+
+use crate::{
+    errors::Error,
+    types::{Block, Complex},
+};
+
+/// Stream of consecutive blocks merged into larger ones,
+/// see `QueryResult::stream_coalesced`.
+pub(crate) struct Coalesce<S> {
+    inner: S,
+    max_rows: usize,
+    pending: Vec<Block>,
+    rows: usize,
+    done: bool,
+}
+
+impl<S> Coalesce<S> {
+    pub(crate) fn new(inner: S, max_rows: usize) -> Self {
+        Self {
+            inner,
+            max_rows,
+            pending: Vec::new(),
+            rows: 0,
+            done: false,
+        }
+    }
+
+    /// Merges the pending blocks, `next` is kept for the following merge.
+    fn flush(&mut self, next: Option<Block>) -> Poll<Option<Block<Complex>>, Error> {
+        let blocks = mem::replace(&mut self.pending, next.into_iter().collect());
+        self.rows = self.pending.iter().map(Block::row_count).sum();
+        Ok(Async::Ready(Some(Block::concat(&blocks)?)))
+    }
+}
+
+impl<S> Stream for Coalesce<S>
+where
+    S: Stream<Item = Block, Error = Error>,
+{
+    type Item = Block<Complex>;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            if self.done {
+                return Ok(Async::Ready(None));
+            }
+
+            match self.inner.poll()? {
+                Async::NotReady => return Ok(Async::NotReady),
+                Async::Ready(None) => {
+                    self.done = true;
+                    if !self.pending.is_empty() {
+                        return self.flush(None);
+                    }
+                }
+                Async::Ready(Some(block)) => {
+                    let rows = block.row_count();
+                    if !self.pending.is_empty() && self.rows + rows > self.max_rows {
+                        return self.flush(Some(block));
+                    }
+
+                    self.rows += rows;
+                    self.pending.push(block);
+                    if self.rows >= self.max_rows {
+                        return self.flush(None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{stream, Future};
+
+    use super::*;
+
+    #[test]
+    fn test_coalesce() {
+        let blocks: Vec<Block> = (0..10_u32)
+            .map(|i| Block::new().column("id", vec![i; (i % 3 + 1) as usize]))
+            .collect();
+
+        let merged = Coalesce::new(stream::iter_ok(blocks), 5)
+            .collect()
+            .wait()
+            .unwrap();
+        let sizes: Vec<_> = merged.iter().map(Block::row_count).collect();
+        assert_eq!(sizes, vec![3, 4, 5, 3, 4]);
+        assert_eq!(merged[1].get::<u32, _>(0, "id").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_coalesce_large_blocks() {
+        let blocks = vec![
+            Block::new().column("id", vec![1_u8; 100]),
+            Block::new().column("id", vec![2_u8; 100]),
+        ];
+
+        let merged = Coalesce::new(stream::iter_ok(blocks), 10)
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[1].get::<u8, _>(0, "id").unwrap(), 2);
+    }
+}
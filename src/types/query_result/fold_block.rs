@@ -5,7 +5,7 @@ use crate::{
     types::block::{Block, BlockRef, Row, Rows},
 };
 
-use std::{mem, sync::Arc, marker};
+use std::{mem, sync::Arc};
 use crate::types::Simple;
 
 enum State<T, Fut> {
@@ -80,9 +80,5 @@ where
 }
 
 fn rows(block: Arc<Block>) -> Rows<'static, Simple> {
-    Rows {
-        row: 0,
-        block_ref: BlockRef::Owned(block),
-        kind: marker::PhantomData,
-    }
+    Rows::new(BlockRef::Owned(block))
 }
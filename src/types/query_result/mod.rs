@@ -1,9 +1,14 @@
-use std::{marker, sync::Arc};
+use std::{
+    ops::ControlFlow,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use serde::de::DeserializeOwned;
 use tokio::prelude::*;
 
 use crate::{
-    errors::{DriverError, Error},
+    errors::{DriverError, Error, Result},
     io::{BoxFuture, BoxStream, ClickhouseTransport},
     types::{
         block::BlockRef, query_result::stream_blocks::BlockStream, Block, Cmd, Packet, Query, Row,
@@ -12,12 +17,21 @@ use crate::{
     ClientHandle,
 };
 
-use self::{either::Either, fold_block::FoldBlock};
+use self::{
+    coalesce::Coalesce, either::Either, fold_block::FoldBlock, try_fold_blocks::TryFoldBlocks,
+};
 use crate::types::Simple;
 
+pub use self::{paginate::Pages, stats::QueryStats};
+pub(crate) use self::stats::{QueryTimer, StatsHook};
+
+mod coalesce;
 mod either;
 mod fold_block;
+mod paginate;
+mod stats;
 mod stream_blocks;
+mod try_fold_blocks;
 
 macro_rules! try_opt_stream {
     ($expr:expr) => {
@@ -44,6 +58,83 @@ pub struct QueryResult {
 }
 
 impl QueryResult {
+    /// Sets `max_execution_time` of the query and fails it with a timeout error
+    /// if the result isn't received within `timeout`.
+    ///
+    /// The query is cancelled on the server when the deadline is exceeded,
+    /// as well as when the future or the stream is dropped before the end
+    /// of the result (e.g. because it is timed out externally).
+    ///
+    /// ```rust
+    /// # extern crate clickhouse_rs;
+    /// # extern crate futures;
+    /// # use futures::Future;
+    /// # use clickhouse_rs::Pool;
+    /// # use std::{env, time::Duration};
+    /// # let database_url = env::var("DATABASE_URL").unwrap_or("tcp://localhost:9000?compression=lz4".into());
+    /// # let pool = Pool::new(database_url);
+    /// # let done =
+    /// pool.get_handle()
+    ///     .and_then(|c| {
+    ///         c.query("SELECT count() FROM system.numbers_mt")
+    ///             .timeout(Duration::from_secs(1))
+    ///             .fetch_all()
+    ///     })
+    /// #   .map(|_| ())
+    /// #   .map_err(|err| eprintln!("database error: {}", err));
+    /// # tokio::run(done)
+    /// ```
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            query: self.query.with_timeout(timeout),
+            ..self
+        }
+    }
+
+    /// Fails `fetch_all` and `fetch_all_as` with `DriverError::ResultTooLarge`
+    /// once the received blocks take more than `bytes` (see `Block::byte_size`),
+    /// instead of buffering a result of any size.
+    ///
+    /// Larger results can be processed in parts with `stream_blocks` or `fold_blocks`.
+    pub fn max_result_bytes(self, bytes: usize) -> Self {
+        Self {
+            query: self.query.with_max_result_bytes(bytes),
+            ..self
+        }
+    }
+
+    /// Calls `f` with the time the query took in each of its phases (see `QueryStats`)
+    /// once its result ends, fails or is dropped. A retried query reports each attempt.
+    ///
+    /// ```rust
+    /// # extern crate clickhouse_rs;
+    /// # extern crate futures;
+    /// # use futures::Future;
+    /// # use clickhouse_rs::Pool;
+    /// # use std::env;
+    /// # let database_url = env::var("DATABASE_URL").unwrap_or("tcp://localhost:9000?compression=lz4".into());
+    /// # let pool = Pool::new(database_url);
+    /// # let done =
+    /// pool.get_handle()
+    ///     .and_then(|c| {
+    ///         c.query("SELECT number FROM system.numbers LIMIT 100000")
+    ///             .on_stats(|stats| println!("{:?}", stats))
+    ///             .fetch_all()
+    ///     })
+    /// #   .map(|_| ())
+    /// #   .map_err(|err| eprintln!("database error: {}", err));
+    /// # tokio::run(done)
+    /// ```
+    pub fn on_stats<F>(self, f: F) -> Self
+    where
+        F: Fn(&QueryStats) + Send + Sync + 'static,
+    {
+        Self {
+            query: self.query.with_stats_hook(StatsHook::new(f)),
+            ..self
+        }
+    }
+
     /// Method that applies a function to each row, producing a single, final value.
     ///
     /// example:
@@ -83,17 +174,131 @@ impl QueryResult {
     }
 
     /// Fetch data from table. It returns a block that contains all rows.
+    ///
+    /// Like the other `fetch_*` methods, it's retried on a new connection
+    /// according to the `RetryPolicy` if the query is idempotent.
     pub fn fetch_all(self) -> BoxFuture<(ClientHandle, Block<Complex>)> {
-        wrap_future(
-            self.fold_blocks(Vec::new(), |mut blocks, block| {
-                if !block.is_empty() {
-                    blocks.push(block);
+        self.retrying(|r| {
+            let query = r.query.clone();
+            wrap_future(
+                r.fold_blocks((Vec::new(), 0), move |(mut blocks, size), block| {
+                    let size = size + block.byte_size();
+                    query.check_result_size(size)?;
+                    if !block.is_empty() {
+                        blocks.push(block);
+                    }
+                    Ok((blocks, size))
+                })
+                .map_err(Error::from)
+                .and_then(|(h, (blocks, _))| Ok((h, Block::concat(blocks.as_slice())?))),
+            )
+        })
+    }
+
+    /// Fetch all rows deserialized into `T`.
+    ///
+    /// Struct fields are matched with columns by name, see `Row::deserialize`.
+    ///
+    /// example:
+    /// ```rust
+    /// # extern crate clickhouse_rs;
+    /// # extern crate futures;
+    /// # use futures::Future;
+    /// # use clickhouse_rs::Pool;
+    /// # use std::env;
+    /// # let database_url = env::var("DATABASE_URL").unwrap_or("tcp://localhost:9000?compression=lz4".into());
+    /// # let pool = Pool::new(database_url);
+    /// # let done =
+    /// pool.get_handle()
+    ///     .and_then(|c| {
+    ///         c.query("SELECT number, toString(number) AS name FROM system.numbers LIMIT 10")
+    ///             .fetch_all_as::<(u64, String)>()
+    ///     })
+    ///     .and_then(|(_, rows)| {
+    ///         for (number, name) in rows {
+    ///             println!("{} {}", number, name);
+    ///         }
+    ///         Ok(())
+    ///     })
+    /// #   .map_err(|err| eprintln!("database error: {}", err));
+    /// # tokio::run(done)
+    /// ```
+    pub fn fetch_all_as<T>(self) -> BoxFuture<(ClientHandle, Vec<T>)>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        self.retrying(|r| {
+            let query = r.query.clone();
+            wrap_future(
+                r.fold_blocks((Vec::new(), 0), move |(mut rows, size), block| {
+                    let size = size + block.byte_size();
+                    query.check_result_size(size)?;
+                    for row in block.rows() {
+                        rows.push(row.deserialize()?);
+                    }
+                    Ok((rows, size))
+                })
+                .map(|(h, (rows, _))| (h, rows)),
+            )
+        })
+    }
+
+    /// Fetch exactly one row deserialized into `T`.
+    ///
+    /// It's an error if the query returns no rows or more than one row.
+    pub fn fetch_one<T>(self) -> BoxFuture<(ClientHandle, T)>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        wrap_future(self.fetch_optional().and_then(|(c, row)| match row {
+            Some(row) => Ok((c, row)),
+            None => Err(DriverError::NoRows.into()),
+        }))
+    }
+
+    /// Fetch at most one row deserialized into `T`.
+    ///
+    /// It's an error if the query returns more than one row.
+    pub fn fetch_optional<T>(self) -> BoxFuture<(ClientHandle, Option<T>)>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        self.retrying(|r| {
+            r.fold_blocks(None, |mut found, block| {
+                for row in block.rows() {
+                    if found.is_some() {
+                        return Err(DriverError::TooManyRows.into());
+                    }
+                    found = Some(row.deserialize()?);
                 }
-                Ok(blocks)
+                Ok(found)
             })
-            .map_err(Error::from)
-            .map(|(h, blocks)| (h, Block::concat(blocks.as_slice()))),
-        )
+        })
+    }
+
+    /// Runs `f`, and runs it again on failure if the query is idempotent
+    /// and its retry policy allows.
+    fn retrying<T, F>(self, f: F) -> BoxFuture<(ClientHandle, T)>
+    where
+        F: Fn(QueryResult) -> BoxFuture<(ClientHandle, T)> + Send + Sync + 'static,
+        T: Send + 'static,
+    {
+        let policy = try_opt!(self.client.context.options.get())
+            .retry_policy
+            .clone();
+
+        match self.query.get_retry_policy(&policy) {
+            Some(policy) => {
+                let query = self.query;
+                self.client.retry(policy, move |client| {
+                    f(QueryResult {
+                        client,
+                        query: query.clone(),
+                    })
+                })
+            }
+            None => f(self),
+        }
     }
 
     /// Method that applies a function to each block, producing a single, final value.
@@ -143,6 +348,71 @@ impl QueryResult {
         }
     }
 
+    /// Like `fold_blocks`, but stops early once `f` returns `ControlFlow::Break`.
+    ///
+    /// The query is cancelled on the server, and the rest of its result
+    /// is skipped without being passed to `f`.
+    ///
+    /// example:
+    /// ```rust
+    /// # extern crate clickhouse_rs;
+    /// # extern crate futures;
+    /// # use futures::Future;
+    /// # use clickhouse_rs::Pool;
+    /// # use std::{env, ops::ControlFlow};
+    /// # let database_url = env::var("DATABASE_URL").unwrap_or("tcp://localhost:9000?compression=lz4".into());
+    /// # let pool = Pool::new(database_url);
+    /// # let done =
+    /// pool.get_handle()
+    ///     .and_then(|c| {
+    ///         c.query("SELECT number FROM system.numbers")
+    ///             .try_fold_blocks(None, |_, block| {
+    ///                 for row in block.rows() {
+    ///                     let number: u64 = row.get("number")?;
+    ///                     if number % 1000 == 999 {
+    ///                         return Ok(ControlFlow::Break(Some(number)));
+    ///                     }
+    ///                 }
+    ///                 Ok(ControlFlow::Continue(None))
+    ///             })
+    ///     })
+    /// #   .map(|_| ())
+    /// #   .map_err(|err| eprintln!("database error: {}", err));
+    /// # tokio::run(done)
+    /// ```
+    pub fn try_fold_blocks<F, T>(self, init: T, f: F) -> BoxFuture<(ClientHandle, T)>
+    where
+        F: FnMut(T, Block) -> Result<ControlFlow<T, T>> + Send + 'static,
+        T: Send + 'static,
+    {
+        let timeout = try_opt!(self.client.context.options.get()).query_timeout;
+        let context = self.client.context.clone();
+        let pool = self.client.pool.clone();
+        let query = self.query;
+        let start = Instant::now();
+
+        let future = self.client.wrap_future(move |mut c| {
+            info!("[send query] {}", query.get_sql());
+            c.pool.detach();
+            let deadline = query.get_timeout().map(|timeout| Instant::now() + timeout);
+            let timer = query.timer(start);
+            let stream = c
+                .inner
+                .take()
+                .unwrap()
+                .call(Cmd::SendQuery(query, context.clone()))
+                .deadline(deadline)
+                .timed(timer);
+            TryFoldBlocks::new(stream, init, f, context, pool)
+        });
+
+        if let Some(timeout) = timeout {
+            Box::new(future.timeout(timeout).map_err(|err| err.into()))
+        } else {
+            future
+        }
+    }
+
     fn fold_packets<F, T, Fut>(self, init: T, f: F) -> BoxFuture<T>
     where
         F: Fn(T, Packet<ClickhouseTransport>) -> Fut + Send + 'static,
@@ -152,18 +422,56 @@ impl QueryResult {
     {
         let context = self.client.context.clone();
         let query = self.query;
+        let start = Instant::now();
 
         self.client.wrap_future(move |mut c| {
             info!("[send query] {}", query.get_sql());
             c.pool.detach();
+            let deadline = query.get_timeout().map(|timeout| Instant::now() + timeout);
+            let timer = query.timer(start);
             c.inner
                 .take()
                 .unwrap()
                 .call(Cmd::SendQuery(query, context.clone()))
+                .deadline(deadline)
+                .timed(timer)
                 .fold(init, f)
         })
     }
 
+    /// Returns a stream of pages of `page_size` rows, fetched one by one.
+    ///
+    /// Pages are taken with `LIMIT`/`OFFSET` from the result, so the query
+    /// should have a stable `ORDER BY`. `Pages::keyset` switches to keyset
+    /// pagination by a column.
+    ///
+    /// example:
+    /// ```rust
+    /// # extern crate clickhouse_rs;
+    /// # extern crate futures;
+    /// # use futures::{Future, Stream};
+    /// # use clickhouse_rs::Pool;
+    /// # use std::env;
+    /// # let database_url = env::var("DATABASE_URL").unwrap_or("tcp://localhost:9000?compression=lz4".into());
+    /// # let pool = Pool::new(database_url);
+    /// # let done =
+    /// pool.get_handle()
+    ///     .and_then(|c| {
+    ///         c.query("SELECT number FROM system.numbers LIMIT 1000")
+    ///             .paginate(100)
+    ///             .keyset("number")
+    ///             .for_each(|page| {
+    ///                 println!("{} rows", page.row_count());
+    ///                 Ok(())
+    ///             })
+    ///     })
+    /// #   .map_err(|err| eprintln!("database error: {}", err));
+    /// # tokio::run(done)
+    /// ```
+    pub fn paginate(self, page_size: u64) -> Pages {
+        Pages::new(self.client, self.query, page_size)
+    }
+
     /// Method that produces a stream of blocks containing rows
     ///
     /// example:
@@ -193,6 +501,7 @@ impl QueryResult {
     pub fn stream_blocks(self) -> BoxStream<Block> {
         let query = self.query;
         let timeout = try_opt_stream!(self.client.context.options.get()).query_block_timeout;
+        let start = Instant::now();
 
         self.client.wrap_stream(move |mut c| -> BoxStream<Block> {
             info!("[send query] {}", query.get_sql());
@@ -201,12 +510,16 @@ impl QueryResult {
 
             let context = c.context.clone();
             let pool = c.pool.clone();
+            let deadline = query.get_timeout().map(|timeout| Instant::now() + timeout);
+            let timer = query.timer(start);
 
             let stream = BlockStream::new(
                 c.inner
                     .take()
                     .unwrap()
-                    .call(Cmd::SendQuery(query, context.clone())),
+                    .call(Cmd::SendQuery(query, context.clone()))
+                    .deadline(deadline)
+                    .timed(timer),
                 context,
                 pool,
             );
@@ -219,14 +532,76 @@ impl QueryResult {
         })
     }
 
-    /// Method that produces a stream of rows
+    /// Stream of blocks like `stream_blocks`, with consecutive blocks merged
+    /// as long as they have at most `max_rows` rows, a larger received block
+    /// is passed on alone. Merged blocks share the data of the received ones
+    /// instead of copying it (see `Block::concat`).
+    ///
+    /// It helps with queries that return many blocks of a few rows,
+    /// when the overhead per block dominates processing.
+    ///
+    /// example:
+    /// ```rust
+    /// # extern crate clickhouse_rs;
+    /// # extern crate futures;
+    /// # use futures::{Future, Stream};
+    /// # use clickhouse_rs::Pool;
+    /// # use std::env;
+    /// # let database_url = env::var("DATABASE_URL").unwrap_or("tcp://localhost:9000?compression=lz4".into());
+    /// # let pool = Pool::new(database_url);
+    /// # let done =
+    /// pool.get_handle()
+    ///     .and_then(|c| {
+    ///         c.query("SELECT number FROM numbers(100000) SETTINGS max_block_size = 10")
+    ///             .stream_coalesced(10_000)
+    ///             .for_each(|block| {
+    ///                 println!("{} rows", block.row_count());
+    ///                 Ok(())
+    ///             })
+    ///     })
+    /// #   .map_err(|err| eprintln!("database error: {}", err));
+    /// # tokio::run(done)
+    /// ```
+    pub fn stream_coalesced(self, max_rows: usize) -> BoxStream<Block<Complex>> {
+        Box::new(Coalesce::new(self.stream_blocks(), max_rows))
+    }
+
+    /// Method that produces a stream of rows.
+    ///
+    /// Blocks are received from the server one by one, row boundaries
+    /// of the blocks are not visible to the consumer.
+    ///
+    /// example:
+    /// ```rust
+    /// # extern crate clickhouse_rs;
+    /// # extern crate futures;
+    /// # use futures::{Future, Stream};
+    /// # use clickhouse_rs::{errors::Error, Pool};
+    /// # use std::env;
+    /// # let database_url = env::var("DATABASE_URL").unwrap_or("tcp://localhost:9000?compression=lz4".into());
+    /// # let pool = Pool::new(database_url);
+    /// # let done =
+    /// pool.get_handle()
+    ///     .and_then(|c| {
+    ///         c.query("SELECT number, toString(number) AS name FROM system.numbers LIMIT 10")
+    ///             .stream_rows()
+    ///             .for_each(|row| {
+    ///                 let number: u64 = row.get("number")?;
+    ///                 let name: &str = row.get("name")?;
+    ///                 println!("{} {}", number, name);
+    ///                 Ok::<_, Error>(())
+    ///             })
+    ///     })
+    /// #   .map_err(|err| eprintln!("database error: {}", err));
+    /// # tokio::run(done)
+    /// ```
     pub fn stream_rows(self) -> BoxStream<Row<'static, Simple>> {
         Box::new(
             self.stream_blocks()
                 .map(Arc::new)
                 .map(|block| {
                     let block_ref = BlockRef::Owned(block);
-                    stream::iter_ok(Rows { row: 0, block_ref, kind: marker::PhantomData })
+                    stream::iter_ok(Rows::new(block_ref))
                 })
                 .flatten(),
         )
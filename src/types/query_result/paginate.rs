@@ -0,0 +1,191 @@
+use std::mem;
+
+use futures::{Async, Future, Poll, Stream};
+
+use crate::{
+    errors::{Error, Result},
+    escape::quote_identifier,
+    io::BoxFuture,
+    types::{Block, Complex, Param, Query, Value},
+    ClientHandle,
+};
+
+/// Stream of pages of a query result, see `QueryResult::paginate`.
+///
+/// Every page is fetched with a separate query, the stream ends
+/// with the first page that has fewer than `page_size` rows.
+pub struct Pages {
+    query: Query,
+    cursor: Cursor,
+    state: State,
+}
+
+enum State {
+    Idle(Box<ClientHandle>),
+    Fetching(BoxFuture<(ClientHandle, Block<Complex>)>),
+    Done,
+}
+
+/// Position of the next page.
+struct Cursor {
+    page_size: u64,
+    keyset: Option<String>,
+    offset: u64,
+    last_key: Option<Param>,
+}
+
+impl Pages {
+    pub(crate) fn new(client: ClientHandle, query: Query, page_size: u64) -> Self {
+        Self {
+            query,
+            cursor: Cursor {
+                page_size: page_size.max(1),
+                keyset: None,
+                offset: 0,
+                last_key: None,
+            },
+            state: State::Idle(Box::new(client)),
+        }
+    }
+
+    /// Pages by the values of `column` instead of `LIMIT`/`OFFSET`,
+    /// each page starts after the last value of the previous one.
+    ///
+    /// Values of the column are expected to be unique, rows that have
+    /// the last value of a page are skipped otherwise.
+    pub fn keyset(self, column: impl AsRef<str>) -> Self {
+        Self {
+            cursor: Cursor {
+                keyset: Some(column.as_ref().to_string()),
+                ..self.cursor
+            },
+            ..self
+        }
+    }
+}
+
+impl Cursor {
+    fn page_sql(&self, sql: &str) -> String {
+        match self.keyset {
+            None => format!(
+                "SELECT * FROM ({}) LIMIT {} OFFSET {}",
+                sql, self.page_size, self.offset
+            ),
+            Some(ref column) => {
+                let column = quote_identifier(column);
+                let filter = match self.last_key {
+                    Some(ref key) => format!(" WHERE {} > {}", column, key.as_str()),
+                    None => String::new(),
+                };
+                format!(
+                    "SELECT * FROM ({}){} ORDER BY {} LIMIT {}",
+                    sql, filter, column, self.page_size
+                )
+            }
+        }
+    }
+
+    /// Moves past `page`, returns `false` if it's the last one.
+    fn advance(&mut self, page: &Block<Complex>) -> Result<bool> {
+        let rows = page.row_count();
+        if (rows as u64) < self.page_size {
+            return Ok(false);
+        }
+
+        self.offset += rows as u64;
+        if let Some(ref column) = self.keyset {
+            let key = Value::from(page.get_column(column.as_str())?.at(rows - 1));
+            self.last_key = Some(Param::new(&key));
+        }
+        Ok(true)
+    }
+}
+
+impl Stream for Pages {
+    type Item = Block<Complex>;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            self.state = match mem::replace(&mut self.state, State::Done) {
+                State::Idle(c) => {
+                    let cursor = &self.cursor;
+                    let query = self.query.clone().map_sql(|sql| cursor.page_sql(sql));
+                    State::Fetching(c.query(query).fetch_all())
+                }
+                State::Fetching(mut page) => {
+                    let (c, page) = match page.poll()? {
+                        Async::NotReady => {
+                            self.state = State::Fetching(page);
+                            return Ok(Async::NotReady);
+                        }
+                        Async::Ready(result) => result,
+                    };
+
+                    if self.cursor.advance(&page)? {
+                        self.state = State::Idle(Box::new(c));
+                    }
+                    if page.is_empty() {
+                        return Ok(Async::Ready(None));
+                    }
+                    return Ok(Async::Ready(Some(page)));
+                }
+                State::Done => return Ok(Async::Ready(None)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cursor(page_size: u64, keyset: Option<&str>) -> Cursor {
+        Cursor {
+            page_size,
+            keyset: keyset.map(String::from),
+            offset: 0,
+            last_key: None,
+        }
+    }
+
+    #[test]
+    fn test_offset_pages() {
+        let mut cursor = cursor(2, None);
+        let sql = "SELECT id FROM t ORDER BY id";
+        assert_eq!(
+            cursor.page_sql(sql),
+            "SELECT * FROM (SELECT id FROM t ORDER BY id) LIMIT 2 OFFSET 0"
+        );
+
+        let page = Block::concat(&[Block::new().column("id", vec![1_u32, 2])]).unwrap();
+        assert!(cursor.advance(&page).unwrap());
+        assert_eq!(
+            cursor.page_sql(sql),
+            "SELECT * FROM (SELECT id FROM t ORDER BY id) LIMIT 2 OFFSET 2"
+        );
+
+        let page = Block::concat(&[Block::new().column("id", vec![3_u32])]).unwrap();
+        assert!(!cursor.advance(&page).unwrap());
+    }
+
+    #[test]
+    fn test_keyset_pages() {
+        let mut cursor = cursor(2, Some("name"));
+        let sql = "SELECT name FROM t";
+        assert_eq!(
+            cursor.page_sql(sql),
+            "SELECT * FROM (SELECT name FROM t) ORDER BY `name` LIMIT 2"
+        );
+
+        let page = Block::concat(&[Block::new().column("name", vec!["a", "b'c"])]).unwrap();
+        assert!(cursor.advance(&page).unwrap());
+        assert_eq!(
+            cursor.page_sql(sql),
+            "SELECT * FROM (SELECT name FROM t) WHERE `name` > 'b\\'c' ORDER BY `name` LIMIT 2"
+        );
+
+        let page = Block::concat(&[Block::new().column("id", vec![1_u32, 2])]).unwrap();
+        cursor.advance(&page).unwrap_err();
+    }
+}
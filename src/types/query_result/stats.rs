@@ -0,0 +1,122 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Time a query took in each of its phases, see `QueryResult::on_stats`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryStats {
+    /// Waiting for the connection to be checked before the query is sent
+    /// (see `ping_before_query`).
+    pub connect: Duration,
+    /// Writing the query and its external tables to the socket.
+    pub send: Duration,
+    /// Time until the first block with rows was received since the query
+    /// was started, `None` if there was no such block.
+    pub first_block: Option<Duration>,
+    /// Parsing and decompressing received packets. Columns of lazy blocks
+    /// (see `lazy_blocks`) are decoded later and aren't counted.
+    pub decode: Duration,
+    /// Time from the start of the query until the end of its result.
+    pub total: Duration,
+}
+
+/// Function the stats of a query are reported to.
+#[derive(Clone)]
+pub(crate) struct StatsHook(Arc<dyn Fn(&QueryStats) + Send + Sync>);
+
+impl StatsHook {
+    pub(crate) fn new<F>(f: F) -> Self
+    where
+        F: Fn(&QueryStats) + Send + Sync + 'static,
+    {
+        StatsHook(Arc::new(f))
+    }
+}
+
+impl fmt::Debug for StatsHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("StatsHook")
+    }
+}
+
+/// Measures the phases of a query, the stats are reported once it's dropped.
+pub(crate) struct QueryTimer {
+    start: Instant,
+    stats: QueryStats,
+    hook: StatsHook,
+}
+
+impl QueryTimer {
+    /// Starts measuring a query once its connection is ready,
+    /// `start` is when the query was asked for.
+    pub(crate) fn new(hook: StatsHook, start: Instant) -> Self {
+        Self {
+            start,
+            stats: QueryStats {
+                connect: start.elapsed(),
+                ..QueryStats::default()
+            },
+            hook,
+        }
+    }
+
+    pub(crate) fn sent(&mut self) {
+        self.stats.send = self.start.elapsed() - self.stats.connect;
+    }
+
+    pub(crate) fn block_received(&mut self) {
+        if self.stats.first_block.is_none() {
+            self.stats.first_block = Some(self.start.elapsed());
+        }
+    }
+
+    pub(crate) fn add_decode(&mut self, time: Duration) {
+        self.stats.decode += time;
+    }
+}
+
+impl Drop for QueryTimer {
+    fn drop(&mut self) {
+        self.stats.total = self.start.elapsed();
+        (self.hook.0)(&self.stats);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn test_timer() {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let hook = {
+            let reported = reported.clone();
+            StatsHook::new(move |stats| reported.lock().unwrap().push(stats.clone()))
+        };
+
+        let start = Instant::now();
+        let mut timer = QueryTimer::new(hook, start);
+        timer.sent();
+        timer.add_decode(Duration::from_millis(2));
+        timer.add_decode(Duration::from_millis(3));
+        assert!(reported.lock().unwrap().is_empty());
+
+        timer.block_received();
+        let first_block = timer.stats.first_block;
+        timer.block_received();
+        drop(timer);
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 1);
+        let stats = &reported[0];
+        assert_eq!(stats.decode, Duration::from_millis(5));
+        assert_eq!(stats.first_block, first_block);
+        assert!(stats.connect + stats.send <= stats.first_block.unwrap());
+        assert!(stats.first_block.unwrap() <= stats.total);
+        assert!(stats.total <= start.elapsed());
+    }
+}
@@ -0,0 +1,84 @@
+use std::ops::ControlFlow;
+
+use tokio::prelude::*;
+
+use crate::{
+    errors::{DriverError, Error, Result},
+    io::transport::PacketStream,
+    pool::PoolBinding,
+    types::{Block, Context, Packet},
+    ClientHandle,
+};
+
+pub(super) struct TryFoldBlocks<T, F> {
+    stream: PacketStream,
+    acc: Option<T>,
+    f: F,
+    context: Context,
+    pool: PoolBinding,
+    client: Option<ClientHandle>,
+}
+
+impl<T, F> TryFoldBlocks<T, F>
+where
+    F: FnMut(T, Block) -> Result<ControlFlow<T, T>>,
+{
+    pub(super) fn new(
+        stream: PacketStream,
+        init: T,
+        f: F,
+        context: Context,
+        pool: PoolBinding,
+    ) -> Self {
+        Self {
+            stream,
+            acc: Some(init),
+            f,
+            context,
+            pool,
+            client: None,
+        }
+    }
+}
+
+impl<T, F> Future for TryFoldBlocks<T, F>
+where
+    F: FnMut(T, Block) -> Result<ControlFlow<T, T>>,
+{
+    type Item = (ClientHandle, T);
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            match try_ready!(self.stream.poll()) {
+                Some(Packet::Block(block)) => {
+                    if block.is_empty() {
+                        continue;
+                    }
+                    let acc = self.acc.take().unwrap();
+                    self.acc = Some(match (self.f)(acc, block)? {
+                        ControlFlow::Continue(acc) => acc,
+                        ControlFlow::Break(acc) => {
+                            self.stream.cancel();
+                            acc
+                        }
+                    });
+                }
+                Some(Packet::Eof(inner)) => {
+                    self.client = Some(ClientHandle {
+                        inner: Some(inner),
+                        context: self.context.clone(),
+                        pool: self.pool.clone(),
+                    });
+                }
+                Some(Packet::ProfileInfo(_)) | Some(Packet::Progress(_)) => {}
+                Some(Packet::Exception(exception)) => return Err(Error::Server(exception)),
+                Some(_) => return Err(Error::Driver(DriverError::UnexpectedPacket)),
+                None => {
+                    let client = self.client.take().unwrap();
+                    return Ok(Async::Ready((client, self.acc.take().unwrap())));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::{cmp, fmt, sync::Arc, time::Duration};
+
+use crate::errors::{DriverError, Error};
+
+/// Server error codes of failures that are likely to go away on a new attempt.
+const TRANSIENT_SERVER_ERRORS: &[u32] = &[
+    202, // TOO_MANY_SIMULTANEOUS_QUERIES
+    203, // NO_FREE_CONNECTION
+    209, // SOCKET_TIMEOUT
+    210, // NETWORK_ERROR
+];
+
+type RetryOn = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// Policy of sending a failed query again on a new connection.
+///
+/// It's applied to `SELECT`-like queries and to queries marked with
+/// `Query::idempotent`. Inserts are only retried when given a deduplication
+/// token (see `Query::dedup_token`), so a retried block isn't written twice.
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use clickhouse_rs::{errors::Error, types::RetryPolicy};
+/// let policy = RetryPolicy::new(3)
+///     .backoff(Duration::from_millis(50), Duration::from_secs(1))
+///     .retry_on(|err| matches!(err, Error::Io(_)));
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    backoff_base: Duration,
+    backoff_max: Duration,
+    /// `None` stands for `is_transient`.
+    retry_on: Option<RetryOn>,
+}
+
+impl RetryPolicy {
+    /// Makes at most `max_attempts` attempts (the first one included) on
+    /// network errors and timeouts.
+    pub fn new(max_attempts: usize) -> Self {
+        Self {
+            max_attempts,
+            backoff_base: Duration::from_millis(100),
+            backoff_max: Duration::from_secs(5),
+            retry_on: None,
+        }
+    }
+
+    /// Never retries.
+    pub fn none() -> Self {
+        Self::new(1)
+    }
+
+    /// Delay before the second attempt, doubled for each further one
+    /// up to `max` (defaults to `100 ms` and `5 sec`).
+    pub fn backoff(self, base: Duration, max: Duration) -> Self {
+        Self {
+            backoff_base: base,
+            backoff_max: max,
+            ..self
+        }
+    }
+
+    /// Errors the query is retried on (defaults to network errors and timeouts).
+    pub fn retry_on<F>(self, retry_on: F) -> Self
+    where
+        F: Fn(&Error) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            retry_on: Some(Arc::new(retry_on)),
+            ..self
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.max_attempts > 1
+    }
+
+    /// Returns `true` if another attempt follows the failed `attempt` (1-based).
+    pub(crate) fn should_retry(&self, attempt: usize, err: &Error) -> bool {
+        attempt < self.max_attempts
+            && match self.retry_on {
+                Some(ref retry_on) => retry_on(err),
+                None => is_transient(err),
+            }
+    }
+
+    /// Delay after the failed `attempt` (1-based).
+    pub(crate) fn delay(&self, attempt: usize) -> Duration {
+        let exp = cmp::min(attempt.saturating_sub(1), 31) as u32;
+        cmp::min(
+            self.backoff_base.saturating_mul(1 << exp),
+            self.backoff_max,
+        )
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff_base", &self.backoff_base)
+            .field("backoff_max", &self.backoff_max)
+            .finish()
+    }
+}
+
+impl PartialEq for RetryPolicy {
+    fn eq(&self, other: &Self) -> bool {
+        self.max_attempts == other.max_attempts
+            && self.backoff_base == other.backoff_base
+            && self.backoff_max == other.backoff_max
+            && match (&self.retry_on, &other.retry_on) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
+}
+
+/// Default predicate of `RetryPolicy`, matches network errors and timeouts.
+pub(crate) fn is_transient(err: &Error) -> bool {
+    match err {
+        Error::Io(_) | Error::Driver(DriverError::Timeout) => true,
+        Error::Server(err) => TRANSIENT_SERVER_ERRORS.contains(&err.code),
+        _ => false,
+    }
+}
+
+/// Returns `true` if the statement only reads data.
+pub(crate) fn is_read_only(sql: &str) -> bool {
+    let keyword = sql
+        .trim_start_matches(|ch: char| ch.is_whitespace() || ch == '(')
+        .split(|ch: char| !ch.is_ascii_alphabetic())
+        .next()
+        .unwrap_or("");
+
+    ["SELECT", "WITH", "SHOW", "DESCRIBE", "DESC", "EXISTS", "EXPLAIN"]
+        .iter()
+        .any(|read_only| keyword.eq_ignore_ascii_case(read_only))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use crate::errors::ServerError;
+
+    use super::*;
+
+    #[test]
+    fn test_should_retry() {
+        let policy = RetryPolicy::new(3);
+        let io_error = Error::Io(io::Error::from(io::ErrorKind::ConnectionReset));
+
+        assert!(policy.should_retry(1, &io_error));
+        assert!(policy.should_retry(2, &io_error));
+        assert!(!policy.should_retry(3, &io_error));
+        assert!(!policy.should_retry(1, &Error::Driver(DriverError::NoRows)));
+        assert!(!RetryPolicy::none().should_retry(1, &io_error));
+
+        let server_error = |code| {
+            Error::Server(ServerError {
+                code,
+                name: "DB::Exception".into(),
+                message: "".into(),
+                stack_trace: "".into(),
+            })
+        };
+        assert!(policy.should_retry(1, &server_error(210)));
+        assert!(!policy.should_retry(1, &server_error(60)));
+
+        let policy = policy.retry_on(|_| true);
+        assert!(policy.should_retry(1, &server_error(60)));
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy =
+            RetryPolicy::new(10).backoff(Duration::from_millis(100), Duration::from_secs(1));
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(5), Duration::from_secs(1));
+        assert_eq!(policy.delay(usize::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_is_read_only() {
+        assert!(is_read_only("SELECT 1"));
+        assert!(is_read_only("  with x AS (SELECT 1) SELECT * FROM x"));
+        assert!(is_read_only("(SELECT 1) UNION ALL (SELECT 2)"));
+        assert!(is_read_only("DESC events"));
+        assert!(!is_read_only("INSERT INTO events SELECT 1"));
+        assert!(!is_read_only("ALTER TABLE events DELETE WHERE 1"));
+        assert!(!is_read_only("SELECTED"));
+    }
+}
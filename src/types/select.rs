@@ -0,0 +1,341 @@
+use std::{fmt, ops};
+
+use crate::{
+    escape::{quote_identifier, quote_table},
+    types::{Param, Query, ToSql},
+};
+
+/// Expression used in a `Select`, e.g. a column or a condition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr(String);
+
+/// Returns an expression referencing the column `name`.
+///
+/// The name is quoted, so any column name is safe to use here.
+pub fn col(name: impl AsRef<str>) -> Expr {
+    Expr(quote_identifier(name.as_ref()))
+}
+
+/// Returns an expression from raw SQL, it's written to the query as is.
+pub fn raw(sql: impl Into<String>) -> Expr {
+    Expr(sql.into())
+}
+
+impl Expr {
+    fn literal<T: ToSql + ?Sized>(value: &T) -> String {
+        Param::new(value).as_str().to_string()
+    }
+
+    fn binary<T: ToSql + ?Sized>(self, op: &str, value: &T) -> Self {
+        Expr(format!("{} {} {}", self.0, op, Self::literal(value)))
+    }
+
+    /// `self = value`
+    pub fn eq<T: ToSql + ?Sized>(self, value: &T) -> Self {
+        self.binary("=", value)
+    }
+
+    /// `self != value`
+    pub fn ne<T: ToSql + ?Sized>(self, value: &T) -> Self {
+        self.binary("!=", value)
+    }
+
+    /// `self < value`
+    pub fn lt<T: ToSql + ?Sized>(self, value: &T) -> Self {
+        self.binary("<", value)
+    }
+
+    /// `self <= value`
+    pub fn le<T: ToSql + ?Sized>(self, value: &T) -> Self {
+        self.binary("<=", value)
+    }
+
+    /// `self > value`
+    pub fn gt<T: ToSql + ?Sized>(self, value: &T) -> Self {
+        self.binary(">", value)
+    }
+
+    /// `self >= value`
+    pub fn ge<T: ToSql + ?Sized>(self, value: &T) -> Self {
+        self.binary(">=", value)
+    }
+
+    /// `self LIKE pattern`
+    pub fn like(self, pattern: &str) -> Self {
+        self.binary("LIKE", pattern)
+    }
+
+    /// `self IN (values...)`, an empty list is never matched.
+    pub fn is_in<T: ToSql>(self, values: &[T]) -> Self {
+        if values.is_empty() {
+            return Expr("0".into());
+        }
+        let values: Vec<_> = values.iter().map(|v| Self::literal(v)).collect();
+        Expr(format!("{} IN ({})", self.0, values.join(", ")))
+    }
+
+    /// `self IS NULL`
+    pub fn is_null(self) -> Self {
+        Expr(format!("{} IS NULL", self.0))
+    }
+
+    /// `self IS NOT NULL`
+    pub fn is_not_null(self) -> Self {
+        Expr(format!("{} IS NOT NULL", self.0))
+    }
+
+    /// `(self) AND (other)`
+    pub fn and(self, other: Expr) -> Self {
+        Expr(format!("({}) AND ({})", self.0, other.0))
+    }
+
+    /// `(self) OR (other)`
+    pub fn or(self, other: Expr) -> Self {
+        Expr(format!("({}) OR ({})", self.0, other.0))
+    }
+
+    /// `self AS alias`
+    pub fn alias(self, alias: impl AsRef<str>) -> Self {
+        Expr(format!(
+            "{} AS {}",
+            self.0,
+            quote_identifier(alias.as_ref())
+        ))
+    }
+}
+
+impl ops::Not for Expr {
+    type Output = Self;
+
+    /// `NOT (self)`
+    fn not(self) -> Self {
+        Expr(format!("NOT ({})", self.0))
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Expr {
+    fn from(name: &str) -> Self {
+        col(name)
+    }
+}
+
+impl From<String> for Expr {
+    fn from(name: String) -> Self {
+        col(name)
+    }
+}
+
+/// Builder of `SELECT` queries.
+///
+/// Identifiers are quoted and values are escaped, so the built SQL is safe
+/// to run whatever the input is. Use `raw` for the parts it can't express.
+///
+/// ```rust
+/// # use clickhouse_rs::types::{col, raw, Select};
+/// let query = Select::from("events")
+///     .columns(vec![col("kind"), raw("count()").alias("n")])
+///     .filter(col("user").eq("O'Brien"))
+///     .filter(col("id").gt(&5))
+///     .group_by(vec!["kind"])
+///     .order_by_desc("n")
+///     .limit(10);
+///
+/// assert_eq!(
+///     query.to_string(),
+///     "SELECT `kind`, count() AS `n` FROM `events` \
+///      WHERE (`user` = 'O\\'Brien') AND (`id` > 5) \
+///      GROUP BY `kind` ORDER BY `n` DESC LIMIT 10"
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Select {
+    table: String,
+    columns: Vec<Expr>,
+    filters: Vec<Expr>,
+    group_by: Vec<Expr>,
+    order_by: Vec<(Expr, bool)>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+impl Select {
+    /// Starts a query from the `table`, `database.table` is also accepted.
+    pub fn from(table: impl AsRef<str>) -> Self {
+        Self {
+            table: quote_table(table.as_ref()),
+            columns: Vec::new(),
+            filters: Vec::new(),
+            group_by: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+        }
+    }
+
+    /// Selected columns or expressions, all columns if not given.
+    pub fn columns<I, E>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<Expr>,
+    {
+        self.columns.extend(columns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds a `WHERE` condition, several conditions are joined with `AND`.
+    pub fn filter(mut self, condition: Expr) -> Self {
+        self.filters.push(condition);
+        self
+    }
+
+    pub fn group_by<I, E>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<Expr>,
+    {
+        self.group_by.extend(columns.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn order_by(mut self, column: impl Into<Expr>) -> Self {
+        self.order_by.push((column.into(), false));
+        self
+    }
+
+    pub fn order_by_desc(mut self, column: impl Into<Expr>) -> Self {
+        self.order_by.push((column.into(), true));
+        self
+    }
+
+    pub fn limit(self, limit: u64) -> Self {
+        Self {
+            limit: Some(limit),
+            ..self
+        }
+    }
+
+    pub fn offset(self, offset: u64) -> Self {
+        Self {
+            offset: Some(offset),
+            ..self
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter, items: &[Expr]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Select {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SELECT ")?;
+        if self.columns.is_empty() {
+            f.write_str("*")?;
+        } else {
+            write_list(f, &self.columns)?;
+        }
+        write!(f, " FROM {}", self.table)?;
+
+        match self.filters.as_slice() {
+            [] => {}
+            [filter] => write!(f, " WHERE {}", filter)?,
+            filters => {
+                f.write_str(" WHERE ")?;
+                for (i, filter) in filters.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" AND ")?;
+                    }
+                    write!(f, "({})", filter)?;
+                }
+            }
+        }
+
+        if !self.group_by.is_empty() {
+            f.write_str(" GROUP BY ")?;
+            write_list(f, &self.group_by)?;
+        }
+
+        for (i, (column, desc)) in self.order_by.iter().enumerate() {
+            f.write_str(if i == 0 { " ORDER BY " } else { ", " })?;
+            write!(f, "{}", column)?;
+            if *desc {
+                f.write_str(" DESC")?;
+            }
+        }
+
+        if let Some(limit) = self.limit {
+            write!(f, " LIMIT {}", limit)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " OFFSET {}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Select> for Query {
+    fn from(select: Select) -> Self {
+        Query::new(select.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_select_all() {
+        assert_eq!(Select::from("t").to_string(), "SELECT * FROM `t`");
+        assert_eq!(
+            Select::from("db.t").limit(10).offset(20).to_string(),
+            "SELECT * FROM `db`.`t` LIMIT 10 OFFSET 20"
+        );
+    }
+
+    #[test]
+    fn test_select_quoting() {
+        let query = Select::from("t`x")
+            .columns(vec!["a`b"])
+            .filter(col("name").eq("'; DROP TABLE t; --"));
+        assert_eq!(
+            query.to_string(),
+            r"SELECT `a\`b` FROM `t\`x` WHERE `name` = '\'; DROP TABLE t; --'"
+        );
+    }
+
+    #[test]
+    fn test_conditions() {
+        let query = Select::from("t").filter(
+            col("a")
+                .is_in(&[1, 2])
+                .or(!col("b").is_null())
+                .and(col("c").like("x%")),
+        );
+        assert_eq!(
+            query.to_string(),
+            "SELECT * FROM `t` WHERE ((`a` IN (1, 2)) OR (NOT (`b` IS NULL))) AND (`c` LIKE 'x%')"
+        );
+        assert_eq!(col("a").is_in::<u8>(&[]).to_string(), "0");
+    }
+
+    #[test]
+    fn test_order_by() {
+        let query = Select::from("t").order_by("a").order_by_desc("b");
+        assert_eq!(
+            query.to_string(),
+            "SELECT * FROM `t` ORDER BY `a`, `b` DESC"
+        );
+    }
+}
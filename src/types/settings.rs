@@ -0,0 +1,430 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crate::{
+    binary::Encoder,
+    errors::{DriverError, Result},
+};
+
+/// Value of a ClickHouse setting.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingValue {
+    UInt64(u64),
+    Int64(i64),
+    Float64(f64),
+    Bool(bool),
+    String(String),
+}
+
+/// ClickHouse settings, indexed by name.
+pub type Settings = BTreeMap<String, SettingValue>;
+
+macro_rules! setting_value_from {
+    ( $( $t:ty => $k:ident as $c:ty ),* ) => {
+        $(
+            impl From<$t> for SettingValue {
+                fn from(value: $t) -> Self {
+                    SettingValue::$k(value as $c)
+                }
+            }
+        )*
+    };
+}
+
+setting_value_from! {
+    u8 => UInt64 as u64,
+    u16 => UInt64 as u64,
+    u32 => UInt64 as u64,
+    u64 => UInt64 as u64,
+    usize => UInt64 as u64,
+    i8 => Int64 as i64,
+    i16 => Int64 as i64,
+    i32 => Int64 as i64,
+    i64 => Int64 as i64,
+    f32 => Float64 as f64,
+    f64 => Float64 as f64
+}
+
+impl From<bool> for SettingValue {
+    fn from(value: bool) -> Self {
+        SettingValue::Bool(value)
+    }
+}
+
+impl From<&str> for SettingValue {
+    fn from(value: &str) -> Self {
+        SettingValue::String(value.to_string())
+    }
+}
+
+impl From<String> for SettingValue {
+    fn from(value: String) -> Self {
+        SettingValue::String(value)
+    }
+}
+
+impl fmt::Display for SettingValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingValue::UInt64(v) => fmt::Display::fmt(v, f),
+            SettingValue::Int64(v) => fmt::Display::fmt(v, f),
+            SettingValue::Float64(v) => fmt::Display::fmt(v, f),
+            SettingValue::Bool(v) => write!(f, "{}", *v as u8),
+            SettingValue::String(v) => fmt::Display::fmt(v, f),
+        }
+    }
+}
+
+/// Wire representation of a setting for the binary settings serialization.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum SettingType {
+    UInt64,
+    Int64,
+    Float,
+    Bool,
+    String,
+}
+
+const UINT64_SETTINGS: &[&str] = &[
+    "async_insert_busy_timeout_ms",
+    "async_insert_max_data_size",
+    "background_pool_size",
+    "background_schedule_pool_size",
+    "connect_timeout",
+    "connect_timeout_with_failover_ms",
+    "connections_with_failover_max_tries",
+    "distributed_connections_pool_size",
+    "distributed_ddl_task_timeout",
+    "group_by_two_level_threshold",
+    "group_by_two_level_threshold_bytes",
+    "http_connection_timeout",
+    "http_receive_timeout",
+    "http_send_timeout",
+    "insert_quorum",
+    "insert_quorum_timeout",
+    "interactive_delay",
+    "live_view_heartbeat_interval",
+    "log_queries_cut_to_length",
+    "mark_cache_min_lifetime",
+    "max_ast_depth",
+    "max_ast_elements",
+    "max_block_size",
+    "max_bytes_before_external_group_by",
+    "max_bytes_before_external_sort",
+    "max_bytes_before_remerge_sort",
+    "max_bytes_in_distinct",
+    "max_bytes_in_join",
+    "max_bytes_in_set",
+    "max_bytes_to_read",
+    "max_bytes_to_sort",
+    "max_bytes_to_transfer",
+    "max_columns_to_read",
+    "max_compress_block_size",
+    "max_concurrent_queries_for_user",
+    "max_distributed_connections",
+    "max_distributed_processing_threads",
+    "max_execution_time",
+    "max_expanded_ast_elements",
+    "max_insert_block_size",
+    "max_insert_threads",
+    "max_memory_usage",
+    "max_memory_usage_for_all_queries",
+    "max_memory_usage_for_user",
+    "max_network_bandwidth",
+    "max_network_bandwidth_for_user",
+    "max_network_bytes",
+    "max_parser_depth",
+    "max_partitions_per_insert_block",
+    "max_query_size",
+    "max_read_buffer_size",
+    "max_replica_delay_for_distributed_queries",
+    "max_result_bytes",
+    "max_result_rows",
+    "max_rows_in_distinct",
+    "max_rows_in_join",
+    "max_rows_in_set",
+    "max_rows_to_group_by",
+    "max_rows_to_read",
+    "max_rows_to_sort",
+    "max_rows_to_transfer",
+    "max_subquery_depth",
+    "max_temporary_columns",
+    "max_temporary_non_const_columns",
+    "max_threads",
+    "merge_tree_coarse_index_granularity",
+    "merge_tree_max_rows_to_use_cache",
+    "merge_tree_min_rows_for_concurrent_read",
+    "merge_tree_min_rows_for_seek",
+    "min_bytes_to_use_direct_io",
+    "min_compress_block_size",
+    "min_count_to_compile",
+    "min_execution_speed",
+    "min_insert_block_size_bytes",
+    "min_insert_block_size_rows",
+    "mutations_sync",
+    "optimize_min_equality_disjunction_chain_length",
+    "parallel_replicas_count",
+    "parallel_replica_offset",
+    "partial_merge_join_rows_in_right_blocks",
+    "poll_interval",
+    "preferred_block_size_bytes",
+    "preferred_max_column_in_block_size_bytes",
+    "priority",
+    "readonly",
+    "queue_max_wait_ms",
+    "receive_timeout",
+    "replication_alter_partitions_sync",
+    "send_timeout",
+    "tcp_keep_alive_timeout",
+    "timeout_before_checking_execution_speed",
+    "replication_alter_columns_timeout",
+    "wait_for_async_insert_timeout",
+];
+
+const INT64_SETTINGS: &[&str] = &["network_zstd_compression_level", "os_thread_priority"];
+
+const FLOAT_SETTINGS: &[&str] = &[
+    "input_format_allow_errors_ratio",
+    "max_streams_multiplier_for_merge_tables",
+    "max_streams_to_max_threads_ratio",
+    "memory_profiler_sample_probability",
+    "totals_auto_threshold",
+];
+
+const BOOL_SETTINGS: &[&str] = &[
+    "add_http_cors_header",
+    "allow_ddl",
+    "allow_experimental_live_view",
+    "allow_experimental_low_cardinality_type",
+    "allow_introspection_functions",
+    "allow_simdjson",
+    "allow_suspicious_low_cardinality_types",
+    "asterisk_left_columns_only",
+    "async_insert",
+    "calculate_text_stack_trace",
+    "compile",
+    "compile_expressions",
+    "distributed_aggregation_memory_efficient",
+    "distributed_group_by_no_merge",
+    "empty_result_for_aggregation_by_empty_set",
+    "enable_http_compression",
+    "enable_optimize_predicate_expression",
+    "extremes",
+    "fallback_to_stale_replicas_for_distributed_queries",
+    "force_index_by_date",
+    "force_primary_key",
+    "format_csv_allow_double_quotes",
+    "format_csv_allow_single_quotes",
+    "http_native_compression_disable_checksumming_on_decompress",
+    "input_format_defaults_for_omitted_fields",
+    "input_format_import_nested_json",
+    "input_format_null_as_default",
+    "input_format_skip_unknown_fields",
+    "input_format_values_interpret_expressions",
+    "insert_allow_materialized_columns",
+    "insert_deduplicate",
+    "insert_distributed_sync",
+    "join_any_take_last_row",
+    "join_use_nulls",
+    "joined_subquery_requires_alias",
+    "log_profile_events",
+    "log_queries",
+    "log_query_settings",
+    "log_query_threads",
+    "low_cardinality_allow_in_native_format",
+    "merge_tree_uniform_read_distribution",
+    "optimize_move_to_prewhere",
+    "optimize_skip_unused_shards",
+    "optimize_throw_if_noop",
+    "output_format_json_escape_forward_slashes",
+    "output_format_json_quote_64bit_integers",
+    "output_format_json_quote_denormals",
+    "output_format_write_statistics",
+    "parallel_view_processing",
+    "prefer_localhost_replica",
+    "select_sequential_consistency",
+    "skip_unavailable_shards",
+    "use_client_time_zone",
+    "use_index_for_in_with_subqueries",
+    "use_uncompressed_cache",
+    "wait_for_async_insert",
+];
+
+const STRING_SETTINGS: &[&str] = &[
+    "count_distinct_implementation",
+    "date_time_input_format",
+    "distributed_ddl_output_mode",
+    "distributed_product_mode",
+    "format_csv_delimiter",
+    "format_schema",
+    "insert_deduplication_token",
+    "join_algorithm",
+    "join_default_strictness",
+    "load_balancing",
+    "log_comment",
+    "network_compression_method",
+    "overflow_mode",
+    "read_overflow_mode",
+    "result_overflow_mode",
+    "send_logs_level",
+    "set_overflow_mode",
+    "sort_overflow_mode",
+    "timeout_overflow_mode",
+    "totals_mode",
+    "transfer_overflow_mode",
+    "distinct_overflow_mode",
+    "group_by_overflow_mode",
+    "join_overflow_mode",
+];
+
+/// Returns `true` if `name` is a setting known to this driver.
+pub(crate) fn is_known(name: &str) -> bool {
+    setting_type(name).is_some()
+}
+
+fn setting_type(name: &str) -> Option<SettingType> {
+    let catalog = [
+        (UINT64_SETTINGS, SettingType::UInt64),
+        (INT64_SETTINGS, SettingType::Int64),
+        (FLOAT_SETTINGS, SettingType::Float),
+        (BOOL_SETTINGS, SettingType::Bool),
+        (STRING_SETTINGS, SettingType::String),
+    ];
+
+    catalog
+        .iter()
+        .find(|(names, _)| names.contains(&name))
+        .map(|(_, setting_type)| *setting_type)
+}
+
+fn parse_bool(source: &str) -> Option<bool> {
+    match source {
+        "1" | "true" => Some(true),
+        "0" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn invalid_value(name: &str, value: &SettingValue) -> DriverError {
+    DriverError::InvalidSettingValue {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// Checks that `name` is a known setting and `value` fits its type.
+pub(crate) fn validate(name: &str, value: &SettingValue) -> Result<()> {
+    write(&mut Encoder::new(), name, value)
+}
+
+/// Writes the setting in the binary (pre-strings) settings serialization
+/// format: the name followed by the value in the setting's own type.
+pub(crate) fn write(encoder: &mut Encoder, name: &str, value: &SettingValue) -> Result<()> {
+    let setting_type = match setting_type(name) {
+        Some(setting_type) => setting_type,
+        None => {
+            return Err(DriverError::UnknownSetting {
+                name: name.to_string(),
+            }
+            .into())
+        }
+    };
+
+    encoder.string(name);
+
+    match setting_type {
+        SettingType::UInt64 => {
+            let v = match value {
+                SettingValue::UInt64(v) => Some(*v),
+                SettingValue::Int64(v) if *v >= 0 => Some(*v as u64),
+                SettingValue::Bool(v) => Some(*v as u64),
+                SettingValue::String(v) => u64::from_str(v).ok(),
+                _ => None,
+            };
+            encoder.uvarint(v.ok_or_else(|| invalid_value(name, value))?);
+        }
+        SettingType::Int64 => {
+            let v = match value {
+                SettingValue::Int64(v) => Some(*v),
+                SettingValue::UInt64(v) if *v <= i64::MAX as u64 => Some(*v as i64),
+                SettingValue::String(v) => i64::from_str(v).ok(),
+                _ => None,
+            };
+            let v = v.ok_or_else(|| invalid_value(name, value))?;
+            encoder.uvarint(((v << 1) ^ (v >> 63)) as u64);
+        }
+        SettingType::Bool => {
+            let v = match value {
+                SettingValue::Bool(v) => Some(*v),
+                SettingValue::UInt64(v) if *v <= 1 => Some(*v == 1),
+                SettingValue::Int64(v) if *v == 0 || *v == 1 => Some(*v == 1),
+                SettingValue::String(v) => parse_bool(v),
+                _ => None,
+            };
+            encoder.uvarint(v.ok_or_else(|| invalid_value(name, value))? as u64);
+        }
+        SettingType::Float => {
+            let valid = match value {
+                SettingValue::Float64(_) | SettingValue::UInt64(_) | SettingValue::Int64(_) => {
+                    true
+                }
+                SettingValue::String(v) => f64::from_str(v).is_ok(),
+                SettingValue::Bool(_) => false,
+            };
+            if !valid {
+                return Err(invalid_value(name, value).into());
+            }
+            encoder.string(value.to_string());
+        }
+        SettingType::String => encoder.string(value.to_string()),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encode(name: &str, value: impl Into<SettingValue>) -> Result<Vec<u8>> {
+        let mut encoder = Encoder::new();
+        write(&mut encoder, name, &value.into())?;
+        Ok(encoder.get_buffer())
+    }
+
+    #[test]
+    fn test_write_uint64() {
+        let actual = encode("max_threads", 300_u64).unwrap();
+        let mut expected = vec![11_u8];
+        expected.extend_from_slice(b"max_threads");
+        expected.extend_from_slice(&[0xac, 0x02]);
+        assert_eq!(actual, expected);
+
+        assert_eq!(encode("max_threads", "300").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_write_int64() {
+        let actual = encode("os_thread_priority", -1_i64).unwrap();
+        assert_eq!(actual.last(), Some(&1_u8));
+    }
+
+    #[test]
+    fn test_write_bool() {
+        assert_eq!(encode("join_use_nulls", true).unwrap().last(), Some(&1_u8));
+        assert_eq!(encode("join_use_nulls", "0").unwrap().last(), Some(&0_u8));
+        encode("join_use_nulls", 2_u64).unwrap_err();
+    }
+
+    #[test]
+    fn test_write_string() {
+        let actual = encode("load_balancing", "random").unwrap();
+        assert!(actual.ends_with(b"\x06random"));
+    }
+
+    #[test]
+    fn test_unknown_setting() {
+        encode("no_such_setting", 1_u64).unwrap_err();
+        assert!(!is_known("no_such_setting"));
+        assert!(is_known("max_memory_usage"));
+    }
+}
@@ -0,0 +1,121 @@
+use std::{fmt, time::Duration};
+
+use crate::{
+    escape::{quote_identifier, quote_table},
+    types::Query,
+};
+
+/// `SYSTEM` statement, run with `ClientHandle::system`.
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use clickhouse_rs::types::SystemCommand;
+/// let command = SystemCommand::SyncReplica {
+///     table: "db.events".into(),
+///     timeout: Some(Duration::from_secs(60)),
+/// };
+/// assert_eq!(command.to_string(), "SYSTEM SYNC REPLICA `db`.`events`");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum SystemCommand {
+    /// Reloads all dictionaries.
+    ReloadDictionaries,
+    /// Reloads the dictionary with the given name.
+    ReloadDictionary(String),
+    /// Flushes buffered system log tables (`query_log` etc).
+    FlushLogs,
+    /// Drops the DNS cache.
+    DropDnsCache,
+    /// Stops background merges of the table, or of all tables if `None`.
+    StopMerges(Option<String>),
+    /// Starts background merges of the table, or of all tables if `None`.
+    StartMerges(Option<String>),
+    /// Waits until the replicated table is in sync with the other replicas.
+    ///
+    /// If `timeout` is given, the command fails with a timeout error
+    /// when the replica doesn't catch up in time.
+    SyncReplica {
+        table: String,
+        timeout: Option<Duration>,
+    },
+}
+
+impl SystemCommand {
+    pub(crate) fn timeout(&self) -> Option<Duration> {
+        match self {
+            SystemCommand::SyncReplica { timeout, .. } => *timeout,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SystemCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SYSTEM ")?;
+        match self {
+            SystemCommand::ReloadDictionaries => f.write_str("RELOAD DICTIONARIES"),
+            SystemCommand::ReloadDictionary(name) => {
+                write!(f, "RELOAD DICTIONARY {}", quote_identifier(name))
+            }
+            SystemCommand::FlushLogs => f.write_str("FLUSH LOGS"),
+            SystemCommand::DropDnsCache => f.write_str("DROP DNS CACHE"),
+            SystemCommand::StopMerges(None) => f.write_str("STOP MERGES"),
+            SystemCommand::StopMerges(Some(table)) => {
+                write!(f, "STOP MERGES {}", quote_table(table))
+            }
+            SystemCommand::StartMerges(None) => f.write_str("START MERGES"),
+            SystemCommand::StartMerges(Some(table)) => {
+                write!(f, "START MERGES {}", quote_table(table))
+            }
+            SystemCommand::SyncReplica { table, .. } => {
+                write!(f, "SYNC REPLICA {}", quote_table(table))
+            }
+        }
+    }
+}
+
+impl From<SystemCommand> for Query {
+    fn from(command: SystemCommand) -> Self {
+        let query = Query::new(command.to_string());
+        match command.timeout() {
+            // The server gives up waiting for the replica after `receive_timeout`.
+            Some(timeout) => query.setting("receive_timeout", timeout.as_secs().max(1)),
+            None => query,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_system_sql() {
+        assert_eq!(
+            SystemCommand::ReloadDictionaries.to_string(),
+            "SYSTEM RELOAD DICTIONARIES"
+        );
+        assert_eq!(
+            SystemCommand::ReloadDictionary("geo".into()).to_string(),
+            "SYSTEM RELOAD DICTIONARY `geo`"
+        );
+        assert_eq!(SystemCommand::FlushLogs.to_string(), "SYSTEM FLUSH LOGS");
+        assert_eq!(
+            SystemCommand::StopMerges(Some("db.t".into())).to_string(),
+            "SYSTEM STOP MERGES `db`.`t`"
+        );
+        assert_eq!(
+            SystemCommand::StartMerges(None).to_string(),
+            "SYSTEM START MERGES"
+        );
+    }
+
+    #[test]
+    fn test_sync_replica_timeout() {
+        let query = Query::from(SystemCommand::SyncReplica {
+            table: "t".into(),
+            timeout: Some(Duration::from_secs(30)),
+        });
+        assert!(query.get_settings().contains_key("receive_timeout"));
+    }
+}
@@ -0,0 +1,89 @@
+use tokio::prelude::*;
+
+use crate::{
+    io::BoxFuture,
+    types::{Block, Complex, Query, RetryPolicy},
+    ClientHandle,
+};
+
+/// Experimental ClickHouse transaction, see `ClientHandle::begin_transaction`.
+///
+/// Statements of the transaction are sent through its connection and
+/// never retried. A transaction that is dropped without `commit` or
+/// `rollback` closes its connection, so the server rolls it back.
+pub struct Transaction {
+    client: Option<ClientHandle>,
+}
+
+impl Transaction {
+    pub(crate) fn new(client: ClientHandle) -> Self {
+        Self {
+            client: Some(client),
+        }
+    }
+
+    /// Executes a statement within the transaction.
+    pub fn execute<Q>(mut self, sql: Q) -> BoxFuture<Self>
+    where
+        Query: From<Q>,
+    {
+        let query = Query::from(sql).retry(RetryPolicy::none());
+        Box::new(
+            self.take_client()
+                .execute::<Query>(query)
+                .map(Transaction::new),
+        )
+    }
+
+    /// Runs a query within the transaction and returns all rows.
+    pub fn query<Q>(mut self, sql: Q) -> BoxFuture<(Self, Block<Complex>)>
+    where
+        Query: From<Q>,
+    {
+        let query = Query::from(sql).retry(RetryPolicy::none());
+        Box::new(
+            self.take_client()
+                .query::<Query>(query)
+                .fetch_all()
+                .map(|(c, block)| (Transaction::new(c), block)),
+        )
+    }
+
+    /// Inserts the block within the transaction.
+    pub fn insert<Q>(mut self, table: Q, block: Block) -> BoxFuture<Self>
+    where
+        Query: From<Q>,
+    {
+        let query = Query::from(table).retry(RetryPolicy::none());
+        Box::new(
+            self.take_client()
+                .insert::<Query>(query, block)
+                .map(Transaction::new),
+        )
+    }
+
+    /// Commits the transaction.
+    pub fn commit(mut self) -> BoxFuture<ClientHandle> {
+        self.take_client().execute("COMMIT")
+    }
+
+    /// Rolls the transaction back.
+    pub fn rollback(mut self) -> BoxFuture<ClientHandle> {
+        self.take_client().execute("ROLLBACK")
+    }
+
+    fn take_client(&mut self) -> ClientHandle {
+        self.client.take().unwrap()
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if let Some(mut client) = self.client.take() {
+            // A detached connection is closed instead of returning to the pool.
+            if client.pool.is_attached() {
+                client.pool.detach();
+            }
+        }
+    }
+}
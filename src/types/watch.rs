@@ -0,0 +1,92 @@
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Poll, Stream};
+use tokio_timer::Delay;
+
+use crate::{
+    errors::{DriverError, Error},
+    io::transport::PacketStream,
+    pool::PoolBinding,
+    types::{Block, Context, Packet},
+    ClientHandle,
+};
+
+/// Column of a `WATCH` result with the version of the live view.
+const VERSION_COLUMN: &str = "_version";
+
+/// Updates of a live view, see `ClientHandle::watch`.
+///
+/// The server sends an empty block every heartbeat interval while the view
+/// doesn't change, the stream fails with a timeout error if nothing is
+/// received for two intervals.
+pub(crate) struct Watch {
+    inner: PacketStream,
+    rest: Option<(Context, PoolBinding)>,
+    liveness: Duration,
+    timer: Delay,
+}
+
+impl Watch {
+    pub(crate) fn new(
+        inner: PacketStream,
+        context: Context,
+        pool: PoolBinding,
+        heartbeat: Duration,
+    ) -> Self {
+        let liveness = heartbeat * 2;
+        Self {
+            inner,
+            rest: Some((context, pool)),
+            liveness,
+            timer: Delay::new(Instant::now() + liveness),
+        }
+    }
+}
+
+impl Stream for Watch {
+    type Item = (u64, Block);
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            let packet = match self.inner.poll()? {
+                Async::Ready(Some(packet)) => packet,
+                Async::Ready(None) => return Ok(Async::Ready(None)),
+                Async::NotReady => {
+                    if self.timer.poll()?.is_ready() {
+                        warn!("[watch] no heartbeat from the server");
+                        return Err(DriverError::Timeout.into());
+                    }
+                    return Ok(Async::NotReady);
+                }
+            };
+
+            self.timer.reset(Instant::now() + self.liveness);
+
+            match packet {
+                Packet::Block(block) => {
+                    // The header and heartbeats are empty.
+                    if !block.is_empty() {
+                        let version = block.get(0, VERSION_COLUMN)?;
+                        return Ok(Async::Ready(Some((version, block))));
+                    }
+                }
+                Packet::Eof(inner) => {
+                    // `WATCH ... LIMIT n` ends after `n` updates.
+                    let (context, pool) = self.rest.take().unwrap();
+                    let mut client = ClientHandle {
+                        inner: Some(inner),
+                        context,
+                        pool,
+                    };
+                    if !client.pool.is_attached() {
+                        client.pool.attach();
+                    }
+                }
+                Packet::ProfileInfo(_) | Packet::Progress(_) => {}
+                Packet::Exception(exception) => return Err(Error::Server(exception)),
+                _ => return Err(Error::Driver(DriverError::UnexpectedPacket)),
+            }
+        }
+    }
+}
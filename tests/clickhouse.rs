@@ -7,10 +7,12 @@ use std::{
     env,
     f64::EPSILON,
     fmt::Debug,
+    ops::ControlFlow,
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
+    time::Duration,
 };
 
 use chrono::prelude::*;
@@ -18,7 +20,21 @@ use chrono_tz::Tz::{self, UTC};
 use tokio::prelude::*;
 
 use clickhouse_rs::{
-    errors::Error, types::Block, types::Decimal, types::FromSql, ClientHandle, Pool,
+    errors::{DriverError, Error},
+    params,
+    types::Block,
+    types::Decimal,
+    types::DefaultKind,
+    types::FromSql,
+    types::InsertRow,
+    types::Optimize,
+    types::Param,
+    types::Query,
+    types::RetryPolicy,
+    types::SystemCommand,
+    types::SqlType,
+    types::Value,
+    query, ClientHandle, Pool, Row,
 };
 
 type BoxFuture<T> = Box<dyn Future<Item = T, Error = Error> + Send>;
@@ -727,6 +743,1000 @@ fn test_reconnect() {
     assert_eq!(2, counter.load(Ordering::SeqCst))
 }
 
+#[test]
+fn test_sticky_handle() {
+    let pool = Pool::new(database_url());
+
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("SET max_block_size = 1234"));
+    let c = run(done).unwrap();
+
+    // The connection is bound to a finished runtime, but it already holds
+    // session state, so it must not be silently replaced by a new one.
+    let done = c.query("SELECT 1").fetch_all();
+    run(done).unwrap_err();
+}
+
+#[test]
+fn test_use_database() {
+    let url = format!("{}{}", database_url(), "&pool_max=1&pool_min=1");
+    let pool = Pool::new(url);
+
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.query("SELECT currentDatabase()").fetch_all())
+        .and_then(|(c, block)| {
+            let default: String = block.get(0, 0)?;
+            Ok((c, default))
+        })
+        .and_then(|(c, default)| {
+            c.use_database("system")
+                .and_then(|c| c.query("SELECT currentDatabase()").fetch_all())
+                .and_then(|(_, block)| {
+                    let current: &str = block.get(0, 0)?;
+                    assert_eq!(current, "system");
+                    Ok(default)
+                })
+        })
+        .and_then(move |default| {
+            pool.get_handle()
+                .and_then(|c| c.query("SELECT currentDatabase()").fetch_all())
+                .and_then(move |(_, block)| {
+                    let current: String = block.get(0, 0)?;
+                    assert_eq!(current, default);
+                    Ok(())
+                })
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_set_role() {
+    let url = format!("{}{}", database_url(), "&pool_max=1&pool_min=1");
+    let pool = Pool::new(url);
+
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.set_role::<&str>(&[]))
+        .and_then(|c| c.query("SELECT count() FROM system.current_roles").fetch_all())
+        .and_then(|(_, block)| {
+            let count: u64 = block.get(0, 0)?;
+            assert_eq!(count, 0);
+            Ok(())
+        })
+        .and_then(move |_| pool.get_handle().and_then(|c| c.ping()))
+        .map(|_| ());
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_query_with_params() {
+    let pool = Pool::new(database_url());
+
+    let done = pool
+        .get_handle()
+        .and_then(|c| {
+            c.query_with_params(
+                "SELECT ? AS id, ? AS name, ? AS tags",
+                params!(42_u32, "O'Brien", vec!["a", "b?"]),
+            )
+            .fetch_all()
+        })
+        .and_then(|(_, block)| {
+            let id: u32 = block.get(0, "id")?;
+            let name: &str = block.get(0, "name")?;
+            let tags: Vec<&str> = block.get(0, "tags")?;
+            assert_eq!(id, 42);
+            assert_eq!(name, "O'Brien");
+            assert_eq!(tags, vec!["a", "b?"]);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_query_in_list() {
+    let separator = if database_url().contains('?') { '&' } else { '?' };
+    let pool = Pool::new(format!("{}{}max_in_list_literals=2", database_url(), separator));
+    let sql = "SELECT count() FROM numbers(10) WHERE number IN (?)";
+
+    let done = pool
+        .get_handle()
+        .and_then(move |c| {
+            c.query_scalar::<u64, _>(Query::new(sql).params(vec![Param::in_list(vec![1_u64, 2])]))
+        })
+        .and_then(move |(c, count)| {
+            assert_eq!(count, 2);
+            let odd = vec![1_u64, 3, 5, 7, 9, 11];
+            c.query_scalar::<u64, _>(Query::new(sql).params(vec![Param::in_list(odd)]))
+        })
+        .and_then(|(_, count)| {
+            assert_eq!(count, 5);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_fetch_all_as() {
+    let pool = Pool::new(database_url());
+
+    let done = pool
+        .get_handle()
+        .and_then(|c| {
+            c.query(
+                "SELECT number, toString(number) AS name, \
+                 if(number % 2 = 0, NULL, number) AS odd \
+                 FROM system.numbers LIMIT 3",
+            )
+            .fetch_all_as::<(u64, String, Option<u64>)>()
+        })
+        .and_then(|(_, rows)| {
+            assert_eq!(
+                rows,
+                vec![
+                    (0, "0".to_string(), None),
+                    (1, "1".to_string(), Some(1)),
+                    (2, "2".to_string(), None),
+                ]
+            );
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_fetch_one() {
+    let pool = Pool::new(database_url());
+
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.query("SELECT 1 AS a, 'foo' AS b").fetch_one::<(u8, String)>())
+        .and_then(|(c, row)| {
+            assert_eq!(row, (1, "foo".to_string()));
+            c.query("SELECT 1 LIMIT 0").fetch_optional::<(u8,)>()
+        })
+        .and_then(|(c, row)| {
+            assert_eq!(row, None);
+            c.query("SELECT 1 LIMIT 0").fetch_one::<(u8,)>().then(|result| {
+                match result {
+                    Err(Error::Driver(DriverError::NoRows)) => (),
+                    _ => panic!("expected NoRows"),
+                }
+                Ok(())
+            })
+        });
+    run(done).unwrap();
+
+    let done = pool
+        .get_handle()
+        .and_then(|c| {
+            c.query("SELECT number FROM system.numbers LIMIT 2")
+                .fetch_optional::<(u64,)>()
+        })
+        .then(|result| {
+            match result {
+                Err(Error::Driver(DriverError::TooManyRows)) => (),
+                _ => panic!("expected TooManyRows"),
+            }
+            Ok::<_, Error>(())
+        });
+    run(done).unwrap();
+}
+
+#[derive(Row)]
+struct Payment {
+    customer_id: u32,
+    amount: u32,
+    #[clickhouse(rename = "account_name")]
+    account: Option<String>,
+    #[clickhouse(skip)]
+    #[allow(dead_code)]
+    note: String,
+}
+
+#[test]
+fn test_insert_aligns_columns() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_insert_align (
+            id   UInt32,
+            name String
+        ) Engine=Memory";
+
+    let block = Block::new()
+        .column("name", vec!["foo", "bar"])
+        .column("id", vec![1_u32, 2]);
+    let extra = Block::new()
+        .column("id", vec![3_u32])
+        .column("name", vec!["baz"])
+        .column("note", vec!["qux"]);
+    let partial = Block::new().column("id", vec![4_u32]);
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_insert_align"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(move |c| c.insert("clickhouse_test_insert_align", block))
+        .and_then(|c| {
+            c.query("SELECT id, name FROM clickhouse_test_insert_align ORDER BY id")
+                .fetch_all_as::<(u32, String)>()
+        })
+        .and_then(move |(c, rows)| {
+            assert_eq!(rows, vec![(1, "foo".to_string()), (2, "bar".to_string())]);
+            c.insert("clickhouse_test_insert_align", extra).then(|result| {
+                match result {
+                    Err(Error::Driver(DriverError::UnknownColumn { name })) => {
+                        assert_eq!(name, "note")
+                    }
+                    Err(err) => panic!("unexpected error {}", err),
+                    Ok(_) => panic!("extra column was inserted"),
+                }
+                Ok::<(), Error>(())
+            })
+        })
+        .and_then(move |_| pool.get_handle())
+        .and_then(move |c| {
+            c.insert("clickhouse_test_insert_align", partial).then(|result| {
+                match result {
+                    Err(Error::Driver(DriverError::MissingColumn { name })) => {
+                        assert_eq!(name, "name")
+                    }
+                    Err(err) => panic!("unexpected error {}", err),
+                    Ok(_) => panic!("block without a column was inserted"),
+                }
+                Ok::<(), Error>(())
+            })
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_insert_rows() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_insert_rows (
+            customer_id  UInt32,
+            amount       UInt32,
+            account_name Nullable(String)
+        ) Engine=Memory";
+
+    let rows = vec![
+        Payment {
+            customer_id: 1,
+            amount: 2,
+            account: None,
+            note: String::new(),
+        },
+        Payment {
+            customer_id: 3,
+            amount: 4,
+            account: Some("foo".into()),
+            note: String::new(),
+        },
+    ];
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_insert_rows"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(move |c| c.insert_rows("clickhouse_test_insert_rows", &rows))
+        .and_then(|c| {
+            c.query("SELECT customer_id, amount, account_name FROM clickhouse_test_insert_rows")
+                .fetch_all_as::<(u32, u32, Option<String>)>()
+        })
+        .and_then(|(_, rows)| {
+            assert_eq!(rows, vec![(1, 2, None), (3, 4, Some("foo".to_string()))]);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[derive(Row)]
+#[clickhouse(engine = "ReplacingMergeTree", order_by = "(user_id, time)")]
+#[clickhouse(partition_by = "toYYYYMM(time)")]
+struct Event {
+    user_id: u64,
+    time: DateTime<Tz>,
+    #[clickhouse(rename = "event_kind")]
+    kind: String,
+}
+
+#[test]
+fn test_create_table_sql() {
+    assert_eq!(
+        <Event as InsertRow>::create_table_sql("events"),
+        "CREATE TABLE `events` (`user_id` UInt64, `time` DateTime, `event_kind` String) \
+         ENGINE = ReplacingMergeTree PARTITION BY toYYYYMM(time) ORDER BY (user_id, time)"
+    );
+}
+
+#[test]
+fn test_create_table_from_row() {
+    let rows = vec![Event {
+        user_id: 1,
+        time: UTC.ymd(2019, 3, 14).and_hms(10, 20, 30),
+        kind: "click".into(),
+    }];
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_create_table_from_row"))
+        .and_then(|c| c.create_table::<Event>("clickhouse_test_create_table_from_row"))
+        .and_then(move |c| c.insert_rows("clickhouse_test_create_table_from_row", &rows))
+        .and_then(|c| {
+            c.query("SELECT user_id, event_kind FROM clickhouse_test_create_table_from_row")
+                .fetch_all_as::<(u64, String)>()
+        })
+        .and_then(|(_, rows)| {
+            assert_eq!(rows, vec![(1, "click".to_string())]);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_insert_iter() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_insert_iter (
+            customer_id  UInt32,
+            amount       UInt32,
+            account_name Nullable(String)
+        ) Engine=Memory";
+
+    let rows = (0..2500).map(|i| Payment {
+        customer_id: i,
+        amount: i * 2,
+        account: None,
+        note: String::new(),
+    });
+
+    let separator = if database_url().contains('?') { '&' } else { '?' };
+    let pool = Pool::new(format!("{}{}insert_block_size=1000", database_url(), separator));
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_insert_iter"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(move |c| c.insert_iter("clickhouse_test_insert_iter", rows))
+        .and_then(|c| {
+            c.query("SELECT count(), sum(amount) FROM clickhouse_test_insert_iter")
+                .fetch_one::<(u64, u64)>()
+        })
+        .and_then(|(_, (count, sum))| {
+            assert_eq!(count, 2500);
+            assert_eq!(sum, 2499 * 2500);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_insert_sink() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_insert_sink (
+            customer_id  UInt32,
+            amount       UInt32,
+            account_name Nullable(String)
+        ) Engine=Memory";
+
+    let rows = (0..2500).map(|i| Payment {
+        customer_id: i,
+        amount: i * 2,
+        account: None,
+        note: String::new(),
+    });
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_insert_sink"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(|c| c.insert_row_sink::<_, Payment>("clickhouse_test_insert_sink"))
+        .and_then(move |sink| sink.flush_rows(1000).send_all(stream::iter_ok::<_, Error>(rows)))
+        .and_then(|(sink, _)| sink.commit())
+        .and_then(|c| {
+            c.query("SELECT count(), sum(amount) FROM clickhouse_test_insert_sink")
+                .fetch_one::<(u64, u64)>()
+        })
+        .and_then(|(_, (count, sum))| {
+            assert_eq!(count, 2500);
+            assert_eq!(sum, 2499 * 2500);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_insert_from_stream() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_insert_from_stream (
+            customer_id  UInt32,
+            amount       UInt32,
+            account_name Nullable(String)
+        ) Engine=Memory";
+
+    let rows = stream::iter_ok::<_, Error>((0..2500).map(|i| Payment {
+        customer_id: i,
+        amount: 1,
+        account: Some(i.to_string()),
+        note: String::new(),
+    }));
+
+    let separator = if database_url().contains('?') { '&' } else { '?' };
+    let pool = Pool::new(format!(
+        "{}{}insert_block_size=1000&insert_flush_interval=1s",
+        database_url(),
+        separator
+    ));
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_insert_from_stream"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(move |c| c.insert_from_stream("clickhouse_test_insert_from_stream", rows))
+        .and_then(|c| {
+            c.query("SELECT count(), sum(amount) FROM clickhouse_test_insert_from_stream")
+                .fetch_one::<(u64, u64)>()
+        })
+        .and_then(|(_, (count, sum))| {
+            assert_eq!(count, 2500);
+            assert_eq!(sum, 2500);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_insert_dedup_token() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_insert_dedup (
+            id UInt32
+        ) Engine=MergeTree ORDER BY id
+        SETTINGS non_replicated_deduplication_window = 100";
+
+    let block = Block::new().column("id", vec![1_u32, 2, 3]);
+    let retried = block.clone();
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_insert_dedup"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(move |c| {
+            c.insert(Query::new("clickhouse_test_insert_dedup").dedup_token("batch-1"), block)
+        })
+        .and_then(move |c| {
+            c.insert(Query::new("clickhouse_test_insert_dedup").dedup_token("batch-1"), retried)
+        })
+        .and_then(|c| {
+            c.query("SELECT count() FROM clickhouse_test_insert_dedup")
+                .fetch_one::<(u64,)>()
+        })
+        .and_then(|(_, (count,))| {
+            assert_eq!(count, 3);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_paginate() {
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| {
+            c.query("SELECT number FROM system.numbers LIMIT 250")
+                .paginate(100)
+                .keyset("number")
+                .map(|page| page.row_count())
+                .collect()
+        })
+        .and_then(|pages| {
+            assert_eq!(pages, vec![100, 100, 50]);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_query_scalar() {
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.query_scalar::<u64, _>("SELECT count() FROM numbers(10)"))
+        .and_then(|(c, count)| {
+            assert_eq!(count, 10);
+            c.query_scalar_optional::<String, _>("SELECT 'a' WHERE 0")
+        })
+        .and_then(|(c, value)| {
+            assert_eq!(value, None);
+            c.query_scalar::<u64, _>("SELECT 1, 2")
+        });
+
+    match run(done) {
+        Err(Error::Driver(DriverError::NotScalar { columns: 2 })) => {}
+        result => panic!("unexpected result {:?}", result.map(|(_, v)| v)),
+    }
+}
+
+#[test]
+fn test_watch() {
+    let ddl = "
+        CREATE LIVE VIEW clickhouse_test_live_view AS
+        SELECT sum(id) AS total FROM clickhouse_test_watch";
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_live_view"))
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_watch"))
+        .and_then(|c| c.execute("CREATE TABLE clickhouse_test_watch (id UInt32) Engine=Memory"))
+        .and_then(move |c| {
+            c.execute(Query::new(ddl).setting("allow_experimental_live_view", true))
+        })
+        .and_then(|c| {
+            let block = Block::new().column("id", vec![1_u32, 2, 3]);
+            c.insert("clickhouse_test_watch", block)
+        })
+        .and_then(|c| c.watch("clickhouse_test_live_view LIMIT 1").collect())
+        .and_then(|updates| {
+            assert_eq!(updates.len(), 1);
+            let (version, ref block) = updates[0];
+            assert!(version > 0);
+            assert_eq!(block.get::<u64, _>(0, "total")?, 6);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_try_fold_blocks() {
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| {
+            c.query("SELECT number FROM system.numbers")
+                .try_fold_blocks(0_u64, |_, block| {
+                    for row in block.rows() {
+                        let number: u64 = row.get("number")?;
+                        if number == 100_000 {
+                            return Ok(ControlFlow::Break(number));
+                        }
+                    }
+                    Ok(ControlFlow::Continue(0))
+                })
+        })
+        .and_then(|(c, found)| {
+            assert_eq!(found, 100_000);
+            c.query("SELECT 1 AS one").fetch_one::<(u8,)>()
+        })
+        .and_then(|(_, (one,))| {
+            assert_eq!(one, 1);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[cfg(feature = "transactions")]
+#[test]
+fn test_transaction_rollback() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_transaction (
+            id UInt32
+        ) Engine=MergeTree ORDER BY id";
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_transaction"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(|c| c.begin_transaction())
+        .and_then(|tx| {
+            let block = Block::new().column("id", vec![1_u32, 2, 3]);
+            tx.insert("clickhouse_test_transaction", block)
+        })
+        .and_then(|tx| tx.rollback())
+        .and_then(|c| {
+            c.query("SELECT count() FROM clickhouse_test_transaction")
+                .fetch_one::<(u64,)>()
+        })
+        .and_then(|(_, (count,))| {
+            assert_eq!(count, 0);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_exists() {
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP DATABASE IF EXISTS clickhouse_test_exists"))
+        .and_then(|c| c.database_exists("clickhouse_test_exists"))
+        .and_then(|(c, exists)| {
+            assert!(!exists);
+            c.create_database_if_not_exists("clickhouse_test_exists")
+        })
+        .and_then(|c| c.create_database_if_not_exists("clickhouse_test_exists"))
+        .and_then(|c| c.database_exists("clickhouse_test_exists"))
+        .and_then(|(c, exists)| {
+            assert!(exists);
+            c.table_exists("clickhouse_test_exists", "t")
+        })
+        .and_then(|(c, exists)| {
+            assert!(!exists);
+            c.execute("CREATE TABLE clickhouse_test_exists.t (x UInt8) Engine=Memory")
+        })
+        .and_then(|c| c.table_exists("clickhouse_test_exists", "t"))
+        .and_then(|(c, exists)| {
+            assert!(exists);
+            c.execute("DROP DATABASE clickhouse_test_exists")
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_mutate_wait() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_mutate (
+            id UInt32
+        ) Engine=MergeTree ORDER BY id";
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_mutate"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(|c| {
+            let block = Block::new().column("id", vec![1_u32, 2, 3]);
+            c.insert("clickhouse_test_mutate", block)
+        })
+        .and_then(|c| {
+            c.mutate("ALTER TABLE clickhouse_test_mutate DELETE WHERE id > 1")
+                .wait()
+        })
+        .and_then(|c| c.query("SELECT count() FROM clickhouse_test_mutate").fetch_one::<(u64,)>())
+        .and_then(|(c, (count,))| {
+            assert_eq!(count, 1);
+            c.mutate("ALTER TABLE clickhouse_test_mutate UPDATE id = throwIf(1) WHERE 1")
+                .wait()
+        })
+        .then(|result| {
+            match result {
+                Err(Error::Driver(DriverError::MutationFailed { .. })) => {}
+                Err(err) => panic!("unexpected error {}", err),
+                Ok(_) => panic!("mutation is expected to fail"),
+            }
+            Ok::<_, Error>(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_optimize() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_optimize (
+            id UInt32
+        ) Engine=ReplacingMergeTree ORDER BY id";
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_optimize"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(|c| c.insert("clickhouse_test_optimize", Block::new().column("id", vec![1_u32])))
+        .and_then(|c| c.insert("clickhouse_test_optimize", Block::new().column("id", vec![1_u32])))
+        .and_then(|c| c.optimize(Optimize::table("clickhouse_test_optimize").final_merge().wait(true)))
+        .and_then(|c| c.query("SELECT count() FROM clickhouse_test_optimize").fetch_one::<(u64,)>())
+        .and_then(|(_, (count,))| {
+            assert_eq!(count, 1);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_system_commands() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_system (
+            id UInt32
+        ) Engine=MergeTree ORDER BY id";
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_system"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(|c| c.system(SystemCommand::FlushLogs))
+        .and_then(|c| c.system(SystemCommand::StopMerges(Some("clickhouse_test_system".into()))))
+        .and_then(|c| c.system(SystemCommand::StartMerges(Some("clickhouse_test_system".into()))))
+        .and_then(|c| c.system(SystemCommand::ReloadDictionary("no_such_dictionary".into())))
+        .then(|result| {
+            match result {
+                Err(Error::Server(_)) => {}
+                Err(err) => panic!("unexpected error {}", err),
+                Ok(_) => panic!("reloading an unknown dictionary is expected to fail"),
+            }
+            Ok::<_, Error>(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_retry_policy() {
+    let failures = Arc::new(AtomicUsize::new(0));
+    let counter = failures.clone();
+    let policy = RetryPolicy::new(3)
+        .backoff(Duration::from_millis(10), Duration::from_millis(10))
+        .retry_on(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            true
+        });
+
+    let query = Query::new("SELECT throwIf(number = 0) FROM system.numbers LIMIT 1").retry(policy);
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(move |c| c.query(query).fetch_all())
+        .then(|result| {
+            match result {
+                Err(Error::Server(_)) => {}
+                Err(err) => panic!("unexpected error {}", err),
+                Ok(_) => panic!("throwIf is expected to fail"),
+            }
+            Ok::<_, Error>(())
+        });
+
+    run(done).unwrap();
+    assert_eq!(failures.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_query_timeout() {
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| {
+            c.query("SELECT sleep(3)")
+                .timeout(Duration::from_millis(500))
+                .fetch_all()
+        })
+        .then(|result| {
+            match result {
+                Err(Error::Driver(DriverError::Timeout)) => {}
+                Err(err) => panic!("unexpected error {}", err),
+                Ok(_) => panic!("the query is expected to time out"),
+            }
+            Ok::<_, Error>(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_stream_coalesced() {
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| {
+            c.query("SELECT number FROM numbers(1000) SETTINGS max_block_size = 10")
+                .stream_coalesced(300)
+                .map(|block| block.row_count())
+                .collect()
+        })
+        .and_then(|sizes| {
+            assert_eq!(sizes.iter().sum::<usize>(), 1000);
+            assert!(sizes.iter().all(|&rows| rows <= 300));
+            assert!(sizes[..sizes.len() - 1].iter().all(|&rows| rows > 290));
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_query_stats() {
+    let reported = Arc::new(AtomicUsize::new(0));
+    let hook = reported.clone();
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| {
+            c.query("SELECT number FROM numbers(1000)")
+                .on_stats(move |stats| {
+                    assert!(stats.first_block.is_some());
+                    assert!(stats.first_block.unwrap() <= stats.total);
+                    assert!(stats.connect + stats.send <= stats.total);
+                    hook.fetch_add(1, Ordering::SeqCst);
+                })
+                .fetch_all()
+        })
+        .and_then(|(_, block)| {
+            assert_eq!(block.row_count(), 1000);
+            Ok(())
+        });
+
+    run(done).unwrap();
+    assert_eq!(reported.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_blocking_client() {
+    let pool = clickhouse_rs::blocking::Pool::new(database_url()).unwrap();
+    let mut c = pool.get_handle().unwrap();
+
+    c.ping().unwrap();
+    c.execute("DROP TABLE IF EXISTS clickhouse_blocking").unwrap();
+    c.execute("CREATE TABLE clickhouse_blocking (id UInt32) ENGINE = Memory")
+        .unwrap();
+    let block = Block::new().column("id", vec![1_u32, 2, 3]);
+    c.insert("clickhouse_blocking", block).unwrap();
+
+    let block = c.query("SELECT id FROM clickhouse_blocking").fetch_all().unwrap();
+    assert_eq!(block.row_count(), 3);
+
+    let ids = c
+        .query("SELECT id FROM clickhouse_blocking ORDER BY id")
+        .rows()
+        .map(|row| row.and_then(|row| row.get::<u32, _>("id")))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(ids, vec![1, 2, 3]);
+
+    let blocks = c.query("SELECT number FROM numbers(10)").blocks().count();
+    assert!(blocks > 0);
+}
+
+#[test]
+fn test_max_result_bytes() {
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| {
+            c.query("SELECT number FROM numbers(1000)")
+                .max_result_bytes(8000)
+                .fetch_all()
+        })
+        .and_then(|(c, block)| {
+            assert_eq!(block.row_count(), 1000);
+            c.query("SELECT number FROM numbers(100000)")
+                .max_result_bytes(8000)
+                .fetch_all()
+        })
+        .then(|result| {
+            match result {
+                Err(Error::Driver(DriverError::ResultTooLarge { limit: 8000 })) => {}
+                Err(err) => panic!("unexpected error {}", err),
+                Ok(_) => panic!("the result is expected to exceed the limit"),
+            }
+            Ok::<_, Error>(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_describe_table() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_describe_table (
+            id  UInt64 COMMENT 'key',
+            day Date MATERIALIZED today()
+        ) Engine=Memory";
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_describe_table"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(|c| c.describe_table("clickhouse_test_describe_table"))
+        .and_then(|(_, columns)| {
+            assert_eq!(columns.len(), 2);
+            assert_eq!(columns[0].name, "id");
+            assert_eq!(columns[0].sql_type, SqlType::UInt64);
+            assert_eq!(columns[0].comment, "key");
+            assert_eq!(columns[1].default_kind, Some(DefaultKind::Materialized));
+            assert_eq!(columns[1].default_expression, "today()");
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_block_builder() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_block_builder (
+            id    UInt64,
+            name  FixedString(8),
+            score Nullable(Float64)
+        ) Engine=Memory";
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_block_builder"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(|c| c.block_builder("clickhouse_test_block_builder"))
+        .and_then(|(c, mut builder)| {
+            builder.push(vec![
+                ("id", Value::from(1_u8)),
+                ("name", Value::from("foo")),
+                ("score", Value::from(0.5_f64)),
+            ])?;
+            builder
+                .push(vec![
+                    ("id", Value::from("x")),
+                    ("name", Value::from("")),
+                    ("score", Value::from(1.0_f64)),
+                ])
+                .unwrap_err();
+            Ok((c, builder.build()))
+        })
+        .and_then(|(c, block)| c.insert("clickhouse_test_block_builder", block))
+        .and_then(|c| {
+            c.query("SELECT id, score FROM clickhouse_test_block_builder")
+                .fetch_one::<(u64, Option<f64>)>()
+        })
+        .and_then(|(_, row)| {
+            assert_eq!(row, (1, Some(0.5)));
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_insert_async() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_insert_async (
+            id UInt32
+        ) Engine=MergeTree ORDER BY id";
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.execute("DROP TABLE IF EXISTS clickhouse_test_insert_async"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(|c| {
+            let block = Block::new().column("id", vec![1_u32, 2, 3]);
+            c.insert_async("clickhouse_test_insert_async", block, true)
+        })
+        .and_then(|c| {
+            c.query("SELECT count() FROM clickhouse_test_insert_async")
+                .fetch_one::<(u64,)>()
+        })
+        .and_then(|(_, (count,))| {
+            assert_eq!(count, 3);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
 #[test]
 fn test_column_iter() {
     let ddl = r"
@@ -819,3 +1829,109 @@ fn test_column_iter() {
 
     run(done).unwrap();
 }
+
+#[test]
+fn test_catalog() {
+    let ddl = "
+        CREATE TABLE clickhouse_test_catalog (
+            id  UInt32,
+            day Date
+        ) Engine=MergeTree PARTITION BY toYYYYMM(day) ORDER BY id";
+
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(move |c| c.execute("DROP TABLE IF EXISTS clickhouse_test_catalog"))
+        .and_then(move |c| c.execute(ddl))
+        .and_then(|c| {
+            c.execute(
+                "INSERT INTO clickhouse_test_catalog \
+                 VALUES (1, '2020-01-01'), (2, '2020-01-02'), (3, '2020-02-01')",
+            )
+        })
+        .and_then(|c| c.query_scalar::<String, _>("SELECT currentDatabase()"))
+        .and_then(|(c, database)| {
+            c.list_databases()
+                .and_then(move |(c, databases)| {
+                    assert!(databases.iter().any(|db| db.name == database));
+                    c.list_tables(&database)
+                        .map(move |(c, tables)| (c, tables, database))
+                })
+        })
+        .and_then(|(c, tables, database)| {
+            let table = tables
+                .iter()
+                .find(|table| table.name == "clickhouse_test_catalog")
+                .unwrap();
+            assert_eq!(table.engine, "MergeTree");
+
+            c.list_columns(&database, "clickhouse_test_catalog")
+                .map(move |(c, columns)| (c, columns, database))
+        })
+        .and_then(|(c, columns, database)| {
+            let names: Vec<&str> = columns.iter().map(|column| column.name.as_str()).collect();
+            assert_eq!(names, vec!["id", "day"]);
+            assert!(columns[0].is_in_primary_key);
+
+            c.table_partitions(&database, "clickhouse_test_catalog")
+        })
+        .and_then(|(_, partitions)| {
+            let partitions: Vec<(&str, u64)> = partitions
+                .iter()
+                .map(|partition| (partition.partition.as_str(), partition.rows))
+                .collect();
+            assert_eq!(partitions, vec![("202001", 2), ("202002", 1)]);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+#[test]
+fn test_running_queries() {
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.running_queries())
+        .and_then(|(_, queries)| {
+            // The query itself is listed.
+            assert!(queries
+                .iter()
+                .any(|query| query.query.contains("system.processes")));
+            Ok(())
+        });
+
+    run(done).unwrap();
+}
+
+query! {
+    #[schema = "tests/schema.tsv"]
+    struct NumberRow = "SELECT number AS n FROM system.numbers LIMIT 3";
+}
+
+#[test]
+fn test_query_macro_from_block() {
+    assert_eq!(
+        NumberRow::SQL,
+        "SELECT number AS n FROM system.numbers LIMIT 3"
+    );
+
+    let block = Block::new().column("n", vec![1_u64, 2]);
+    let rows = NumberRow::from_block(&block).unwrap();
+    assert_eq!(rows, vec![NumberRow { n: 1 }, NumberRow { n: 2 }]);
+}
+
+#[test]
+fn test_query_macro() {
+    let pool = Pool::new(database_url());
+    let done = pool
+        .get_handle()
+        .and_then(|c| c.query(NumberRow::query()).fetch_all())
+        .and_then(|(_, block)| {
+            let rows = NumberRow::from_block(&block)?;
+            assert_eq!(rows, vec![NumberRow { n: 0 }, NumberRow { n: 1 }, NumberRow { n: 2 }]);
+            Ok(())
+        });
+
+    run(done).unwrap();
+}